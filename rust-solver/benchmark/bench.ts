@@ -1,20 +1,39 @@
+// Requires the addon built with `pnpm build:benchmarks`.
+// Set THREADS to run the parallel matrix build on that many workers, as `threads` does for a solve.
+
 import { Bench } from 'tinybench'
 
-import { addNumbers } from '../index.js'
+import * as solver from '../index.js'
+import type { Location, Problem } from '../index.js'
+
+const { benchDistanceMatrices } = solver as unknown as { benchDistanceMatrices: (problem: Problem, threads?: number) => number }
+const threads = process.env.THREADS ? Number(process.env.THREADS) : undefined
 
-function add(a: number) {
-  return a + 100
+function randomLocation(i: number): Location {
+  return { hash: `loc-${i}`, latitude: 54 + Math.random() * 2, longitude: 23 + Math.random() * 3 }
 }
 
-const b = new Bench()
+// `locations` counts pickup and delivery nodes, i.e. twice the number of orders
+function randomProblem(locations: number, vehicles: number): Problem {
+  return {
+    vehicles: Array.from({ length: vehicles }, (_, id) => ({ id, startLocation: randomLocation(-id - 1), priceKm: 1 })),
+    orders: Array.from({ length: locations / 2 }, (_, id) => ({
+      id,
+      pickupLocation: randomLocation(2 * id),
+      deliveryLocation: randomLocation(2 * id + 1),
+      loadFactor: 2,
+    })),
+  }
+}
 
-b.add('Native a + 100', () => {
-  addNumbers(10, 100)
-})
+const b = new Bench()
 
-b.add('JavaScript a + 100', () => {
-  add(10)
-})
+for (const locations of [200, 800, 2000]) {
+  const problem = randomProblem(locations, 20)
+  b.add(`distance matrices, ${locations} locations`, () => {
+    benchDistanceMatrices(problem, threads)
+  })
+}
 
 await b.run()
 
@@ -0,0 +1,101 @@
+// Side-by-side comparison of the native solver and OR-Tools.
+//
+//   pnpm compare --dir instances/ [--out report.csv] [--time-limit 10] [--python python3]
+//
+// Instances are `Problem` JSON files, or Li & Lim `.txt` files when the addon was built
+// with `pnpm build:benchmarks`. Requires `ortools` to be installed for the chosen python.
+
+import { spawnSync } from 'node:child_process'
+import { readdirSync, readFileSync, writeFileSync } from 'node:fs'
+import { join } from 'node:path'
+import { fileURLToPath } from 'node:url'
+
+import * as solver from '../index.js'
+import type { DistanceOptions, Problem, ProblemSolution } from '../index.js'
+
+interface OrToolsResult {
+  feasible: boolean
+  totalDistance?: number
+  vehiclesUsed?: number
+  timeMs: number
+}
+
+function arg(name: string, fallback?: string): string | undefined {
+  const idx = process.argv.indexOf(`--${name}`)
+  return idx >= 0 ? process.argv[idx + 1] : fallback
+}
+
+interface Instance {
+  problem: Problem
+  distance?: DistanceOptions
+}
+
+function loadInstance(path: string): Instance {
+  const text = readFileSync(path, 'utf8')
+  if (path.endsWith('.json')) {
+    return { problem: JSON.parse(text) }
+  }
+  const load = (solver as Record<string, unknown>).loadLiLimInstance as ((text: string) => Instance) | undefined
+  if (!load) {
+    throw new Error(`${path}: Li & Lim instances need the addon built with 'pnpm build:benchmarks'`)
+  }
+  return load(text)
+}
+
+function runOrTools({ problem, distance }: Instance, python: string, timeLimit: string): OrToolsResult {
+  const script = fileURLToPath(new URL('./ortools/solve_pdp.py', import.meta.url))
+  const mode = distance?.mode ?? 'greatcircle'
+  const res = spawnSync(python, [script, timeLimit, mode], { input: JSON.stringify(problem), encoding: 'utf8' })
+  if (res.status !== 0) {
+    throw new Error(`OR-Tools script failed: ${res.stderr}`)
+  }
+  return JSON.parse(res.stdout)
+}
+
+const usedVehicles = (solution: ProblemSolution) => Object.values(solution.routes).filter((r) => r.stops.length).length
+
+const dir = arg('dir')
+if (process.argv[2] !== 'compare' || !dir) {
+  console.error('usage: compare --dir <instances> [--out report.csv] [--time-limit 10] [--python python3]')
+  process.exit(1)
+}
+
+const python = arg('python', 'python3')!
+const timeLimit = arg('time-limit', '10')!
+const rows = ['instance,orders,vehicles,native_distance,native_vehicles,native_ms,ortools_distance,ortools_vehicles,ortools_ms,gap_percent']
+
+for (const file of readdirSync(dir).filter((f) => f.endsWith('.json') || f.endsWith('.txt')).sort()) {
+  const instance = loadInstance(join(dir, file))
+  const { problem, distance } = instance
+
+  const start = performance.now()
+  const native = solver.solveBruteForceWithOptions(problem, { distance }).bestDistanceSolution
+  const nativeMs = performance.now() - start
+
+  const ortools = runOrTools(instance, python, timeLimit)
+  const ortoolsDistance = ortools.feasible ? ortools.totalDistance! : NaN
+  const gap = ((native.totalDistance - ortoolsDistance) / ortoolsDistance) * 100
+
+  rows.push(
+    [
+      file,
+      problem.orders.length,
+      problem.vehicles.length,
+      native.totalDistance.toFixed(3),
+      usedVehicles(native),
+      nativeMs.toFixed(1),
+      ortools.feasible ? ortoolsDistance.toFixed(3) : '',
+      ortools.vehiclesUsed ?? '',
+      ortools.timeMs.toFixed(1),
+      Number.isFinite(gap) ? gap.toFixed(2) : '',
+    ].join(','),
+  )
+  console.log(`${file}: native ${native.totalDistance.toFixed(3)} vs OR-Tools ${ortoolsDistance.toFixed(3)}`)
+}
+
+const out = arg('out')
+if (out) {
+  writeFileSync(out, rows.join('\n') + '\n')
+} else {
+  console.log(rows.join('\n'))
+}
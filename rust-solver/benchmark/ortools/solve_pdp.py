@@ -0,0 +1,107 @@
+"""Solves a rust-solver `Problem` (JSON on stdin) with OR-Tools and prints the result as JSON.
+
+Mirrors the native solver's model: open routes starting at each vehicle's start location,
+pickup-before-delivery on the same vehicle, order loads of demand / capacity on vehicles with a
+capacity and 1 / loadFactor otherwise, and great-circle distances in km, or planar ones when the
+second argument is `euclidean`. Time windows and service times are not modelled. The objective is
+total distance.
+"""
+
+import json
+import math
+import sys
+import time
+
+from ortools.constraint_solver import pywrapcp, routing_enums_pb2
+
+# OR-Tools works on integer costs/loads, so scale them up
+DIST_SCALE = 1000
+LOAD_SCALE = 1_000_000
+
+
+def euclidean(a, b):
+    return math.hypot(a["longitude"] - b["longitude"], a["latitude"] - b["latitude"])
+
+
+def great_circle(a, b):
+    lat1, lon1 = math.radians(a["latitude"]), math.radians(a["longitude"])
+    lat2, lon2 = math.radians(b["latitude"]), math.radians(b["longitude"])
+    val = math.sin(lat1) * math.sin(lat2) + math.cos(lat1) * math.cos(lat2) * math.cos(lon1 - lon2)
+    return math.acos(max(-1.0, min(1.0, val))) * 6371.0
+
+
+def main():
+    problem = json.load(sys.stdin)
+    time_limit_s = int(sys.argv[1]) if len(sys.argv) > 1 else 10
+    distance = euclidean if len(sys.argv) > 2 and sys.argv[2] == "euclidean" else great_circle
+    vehicles, orders = problem["vehicles"], problem["orders"]
+
+    # Nodes: one start per vehicle, a shared dummy end (free to reach), then pickup/delivery pairs
+    locations = [v["startLocation"] for v in vehicles] + [None]
+    end = len(vehicles)
+    for order in orders:
+        locations += [order["pickupLocation"], order["deliveryLocation"]]
+
+    def share(order, vehicle):
+        if order.get("demand") is not None and vehicle.get("capacity") is not None:
+            return order["demand"] / vehicle["capacity"]
+        return 1 / order["loadFactor"]
+
+    # Loads differ per vehicle once capacities do, so each vehicle gets its own demands
+    demands = []
+    for vehicle in vehicles:
+        loads = [0] * (end + 1)
+        for order in orders:
+            load = round(LOAD_SCALE * share(order, vehicle))
+            loads += [load, -load]
+        demands.append(loads)
+
+    def cost(i, j):
+        if j == end or locations[i] is None or locations[j] is None:
+            return 0
+        return round(distance(locations[i], locations[j]) * DIST_SCALE)
+
+    manager = pywrapcp.RoutingIndexManager(len(locations), len(vehicles), list(range(len(vehicles))), [end] * len(vehicles))
+    routing = pywrapcp.RoutingModel(manager)
+
+    transit = routing.RegisterTransitCallback(lambda i, j: cost(manager.IndexToNode(i), manager.IndexToNode(j)))
+    routing.SetArcCostEvaluatorOfAllVehicles(transit)
+    routing.AddDimension(transit, 0, 2**40, True, "Distance")
+    distance_dim = routing.GetDimensionOrDie("Distance")
+
+    loads = [routing.RegisterUnaryTransitCallback(lambda i, d=d: d[manager.IndexToNode(i)]) for d in demands]
+    routing.AddDimensionWithVehicleTransitAndCapacity(loads, 0, [LOAD_SCALE] * len(vehicles), True, "Load")
+
+    for k in range(len(orders)):
+        pickup = manager.NodeToIndex(end + 1 + 2 * k)
+        delivery = manager.NodeToIndex(end + 2 + 2 * k)
+        routing.AddPickupAndDelivery(pickup, delivery)
+        routing.solver().Add(routing.VehicleVar(pickup) == routing.VehicleVar(delivery))
+        routing.solver().Add(distance_dim.CumulVar(pickup) <= distance_dim.CumulVar(delivery))
+
+    params = pywrapcp.DefaultRoutingSearchParameters()
+    params.first_solution_strategy = routing_enums_pb2.FirstSolutionStrategy.PARALLEL_CHEAPEST_INSERTION
+    params.local_search_metaheuristic = routing_enums_pb2.LocalSearchMetaheuristic.GUIDED_LOCAL_SEARCH
+    params.time_limit.seconds = time_limit_s
+
+    start = time.perf_counter()
+    solution = routing.SolveWithParameters(params)
+    elapsed_ms = (time.perf_counter() - start) * 1000
+
+    if solution is None:
+        json.dump({"feasible": False, "timeMs": elapsed_ms}, sys.stdout)
+        return
+
+    json.dump(
+        {
+            "feasible": True,
+            "totalDistance": solution.ObjectiveValue() / DIST_SCALE,
+            "vehiclesUsed": sum(routing.IsVehicleUsed(solution, v) for v in range(len(vehicles))),
+            "timeMs": elapsed_ms,
+        },
+        sys.stdout,
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -1,9 +1,386 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
 export interface AlgorithmSolution {
+  /** Solutions of criteria left out of `SolveOptions::compute` are empty */
   bestDistanceSolution: ProblemSolution
   bestPriceSolution: ProblemSolution
   bestEmptySolution: ProblemSolution
+  /** Assignment minimizing the longest route distance */
+  bestMakespanSolution: ProblemSolution
+  bestDurationSolution: ProblemSolution
+  /** Assignment using the fewest vehicles, the shortest total distance among those */
+  bestFleetSolution: ProblemSolution
+  stats: SolveStats
+  /**
+   * False when `time_limit_ms` or `max_nodes` stopped the search early, the solutions are then
+   * the best found so far
+   */
+  optimal: boolean
+  /** Whether any complete assignment meeting every constraint was found */
+  feasible: boolean
+  /**
+   * Orders no vehicle can serve even on its own, with the reason. When each one can, the orders
+   * the plans leave out because no route takes them alongside the others
+   */
+  unassignableOrders: Array<UnassignableOrder>
+  /**
+   * The `top_k` best distance plans, best first, none unless `top_k` is above 1. Plans only
+   * swapping the orders of interchangeable vehicles count once
+   */
+  alternatives: Array<ProblemSolution>
+}
+
+export interface AnnealingOptions {
+  /** Defaults to 20000 */
+  iterations?: number
+  /** Defaults to 10 */
+  initialTemperature?: number
+  /** Temperature multiplier per iteration, defaults to 0.9995 */
+  coolingRate?: number
+  /** Same seed and problem always give the same result, defaults to 1 */
+  seed?: number
+  /** Defaults to total distance only */
+  weights?: ObjectiveWeights
+  /** How distances are measured, great-circle km by default */
+  distance?: DistanceOptions
+}
+
+export interface AnnealingResult {
+  solution: ProblemSolution
+  stats: AnnealingStats
+}
+
+export interface AnnealingStats {
+  iterations: number
+  /** Moves that kept every route feasible */
+  feasibleMoves: number
+  acceptedMoves: number
+  /** Iteration at which the returned solution was found, 0 for the starting plan */
+  bestIteration: number
+  finalTemperature: number
+  initialCost: number
+  bestCost: number
+}
+
+export interface AppliedMove {
+  kind: LocalSearchMove
+  /** Orders that changed vehicle, those leaving `fromVehicleId` first */
+  orderIds: Array<number>
+  fromVehicleId: number
+  toVehicleId: number
+  /** Total distance the move saved */
+  savedDistance: number
+}
+
+export interface AssignmentSuggestion {
+  /** Vehicle id -> order ids, in the shape `routeAssignment` accepts */
+  assignments: Record<string, Array<number>>
+  unassignedOrderIds: Array<number>
+  estimatedDistance: number
+  estimatedEmptyDistance: number
+  estimatedPrice: number
+  /** Always true, metrics are estimates rather than optimal routes */
+  approximate: boolean
+}
+
+/** Algorithm `solve_auto` picked for a problem. */
+export declare enum AutoAlgorithm {
+  /** The exact search alone */
+  Exact = 'exact',
+  /** The exact search, pruning from the greedy plan's costs */
+  SeededExact = 'seeded_exact',
+  /** Large neighbourhood search from a regret insertion plan */
+  Lns = 'lns'
+}
+
+export interface AutoOptions {
+  /** Largest problem searched exactly without seeding, defaults to 6 orders */
+  exactMaxOrders?: number
+  /** Largest problem searched exactly from the greedy plan, at most and by default 8 orders */
+  seededMaxOrders?: number
+  /** Beyond this many vehicles the exact search is never tried, defaults to 8 */
+  exactMaxVehicles?: number
+  /** Stop after this long with the best plan so far, whichever algorithm runs */
+  timeLimitMs?: number
+  /** Earth radius and unit of every distance in and out, defaults to km on a 6371 km sphere */
+  distance?: DistanceOptions
+}
+
+export interface AutoSolution {
+  algorithm: AutoAlgorithm
+  /** Whether the plans are proven optimal: the exact search ran to the end */
+  optimal: boolean
+  solution: AlgorithmSolution
+}
+
+export interface BeamOptions {
+  /** Partial assignments kept per vehicle, defaults to 50. Infinity keeps every one, which is exact */
+  beamWidth?: number
+  /** Criterion the kept assignments are ranked by and the routes optimized for, defaults to distance */
+  criterion?: Criterion
+  /** How distances are measured, great-circle km by default */
+  distance?: DistanceOptions
+}
+
+export interface BoundingBox {
+  minLatitude: number
+  maxLatitude: number
+  minLongitude: number
+  maxLongitude: number
+}
+
+/**
+ * Cancels the async solves it was passed to. A cancelled solve rejects with an error whose
+ * `code` is `"Cancelled"`; cancelling once a solve has finished does nothing.
+ */
+export declare class CancellationToken {
+  constructor()
+  cancel(): void
+  get cancelled(): boolean
+}
+
+export interface ClusteredSolution {
+  solution: ProblemSolution
+  clusters: Array<OrderCluster>
+  /** Orders their cluster's route could not take, served on another vehicle's route instead */
+  spilledOrderIds: Array<number>
+}
+
+export interface ClusterOptions {
+  /**
+   * Groups the orders are split into, each routed by its own vehicle. At most the number of
+   * vehicles, which is also the default
+   */
+  clusters?: number
+  /** How distances are measured, great-circle km by default */
+  distance?: DistanceOptions
+}
+
+/**
+ * How plan `b` fares against plan `a` of `problem` under every criterion, e.g. a heuristic
+ * against the exact optimum.
+ */
+export declare function compareSolutions(problem: Problem, a: ProblemSolution, b: ProblemSolution): SolutionComparison
+
+export interface CostBreakdown {
+  /** Loaded km at `price_km` */
+  loadedCost: number
+  /** Empty km at `price_km_empty` */
+  emptyCost: number
+  /** `stop_fee` times the pickups and deliveries served */
+  stopFees: number
+  /**
+   * What the `routeCostFn` price of the route adds to the other parts, which with the leg prices
+   * stay at the vehicle's own rates. 0 without a callback
+   */
+  customAdjustment: number
+}
+
+export declare enum Criterion {
+  Distance = 'distance',
+  Price = 'price',
+  Empty = 'empty',
+  /** Longest single route distance, also when speeds, service times or windows are set */
+  Makespan = 'makespan',
+  /** Sum of route durations */
+  Duration = 'duration',
+  /** Fewest vehicles used, ties broken by total distance */
+  Fleet = 'fleet'
+}
+
+export interface CriterionDelta {
+  criterion: Criterion
+  a: number
+  b: number
+  /** `b - a`, positive when `b` is worse */
+  delta: number
+  /** `delta` as a percentage of `a`, none when `a` is 0 */
+  percent?: number
+}
+
+/**
+ * Distance cache that survives between solves, keyed by location hash pairs.
+ * Locations whose coordinates change must be passed to `invalidate`. A solve measuring
+ * distances differently from the last one empties it.
+ */
+export declare class DistanceCache {
+  constructor(maxEntries?: number | undefined | null)
+  /** Drops every cached distance from or to any of `hashes`. */
+  invalidate(hashes: Array<string>): void
+  clear(): void
+  get size(): number
+}
+
+/**
+ * `GreatCircle` measures on a sphere. `Euclidean` reads latitude as y and longitude as x on a
+ * plane, for synthetic benchmarks, with distances in whatever unit the coordinates are in.
+ */
+export declare enum DistanceMode {
+  GreatCircle = 'greatcircle',
+  Euclidean = 'euclidean'
+}
+
+/**
+ * How distances are measured and reported. With another unit every distance-like input is read
+ * in it too: `price_km` is the price per unit, `speed_kmh` units per hour, and `max_distance_km`,
+ * `max_route_spread_km` and `max_candidate_radius_km` are in units. Unset speeds keep the default in km/h.
+ */
+export interface DistanceOptions {
+  /** Defaults to 6371.0, e.g. 6371.0088 for the IUGG mean radius */
+  radiusKm?: number
+  /** Defaults to km */
+  unit?: DistanceUnit
+  /**
+   * Defaults to great circle. Euclidean distances are in coordinate units already, so
+   * `radius_km` and `unit` are ignored and inputs are read per coordinate unit
+   */
+  mode?: DistanceMode
+  /** Road detour factor every distance is multiplied by, e.g. 1.3. Defaults to 1.0 */
+  circuity?: number
+  /**
+   * Circuity of locations whose hash starts with the key, the longest matching prefix wins.
+   * A leg between two locations takes the mean of their factors
+   */
+  circuityByPrefix?: Record<string, number>
+}
+
+export declare enum DistanceUnit {
+  Km = 'km',
+  Miles = 'miles'
+}
+
+/** Cheapest insertion of `new_order` into the existing routes of `solution`, without re-optimizing them. */
+export declare function evaluateInsertion(problem: Problem, solution: ProblemSolution, newOrder: Order, distance?: DistanceOptions | undefined | null): InsertionEvaluation
+
+/** What cancelling `order_id` saves with its stops spliced out of the route and nothing re-optimized. */
+export declare function evaluateRemoval(problem: Problem, solution: ProblemSolution, orderId: number, distance?: DistanceOptions | undefined | null): RemovalEvaluation
+
+/** Distance, empty distance, price and duration of routes built outside the solver, measured the way it measures its own. */
+export declare function evaluateSolution(problem: Problem, routes: Record<string, Array<RouteStop>>, distance?: DistanceOptions | undefined | null): ProblemSolution
+
+export interface ForbiddenLeg {
+  fromHash: string
+  toHash: string
+}
+
+/** A random problem for tests and benchmarks, the same for the same seed and options. */
+export declare function generateProblem(options: GeneratorOptions): Problem
+
+export interface GeneratorOptions {
+  nOrders: number
+  nVehicles: number
+  /** Same seed, same problem. Defaults to 1 */
+  seed?: number
+  /** Where every location is drawn, defaults to latitudes 54-56 and longitudes 23-26 */
+  bbox?: BoundingBox
+  /** Load factors are whole numbers within this range, defaults to 1-4 */
+  loadFactorRange?: NumberRange
+  /** Price per km of each vehicle, rounded to cents, defaults to 0.5-1.5 */
+  priceRange?: NumberRange
+  /** Gather pickups around this many random centres instead of spreading them over the box */
+  clustering?: number
+}
+
+/** A new best value of one criterion, as passed to an `onImprovement` callback. */
+export interface Improvement {
+  criterion: Criterion
+  /** The criterion's new best value, in the distance unit of the solve for distances */
+  value: number
+  /** Milliseconds since the solve started */
+  elapsedMs: number
+  /** The improved plan, only with `improvement_solutions` */
+  solution?: ProblemSolution
+}
+
+/**
+ * Improves `solution`, e.g. from `solveGreedy` or `solveBeam`, by large neighbourhood search.
+ * See `LnsOptions` for the knobs.
+ */
+export declare function improveSolution(problem: Problem, solution: ProblemSolution, options?: LnsOptions | undefined | null): LnsResult
+
+export interface InsertionCost {
+  feasible: boolean
+  /** Vehicle with the lowest price delta */
+  bestVehicleId?: number
+  options: Array<InsertionOption>
+}
+
+/** Per-vehicle cost of adding `new_order` to an existing plan, for live quotes. */
+export declare function insertionCost(problem: Problem, solution: ProblemSolution, newOrder: Order, distance?: DistanceOptions | undefined | null): InsertionCost
+
+export interface InsertionEvaluation {
+  feasible: boolean
+  vehicleId?: number
+  /** Index of the new pickup among the vehicle's updated stops */
+  pickupPosition?: number
+  /** Index of the new delivery among the vehicle's updated stops */
+  deliveryPosition?: number
+  deltaDistance: number
+  deltaEmptyDistance: number
+  deltaPrice: number
+  /** The plan with the order inserted, the input plan when infeasible */
+  solution: ProblemSolution
+  /** Constraint that ruled out the closest insertion, only set when infeasible */
+  bindingConstraint?: string
+}
+
+export interface InsertionOption {
+  vehicleId: number
+  feasible: boolean
+  /** Why the order cannot be added to this vehicle */
+  reason?: string
+  /** Whether the route was re-solved exactly or the order was inserted heuristically */
+  exact: boolean
+  deltaDistance: number
+  deltaEmptyDistance: number
+  deltaPrice: number
+  route?: VehicleRoute
+}
+
+export interface LnsOptions {
+  /** Destroy and repair rounds, defaults to 1000 */
+  iterations?: number
+  /** Same seed, problem and solution always give the same result, defaults to 1 */
+  seed?: number
+  /** Stop after this long with the best plan so far, even before `iterations` rounds */
+  timeLimitMs?: number
+  /** How distances are measured, great-circle km by default */
+  distance?: DistanceOptions
+}
+
+export interface LnsResult {
+  solution: ProblemSolution
+  /** Rounds whose repaired plan replaced the current one */
+  acceptedMoves: number
+}
+
+/**
+ * Polishes `solution` with relocate, swap and 2-opt* moves until none shortens it, logging each
+ * move applied. See `LocalSearchOptions`.
+ */
+export declare function localSearch(problem: Problem, solution: ProblemSolution, options?: LocalSearchOptions | undefined | null): LocalSearchResult
+
+export declare enum LocalSearchMove {
+  /** Move one order to another vehicle */
+  Relocate = 'relocate',
+  /** Exchange two orders between vehicles */
+  Swap = 'swap',
+  /** Exchange the tails of two routes */
+  TwoOptStar = 'two_opt_star'
+}
+
+export interface LocalSearchOptions {
+  /** Moves to try, in this order, defaults to all of them */
+  moves?: Array<LocalSearchMove>
+  /** How distances are measured, great-circle km by default */
+  distance?: DistanceOptions
+}
+
+export interface LocalSearchResult {
+  solution: ProblemSolution
+  /** Moves applied, in order */
+  moves: Array<AppliedMove>
+  savedDistance: number
 }
 
 export interface Location {
@@ -12,41 +389,677 @@ export interface Location {
   longitude: number
 }
 
+/**
+ * A value no plan serving every order of `problem` can beat under `criterion`, to report a
+ * heuristic's gap where the exact search cannot run.
+ */
+export declare function lowerBound(problem: Problem, criterion: Criterion, distance?: DistanceOptions | undefined | null): number
+
+export interface NumberRange {
+  min: number
+  max: number
+}
+
+/** Relative weight of each objective in a heuristic's single cost, unset weights are 0. */
+export interface ObjectiveWeights {
+  distance?: number
+  price?: number
+  empty?: number
+}
+
 export interface Order {
   id: number
   pickupLocation: Location
   deliveryLocation: Location
+  /**
+   * How many such orders fill a vehicle, the order takes `1 / load_factor` of its capacity.
+   * Must be a finite number of at least 1
+   */
   loadFactor: number
+  /** Room the order takes on vehicles with a `capacity`, on any other vehicle `load_factor` applies */
+  demand?: number
+  /** When the pickup may be served, a vehicle arriving early waits for it to open */
+  pickupWindow?: TimeWindow
+  /** When the delivery may be served, same rules as `pickup_window` */
+  deliveryWindow?: TimeWindow
+  /** Minutes spent loading at the pickup, 0 when missing */
+  pickupServiceMin?: number
+  /** Minutes spent unloading at the delivery, 0 when missing */
+  deliveryServiceMin?: number
+  /** Tags a vehicle needs all of to be given the order */
+  requiredTags?: Array<string>
+}
+
+export interface OrderCluster {
+  /** Vehicle routing the cluster */
+  vehicleId: number
+  /** Orders grouped into the cluster, spilled ones included */
+  orderIds: Array<number>
+  /** Whether the cluster was routed exactly, or greedily for being too large or not fitting one route */
+  exact: boolean
+}
+
+export interface OrderConflict {
+  firstOrderId: number
+  secondOrderId: number
 }
 
 export interface Problem {
   vehicles: Array<Vehicle>
   orders: Array<Order>
+  /** Direct moves between locations that are never allowed, whatever they would save */
+  forbiddenLegs?: Array<ForbiddenLeg>
+  /** Order id -> vehicle id. A pinned order is only ever given to that vehicle */
+  fixedAssignments?: Record<string, number>
+  /** Order ids that must all ride on the same vehicle, groups sharing an order are merged */
+  sameVehicleGroups?: Array<Array<number>>
+  /** Pairs of orders that may never share a vehicle */
+  conflictPairs?: Array<OrderConflict>
 }
 
 export interface ProblemSolution {
+  /** Vehicle id as a string -> route, kept for compatibility with `route_list` */
   routes: Record<string, VehicleRoute>
+  /** The same routes ordered by vehicle id, the canonical output */
+  routeList: Array<VehicleRouteEntry>
   totalDistance: number
   emptyDistance: number
   totalPrice: number
+  totalDuration: number
+  /** Grams of CO2 over every route */
+  totalCo2: number
+  /** Longest minus shortest route distance, for auditing `max_route_spread_km` */
+  routeSpread: number
+  /** Longest single route distance, what the makespan criterion minimizes */
+  maxRouteDistance: number
+  /** Orders no route serves, only non-empty when the fleet cannot cover every order */
+  unassignedOrderIds: Array<number>
+}
+
+/** Vehicles ranked by the cost of serving exactly `order_ids`, for building loads by hand. */
+export declare function rankVehicles(problem: Problem, orderIds: Array<number>, criterion: Criterion, distance?: DistanceOptions | undefined | null): Array<VehicleRanking>
+
+export interface RegretOptions {
+  /**
+   * Vehicles compared per order: the regret is what the order loses over its next
+   * `regret_k - 1` cheapest vehicles. 1 is plain cheapest insertion, defaults to 2
+   */
+  regretK?: number
+  /** How distances are measured, great-circle km by default */
+  distance?: DistanceOptions
+}
+
+export interface RemovalEvaluation {
+  vehicleId: number
+  savedDistance: number
+  savedEmptyDistance: number
+  savedPrice: number
+  /** The plan without the order, its vehicle's route gone if it served nothing else */
+  solution: ProblemSolution
+}
+
+export interface RemovalSaving {
+  vehicleId: number
+  savedDistance: number
+  savedEmptyDistance: number
+  savedPrice: number
+  /** Whether the reduced route was re-solved exactly or only had the order's stops spliced out */
+  exact: boolean
+  /** The vehicle's route without the order, empty if it was the only one */
+  route: VehicleRoute
+}
+
+/** How much distance, price and empty distance dropping `order_id` from `solution` saves. */
+export declare function removalSaving(problem: Problem, solution: ProblemSolution, orderId: number, distance?: DistanceOptions | undefined | null): RemovalSaving
+
+export interface ReoptimizedSolution {
+  solution: ProblemSolution
+  /** Per-vehicle improvement over the input routes, positive values are savings */
+  savings: Array<RouteSavings>
+}
+
+/** Re-sequences the stops of every route in `solution` without moving orders between vehicles. */
+export declare function reoptimizeRoutes(problem: Problem, solution: ProblemSolution, criterion: Criterion, distance?: DistanceOptions | undefined | null): ReoptimizedSolution
+
+/**
+ * Routes each vehicle optimally over exactly the orders given for it (vehicle id -> order ids),
+ * skipping the assignment search.
+ */
+export declare function routeAssignment(problem: Problem, assignments: Record<string, Array<number>>, criterion: Criterion, distance?: DistanceOptions | undefined | null): ProblemSolution
+
+/**
+ * One drive of a route, from the vehicle's start to its first stop, between stops, back to the
+ * start for a reload or on to the end location.
+ */
+export interface RouteLeg {
+  from: Location
+  to: Location
+  /** The legs of a route add up to exactly its `total_distance` */
+  distance: number
+  /** Nothing was on board for the drive */
+  empty: boolean
+  /** Share of capacity on board once the stop at `to` is served */
+  loadAfter: number
+  /** The drive at `price_km` or `price_km_empty`, plus the `stop_fee` of a pickup or delivery at `to` */
+  price: number
+}
+
+export interface RouteSavings {
+  vehicleId: number
+  distance: number
+  emptyDistance: number
+  price: number
 }
 
 export interface RouteStop {
+  /** 0 for reload stops */
   orderId: number
+  /** "pickup", "delivery" or "reload" */
   type: string
+  /** Minutes since the vehicle left its start when the stop is served, after any wait for its window */
+  arrivalTime: number
+  /** Minutes since the vehicle left its start when it moves on, `arrival_time` plus the service time */
+  departureTime: number
+  /** Where the stop is served, the vehicle's start for reload stops */
+  locationHash: string
+  latitude: number
+  longitude: number
+  /** 0-based position of the stop in its route, reloads included */
+  sequence: number
+  /** Share of capacity on board once the stop is served */
+  loadAfter: number
 }
 
+/** What a JS route cost callback is told about one vehicle's route. */
+export interface RouteSummary {
+  vehicleId: number
+  orderIds: Array<number>
+  distance: number
+  emptyDistance: number
+  /** Built-in price, `distance * price_km` */
+  price: number
+  duration: number
+}
+
+/** A named what-if patch on a base problem: removals first, then replacements by id, then additions. */
+export interface Scenario {
+  name: string
+  addVehicles?: Array<Vehicle>
+  removeVehicleIds?: Array<number>
+  /** Replace the vehicle with the same id */
+  updateVehicles?: Array<Vehicle>
+  addOrders?: Array<Order>
+  removeOrderIds?: Array<number>
+  /** Replace the order with the same id */
+  updateOrders?: Array<Order>
+}
+
+export interface ScenarioComparison {
+  base: AlgorithmSolution
+  scenarios: Array<ScenarioResult>
+}
+
+/** Scenario minus base for each objective's best solution, negative values are improvements. */
+export interface ScenarioDelta {
+  distance: number
+  price: number
+  emptyDistance: number
+  makespan: number
+  duration: number
+}
+
+export interface ScenarioResult {
+  name: string
+  solution: AlgorithmSolution
+  delta: ScenarioDelta
+}
+
+export interface SolutionComparison {
+  /** One per criterion, fleet counting the vehicles used */
+  deltas: Array<CriterionDelta>
+}
+
+/** Whether `a` is no worse than `b` in distance, price and empty distance, and better in at least one. */
+export declare function solutionDominates(a: ProblemSolution, b: ProblemSolution): boolean
+
+export interface SolutionReport {
+  valid: boolean
+  violations: Array<SolutionViolation>
+  /** Totals recomputed from the stops of every route, with the same leg rules as the solver */
+  totalDistance: number
+  emptyDistance: number
+  totalPrice: number
+  totalDuration: number
+}
+
+export interface SolutionViolation {
+  /** Key of the route in `solution.routes`, absent for orders no route serves and plan totals */
+  routeKey?: string
+  /** Index into the route's stops, absent when the route as a whole is at fault */
+  stopIndex?: number
+  orderId?: number
+  code: ViolationCode
+  message: string
+}
+
+/**
+ * Plans `problem` as `options` ask: exactly, by weights or criteria, or greedily above
+ * `max_orders_exact`. Without options it matches `solveBruteForce`.
+ */
+export declare function solve(problem: Problem, options?: SolveOptions | undefined | null): AlgorithmSolution
+
+/**
+ * Plans `problem` with the exact search or a heuristic depending on its size, and says which
+ * ran. See `AutoOptions` for the thresholds.
+ */
+export declare function solveAuto(problem: Problem, options?: AutoOptions | undefined | null): AutoSolution
+
+/**
+ * One plan from a beam search over the assignments, between `solveGreedy` and the exact search.
+ * Takes up to 64 orders, at most 8 per vehicle.
+ */
+export declare function solveBeam(problem: Problem, options?: BeamOptions | undefined | null): ProblemSolution
+
 export declare function solveBruteForce(problem: Problem): AlgorithmSolution
 
+/**
+ * Same as `solveBruteForceWithOptions`, run on the libuv thread pool so the event loop stays
+ * responsive. Invalid input rejects the returned promise, as does a panic inside the solver.
+ * Cancelling `token` stops the search and rejects with a `Cancelled` error.
+ * `on_improvement` is called on the event loop with every new best value of a criterion, in
+ * improving order per criterion. The search never waits for it.
+ */
+export declare function solveBruteForceAsync(problem: Problem, options?: SolveOptions | undefined | null, token?: CancellationToken | undefined | null, onImprovement?: ((arg: Improvement) => void) | undefined | null): Promise<AlgorithmSolution>
+
+/**
+ * Same as `solveBruteForce`, starting from `previous_solution` as incumbent. Stops of orders no
+ * longer in `problem` are dropped and new orders added where they cost the least, the result
+ * is still optimal.
+ */
+export declare function solveBruteForceWarm(problem: Problem, previousSolution: ProblemSolution, distance?: DistanceOptions | undefined | null): AlgorithmSolution
+
+/** Same as `solveBruteForce`, reusing and filling `cache` for every pairwise distance. */
+export declare function solveBruteForceWithCache(problem: Problem, cache: DistanceCache, distance?: DistanceOptions | undefined | null): AlgorithmSolution
+
+/**
+ * Same as `solveBruteForce`, reusing routing results from `memo` for orders and vehicles
+ * unchanged since the previous solve, and storing this solve's results in it afterwards.
+ */
+export declare function solveBruteForceWithMemo(problem: Problem, memo: TspMemo, distance?: DistanceOptions | undefined | null): AlgorithmSolution
+
+/** Same as `solveBruteForce`, with the search tuned by `options`. */
+export declare function solveBruteForceWithOptions(problem: Problem, options: SolveOptions): AlgorithmSolution
+
+/** Plans `problem` cluster first, route second, for hundreds of orders. See `ClusterOptions`. */
+export declare function solveClustered(problem: Problem, options?: ClusterOptions | undefined | null): ClusteredSolution
+
+/**
+ * One plan from greedy cheapest insertion, for problems too large for `solveBruteForce`.
+ * The same plan is reported under every criterion.
+ */
+export declare function solveGreedy(problem: Problem, distance?: DistanceOptions | undefined | null): AlgorithmSolution
+
+/** A solve running on a background thread, whose incumbents can be read while it searches. */
+export declare class SolveHandle {
+  /**
+   * Starts solving `problem` on a background thread.
+   *
+   * `route_cost_fn` optionally replaces the price of each candidate route used by the price
+   * objective. Each search worker calls it once per distinct (vehicle, order set) it routes, which can
+   * be up to vehicles * 2^orders calls per worker, each a round trip to the JS thread while the worker waits,
+   * so expect price solves to be far slower with it. Prices are cached per worker, not per solve, so with
+   * several threads the same route may be priced once by each. If it throws or returns anything but a
+   * finite number the search stops and finishes with `error` set. Without it the built-in pricing is used.
+   * Routes it priced keep their leg prices and report the difference in `costBreakdown.customAdjustment`.
+   */
+  static start(problem: Problem, options?: SolveOptions | undefined | null, routeCostFn?: ((arg: RouteSummary) => number) | undefined | null): SolveHandle
+  /** Stops the search, which then finishes as `cancelled`. Does nothing once it has finished. */
+  cancel(): void
+  /** Snapshot of the search, the final result once `finished` is set. */
+  current(): SolveProgress
+}
+
+export interface SolveOptions {
+  /**
+   * Added to every criterion's cost per pickup/delivery switch, so simpler routes win
+   * unless the zig-zag saves more than this
+   */
+  interleavingPenalty?: number
+  /**
+   * No used route may be longer than the shortest used route by more than this. When no
+   * assignment complies, each solution is the one with the smallest spread
+   */
+  maxRouteSpreadKm?: number
+  /** Every vehicle must serve at least one order, infeasible when there are fewer orders than vehicles */
+  requireAllVehicles?: boolean
+  /**
+   * Vehicles whose start is farther than this from an order's pickup are not considered for it.
+   * Grows automatically when it would leave some order without any vehicle
+   */
+  maxCandidateRadiusKm?: number
+  /**
+   * Stop searching after this long and return the best solutions found so far. The first
+   * complete assignment is always evaluated before the limit is enforced
+   */
+  timeLimitMs?: number
+  /**
+   * Stop searching after expanding this many assignment and route nodes, as counted in `stats`,
+   * and return the best solutions found so far. The search then runs on one thread, so a budget
+   * always gives the same result. Like `time_limit_ms`, enforced once a complete assignment was evaluated
+   */
+  maxNodes?: number
+  /**
+   * Start the search from the greedy plan's costs, so pruning has a bound from the first
+   * branch. Defaults to true, turn off to compare node counts
+   */
+  heuristicSeed?: boolean
+  /**
+   * Extend routes with the nearest next stop first, which finds good routes early and prunes
+   * more. Results are the same either way. Defaults to true, turn off to compare node counts
+   */
+  nearestFirst?: boolean
+  /**
+   * Prune partial routes on a lower bound of their remaining distance. Results are the same
+   * either way. Defaults to true, turn off to compare node counts
+   */
+  routeLowerBound?: boolean
+  /**
+   * Route order sets by dynamic programming instead of DFS. Results are the same either way.
+   * Unset picks the DP for sets of 7 or more orders. Multi-trip vehicles and time windows always use the DFS
+   */
+  routeDp?: boolean
+  /**
+   * Assign orders by dynamic programming over covered order sets instead of the recursive
+   * search, far faster with many vehicles. Same optimal values, ignored with `max_route_spread_km`
+   */
+  assignmentDp?: boolean
+  /**
+   * Minimize these metrics in order instead, each of "distance", "price", "empty" or "co2" at most
+   * once, "co2" only when every vehicle has `co2_per_km`.
+   * A later one only breaks ties of the earlier ones, and the one plan is reported under every
+   * criterion. Searched by a single sequential search, `assignment_dp` and the spread cap do not apply
+   */
+  criteria?: Array<string>
+  /** Earth radius and unit of every distance in and out, defaults to km on a 6371 km sphere */
+  distance?: DistanceOptions
+  /**
+   * Minimize `distance * km + price * price + empty * empty km` instead, reported under every
+   * criterion like `criteria`, which it cannot be combined with
+   */
+  weights?: ObjectiveWeights
+  /**
+   * Problems with more orders than this, at most 8, get the greedy plan of `solve_greedy` instead
+   * of the exact search. Only the distance options apply to it. Unset, larger problems are rejected
+   */
+  maxOrdersExact?: number
+  /** Worker threads of the search, defaults to one per core */
+  threads?: number
+  /**
+   * Accept a plan within this fraction above the optimum, pruning every branch that cannot beat
+   * the incumbent by more. Such plans are not `optimal`. Not with `criteria`, `weights` or `assignment_dp`
+   */
+  epsilon?: number
+  /**
+   * `epsilon` by the name of what it guarantees: the plans are at most this fraction above the
+   * optimum, `stats.achieved_gap` telling how close they provably came. Not together with `epsilon`
+   */
+  gapTolerance?: number
+  /** Report each route's legs, defaults to true. Turn off to keep results small */
+  returnLegs?: boolean
+  /**
+   * Only search for the best plans of these criteria, the others come back empty. Fewer
+   * criteria prune far more, e.g. `["price"]` when only the price plan is used. Defaults to all,
+   * not with `criteria` or `weights`
+   */
+  compute?: Array<Criterion>
+  /**
+   * Pass the improved plan to every `onImprovement` call. Rebuilding it costs about as much
+   * as the final result, so it is off by default
+   */
+  improvementSolutions?: boolean
+  /**
+   * Also list this many of the best distinct distance plans as `alternatives`, defaults to 1
+   * (none). Prunes far less, as the runners-up have to be searched for too. Not with `criteria`,
+   * `weights` or `assignment_dp`
+   */
+  topK?: number
+}
+
+/** Best objective values found so far, None until a complete assignment is reached. */
+export interface SolveProgress {
+  bestDistance?: number
+  bestPrice?: number
+  bestEmpty?: number
+  bestMakespan?: number
+  bestDuration?: number
+  finished: boolean
+  /** Set together with `finished` when `cancel` stopped the search, `solution` stays empty */
+  cancelled: boolean
+  /** Only set once the search has finished */
+  solution?: AlgorithmSolution
+  /** Set together with `finished` when the search failed, as `"<code>: <message>"` like a thrown error's message */
+  error?: string
+}
+
+/**
+ * Complete plans not dominated in distance, price and empty distance, sorted by distance.
+ * Keeps at most `max_solutions` (default 20), dropping those in the most crowded part of the front.
+ */
+export declare function solveParetoFront(problem: Problem, maxSolutions?: number | undefined | null, distance?: DistanceOptions | undefined | null): Array<ProblemSolution>
+
+/**
+ * One plan from regret insertion, usually a better start than `solveGreedy` for `improveSolution`.
+ * See `RegretOptions`.
+ */
+export declare function solveRegretInsertion(problem: Problem, options?: RegretOptions | undefined | null): ProblemSolution
+
+/** Solves `base_problem` and each what-if scenario in one call, with objective deltas against the base. */
+export declare function solveScenarios(baseProblem: Problem, scenarios: Array<Scenario>, options?: SolveOptions | undefined | null): ScenarioComparison
+
+/** Improves the greedy plan by simulated annealing, see `AnnealingOptions` for the knobs. */
+export declare function solveSimulatedAnnealing(problem: Problem, options?: AnnealingOptions | undefined | null): AnnealingResult
+
+/** Optimal pickup/delivery sequence when the vehicle and its orders are already decided. */
+export declare function solveSingleVehicle(vehicle: Vehicle, orders: Array<Order>, criterion: Criterion, options?: SolveOptions | undefined | null): VehicleRoute
+
+/**
+ * One plan from the sweep heuristic, e.g. as the `previousSolution` of `solveBruteForceWarm`.
+ * Takes up to 64 orders, at most 8 per vehicle.
+ */
+export declare function solveSweep(problem: Problem, distance?: DistanceOptions | undefined | null): ProblemSolution
+
+/**
+ * Plan minimizing `weights.distance * km + weights.price * price + weights.empty * empty km`,
+ * with the weighted cost broken down per objective. Unset weights are 0.
+ */
+export declare function solveWeighted(problem: Problem, weights: ObjectiveWeights, distance?: DistanceOptions | undefined | null): WeightedSolution
+
+export interface SolveStats {
+  /** Vehicle-order pairs excluded by `max_candidate_radius_km` */
+  filteredCandidatePairs: number
+  /** Partial assignments expanded by the search */
+  assignmentNodes: number
+  /** Partial routes expanded while routing candidate order sets */
+  routeNodes: number
+  /** Partial routes cut because even a lower bound on their remaining distance was too much */
+  routeBoundPrunes: number
+  /** Partial assignments cut because they could no longer beat the incumbents */
+  assignmentBoundPrunes: number
+  /** Partial assignments cut because the same orders were already reached more cheaply */
+  assignmentRevisitPrunes: number
+  /** Order sets offered to a vehicle by the assignment search */
+  submasks: number
+  /** Order sets whose routes were already in the memo */
+  memoHits: number
+  /** Order sets routed from scratch */
+  memoMisses: number
+  /** Routing results held by the largest worker's memo at the end of the search */
+  memoEntries: number
+  /** Milliseconds the search ran, rebuilding the reported routes excluded */
+  elapsedMs: number
+  /**
+   * How far above the optimum the reported plans may be at most, as a fraction. 0 for an exact
+   * search, at most `gap_tolerance` otherwise, none when the search was stopped early
+   */
+  achievedGap?: number
+}
+
+/** Quick vehicle suggestions for a batch of orders based on insertion estimates, without exact routing. */
+export declare function suggestAssignments(problem: Problem, criterion: Criterion, distance?: DistanceOptions | undefined | null): AssignmentSuggestion
+
+/**
+ * Per-(vehicle, order set) routing results carried from one solve to the next.
+ *
+ * Entries are matched by fingerprints of the orders and vehicles they were computed for,
+ * so orders may be added, removed or reordered between solves. Entries touching anything
+ * that changed are discarded.
+ */
+export declare class TspMemo {
+  constructor()
+  clear(): void
+  /** Number of stored (vehicle, order set) entries. */
+  get size(): number
+}
+
+/** Minutes since the vehicles leave their starts, which they all do at 0. A missing bound is open. */
+export interface TimeWindow {
+  earliest?: number
+  latest?: number
+}
+
+export interface UnassignableOrder {
+  orderId: number
+  reason: string
+}
+
+/** Every rule a hand-edited plan breaks, with its totals recomputed the way the solver measures them. */
+export declare function validateSolution(problem: Problem, solution: ProblemSolution, distance?: DistanceOptions | undefined | null): SolutionReport
+
 export interface Vehicle {
   id: number
   startLocation: Location
   priceKm: number
+  /** Price per km driven empty (repositioning, the leg to `end_location`), defaults to `price_km` */
+  priceKmEmpty?: number
+  /** Flat handling fee of every pickup and delivery the vehicle makes, added to the route price */
+  stopFee?: number
+  /** Grams of CO2 per km, emissions are reported as 0 without it */
+  co2PerKm?: number
+  /** Grams of CO2 per km driven empty, defaults to `co2_per_km` */
+  co2PerKmEmpty?: number
+  /**
+   * Trips per route, each one after the first starts with a reload stop back at
+   * `start_location`. Defaults to 1
+   */
+  maxTrips?: number
+  /**
+   * Lets the vehicle run as many trips as it needs when `max_trips` is unset, e.g. a van
+   * doing several loops per day with its capacity reset on each
+   */
+  allowMultiTrip?: boolean
+  /** Average travel speed, defaults to `DEFAULT_SPEED_KMH` */
+  speedKmh?: number
+  /**
+   * Smallest route price the vehicle is dispatched for, otherwise it stays unused. Compared
+   * against the `routeCostFn` price when a solve is given one
+   */
+  minRoutePrice?: number
+  /**
+   * Room for order `demand`, in whatever unit the orders use (pallets, kg). Orders without a
+   * demand still take `1 / load_factor` of it
+   */
+  capacity?: number
+  /**
+   * Where the route has to finish, the leg there from the last delivery is empty distance.
+   * Without one routes end at their last delivery
+   */
+  endLocation?: Location
+  /** Equipment the vehicle has, e.g. "tail-lift" or "refrigerated" */
+  tags?: Array<string>
+  /** Longest route the driver may cover, including the leg to `end_location` */
+  maxDistanceKm?: number
+  /** Longest route in minutes: travel at `speed_kmh`, waiting for windows and service time */
+  maxDurationMin?: number
+  /** Most orders one route may serve, however short it is. At least 1 */
+  maxOrders?: number
+  /**
+   * Orders already picked up and still on board, e.g. when re-planning mid-day from the live
+   * position given as `start_location`. Only their deliveries are routed, by this vehicle
+   */
+  onboardOrderIds?: Array<number>
+}
+
+export interface VehicleRanking {
+  vehicleId: number
+  feasible: boolean
+  /** Value of the requested criterion, Infinity when infeasible */
+  cost: number
+  reason?: string
+  route?: VehicleRoute
 }
 
 export interface VehicleRoute {
+  /** Id of the vehicle driving the route */
+  vehicleId: number
   stops: Array<RouteStop>
+  /** Every drive of the route in order, the stops between them */
+  legs: Array<RouteLeg>
   totalDistance: number
   emptyDistance: number
   totalPrice: number
+  /** Minutes, travel time at the vehicle's speed, waiting for windows and service time at the stops */
+  totalDurationMin: number
+  /** Minutes spent waiting for time windows to open, included in `total_duration_min` */
+  waitingTime: number
+  /** Last delivery to the vehicle's `end_location`, already part of the distance totals */
+  returnDistance: number
+  /** Switches between picking up and delivering along the route, reloads start a fresh count */
+  interleavings: number
+  /** Built-in price split by `price_km`, `price_km_empty` and `stop_fee`, before any route cost callback */
+  costBreakdown: CostBreakdown
+  /** Grams of CO2 at the vehicle's `co2_per_km`, 0 without it */
+  totalCo2: number
+  /** Highest share of capacity ever on board */
+  maxLoad: number
+  /** Share of capacity on board on average over the km driven, 0 for a route that never moves */
+  avgUtilization: number
+}
+
+export interface VehicleRouteEntry {
+  vehicleId: number
+  route: VehicleRoute
+}
+
+/** What is wrong with a route, one code per rule `validateSolution` checks. */
+export declare enum ViolationCode {
+  UnknownVehicle = 'unknown_vehicle',
+  UnknownOrder = 'unknown_order',
+  UnknownStopType = 'unknown_stop_type',
+  DeliveryBeforePickup = 'delivery_before_pickup',
+  CapacityExceeded = 'capacity_exceeded',
+  /** Picked up or delivered twice, on one route or on two */
+  ServedTwice = 'served_twice',
+  MissingDelivery = 'missing_delivery',
+  /** Neither on any route nor listed as unassigned */
+  MissingOrder = 'missing_order',
+  TimeWindowMissed = 'time_window_missed',
+  MissingTags = 'missing_tags',
+  RouteLimitExceeded = 'route_limit_exceeded',
+  TooManyOrders = 'too_many_orders',
+  TooManyTrips = 'too_many_trips',
+  ReloadWhileLoaded = 'reload_while_loaded',
+  /** Claimed distance, empty distance, price or duration differs from the recomputed one */
+  TotalsMismatch = 'totals_mismatch'
+}
+
+/** Assignment minimizing one weighted cost, with that cost split per objective. */
+export interface WeightedSolution {
+  solution: ProblemSolution
+  /** Weighted cost of `solution`, the sum of the three parts below */
+  cost: number
+  distanceCost: number
+  priceCost: number
+  emptyCost: number
+  /** Whether an assignment covering every order was found */
+  feasible: boolean
 }
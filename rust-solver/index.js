@@ -576,4 +576,47 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.AutoAlgorithm = nativeBinding.AutoAlgorithm
+module.exports.CancellationToken = nativeBinding.CancellationToken
+module.exports.compareSolutions = nativeBinding.compareSolutions
+module.exports.Criterion = nativeBinding.Criterion
+module.exports.DistanceCache = nativeBinding.DistanceCache
+module.exports.DistanceMode = nativeBinding.DistanceMode
+module.exports.DistanceUnit = nativeBinding.DistanceUnit
+module.exports.evaluateInsertion = nativeBinding.evaluateInsertion
+module.exports.evaluateRemoval = nativeBinding.evaluateRemoval
+module.exports.evaluateSolution = nativeBinding.evaluateSolution
+module.exports.generateProblem = nativeBinding.generateProblem
+module.exports.improveSolution = nativeBinding.improveSolution
+module.exports.insertionCost = nativeBinding.insertionCost
+module.exports.localSearch = nativeBinding.localSearch
+module.exports.LocalSearchMove = nativeBinding.LocalSearchMove
+module.exports.lowerBound = nativeBinding.lowerBound
+module.exports.rankVehicles = nativeBinding.rankVehicles
+module.exports.removalSaving = nativeBinding.removalSaving
+module.exports.reoptimizeRoutes = nativeBinding.reoptimizeRoutes
+module.exports.routeAssignment = nativeBinding.routeAssignment
+module.exports.solutionDominates = nativeBinding.solutionDominates
+module.exports.solve = nativeBinding.solve
+module.exports.solveAuto = nativeBinding.solveAuto
+module.exports.solveBeam = nativeBinding.solveBeam
 module.exports.solveBruteForce = nativeBinding.solveBruteForce
+module.exports.solveBruteForceAsync = nativeBinding.solveBruteForceAsync
+module.exports.solveBruteForceWarm = nativeBinding.solveBruteForceWarm
+module.exports.solveBruteForceWithCache = nativeBinding.solveBruteForceWithCache
+module.exports.solveBruteForceWithMemo = nativeBinding.solveBruteForceWithMemo
+module.exports.solveBruteForceWithOptions = nativeBinding.solveBruteForceWithOptions
+module.exports.solveClustered = nativeBinding.solveClustered
+module.exports.solveGreedy = nativeBinding.solveGreedy
+module.exports.SolveHandle = nativeBinding.SolveHandle
+module.exports.solveParetoFront = nativeBinding.solveParetoFront
+module.exports.solveRegretInsertion = nativeBinding.solveRegretInsertion
+module.exports.solveScenarios = nativeBinding.solveScenarios
+module.exports.solveSimulatedAnnealing = nativeBinding.solveSimulatedAnnealing
+module.exports.solveSingleVehicle = nativeBinding.solveSingleVehicle
+module.exports.solveSweep = nativeBinding.solveSweep
+module.exports.solveWeighted = nativeBinding.solveWeighted
+module.exports.suggestAssignments = nativeBinding.suggestAssignments
+module.exports.TspMemo = nativeBinding.TspMemo
+module.exports.validateSolution = nativeBinding.validateSolution
+module.exports.ViolationCode = nativeBinding.ViolationCode
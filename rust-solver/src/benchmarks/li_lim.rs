@@ -0,0 +1,157 @@
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+
+use crate::models::{DistanceMode, DistanceOptions, Location, Order, Problem, TimeWindow, Vehicle};
+
+// Li & Lim PDPTW instance layout:
+//   first line:  <vehicles> <capacity> <speed>
+//   other lines: <task> <x> <y> <demand> <earliest> <latest> <service> <pickup idx> <delivery idx>
+// Task 0 is the depot. Pickups have pickup idx = 0 and point at their delivery task,
+// deliveries have delivery idx = 0 and point back at their pickup task.
+struct Task {
+    id: u32,
+    x: f64,
+    y: f64,
+    demand: f64,
+    earliest: f64,
+    latest: f64,
+    service: f64,
+    delivery: usize,
+}
+
+/// A Li & Lim instance and the distance options its coordinates and reference results are measured with.
+#[napi(object)]
+pub struct LiLimInstance {
+    pub problem: Problem,
+    /// Euclidean over the instance's x/y, pass it as `SolveOptions.distance`
+    pub distance: DistanceOptions,
+}
+
+fn parse_error(line_no: usize, msg: &str) -> Error {
+    Error::new(Status::InvalidArg, format!("Li & Lim instance, line {}: {}", line_no + 1, msg))
+}
+
+fn parse_num<T: std::str::FromStr>(line_no: usize, field: &str) -> Result<T> {
+    field.parse::<T>().map_err(|_| parse_error(line_no, &format!("invalid number '{}'", field)))
+}
+
+fn location(task: &Task) -> Location {
+    // Planar x/y are carried in longitude/latitude, as `DistanceMode::Euclidean` reads them
+    Location {
+        hash: format!("lilim-{}", task.id),
+        latitude: task.y,
+        longitude: task.x,
+    }
+}
+
+fn window(task: &Task) -> Option<TimeWindow> {
+    Some(TimeWindow { earliest: Some(task.earliest), latest: Some(task.latest) })
+}
+
+/// Parses a Li & Lim PDPTW instance into a `Problem` and the euclidean distance options to solve it with.
+///
+/// Every vehicle starts at the depot with unit price per distance unit and the instance's
+/// `capacity`, each order takes its pickup's `demand`. The instance's time unit is read as a
+/// minute, so travelling at its speed gives `speed_kmh` of 60 units per hour and windows and
+/// service times carry over as they are.
+#[napi]
+pub fn load_li_lim_instance(text: String) -> Result<LiLimInstance> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let (header_no, header) = lines.next().ok_or_else(|| parse_error(0, "empty instance"))?;
+    let header: Vec<&str> = header.split_whitespace().collect();
+    if header.len() < 2 {
+        return Err(parse_error(header_no, "expected '<vehicles> <capacity> <speed>'"));
+    }
+    let n_vehicles: u32 = parse_num(header_no, header[0])?;
+    let capacity: f64 = parse_num(header_no, header[1])?;
+    let speed: f64 = match header.get(2) {
+        Some(field) => parse_num(header_no, field)?,
+        None => 1.0,
+    };
+    if capacity <= 0.0 || speed <= 0.0 {
+        return Err(parse_error(header_no, "capacity and speed must be positive"));
+    }
+
+    let mut tasks = Vec::new();
+    for (line_no, line) in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 9 {
+            return Err(parse_error(line_no, "expected 9 columns"));
+        }
+        let id: u32 = parse_num(line_no, fields[0])?;
+        if id as usize != tasks.len() {
+            return Err(parse_error(line_no, "task numbers must be consecutive and start at 0"));
+        }
+        tasks.push(Task {
+            id,
+            x: parse_num(line_no, fields[1])?,
+            y: parse_num(line_no, fields[2])?,
+            demand: parse_num(line_no, fields[3])?,
+            earliest: parse_num(line_no, fields[4])?,
+            latest: parse_num(line_no, fields[5])?,
+            service: parse_num(line_no, fields[6])?,
+            delivery: parse_num(line_no, fields[8])?,
+        });
+    }
+
+    let depot = tasks.first().ok_or_else(|| parse_error(header_no, "missing depot"))?;
+    let vehicles = (0..n_vehicles)
+        .map(|id| Vehicle {
+            id,
+            start_location: location(depot),
+            price_km: 1.0,
+            price_km_empty: None,
+            stop_fee: None,
+            co2_per_km: None,
+            co2_per_km_empty: None,
+            max_trips: None,
+            allow_multi_trip: None,
+            speed_kmh: Some(speed * 60.0),
+            min_route_price: None,
+            capacity: Some(capacity),
+            end_location: None,
+            tags: None,
+            max_distance_km: None,
+            max_duration_min: None,
+            max_orders: None,
+            onboard_order_ids: None,
+        })
+        .collect();
+
+    let mut orders = Vec::new();
+    for pickup in tasks.iter().skip(1).filter(|t| t.delivery != 0) {
+        let delivery = tasks
+            .get(pickup.delivery)
+            .ok_or_else(|| parse_error(pickup.id as usize + 1, "delivery task does not exist"))?;
+        if pickup.demand <= 0.0 || pickup.demand > capacity {
+            return Err(parse_error(pickup.id as usize + 1, "pickup demand must be positive and fit the capacity"));
+        }
+        orders.push(Order {
+            id: pickup.id,
+            pickup_location: location(pickup),
+            delivery_location: location(delivery),
+            // Only read by vehicles without a capacity, which the instance has none of
+            load_factor: 1.0,
+            demand: Some(pickup.demand),
+            pickup_window: window(pickup),
+            delivery_window: window(delivery),
+            pickup_service_min: Some(pickup.service),
+            delivery_service_min: Some(delivery.service),
+            required_tags: None,
+        });
+    }
+
+    let problem = Problem {
+        vehicles,
+        orders,
+        forbidden_legs: None,
+        fixed_assignments: None,
+        same_vehicle_groups: None,
+        conflict_pairs: None,
+    };
+    Ok(LiLimInstance { problem, distance: DistanceOptions { mode: Some(DistanceMode::Euclidean), ..Default::default() } })
+}
@@ -0,0 +1,21 @@
+use napi_derive::napi;
+use std::time::Instant;
+
+use crate::models::{Problem, SolveOptions};
+use crate::utils::DistanceModel;
+use crate::solver::matrix::DistanceMatrices;
+use crate::solver::with_threads;
+
+/// Builds the distance matrices for `problem` on `threads` workers, like the exact search with
+/// `SolveOptions.threads`, and returns the elapsed time in milliseconds.
+#[napi]
+pub fn bench_distance_matrices(problem: Problem, threads: Option<u32>) -> f64 {
+    let options = SolveOptions { threads, ..Default::default() };
+    let build = || {
+        let start = Instant::now();
+        let matrices = DistanceMatrices::build(&problem.orders, None, &DistanceModel::default());
+        std::hint::black_box(&matrices.dist_mat);
+        start.elapsed().as_secs_f64() * 1000.0
+    };
+    with_threads(&options, build).expect("the benchmark pool starts")
+}
@@ -0,0 +1,6 @@
+//! Tooling for running the solver against published benchmark instances.
+//! Only compiled with the `benchmarks` cargo feature, so none of this ships in the npm package.
+
+pub mod li_lim;
+pub mod matrix;
+pub mod report;
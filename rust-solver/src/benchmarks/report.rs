@@ -0,0 +1,40 @@
+use napi_derive::napi;
+
+use crate::models::DistanceMode;
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    pub instance: String,
+    pub vehicles_used: u32,
+    pub total_distance: f64,
+    /// Best known distance from the literature, if any.
+    pub reference_distance: Option<f64>,
+    pub time_ms: f64,
+}
+
+/// Renders benchmark results as a CSV table, including the gap to the reference distance.
+/// `distance_mode` is what the distances were measured with, great circle km when missing,
+/// and is written on every row so tables of planar and spherical runs are not mixed up.
+#[napi]
+pub fn write_results_table(results: Vec<BenchmarkResult>, distance_mode: Option<DistanceMode>) -> String {
+    let mode = match distance_mode.unwrap_or(DistanceMode::GreatCircle) {
+        DistanceMode::GreatCircle => "great_circle_km",
+        DistanceMode::Euclidean => "euclidean",
+    };
+    let mut out = String::from("instance,vehicles_used,distance_mode,total_distance,reference_distance,gap_percent,time_ms\n");
+    for r in &results {
+        let (reference, gap) = match r.reference_distance {
+            Some(reference) if reference > 0.0 => (
+                format!("{:.2}", reference),
+                format!("{:.2}", (r.total_distance - reference) / reference * 100.0),
+            ),
+            _ => (String::new(), String::new()),
+        };
+        out.push_str(&format!(
+            "{},{},{},{:.2},{},{},{:.1}\n",
+            r.instance, r.vehicles_used, mode, r.total_distance, reference, gap, r.time_ms
+        ));
+    }
+    out
+}
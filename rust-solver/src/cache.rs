@@ -0,0 +1,147 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use napi_derive::napi;
+
+use crate::utils::DistanceModel;
+
+const DEFAULT_MAX_ENTRIES: usize = 1_000_000;
+
+/// Distances keyed by (from hash, to hash), evicting the oldest entries beyond `max_entries`.
+/// All of them measured by `model`.
+pub struct DistanceStore {
+    model: Option<DistanceModel>,
+    entries: HashMap<String, HashMap<String, f64>>,
+    insertion_order: VecDeque<(String, String)>,
+    len: usize,
+    max_entries: usize,
+}
+
+pub type SharedDistanceStore = Arc<Mutex<DistanceStore>>;
+
+impl DistanceStore {
+    fn new(max_entries: usize) -> Self {
+        DistanceStore {
+            model: None,
+            entries: HashMap::new(),
+            insertion_order: VecDeque::new(),
+            len: 0,
+            max_entries,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, from: &str, to: &str) -> Option<f64> {
+        self.entries.get(from).and_then(|row| row.get(to)).copied()
+    }
+
+    pub fn insert(&mut self, from: &str, to: &str, distance: f64) {
+        if self.max_entries == 0 {
+            return;
+        }
+        while self.len >= self.max_entries {
+            let Some((old_from, old_to)) = self.insertion_order.pop_front() else {
+                break;
+            };
+            if let Some(row) = self.entries.get_mut(&old_from) {
+                if row.remove(&old_to).is_some() {
+                    self.len -= 1;
+                }
+            }
+        }
+
+        let previous = self.entries.entry(from.to_string()).or_default().insert(to.to_string(), distance);
+        if previous.is_none() {
+            self.len += 1;
+            self.insertion_order.push_back((from.to_string(), to.to_string()));
+        }
+    }
+
+    /// Empties the store unless its distances were measured by `model`, which measures from now on.
+    pub fn measure_by(&mut self, model: &DistanceModel) {
+        if self.model.as_ref() != Some(model) {
+            self.clear();
+            self.model = Some(model.clone());
+        }
+    }
+
+    pub fn invalidate(&mut self, hashes: &[String]) {
+        // Queued keys go too, or a re-inserted pair would be queued twice and evicted by its stale key
+        self.insertion_order.retain(|(from, to)| !hashes.contains(from) && !hashes.contains(to));
+        for hash in hashes {
+            if let Some(row) = self.entries.remove(hash) {
+                self.len -= row.len();
+            }
+        }
+        for row in self.entries.values_mut() {
+            for hash in hashes {
+                if row.remove(hash).is_some() {
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.insertion_order.clear();
+        self.len = 0;
+    }
+}
+
+/// Distance cache that survives between solves, keyed by location hash pairs.
+/// Locations whose coordinates change must be passed to `invalidate`. A solve measuring
+/// distances differently from the last one empties it.
+#[napi]
+pub struct DistanceCache {
+    pub(crate) store: SharedDistanceStore,
+}
+
+#[napi]
+impl DistanceCache {
+    #[napi(constructor)]
+    pub fn new(max_entries: Option<u32>) -> Self {
+        let max_entries = max_entries.map_or(DEFAULT_MAX_ENTRIES, |m| m as usize);
+        DistanceCache {
+            store: Arc::new(Mutex::new(DistanceStore::new(max_entries))),
+        }
+    }
+
+    /// Drops every cached distance from or to any of `hashes`.
+    #[napi]
+    pub fn invalidate(&self, hashes: Vec<String>) {
+        self.store.lock().unwrap().invalidate(&hashes);
+    }
+
+    #[napi]
+    pub fn clear(&self) {
+        self.store.lock().unwrap().clear();
+    }
+
+    #[napi(getter)]
+    pub fn size(&self) -> u32 {
+        self.store.lock().unwrap().len as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DistanceStore;
+
+    #[test]
+    fn reinserting_after_invalidate_queues_the_pair_once() {
+        let mut store = DistanceStore::new(2);
+        store.insert("a", "b", 1.0);
+        store.insert("b", "c", 2.0);
+        store.invalidate(&["a".to_string()]);
+        store.insert("a", "b", 3.0);
+        assert_eq!(store.insertion_order.len(), 2);
+
+        // The oldest live pair is evicted, not the one just re-inserted
+        store.insert("c", "d", 4.0);
+        assert_eq!(store.get("b", "c"), None);
+        assert_eq!(store.get("a", "b"), Some(3.0));
+        assert_eq!(store.get("c", "d"), Some(4.0));
+        assert_eq!(store.len, 2);
+    }
+}
@@ -0,0 +1,114 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use napi::Status;
+
+/// Why the solver rejected a call. The variant name is the `code` of the error JS catches, so
+/// callers can tell bad input from cancellation or an internal bug without parsing messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolverError {
+    /// Input rejected by a check without a code of its own
+    InvalidArg(String),
+    /// More orders than the search can represent
+    TooManyOrders(String),
+    /// Load factors or demands no vehicle could ever load
+    InvalidLoadFactor(String),
+    /// Order ids used by more than one order
+    DuplicateOrderId(Vec<u32>),
+    /// Vehicle ids used by more than one vehicle
+    DuplicateVehicleId(Vec<u32>),
+    /// Non-finite coordinates or a latitude beyond the poles
+    InvalidCoordinate(String),
+    /// A vehicle cannot serve the orders it was handed
+    Infeasible(String),
+    Cancelled,
+    /// A panic inside the solver, caught before it could abort the host process
+    Internal(String),
+}
+
+pub type Result<T> = std::result::Result<T, SolverError>;
+
+impl SolverError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SolverError::InvalidArg(_) => "InvalidArg",
+            SolverError::TooManyOrders(_) => "TooManyOrders",
+            SolverError::InvalidLoadFactor(_) => "InvalidLoadFactor",
+            SolverError::DuplicateOrderId(_) => "DuplicateOrderId",
+            SolverError::DuplicateVehicleId(_) => "DuplicateVehicleId",
+            SolverError::InvalidCoordinate(_) => "InvalidCoordinate",
+            SolverError::Infeasible(_) => "Infeasible",
+            SolverError::Cancelled => "Cancelled",
+            SolverError::Internal(_) => "Internal",
+        }
+    }
+}
+
+fn join(ids: &[u32]) -> String {
+    ids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::InvalidArg(msg)
+            | SolverError::TooManyOrders(msg)
+            | SolverError::InvalidLoadFactor(msg)
+            | SolverError::InvalidCoordinate(msg)
+            | SolverError::Infeasible(msg) => f.write_str(msg),
+            SolverError::DuplicateOrderId(ids) => write!(f, "Order ids used by more than one order: {}", join(ids)),
+            SolverError::DuplicateVehicleId(ids) => write!(f, "Vehicle ids used by more than one vehicle: {}", join(ids)),
+            SolverError::Cancelled => f.write_str("Solve was cancelled"),
+            SolverError::Internal(msg) => write!(f, "Solver panicked: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+/// Status of an error thrown to JS, which reads it as `err.code`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorCode(&'static str);
+
+impl AsRef<str> for ErrorCode {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl From<SolverError> for napi::Error<ErrorCode> {
+    fn from(err: SolverError) -> Self {
+        napi::Error::new(ErrorCode(err.code()), format!("{}: {}", err.code(), err))
+    }
+}
+
+// Promise rejections can only carry a napi `Status`, the code stays in the reason
+impl From<SolverError> for napi::Error {
+    fn from(err: SolverError) -> Self {
+        let status = match err {
+            SolverError::InvalidArg(_) => Status::InvalidArg,
+            SolverError::Cancelled => Status::Cancelled,
+            _ => Status::GenericFailure,
+        };
+        napi::Error::new(status, format!("{}: {}", err.code(), err))
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Runs `body`, reporting a panic as `SolverError::Internal` instead of unwinding into Node.
+pub fn catch_panic<T>(body: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|panic| Err(SolverError::Internal(panic_message(panic))))
+}
+
+/// Body of an exported function: panics are caught and the error's code is what JS sees as `err.code`.
+pub fn guarded<T>(body: impl FnOnce() -> Result<T>) -> napi::Result<T, ErrorCode> {
+    catch_panic(body).map_err(Into::into)
+}
@@ -0,0 +1,153 @@
+//! Seeded random problems for tests and benchmarks.
+
+use std::collections::HashSet;
+
+use crate::error::{Result, SolverError};
+use crate::heuristics::annealing::Rng;
+use crate::models::{BoundingBox, GeneratorOptions, Location, NumberRange, Order, Problem, Vehicle};
+
+const DEFAULT_SEED: u32 = 1;
+const DEFAULT_BBOX: BoundingBox = BoundingBox { min_latitude: 54.0, max_latitude: 56.0, min_longitude: 23.0, max_longitude: 26.0 };
+const DEFAULT_LOAD_FACTORS: NumberRange = NumberRange { min: 1.0, max: 4.0 };
+const DEFAULT_PRICES: NumberRange = NumberRange { min: 0.5, max: 1.5 };
+// Pickups of a cluster scatter around its centre by up to this share of the box on each axis
+const CLUSTER_SPREAD: f64 = 0.05;
+
+fn invalid<T>(message: String) -> Result<T> {
+    Err(SolverError::InvalidArg(format!("Invalid generator options: {}", message)))
+}
+
+/// Draws locations, never the same coordinates twice so no two stops coincide.
+struct Places {
+    rng: Rng,
+    bbox: BoundingBox,
+    seed: u32,
+    taken: HashSet<(u64, u64)>,
+}
+
+impl Places {
+    fn uniform(&mut self) -> (f64, f64) {
+        let b = &self.bbox;
+        (
+            b.min_latitude + self.rng.unit() * (b.max_latitude - b.min_latitude),
+            b.min_longitude + self.rng.unit() * (b.max_longitude - b.min_longitude),
+        )
+    }
+
+    /// Around `centre`, denser towards it, kept inside the box.
+    fn near(&mut self, centre: (f64, f64)) -> (f64, f64) {
+        let b = self.bbox;
+        // Triangular offset in [-1, 1] on each axis
+        let mut offset = || self.rng.unit() - self.rng.unit();
+        let lat = centre.0 + offset() * CLUSTER_SPREAD * (b.max_latitude - b.min_latitude);
+        let lon = centre.1 + offset() * CLUSTER_SPREAD * (b.max_longitude - b.min_longitude);
+        (lat.clamp(b.min_latitude, b.max_latitude), lon.clamp(b.min_longitude, b.max_longitude))
+    }
+
+    fn location(&mut self, prefix: &str, i: usize, mut draw: impl FnMut(&mut Self) -> (f64, f64)) -> Location {
+        loop {
+            let (latitude, longitude) = draw(self);
+            if self.taken.insert((latitude.to_bits(), longitude.to_bits())) {
+                return Location { hash: format!("{}-{}-{}", prefix, self.seed, i), latitude, longitude };
+            }
+        }
+    }
+}
+
+fn check_range(name: &str, range: &NumberRange, floor: f64) -> Result<()> {
+    if !range.min.is_finite() || !range.max.is_finite() || range.min < floor || range.max < range.min {
+        return invalid(format!("{} must be finite with {} <= min <= max, got {}..{}", name, floor, range.min, range.max));
+    }
+    Ok(())
+}
+
+/// A random problem of `n_orders` orders and `n_vehicles` vehicles inside `bbox`, the same for
+/// the same options. Load factors are whole numbers drawn from `load_factor_range`, prices per km
+/// are rounded to cents from `price_range`. With `clustering` the pickups gather around that many
+/// random centres, deliveries and vehicle starts are always spread over the whole box. No two
+/// locations share coordinates, and ids count up from 1.
+pub fn generate_problem(options: GeneratorOptions) -> Result<Problem> {
+    let bbox = options.bbox.unwrap_or(DEFAULT_BBOX);
+    let load_factors = options.load_factor_range.unwrap_or(DEFAULT_LOAD_FACTORS);
+    let prices = options.price_range.unwrap_or(DEFAULT_PRICES);
+    let seed = options.seed.unwrap_or(DEFAULT_SEED);
+
+    if options.n_vehicles == 0 {
+        return invalid("n_vehicles must be at least 1".to_string());
+    }
+    let in_bounds = |value: f64, limit: f64| value.is_finite() && value.abs() <= limit;
+    if !in_bounds(bbox.min_latitude, 90.0)
+        || !in_bounds(bbox.max_latitude, 90.0)
+        || !in_bounds(bbox.min_longitude, 180.0)
+        || !in_bounds(bbox.max_longitude, 180.0)
+        || bbox.min_latitude >= bbox.max_latitude
+        || bbox.min_longitude >= bbox.max_longitude
+    {
+        return invalid("bbox must span valid coordinates with each min below its max".to_string());
+    }
+    check_range("load_factor_range", &load_factors, 1.0)?;
+    check_range("price_range", &prices, 0.0)?;
+    let (min_load, max_load) = (load_factors.min.ceil(), load_factors.max.floor());
+    if min_load > max_load {
+        return invalid(format!("load_factor_range {}..{} holds no whole number", load_factors.min, load_factors.max));
+    }
+    if options.clustering == Some(0) {
+        return invalid("clustering must be at least 1".to_string());
+    }
+
+    let mut places = Places { rng: Rng(seed as u64), bbox, seed, taken: HashSet::new() };
+    let centres: Vec<(f64, f64)> = (0..options.clustering.unwrap_or(0)).map(|_| places.uniform()).collect();
+
+    let vehicles = (0..options.n_vehicles as usize)
+        .map(|i| {
+            let start_location = places.location("v", i, Places::uniform);
+            let price = prices.min + places.rng.unit() * (prices.max - prices.min);
+            Vehicle {
+                id: i as u32 + 1,
+                start_location,
+                price_km: ((price * 100.0).round() / 100.0).clamp(prices.min, prices.max),
+                price_km_empty: None,
+                stop_fee: None,
+                co2_per_km: None,
+                co2_per_km_empty: None,
+                max_trips: None,
+                allow_multi_trip: None,
+                speed_kmh: None,
+                min_route_price: None,
+                capacity: None,
+                end_location: None,
+                tags: None,
+                max_distance_km: None,
+                max_duration_min: None,
+                max_orders: None,
+                onboard_order_ids: None,
+            }
+        })
+        .collect();
+    let orders = (0..options.n_orders as usize)
+        .map(|i| {
+            let pickup_location = if centres.is_empty() {
+                places.location("p", i, Places::uniform)
+            } else {
+                let centre = centres[places.rng.below(centres.len())];
+                places.location("p", i, |places| places.near(centre))
+            };
+            let delivery_location = places.location("d", i, Places::uniform);
+            let steps = (max_load - min_load) as usize + 1;
+            Order {
+                id: i as u32 + 1,
+                pickup_location,
+                delivery_location,
+                load_factor: min_load + places.rng.below(steps) as f64,
+                demand: None,
+                pickup_window: None,
+                delivery_window: None,
+                pickup_service_min: None,
+                delivery_service_min: None,
+                required_tags: None,
+            }
+        })
+        .collect();
+
+    Ok(Problem { vehicles, orders, forbidden_legs: None, fixed_assignments: None, same_vehicle_groups: None, conflict_pairs: None })
+}
@@ -0,0 +1,242 @@
+use crate::error::{Result, SolverError};
+use crate::models::{AnnealingOptions, AnnealingResult, AnnealingStats, ObjectiveWeights, Problem};
+use crate::solver::evaluate::{evaluate_stops, RouteMetrics, Stop};
+use crate::solver::units::{distance_scale, problem_to_base, Rescale};
+use crate::solver::{distance_model, validate_ids, validate_load_factors, validate_vehicles};
+use super::greedy::{greedy_routes, solution_from_routes};
+
+const DEFAULT_ITERATIONS: u32 = 20_000;
+const DEFAULT_INITIAL_TEMPERATURE: f64 = 10.0;
+const DEFAULT_COOLING_RATE: f64 = 0.9995;
+const DEFAULT_SEED: u32 = 1;
+
+/// SplitMix64, small and deterministic across platforms.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, `n` must be positive.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct Weights {
+    distance: f64,
+    price: f64,
+    empty: f64,
+}
+
+impl Weights {
+    /// Weights per caller distance unit, applied to distances in solver km of which one is `scale`
+    /// caller units.
+    fn new(weights: Option<ObjectiveWeights>, scale: f64) -> Self {
+        match weights {
+            Some(w) => Weights {
+                distance: w.distance.unwrap_or(0.0) * scale,
+                price: w.price.unwrap_or(0.0),
+                empty: w.empty.unwrap_or(0.0) * scale,
+            },
+            None => Weights { distance: scale, price: 0.0, empty: 0.0 },
+        }
+    }
+
+    fn cost(&self, metrics: &RouteMetrics) -> f64 {
+        self.distance * metrics.dist + self.price * metrics.price + self.empty * metrics.empty
+    }
+}
+
+/// Plan being annealed: stops and cost of each vehicle's route.
+#[derive(Clone)]
+struct Plan {
+    routes: Vec<Vec<Stop>>,
+    costs: Vec<f64>,
+}
+
+impl Plan {
+    fn total(&self) -> f64 {
+        self.costs.iter().sum()
+    }
+}
+
+/// Removes both stops of `order_idx` from `stops`.
+fn without_order(stops: &[Stop], order_idx: usize) -> Vec<Stop> {
+    stops.iter().copied().filter(|s| s.order_idx != order_idx).collect()
+}
+
+/// Random pickup-before-delivery positions for one more order in a route of `len` stops.
+fn insert_randomly(rng: &mut Rng, stops: &mut Vec<Stop>, order_idx: usize) {
+    let pickup_at = rng.below(stops.len() + 1);
+    stops.insert(pickup_at, Stop { order_idx, pickup: true });
+    let delivery_at = pickup_at + 1 + rng.below(stops.len() - pickup_at);
+    stops.insert(delivery_at, Stop { order_idx, pickup: false });
+}
+
+/// Proposes a neighbouring plan by changing at most two routes, returned with their new stops.
+/// Moves keep pickups before deliveries and orders on board with their vehicle, capacity is
+/// checked by the caller.
+fn propose(rng: &mut Rng, plan: &Plan, onboard: &[bool]) -> Option<Vec<(usize, Vec<Stop>)>> {
+    let used: Vec<usize> = (0..plan.routes.len()).filter(|&v| !plan.routes[v].is_empty()).collect();
+    if used.is_empty() {
+        return None;
+    }
+    let pick_order = |rng: &mut Rng, v: usize| plan.routes[v][rng.below(plan.routes[v].len())].order_idx;
+
+    match rng.below(3) {
+        // Relocate an order to any vehicle, possibly its own at new positions
+        0 => {
+            let from = used[rng.below(used.len())];
+            let order_idx = pick_order(rng, from);
+            let to = rng.below(plan.routes.len());
+            if onboard[order_idx] && from != to {
+                return None;
+            }
+            if from == to {
+                let mut stops = without_order(&plan.routes[from], order_idx);
+                insert_randomly(rng, &mut stops, order_idx);
+                Some(vec![(from, stops)])
+            } else {
+                let mut target = plan.routes[to].clone();
+                insert_randomly(rng, &mut target, order_idx);
+                Some(vec![(from, without_order(&plan.routes[from], order_idx)), (to, target)])
+            }
+        }
+        // Swap two orders of different vehicles, each taking over the other's stop positions
+        1 => {
+            if used.len() < 2 {
+                return None;
+            }
+            let a = used[rng.below(used.len())];
+            let b = used[rng.below(used.len())];
+            if a == b {
+                return None;
+            }
+            let (order_a, order_b) = (pick_order(rng, a), pick_order(rng, b));
+            let swap = |stops: &[Stop], from: usize, to: usize| -> Vec<Stop> {
+                stops
+                    .iter()
+                    .map(|s| if s.order_idx == from { Stop { order_idx: to, pickup: s.pickup } } else { *s })
+                    .collect()
+            };
+            Some(vec![(a, swap(&plan.routes[a], order_a, order_b)), (b, swap(&plan.routes[b], order_b, order_a))])
+        }
+        // Move a single stop within its route
+        _ => {
+            let v = used[rng.below(used.len())];
+            let mut stops = plan.routes[v].clone();
+            let stop = stops.remove(rng.below(stops.len()));
+            let at = rng.below(stops.len() + 1);
+            stops.insert(at, stop);
+            let pickup_pos = stops.iter().position(|s| s.order_idx == stop.order_idx && s.pickup);
+            let delivery_pos = stops.iter().position(|s| s.order_idx == stop.order_idx && !s.pickup);
+            (pickup_pos < delivery_pos).then(|| vec![(v, stops)])
+        }
+    }
+}
+
+/// Improves the greedy plan by simulated annealing on a weighted cost, distance by default.
+///
+/// Each iteration relocates an order, swaps two orders between vehicles or moves one stop
+/// within its route. Candidates breaking capacity or pickup-before-delivery are discarded,
+/// worse ones are accepted with probability `exp(-increase / temperature)`. The best plan seen
+/// is returned, deterministically for a given seed.
+pub fn solve_simulated_annealing(problem: &Problem, options: AnnealingOptions) -> Result<AnnealingResult> {
+    validate_ids(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    let iterations = options.iterations.unwrap_or(DEFAULT_ITERATIONS);
+    let mut temperature = options.initial_temperature.unwrap_or(DEFAULT_INITIAL_TEMPERATURE);
+    let cooling_rate = options.cooling_rate.unwrap_or(DEFAULT_COOLING_RATE);
+    if !(cooling_rate > 0.0 && cooling_rate <= 1.0) {
+        return Err(SolverError::InvalidArg(format!("cooling_rate must be in (0, 1], got {}", cooling_rate)));
+    }
+    if temperature.is_nan() || temperature < 0.0 {
+        return Err(SolverError::InvalidArg(format!("initial_temperature must be non-negative, got {}", temperature)));
+    }
+    let model = distance_model(options.distance.as_ref())?;
+    let scale = distance_scale(options.distance.as_ref());
+    let problem = &problem_to_base(problem.clone(), scale);
+    let weights = Weights::new(options.weights, scale);
+    let mut rng = Rng(options.seed.unwrap_or(DEFAULT_SEED) as u64);
+
+    let route_cost = |v: usize, stops: &[Stop]| -> Option<f64> {
+        if stops.is_empty() {
+            return Some(0.0);
+        }
+        evaluate_stops(&model, &problem.vehicles[v], &problem.orders, stops).ok().map(|m| weights.cost(&m))
+    };
+
+    let onboard: Vec<bool> = problem
+        .orders
+        .iter()
+        .map(|o| problem.vehicles.iter().flat_map(|v| v.onboard_order_ids.iter().flatten()).any(|id| *id == o.id))
+        .collect();
+    let routes = greedy_routes(&problem.orders, &problem.vehicles, &model);
+    let costs = routes
+        .iter()
+        .enumerate()
+        .map(|(v, stops)| route_cost(v, stops).expect("greedy routes are feasible"))
+        .collect();
+    let mut current = Plan { routes, costs };
+    let mut current_cost = current.total();
+    let mut best = current.clone();
+    let mut best_cost = current_cost;
+
+    let mut stats = AnnealingStats {
+        iterations,
+        initial_cost: current_cost,
+        ..Default::default()
+    };
+
+    for iteration in 1..=iterations {
+        if let Some(changes) = propose(&mut rng, &current, &onboard) {
+            let new_costs: Option<Vec<f64>> = changes.iter().map(|(v, stops)| route_cost(*v, stops)).collect();
+            if let Some(new_costs) = new_costs {
+                stats.feasible_moves += 1;
+                let delta: f64 = changes
+                    .iter()
+                    .zip(&new_costs)
+                    .map(|((v, _), cost)| cost - current.costs[*v])
+                    .sum();
+
+                if delta <= 0.0 || (temperature > 0.0 && rng.unit() < (-delta / temperature).exp()) {
+                    stats.accepted_moves += 1;
+                    for ((v, stops), cost) in changes.into_iter().zip(new_costs) {
+                        current.routes[v] = stops;
+                        current.costs[v] = cost;
+                    }
+                    current_cost += delta;
+
+                    if current_cost < best_cost - 1e-9 {
+                        // Re-summed so rounding drift from the running delta never accumulates
+                        current_cost = current.total();
+                        best = current.clone();
+                        best_cost = current_cost;
+                        stats.best_iteration = iteration;
+                    }
+                }
+            }
+        }
+        temperature *= cooling_rate;
+    }
+
+    stats.final_temperature = temperature;
+    stats.best_cost = best_cost;
+
+    Ok(AnnealingResult {
+        solution: solution_from_routes(problem, &best.routes, &model).rescaled(scale),
+        stats,
+    })
+}
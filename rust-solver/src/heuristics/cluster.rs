@@ -0,0 +1,209 @@
+use crate::error::{Result, SolverError};
+use crate::models::{ClusterOptions, ClusteredSolution, Location, OrderCluster, Order, Problem};
+use crate::solver::evaluate::Stop;
+use crate::solver::marginal::{order_index, route_exactly, stops_from_route};
+use crate::solver::types::MAX_ROUTE_ORDERS;
+use crate::solver::units::{distance_scale, problem_to_base, Rescale};
+use crate::solver::{distance_model, validate_ids, validate_load_factors, validate_vehicles};
+use super::greedy::{greedy_routes, solution_from_routes};
+use super::lns::{with_order, Network};
+
+const MAX_ROUNDS: usize = 50;
+
+type Point = (f64, f64);
+
+fn point(location: &Location) -> Point {
+    (location.latitude, location.longitude)
+}
+
+/// Squared distance between coordinates read as a plane, all the clustering needs.
+fn dist2(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+/// Index of the centroid nearest `at` among those `open` allows, the lower index on ties.
+fn nearest(centroids: &[Point], at: Point, open: impl Fn(usize) -> bool) -> Option<usize> {
+    (0..centroids.len()).filter(|&c| open(c)).min_by(|&a, &b| dist2(centroids[a], at).total_cmp(&dist2(centroids[b], at)))
+}
+
+/// Capacity-aware k-means on the pickups: at most `ceil(n / k)` orders per cluster, orders that
+/// would lose the most by missing their nearest centroid placed first. Centroids start from the
+/// first pickup and then the pickups farthest from all chosen so far. Returns the cluster of each
+/// pickup and the centroids.
+fn k_means(pickups: &[Point], k: usize) -> (Vec<usize>, Vec<Point>) {
+    let mut centroids: Vec<Point> = Vec::with_capacity(k);
+    if let Some(&first) = pickups.first() {
+        centroids.push(first);
+    }
+    while centroids.len() < k.min(pickups.len()) {
+        let farthest = (0..pickups.len())
+            .max_by(|&a, &b| {
+                let gap = |p: usize| centroids.iter().map(|&c| dist2(c, pickups[p])).fold(f64::INFINITY, f64::min);
+                gap(a).total_cmp(&gap(b)).then(b.cmp(&a))
+            })
+            .unwrap_or(0);
+        centroids.push(pickups[farthest]);
+    }
+    let cap = pickups.len().div_ceil(centroids.len().max(1));
+
+    let mut members = vec![usize::MAX; pickups.len()];
+    for _ in 0..MAX_ROUNDS {
+        // Regret of missing the nearest centroid for the second nearest
+        let mut queue: Vec<(f64, usize)> = pickups
+            .iter()
+            .enumerate()
+            .map(|(p, &at)| {
+                let mut gaps: Vec<f64> = centroids.iter().map(|&c| dist2(c, at)).collect();
+                gaps.sort_by(f64::total_cmp);
+                (gaps.get(1).copied().unwrap_or(0.0) - gaps[0], p)
+            })
+            .collect();
+        queue.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let mut sizes = vec![0; centroids.len()];
+        let mut next = vec![0; pickups.len()];
+        for (_, p) in queue {
+            let c = nearest(&centroids, pickups[p], |c| sizes[c] < cap).expect("clusters hold every order");
+            sizes[c] += 1;
+            next[p] = c;
+        }
+        if next == members {
+            break;
+        }
+        members = next;
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            let (sum, count) = pickups
+                .iter()
+                .zip(&members)
+                .filter(|&(_, &m)| m == c)
+                .fold(((0.0, 0.0), 0.0), |((lat, lon), n), (p, _)| ((lat + p.0, lon + p.1), n + 1.0));
+            // A cluster left empty keeps its centroid
+            if count > 0.0 {
+                *centroid = (sum.0 / count, sum.1 / count);
+            }
+        }
+    }
+    (members, centroids)
+}
+
+/// Plans `problem` cluster first, route second, for instances far beyond the exact search.
+///
+/// Orders are grouped by capacity-aware k-means on their pickup coordinates, read as a plane.
+/// Largest first, each cluster goes to the unused vehicle able to serve most of it, the nearest to
+/// its centroid on ties. Clusters of up to 8 orders are routed exactly by `solve_tsp`, larger ones
+/// or ones the vehicle cannot serve whole greedily. Orders on board stay with their vehicle. Orders
+/// left out by their cluster's route are inserted into the cheapest place on the nearest other
+/// route able to take them, and only stay unserved when none can.
+pub fn solve_clustered(problem: &Problem, options: ClusterOptions) -> Result<ClusteredSolution> {
+    validate_ids(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    let (orders, vehicles) = (&problem.orders, &problem.vehicles);
+    let k = options.clusters.unwrap_or(vehicles.len() as u32) as usize;
+    if k == 0 || k > vehicles.len() {
+        return Err(SolverError::InvalidArg(format!(
+            "clusters must be between 1 and the number of vehicles ({}), got {}",
+            vehicles.len(),
+            k
+        )));
+    }
+    let model = distance_model(options.distance.as_ref())?;
+    let scale = distance_scale(options.distance.as_ref());
+    let problem = &problem_to_base(problem.clone(), scale);
+    let vehicles = &problem.vehicles;
+    let order_idx = order_index(orders);
+    let on_board = |v_idx: usize| -> Vec<usize> {
+        vehicles[v_idx].onboard_order_ids.iter().flatten().filter_map(|id| order_idx.get(id).copied()).collect()
+    };
+
+    // Orders on board are not clustered, their vehicle delivers them whatever it is given
+    let carried: Vec<usize> = (0..vehicles.len()).flat_map(on_board).collect();
+    let free: Vec<usize> = (0..orders.len()).filter(|o_idx| !carried.contains(o_idx)).collect();
+    let pickups: Vec<Point> = free.iter().map(|&o_idx| point(&orders[o_idx].pickup_location)).collect();
+    let (members, centroids) = k_means(&pickups, k);
+    let mut clusters: Vec<Vec<usize>> = vec![Vec::new(); centroids.len()];
+    for (&o_idx, &c) in free.iter().zip(&members) {
+        clusters[c].push(o_idx);
+    }
+
+    // Largest cluster picks its vehicle first
+    let mut by_size: Vec<usize> = (0..clusters.len()).collect();
+    by_size.sort_by_key(|&c| std::cmp::Reverse(clusters[c].len()));
+    let mut vehicle_of = vec![0; clusters.len()];
+    let mut taken = vec![false; vehicles.len()];
+    for c in by_size {
+        let serves = |v_idx: usize| clusters[c].iter().filter(|&&o_idx| orders[o_idx].servable_by(&vehicles[v_idx])).count();
+        let start = |v_idx: usize| dist2(point(&vehicles[v_idx].start_location), centroids[c]);
+        let v_idx = (0..vehicles.len())
+            .filter(|&v_idx| !taken[v_idx])
+            .min_by(|&a, &b| serves(b).cmp(&serves(a)).then(start(a).total_cmp(&start(b))))
+            .expect("no more clusters than vehicles");
+        taken[v_idx] = true;
+        vehicle_of[c] = v_idx;
+    }
+
+    let mut routes: Vec<Vec<Stop>> = vec![Vec::new(); vehicles.len()];
+    let mut exact = vec![false; clusters.len()];
+    let cluster_of = |v_idx: usize| vehicle_of.iter().position(|&assigned| assigned == v_idx);
+    for (v_idx, vehicle) in vehicles.iter().enumerate() {
+        let mut group = on_board(v_idx);
+        if let Some(c) = cluster_of(v_idx) {
+            group.extend(&clusters[c]);
+        }
+        if group.is_empty() {
+            continue;
+        }
+        let subset: Vec<Order> = group.iter().map(|&o_idx| orders[o_idx].clone()).collect();
+        if group.len() <= MAX_ROUTE_ORDERS {
+            // Routes with reloads are left to the greedy router
+            if let Some(stops) = route_exactly(vehicle, subset.clone(), &model).and_then(|route| stops_from_route(&route, &order_idx).ok()) {
+                routes[v_idx] = stops;
+                if let Some(c) = cluster_of(v_idx) {
+                    exact[c] = true;
+                }
+                continue;
+            }
+        }
+        let greedy = greedy_routes(&subset, std::slice::from_ref(vehicle), &model);
+        routes[v_idx] = greedy[0].iter().map(|s| Stop { order_idx: group[s.order_idx], pickup: s.pickup }).collect();
+    }
+
+    // Spill what the clusters could not route to the nearest other routes with room, vehicles
+    // without a cluster judged by their start
+    let network = Network::new(problem, model);
+    let mut home = vec![usize::MAX; orders.len()];
+    for (c, members) in clusters.iter().enumerate() {
+        for &o_idx in members {
+            home[o_idx] = vehicle_of[c];
+        }
+    }
+    let served: Vec<bool> = (0..orders.len()).map(|o_idx| routes.iter().flatten().any(|s| s.order_idx == o_idx)).collect();
+    let mut spilled_order_ids = Vec::new();
+    for &o_idx in free.iter().filter(|&&o_idx| !served[o_idx]) {
+        let at = point(&orders[o_idx].pickup_location);
+        let anchor = |v_idx: usize| cluster_of(v_idx).map_or_else(|| point(&vehicles[v_idx].start_location), |c| centroids[c]);
+        let mut nearby: Vec<usize> = (0..vehicles.len()).filter(|&v_idx| v_idx != home[o_idx]).collect();
+        nearby.sort_by(|&a, &b| dist2(anchor(a), at).total_cmp(&dist2(anchor(b), at)).then(a.cmp(&b)));
+        let fit = nearby.into_iter().find_map(|v_idx| network.best_insertion(v_idx, &routes[v_idx], o_idx).map(|(_, i, j)| (v_idx, i, j)));
+        if let Some((v_idx, i, j)) = fit {
+            routes[v_idx] = with_order(&routes[v_idx], o_idx, i, j);
+            spilled_order_ids.push(orders[o_idx].id);
+        }
+    }
+
+    let clusters = clusters
+        .iter()
+        .zip(&vehicle_of)
+        .zip(exact)
+        .map(|((members, &v_idx), exact)| OrderCluster {
+            vehicle_id: vehicles[v_idx].id,
+            order_ids: members.iter().map(|&o_idx| orders[o_idx].id).collect(),
+            exact,
+        })
+        .collect();
+    Ok(ClusteredSolution {
+        solution: solution_from_routes(problem, &routes, &network.model).rescaled(scale),
+        clusters,
+        spilled_order_ids,
+    })
+}
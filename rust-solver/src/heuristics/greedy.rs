@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::models::{AlgorithmSolution, Order, Problem, ProblemSolution, Vehicle};
+use crate::solver::evaluate::{evaluate_stops, route_from_stops, Stop};
+use crate::solver::{max_route_distance, route_spread, validate_ids, validate_load_factors, validate_vehicles};
+use crate::utils::DistanceModel;
+use super::lns::{with_order, Network};
+
+/// Builds one plan by repeatedly inserting the order, vehicle and stop positions with the
+/// smallest added distance, for problems far beyond the exact search.
+///
+/// Only capacity, time windows, route limits and `max_orders` are respected. The plan is reported under every criterion with exact metrics
+/// for its routes, `optimal` is always false. Distances are measured by `model`.
+pub fn solve_greedy(problem: &Problem, model: &DistanceModel) -> Result<AlgorithmSolution> {
+    validate_ids(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    Ok(greedy_solution(problem, model))
+}
+
+/// `solve_greedy` without the validation, with distances measured by `model`.
+pub(crate) fn greedy_solution(problem: &Problem, model: &DistanceModel) -> AlgorithmSolution {
+    let routes = greedy_routes(&problem.orders, &problem.vehicles, model);
+    single_plan(solution_from_routes(problem, &routes, model))
+}
+
+/// `solution` reported under every criterion, not proven optimal, feasible when it serves every order.
+pub(crate) fn single_plan(solution: ProblemSolution) -> AlgorithmSolution {
+    AlgorithmSolution {
+        best_distance_solution: solution.clone(),
+        best_price_solution: solution.clone(),
+        best_empty_solution: solution.clone(),
+        best_makespan_solution: solution.clone(),
+        best_duration_solution: solution.clone(),
+        feasible: solution.unassigned_order_ids.is_empty(),
+        best_fleet_solution: solution,
+        optimal: false,
+        ..Default::default()
+    }
+}
+
+/// Stops of each vehicle in the greedy plan. Orders stay unserved only when no vehicle has room
+/// for them or can reach them within their time windows and route limits. Orders on board are
+/// delivered by their vehicle in the order listed, the rest are inserted around them. Distances
+/// are measured by `model`.
+pub(crate) fn greedy_routes(orders: &[Order], vehicles: &[Vehicle], model: &DistanceModel) -> Vec<Vec<Stop>> {
+    let network = Network::of(orders, vehicles, model.clone());
+    let mut routes = network.onboard_routes();
+    let mut pending: Vec<usize> = (0..orders.len()).filter(|&o_idx| !routes.iter().flatten().any(|s| s.order_idx == o_idx)).collect();
+
+    while !pending.is_empty() {
+        // (added distance, order, vehicle, pickup position, delivery position)
+        let mut best: Option<(f64, usize, usize, usize, usize)> = None;
+        for (v_idx, stops) in routes.iter().enumerate() {
+            for &o_idx in &pending {
+                let bound = best.map_or(f64::INFINITY, |(cheapest, ..)| cheapest);
+                if let Some((delta, i, j)) = network.insertion_below(v_idx, stops, o_idx, bound) {
+                    best = Some((delta, o_idx, v_idx, i, j));
+                }
+            }
+        }
+
+        // Without vehicles, or when no pending order fits anywhere within capacity and its windows
+        let Some((_, o_idx, v_idx, i, j)) = best else {
+            break;
+        };
+        routes[v_idx] = with_order(&routes[v_idx], o_idx, i, j);
+        pending.retain(|&pending_idx| pending_idx != o_idx);
+    }
+
+    routes
+}
+
+/// Exact metrics of already feasible stop sequences, one per vehicle.
+pub(crate) fn solution_from_routes(problem: &Problem, routes: &[Vec<Stop>], model: &DistanceModel) -> ProblemSolution {
+    let orders = &problem.orders;
+    let mut solution = ProblemSolution {
+        routes: HashMap::new(),
+        ..Default::default()
+    };
+    for (vehicle, stops) in problem.vehicles.iter().zip(routes) {
+        if stops.is_empty() {
+            continue;
+        }
+        let metrics = evaluate_stops(model, vehicle, orders, stops).expect("routes are built feasible");
+        let route = route_from_stops(model, vehicle, orders, stops, &metrics);
+        solution.total_distance += route.total_distance;
+        solution.empty_distance += route.empty_distance;
+        solution.total_price += route.total_price;
+        solution.total_co2 += route.total_co2;
+        solution.total_duration += route.total_duration_min;
+        solution.insert_route(vehicle.id, route);
+    }
+    solution.route_spread = route_spread(&solution);
+    solution.max_route_distance = max_route_distance(&solution);
+    let served: Vec<bool> = (0..orders.len()).map(|o_idx| routes.iter().flatten().any(|s| s.order_idx == o_idx)).collect();
+    solution.unassigned_order_ids = orders.iter().zip(served).filter(|(_, served)| !served).map(|(o, _)| o.id).collect();
+    solution
+}
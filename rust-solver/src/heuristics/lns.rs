@@ -0,0 +1,379 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, SolverError};
+use crate::models::{LnsOptions, LnsResult, Order, Problem, ProblemSolution, Vehicle};
+use crate::solver::evaluate::{evaluate_stops, Stop};
+use crate::solver::marginal::{order_index, stops_from_route};
+use crate::solver::matrix::DistanceMatrices;
+use crate::solver::units::{distance_scale, problem_to_base, Rescale};
+use crate::solver::{distance_model, validate_ids, validate_load_factors, validate_vehicles};
+use crate::utils::{prepared_distance, DistanceModel};
+use super::annealing::Rng;
+use super::greedy::solution_from_routes;
+
+const DEFAULT_ITERATIONS: u32 = 1_000;
+const DEFAULT_SEED: u32 = 1;
+// Share of the served orders one round removes, drawn uniformly in between
+const MIN_DESTROY_SHARE: f64 = 0.1;
+const MAX_DESTROY_SHARE: f64 = 0.3;
+// Same tolerance as the capacity check in `solve_tsp`
+const CAPACITY: f64 = 1.000001;
+
+/// Orders left unserved, then total distance. Fewer unserved orders always win.
+pub(super) type PlanCost = (usize, f64);
+
+/// Everything the moves are priced with, measured once up front.
+pub(super) struct Network<'a> {
+    orders: &'a [Order],
+    vehicles: &'a [Vehicle],
+    pub(super) model: DistanceModel,
+    matrices: DistanceMatrices,
+    // vehicles x nodes, start to each pickup and delivery
+    start_dist: Vec<Vec<f64>>,
+    // vehicles x orders, delivery to end location, 0 for vehicles that end at their last delivery
+    end_dist: Vec<Vec<f64>>,
+    // vehicles x orders, share of the vehicle's capacity. Infinite where the vehicle lacks a
+    // required tag, so the order never fits it
+    loads: Vec<Vec<f64>>,
+    onboard: Vec<Vec<usize>>,
+    // Windows and route limits depend on the whole route, insertions are then replayed exactly
+    replay: bool,
+}
+
+impl<'a> Network<'a> {
+    pub(super) fn new(problem: &'a Problem, model: DistanceModel) -> Self {
+        Self::of(&problem.orders, &problem.vehicles, model)
+    }
+
+    /// Network of `orders` served by `vehicles` alone.
+    pub(super) fn of(orders: &'a [Order], vehicles: &'a [Vehicle], model: DistanceModel) -> Self {
+        let prepare = |location| model.prepare(location);
+        let nodes: Vec<_> = orders
+            .iter()
+            .flat_map(|o| [prepare(&o.pickup_location), prepare(&o.delivery_location)])
+            .collect();
+        let start_dist: Vec<Vec<f64>> = vehicles
+            .iter()
+            .map(|v| {
+                let start = prepare(&v.start_location);
+                nodes.iter().map(|node| prepared_distance(&start, node)).collect()
+            })
+            .collect();
+        let end_dist: Vec<Vec<f64>> = vehicles
+            .iter()
+            .map(|v| match &v.end_location {
+                Some(end) => {
+                    let end = prepare(end);
+                    (0..orders.len()).map(|o_idx| prepared_distance(&nodes[2 * o_idx + 1], &end)).collect()
+                }
+                None => vec![0.0; orders.len()],
+            })
+            .collect();
+        let loads: Vec<Vec<f64>> = vehicles
+            .iter()
+            .map(|v| orders.iter().map(|o| if o.servable_by(v) { o.load_on(v) } else { f64::INFINITY }).collect())
+            .collect();
+        let onboard: Vec<Vec<usize>> = vehicles
+            .iter()
+            .map(|v| v.onboard_order_ids.iter().flatten().filter_map(|id| orders.iter().position(|o| o.id == *id)).collect())
+            .collect();
+        let has_time_windows = orders.iter().any(|o| o.pickup_window.is_some() || o.delivery_window.is_some());
+
+        Network {
+            orders,
+            vehicles,
+            matrices: DistanceMatrices::build(orders, None, &model),
+            model,
+            start_dist,
+            end_dist,
+            loads,
+            onboard,
+            replay: has_time_windows || vehicles.iter().any(|v| v.has_route_limits()),
+        }
+    }
+
+    #[inline(always)]
+    fn dist(&self, from: usize, to: usize) -> f64 {
+        self.matrices.dist_mat[from * self.matrices.num_nodes + to]
+    }
+
+    /// Distance of driving `stops` with vehicle `v_idx`, on to its end location if it has one.
+    pub(super) fn route_distance(&self, v_idx: usize, stops: &[Stop]) -> f64 {
+        let mut total = 0.0;
+        let mut prev = None;
+        for stop in stops {
+            let to = node(stop);
+            total += match prev {
+                None => self.start_dist[v_idx][to],
+                Some(from) => self.dist(from, to),
+            };
+            prev = Some(to);
+        }
+        total + stops.last().map_or(0.0, |last| self.end_dist[v_idx][last.order_idx])
+    }
+
+    pub(super) fn cost(&self, routes: &[Vec<Stop>]) -> PlanCost {
+        let served = routes.iter().flatten().filter(|s| !s.pickup).count();
+        let distance = routes.iter().enumerate().map(|(v_idx, stops)| self.route_distance(v_idx, stops)).sum();
+        (self.orders.len() - served, distance)
+    }
+
+    /// Stops of every vehicle in `solution`, vehicles without a route keeping the orders on board.
+    pub(super) fn routes_of(&self, solution: &ProblemSolution) -> Result<Vec<Vec<Stop>>> {
+        let (orders, vehicles) = (self.orders, self.vehicles);
+        let order_idx = order_index(orders);
+        let mut routes: Vec<Vec<Stop>> = self
+            .onboard
+            .iter()
+            .map(|on_board| on_board.iter().map(|&order_idx| Stop { order_idx, pickup: false }).collect())
+            .collect();
+        let mut served = vec![false; orders.len()];
+
+        // By vehicle id, so the first problem reported does not depend on map order
+        let mut given: Vec<_> = solution.routes.values().filter(|route| !route.stops.is_empty()).collect();
+        given.sort_by_key(|route| route.vehicle_id);
+        for route in given {
+            let v_idx = vehicles
+                .iter()
+                .position(|v| v.id == route.vehicle_id)
+                .ok_or_else(|| SolverError::InvalidArg(format!("Unknown vehicle id {}", route.vehicle_id)))?;
+            let stops = stops_from_route(route, &order_idx)?;
+            for stop in stops.iter().filter(|s| !s.pickup) {
+                if std::mem::replace(&mut served[stop.order_idx], true) {
+                    return Err(SolverError::InvalidArg(format!("Order {} is served more than once", orders[stop.order_idx].id)));
+                }
+            }
+            if !self.feasible(v_idx, &stops) {
+                return Err(SolverError::Infeasible(format!("Route of vehicle {} breaks the vehicle's constraints", route.vehicle_id)));
+            }
+            routes[v_idx] = stops;
+        }
+        Ok(routes)
+    }
+
+    /// Whether vehicle `v_idx` may drive `stops` at all.
+    pub(super) fn feasible(&self, v_idx: usize, stops: &[Stop]) -> bool {
+        evaluate_stops(&self.model, &self.vehicles[v_idx], self.orders, stops).is_ok()
+    }
+
+    /// Cheapest feasible positions for `o_idx` in the stops of vehicle `v_idx`, as (added
+    /// distance, pickup position, delivery position) in the current route.
+    pub(super) fn best_insertion(&self, v_idx: usize, stops: &[Stop], o_idx: usize) -> Option<(f64, usize, usize)> {
+        self.insertion_below(v_idx, stops, o_idx, f64::INFINITY)
+    }
+
+    /// `best_insertion` among the positions adding less than `bound`, so routes with windows or
+    /// limits are only replayed for insertions that could still win.
+    pub(super) fn insertion_below(&self, v_idx: usize, stops: &[Stop], o_idx: usize, bound: f64) -> Option<(f64, usize, usize)> {
+        let vehicle = &self.vehicles[v_idx];
+        if !vehicle.takes_orders(stops.iter().filter(|s| !s.pickup).count() as u32 + 1) {
+            return None;
+        }
+        let loads = &self.loads[v_idx];
+        let load = loads[o_idx];
+        let initial_load: f64 = self.onboard[v_idx].iter().map(|&on_board| loads[on_board]).sum();
+        // Load on board after each stop
+        let load_after: Vec<f64> = stops
+            .iter()
+            .scan(initial_load, |on_board, s| {
+                *on_board += if s.pickup { loads[s.order_idx] } else { -loads[s.order_idx] };
+                Some(*on_board)
+            })
+            .collect();
+        let load_before = |k: usize| if k == 0 { initial_load } else { load_after[k - 1] };
+        // Distance into the stop at position k from whatever precedes it
+        let leg_into = |k: usize, to: usize| if k == 0 { self.start_dist[v_idx][to] } else { self.dist(node(&stops[k - 1]), to) };
+        let end_leg = stops.last().map_or(0.0, |last| self.end_dist[v_idx][last.order_idx]);
+        let (pickup, delivery) = (2 * o_idx, 2 * o_idx + 1);
+
+        let mut best: Option<(f64, usize, usize)> = None;
+        for i in 0..=stops.len() {
+            if load_before(i) + load > CAPACITY {
+                continue;
+            }
+            let old_into_i = if i < stops.len() { leg_into(i, node(&stops[i])) } else { 0.0 };
+
+            for j in i..=stops.len() {
+                // The order is on board across stops i..j
+                if j > i && load_after[j - 1] + load > CAPACITY {
+                    break;
+                }
+                let delta = if i == j {
+                    let onward = if j < stops.len() { self.dist(delivery, node(&stops[j])) } else { self.end_dist[v_idx][o_idx] - end_leg };
+                    leg_into(i, pickup) + self.dist(pickup, delivery) + onward - old_into_i
+                } else {
+                    let before_delivery = node(&stops[j - 1]);
+                    let (onward, replaced) = if j < stops.len() {
+                        let next = node(&stops[j]);
+                        (self.dist(delivery, next), self.dist(before_delivery, next))
+                    } else {
+                        (self.end_dist[v_idx][o_idx], end_leg)
+                    };
+                    leg_into(i, pickup) + self.dist(pickup, node(&stops[i])) - old_into_i + self.dist(before_delivery, delivery) + onward
+                        - replaced
+                };
+                if delta >= best.map_or(bound, |(cheapest, _, _)| cheapest) {
+                    continue;
+                }
+                if self.replay && !self.feasible(v_idx, &with_order(stops, o_idx, i, j)) {
+                    continue;
+                }
+                best = Some((delta, i, j));
+            }
+        }
+        best
+    }
+
+    /// Deliveries of the orders on board of each vehicle, in the order listed. Left empty where
+    /// the vehicle cannot make them within windows and limits, no other vehicle could take them over.
+    pub(super) fn onboard_routes(&self) -> Vec<Vec<Stop>> {
+        self.onboard
+            .iter()
+            .enumerate()
+            .map(|(v_idx, on_board)| {
+                let stops: Vec<Stop> = on_board.iter().map(|&order_idx| Stop { order_idx, pickup: false }).collect();
+                if self.feasible(v_idx, &stops) { stops } else { Vec::new() }
+            })
+            .collect()
+    }
+
+    /// Removes 10 to 30% of the orders picked up on the routes, either at random or those whose
+    /// removal saves the most distance. Orders on board are never removed, they have no pickup.
+    fn destroy(&self, rng: &mut Rng, routes: &mut [Vec<Stop>]) {
+        let removable: Vec<(usize, usize)> = routes
+            .iter()
+            .enumerate()
+            .flat_map(|(v_idx, stops)| stops.iter().filter(|s| s.pickup).map(move |s| (v_idx, s.order_idx)))
+            .collect();
+        if removable.is_empty() {
+            return;
+        }
+        let share = MIN_DESTROY_SHARE + (MAX_DESTROY_SHARE - MIN_DESTROY_SHARE) * rng.unit();
+        let count = ((removable.len() as f64 * share).round() as usize).clamp(1, removable.len());
+
+        let removed: Vec<usize> = if rng.below(2) == 0 {
+            // Partial Fisher-Yates shuffle
+            let mut pool: Vec<usize> = removable.iter().map(|&(_, o_idx)| o_idx).collect();
+            for k in 0..count {
+                let pick = k + rng.below(pool.len() - k);
+                pool.swap(k, pick);
+            }
+            pool.truncate(count);
+            pool
+        } else {
+            let mut savings: Vec<(f64, usize)> = removable
+                .iter()
+                .map(|&(v_idx, o_idx)| {
+                    let stops = &routes[v_idx];
+                    let without: Vec<Stop> = stops.iter().copied().filter(|s| s.order_idx != o_idx).collect();
+                    (self.route_distance(v_idx, stops) - self.route_distance(v_idx, &without), o_idx)
+                })
+                .collect();
+            savings.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            savings.into_iter().take(count).map(|(_, o_idx)| o_idx).collect()
+        };
+        for stops in routes.iter_mut() {
+            stops.retain(|s| !removed.contains(&s.order_idx));
+        }
+    }
+
+    /// Regret insertion of every unserved order: the order losing the most if it missed its
+    /// cheapest vehicle and had to take one of its next `regret_k - 1` cheapest goes first, orders
+    /// fewer than `regret_k` vehicles can take before all others. Orders fitting nowhere stay unserved.
+    pub(super) fn repair(&self, routes: &mut [Vec<Stop>], regret_k: usize) {
+        let mut pending: Vec<usize> =
+            (0..self.orders.len()).filter(|&o_idx| !routes.iter().flatten().any(|s| s.order_idx == o_idx)).collect();
+
+        while !pending.is_empty() {
+            // (regret, added distance, order, vehicle, pickup position, delivery position)
+            let mut chosen: Option<(f64, f64, usize, usize, usize, usize)> = None;
+            for &o_idx in &pending {
+                // Cheapest insertion per vehicle, cheapest first, lower vehicle index on ties
+                let mut options: Vec<(f64, usize, usize, usize)> = routes
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(v_idx, stops)| self.best_insertion(v_idx, stops, o_idx).map(|(delta, i, j)| (delta, v_idx, i, j)))
+                    .collect();
+                options.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                let Some(&(delta, v_idx, i, j)) = options.first() else {
+                    continue;
+                };
+                let regret = if options.len() < regret_k {
+                    f64::INFINITY
+                } else {
+                    options[1..regret_k].iter().map(|&(other, ..)| other - delta).sum()
+                };
+                if chosen.is_none_or(|(most, cheapest, ..)| regret > most || (regret == most && delta < cheapest)) {
+                    chosen = Some((regret, delta, o_idx, v_idx, i, j));
+                }
+            }
+
+            let Some((_, _, o_idx, v_idx, i, j)) = chosen else {
+                break;
+            };
+            routes[v_idx] = with_order(&routes[v_idx], o_idx, i, j);
+            pending.retain(|&pending_idx| pending_idx != o_idx);
+        }
+    }
+}
+
+/// Matrix node of a stop: order i's pickup is node 2i, its delivery 2i + 1.
+#[inline(always)]
+fn node(stop: &Stop) -> usize {
+    2 * stop.order_idx + usize::from(!stop.pickup)
+}
+
+/// `stops` with the pickup of `o_idx` before stop `pickup_at` and its delivery before stop `delivery_at`.
+pub(super) fn with_order(stops: &[Stop], o_idx: usize, pickup_at: usize, delivery_at: usize) -> Vec<Stop> {
+    let mut candidate = stops.to_vec();
+    candidate.insert(delivery_at, Stop { order_idx: o_idx, pickup: false });
+    candidate.insert(pickup_at, Stop { order_idx: o_idx, pickup: true });
+    candidate
+}
+
+fn better(a: PlanCost, b: PlanCost) -> bool {
+    a.0 < b.0 || (a.0 == b.0 && a.1 < b.1 - 1e-9)
+}
+
+/// Improves `solution` by large neighbourhood search on total distance.
+///
+/// Each round removes 10 to 30% of the orders, at random or those costing the most, and inserts
+/// them and any order left unserved again by regret insertion. A repaired plan serving more orders,
+/// or as many over less distance, replaces the current one. Capacity, pickups before deliveries,
+/// tags, windows, route limits and orders on board are kept throughout. Deterministic for a given
+/// seed unless `time_limit_ms` cuts the rounds short.
+pub fn improve_solution(problem: &Problem, solution: &ProblemSolution, options: LnsOptions) -> Result<LnsResult> {
+    validate_ids(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    let iterations = options.iterations.unwrap_or(DEFAULT_ITERATIONS);
+    let mut rng = Rng(options.seed.unwrap_or(DEFAULT_SEED) as u64);
+    let scale = distance_scale(options.distance.as_ref());
+    let problem = &problem_to_base(problem.clone(), scale);
+    let network = Network::new(problem, distance_model(options.distance.as_ref())?);
+
+    let mut current = network.routes_of(solution)?;
+    let mut current_cost = network.cost(&current);
+    let deadline = options.time_limit_ms.map(|limit| Instant::now() + Duration::from_millis(limit as u64));
+    let mut accepted_moves = 0;
+    for _ in 0..iterations {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        let mut candidate = current.clone();
+        network.destroy(&mut rng, &mut candidate);
+        network.repair(&mut candidate, 2);
+        let cost = network.cost(&candidate);
+        if better(cost, current_cost) {
+            current = candidate;
+            current_cost = cost;
+            accepted_moves += 1;
+        }
+    }
+
+    Ok(LnsResult {
+        solution: solution_from_routes(problem, &current, &network.model),
+        accepted_moves,
+    }
+    .rescaled(scale))
+}
@@ -0,0 +1,181 @@
+use crate::error::Result;
+use crate::models::{AppliedMove, LocalSearchMove, LocalSearchOptions, LocalSearchResult, Problem, ProblemSolution};
+use crate::solver::evaluate::Stop;
+use crate::solver::units::{distance_scale, problem_to_base, Rescale};
+use crate::solver::{distance_model, validate_ids, validate_load_factors, validate_vehicles};
+use super::greedy::solution_from_routes;
+use super::lns::{with_order, Network};
+
+const ALL_MOVES: [LocalSearchMove; 3] = [LocalSearchMove::Relocate, LocalSearchMove::Swap, LocalSearchMove::TwoOptStar];
+// A move has to save more than this to count, so rounding noise cannot cycle
+const MIN_SAVING: f64 = 1e-9;
+
+/// A move found improving: the new stops of two vehicles and what it changes.
+struct Found {
+    from: usize,
+    to: usize,
+    from_stops: Vec<Stop>,
+    to_stops: Vec<Stop>,
+    // Orders changing vehicle, those leaving `from` first
+    moved: Vec<usize>,
+    saved: f64,
+}
+
+/// `stops` without either stop of `o_idx`.
+fn without(stops: &[Stop], o_idx: usize) -> Vec<Stop> {
+    stops.iter().copied().filter(|s| s.order_idx != o_idx).collect()
+}
+
+/// Orders picked up on `stops`, in route order. Orders on board have no pickup and stay put.
+fn picked_up(stops: &[Stop]) -> Vec<usize> {
+    stops.iter().filter(|s| s.pickup).map(|s| s.order_idx).collect()
+}
+
+/// Positions `stops` may be cut at into a head and a tail another vehicle can drive: no order has
+/// a stop on both sides and every delivery of an order on board stays in the head.
+fn cuts(stops: &[Stop]) -> Vec<usize> {
+    let picked = |o_idx: usize| stops.iter().any(|s| s.pickup && s.order_idx == o_idx);
+    // Stops the head has to take in to close every order it has opened, from the start at least
+    // up to the last delivery of an order on board
+    let mut needed = stops.iter().rposition(|s| !s.pickup && !picked(s.order_idx)).map_or(0, |idx| idx + 1);
+    let mut valid = Vec::new();
+    for cut in 0..=stops.len() {
+        if cut > 0 && stops[cut - 1].pickup {
+            let delivery = stops.iter().rposition(|s| s.order_idx == stops[cut - 1].order_idx).unwrap_or(cut - 1);
+            needed = needed.max(delivery + 1);
+        }
+        if needed <= cut {
+            valid.push(cut);
+        }
+    }
+    valid
+}
+
+impl Network<'_> {
+    /// First order on any route that another vehicle serves for less than it saves.
+    fn relocate(&self, routes: &[Vec<Stop>]) -> Option<Found> {
+        for (from, stops) in routes.iter().enumerate() {
+            let old = self.route_distance(from, stops);
+            for o_idx in picked_up(stops) {
+                let rest = without(stops, o_idx);
+                let saving = old - self.route_distance(from, &rest);
+                for (to, other) in routes.iter().enumerate().filter(|&(to, _)| to != from) {
+                    let Some((delta, i, j)) = self.best_insertion(to, other, o_idx) else {
+                        continue;
+                    };
+                    let to_stops = with_order(other, o_idx, i, j);
+                    if saving - delta > MIN_SAVING && self.feasible(from, &rest) && self.feasible(to, &to_stops) {
+                        return Some(Found { from, to, from_stops: rest, to_stops, moved: vec![o_idx], saved: saving - delta });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// First pair of orders on two routes whose exchange, each reinserted at its cheapest, shortens them.
+    fn swap(&self, routes: &[Vec<Stop>]) -> Option<Found> {
+        for from in 0..routes.len() {
+            for to in from + 1..routes.len() {
+                let old = self.route_distance(from, &routes[from]) + self.route_distance(to, &routes[to]);
+                for a_idx in picked_up(&routes[from]) {
+                    let from_rest = without(&routes[from], a_idx);
+                    for b_idx in picked_up(&routes[to]) {
+                        let to_rest = without(&routes[to], b_idx);
+                        let (Some((_, fi, fj)), Some((_, ti, tj))) =
+                            (self.best_insertion(from, &from_rest, b_idx), self.best_insertion(to, &to_rest, a_idx))
+                        else {
+                            continue;
+                        };
+                        let from_stops = with_order(&from_rest, b_idx, fi, fj);
+                        let to_stops = with_order(&to_rest, a_idx, ti, tj);
+                        let saved = old - self.route_distance(from, &from_stops) - self.route_distance(to, &to_stops);
+                        if saved > MIN_SAVING && self.feasible(from, &from_stops) && self.feasible(to, &to_stops) {
+                            return Some(Found { from, to, from_stops, to_stops, moved: vec![a_idx, b_idx], saved });
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// First exchange of route tails between two vehicles that shortens them (2-opt*).
+    fn two_opt_star(&self, routes: &[Vec<Stop>]) -> Option<Found> {
+        for from in 0..routes.len() {
+            for to in from + 1..routes.len() {
+                let (a, b) = (&routes[from], &routes[to]);
+                let old = self.route_distance(from, a) + self.route_distance(to, b);
+                for &cut_a in &cuts(a) {
+                    for &cut_b in &cuts(b) {
+                        // Both tails empty, nothing changes
+                        if cut_a == a.len() && cut_b == b.len() {
+                            continue;
+                        }
+                        let from_stops: Vec<Stop> = a[..cut_a].iter().chain(&b[cut_b..]).copied().collect();
+                        let to_stops: Vec<Stop> = b[..cut_b].iter().chain(&a[cut_a..]).copied().collect();
+                        let saved = old - self.route_distance(from, &from_stops) - self.route_distance(to, &to_stops);
+                        if saved > MIN_SAVING && self.feasible(from, &from_stops) && self.feasible(to, &to_stops) {
+                            let moved = picked_up(&a[cut_a..]).into_iter().chain(picked_up(&b[cut_b..])).collect();
+                            return Some(Found { from, to, from_stops, to_stops, moved, saved });
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Polishes `solution` by first-improvement local search on total distance.
+///
+/// Tries the `moves` asked for in their order: moving one order to another vehicle, exchanging two
+/// orders between vehicles, or exchanging the tails of two routes. The first move found shortening
+/// the plan is applied and the search starts over, until no move helps. Every changed route is
+/// checked against capacity, pickups before deliveries, tags, windows and route limits, and the
+/// returned plan is measured afresh, empty distance included. Orders left unserved stay unserved,
+/// orders on board stay with their vehicle. Deterministic.
+pub fn local_search(problem: &Problem, solution: &ProblemSolution, options: LocalSearchOptions) -> Result<LocalSearchResult> {
+    validate_ids(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    let scale = distance_scale(options.distance.as_ref());
+    let problem = &problem_to_base(problem.clone(), scale);
+    let network = Network::new(problem, distance_model(options.distance.as_ref())?);
+    let moves = options.moves.unwrap_or_else(|| ALL_MOVES.to_vec());
+    let mut routes = network.routes_of(solution)?;
+
+    let mut applied = Vec::new();
+    let mut saved_distance = 0.0;
+    loop {
+        let found = moves.iter().find_map(|kind| {
+            match kind {
+                LocalSearchMove::Relocate => network.relocate(&routes),
+                LocalSearchMove::Swap => network.swap(&routes),
+                LocalSearchMove::TwoOptStar => network.two_opt_star(&routes),
+            }
+            .map(|found| (*kind, found))
+        });
+        let Some((kind, found)) = found else {
+            break;
+        };
+        let vehicles = &problem.vehicles;
+        applied.push(AppliedMove {
+            kind,
+            order_ids: found.moved.iter().map(|&o_idx| problem.orders[o_idx].id).collect(),
+            from_vehicle_id: vehicles[found.from].id,
+            to_vehicle_id: vehicles[found.to].id,
+            saved_distance: found.saved,
+        });
+        saved_distance += found.saved;
+        routes[found.from] = found.from_stops;
+        routes[found.to] = found.to_stops;
+    }
+
+    Ok(LocalSearchResult {
+        solution: solution_from_routes(problem, &routes, &network.model),
+        moves: applied,
+        saved_distance,
+    }
+    .rescaled(scale))
+}
@@ -0,0 +1,9 @@
+//! Approximate solvers for instances the exact search cannot handle.
+
+pub mod annealing;
+pub mod cluster;
+pub mod greedy;
+pub mod lns;
+pub mod local_search;
+pub mod regret;
+pub mod suggest;
@@ -0,0 +1,31 @@
+use crate::error::{Result, SolverError};
+use crate::models::{Problem, ProblemSolution, RegretOptions};
+use crate::solver::units::{distance_scale, problem_to_base, Rescale};
+use crate::solver::{distance_model, validate_ids, validate_load_factors, validate_vehicles};
+use super::greedy::solution_from_routes;
+use super::lns::Network;
+
+const DEFAULT_REGRET_K: u32 = 2;
+
+/// Builds one plan by regret insertion, all routes growing in parallel from empty.
+///
+/// Each round prices every unserved order on every vehicle at its cheapest feasible positions
+/// and inserts the order that would lose the most by missing its cheapest vehicle for one of its
+/// next `regret_k - 1`, where `solveGreedy` inserts the cheapest. Respects what `solveGreedy`
+/// does: capacity, pickups before deliveries, tags, windows, route limits, `max_orders` and
+/// orders on board.
+pub fn solve_regret_insertion(problem: &Problem, options: RegretOptions) -> Result<ProblemSolution> {
+    validate_ids(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    let regret_k = options.regret_k.unwrap_or(DEFAULT_REGRET_K);
+    if regret_k < 1 {
+        return Err(SolverError::InvalidArg(format!("regret_k must be at least 1, got {}", regret_k)));
+    }
+    let scale = distance_scale(options.distance.as_ref());
+    let problem = &problem_to_base(problem.clone(), scale);
+    let network = Network::new(problem, distance_model(options.distance.as_ref())?);
+    let mut routes = network.routes_of(&ProblemSolution::default())?;
+    network.repair(&mut routes, regret_k as usize);
+    Ok(solution_from_routes(problem, &routes, &network.model).rescaled(scale))
+}
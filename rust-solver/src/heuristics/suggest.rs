@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::models::{AssignmentSuggestion, Criterion, Problem, Vehicle};
+use crate::solver::matrix::DistanceMatrices;
+use crate::solver::types::FLEET_VEHICLE_WEIGHT;
+use crate::utils::{prepared_distance, DistanceModel, PreparedLocation};
+
+/// Assigns orders to vehicles from cheap insertion estimates instead of exact routing.
+///
+/// Every vehicle is modelled as serving its orders one after another (approach leg to the pickup,
+/// then straight to the delivery), so each step greedily commits the (order, vehicle) pair with the
+/// lowest estimated marginal cost for `criterion`. The resulting metrics are upper-bound estimates
+/// of what `routeAssignment` would produce for the same assignment. Distances are measured by `model`.
+pub fn suggest_assignments(problem: &Problem, criterion: Criterion, model: &DistanceModel) -> AssignmentSuggestion {
+    let n_orders = problem.orders.len();
+    let matrices = DistanceMatrices::build(&problem.orders, None, model);
+    let dist = |from: usize, to: usize| matrices.dist_mat[from * matrices.num_nodes + to];
+
+    let starts: Vec<PreparedLocation> = problem.vehicles.iter().map(|v| model.prepare(&v.start_location)).collect();
+    let pickups: Vec<PreparedLocation> = problem.orders.iter().map(|o| model.prepare(&o.pickup_location)).collect();
+
+    // Last delivery node of each vehicle, None while it is still at its start
+    let mut positions: Vec<Option<usize>> = vec![None; problem.vehicles.len()];
+    let mut lengths = vec![0.0; problem.vehicles.len()];
+    let mut assigned: Vec<Vec<u32>> = vec![Vec::new(); problem.vehicles.len()];
+    let mut pending: Vec<usize> = (0..n_orders).collect();
+
+    let mut suggestion = AssignmentSuggestion {
+        approximate: true,
+        ..Default::default()
+    };
+
+    while !pending.is_empty() {
+        let mut best: Option<(f64, usize, usize, f64, f64)> = None; // (cost, pending pos, vehicle, approach, direct)
+
+        for (pos, &o_idx) in pending.iter().enumerate() {
+            let direct = dist(2 * o_idx, 2 * o_idx + 1);
+            for (v_idx, vehicle) in problem.vehicles.iter().enumerate() {
+                // Orders are carried one at a time, so each only has to fit on its own
+                let order = &problem.orders[o_idx];
+                if order.load_on(vehicle) > 1.000001 || !order.servable_by(vehicle) {
+                    continue;
+                }
+                let approach = match positions[v_idx] {
+                    None => prepared_distance(&starts[v_idx], &pickups[o_idx]),
+                    Some(node) => dist(node, 2 * o_idx),
+                };
+                let cost = match criterion {
+                    Criterion::Distance => approach + direct,
+                    Criterion::Price => chain_price(vehicle, approach, direct),
+                    Criterion::Duration => (approach + direct) * vehicle.minutes_per_km(),
+                    Criterion::Empty => approach,
+                    // Length of the vehicle's chain once extended, keeps the longest one short
+                    Criterion::Makespan => lengths[v_idx] + approach + direct,
+                    // Opening another vehicle outweighs any detour on one already out
+                    Criterion::Fleet => {
+                        let opens = if assigned[v_idx].is_empty() { FLEET_VEHICLE_WEIGHT } else { 0.0 };
+                        opens + approach + direct
+                    }
+                };
+                if best.is_none_or(|b| cost < b.0) {
+                    best = Some((cost, pos, v_idx, approach, direct));
+                }
+            }
+        }
+
+        let Some((_, pos, v_idx, approach, direct)) = best else {
+            break;
+        };
+        let o_idx = pending.swap_remove(pos);
+
+        positions[v_idx] = Some(2 * o_idx + 1);
+        lengths[v_idx] += approach + direct;
+        assigned[v_idx].push(problem.orders[o_idx].id);
+        suggestion.estimated_distance += approach + direct;
+        suggestion.estimated_empty_distance += approach;
+        suggestion.estimated_price += chain_price(&problem.vehicles[v_idx], approach, direct);
+    }
+
+    // Whatever no vehicle has room for is still pending
+    let mut unassigned_order_ids: Vec<u32> = pending.iter().map(|&o_idx| problem.orders[o_idx].id).collect();
+    unassigned_order_ids.sort_unstable();
+
+    suggestion.assignments = problem
+        .vehicles
+        .iter()
+        .zip(assigned)
+        .filter(|(_, orders)| !orders.is_empty())
+        .map(|(vehicle, orders)| (vehicle.id.to_string(), orders))
+        .collect::<HashMap<_, _>>();
+    suggestion.unassigned_order_ids = unassigned_order_ids;
+    suggestion
+}
+
+// The approach leg is empty, written so it is exactly `(approach + direct) * price_km` when
+// `price_km_empty` and `stop_fee` are unset. Each order adds a pickup and a delivery fee
+fn chain_price(vehicle: &Vehicle, approach: f64, direct: f64) -> f64 {
+    (approach + direct) * vehicle.price_km
+        + approach * (vehicle.empty_price_km() - vehicle.price_km)
+        + 2.0 * vehicle.fee_per_stop()
+}
@@ -1,14 +1,457 @@
 #![deny(clippy::all)]
 
+use napi::bindgen_prelude::AsyncTask;
 use napi_derive::napi;
+use std::collections::HashMap;
+use std::sync::Arc;
 
+mod cache;
+mod error;
+mod generator;
+mod heuristics;
+mod memo;
 mod models;
+mod progress;
 mod solver;
+mod task;
 mod utils;
 
-use models::{Problem, AlgorithmSolution};
+#[cfg(feature = "benchmarks")]
+mod benchmarks;
+#[cfg(test)]
+mod testing;
 
+use cache::DistanceCache;
+use error::{guarded, ErrorCode};
+use memo::TspMemo;
+use progress::ImprovementCallback;
+use models::{
+    Problem, AlgorithmSolution, AnnealingOptions, AnnealingResult, AssignmentSuggestion, AutoOptions, AutoSolution, BeamOptions, ClusterOptions, ClusteredSolution, Criterion, DistanceOptions, GeneratorOptions, InsertionCost, InsertionEvaluation, LnsOptions, LnsResult, LocalSearchOptions, LocalSearchResult, ObjectiveWeights, Order, ProblemSolution, RegretOptions, RemovalEvaluation, RemovalSaving,
+    ReoptimizedSolution, RouteStop, Scenario, ScenarioComparison, SolutionComparison, SolutionReport, SolveOptions, Vehicle, VehicleRanking, VehicleRoute, WeightedSolution,
+};
+use solver::context::{SharedContext, SolverContext};
+use solver::units::{self, Rescale};
+use task::{CancellationToken, SolveTask};
+use utils::DistanceModel;
+
+/// Plans `problem` as `options` ask: exactly, by weights or criteria, or greedily above
+/// `max_orders_exact`. Without options it matches `solveBruteForce`.
+#[napi]
+pub fn solve(mut problem: Problem, options: Option<SolveOptions>) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        let options = options.unwrap_or_default();
+        solver::normalize_locations(problem.locations_mut(), options.distance.as_ref())?;
+        solver::solve_with_options(problem, options)
+    })
+}
+
+#[napi]
+pub fn solve_brute_force(mut problem: Problem) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), None)?;
+        solver::validate_problem(&problem, &DistanceModel::default())?;
+        Ok(solver::solve(problem))
+    })
+}
+
+/// Same as `solveBruteForce`, with the search tuned by `options`.
+#[napi]
+pub fn solve_brute_force_with_options(mut problem: Problem, options: SolveOptions) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.distance.as_ref())?;
+        solver::solve_with_options(problem, options)
+    })
+}
+
+/// Same as `solveBruteForceWithOptions`, run on the libuv thread pool so the event loop stays
+/// responsive. Invalid input rejects the returned promise, as does a panic inside the solver;
+/// the rejection's message starts with the error code the synchronous solve would throw.
+/// Cancelling `token` stops the search and rejects with a `Cancelled` error.
+/// `on_improvement` is called on the event loop with every new best value of a criterion, in
+/// improving order per criterion. The search never waits for it.
+#[napi]
+pub fn solve_brute_force_async(
+    problem: Problem,
+    options: Option<SolveOptions>,
+    token: Option<&CancellationToken>,
+    on_improvement: Option<ImprovementCallback>,
+) -> AsyncTask<SolveTask> {
+    AsyncTask::new(SolveTask {
+        problem: Some(problem),
+        options: options.unwrap_or_default(),
+        cancel: token.map(|token| token.flag.clone()),
+        on_improvement: on_improvement.map(progress::non_blocking_improvements),
+    })
+}
+
+/// Same as `solveBruteForce`, reusing and filling `cache` for every pairwise distance.
+#[napi]
+pub fn solve_brute_force_with_cache(
+    mut problem: Problem,
+    cache: &DistanceCache,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let model = solver::distance_model(distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        solver::validate_problem(&problem, &model)?;
+        let shared = SharedContext::from_problem(problem, Some(cache.store.clone()), model);
+        Ok(solver::solve_shared(Arc::new(shared)).rescaled(scale))
+    })
+}
+
+/// Same as `solveBruteForce`, starting from `previous_solution` as incumbent. Stops of orders no
+/// longer in `problem` are dropped and new orders added where they cost the least, the result
+/// is still optimal.
+#[napi]
+pub fn solve_brute_force_warm(
+    mut problem: Problem,
+    previous_solution: ProblemSolution,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        solver::solve_warm(problem, &previous_solution, distance.as_ref())
+    })
+}
+
+/// Same as `solveBruteForce`, reusing routing results from `memo` for orders and vehicles
+/// unchanged since the previous solve, and storing this solve's results in it afterwards.
+#[napi]
+pub fn solve_brute_force_with_memo(mut problem: Problem, memo: &TspMemo, distance: Option<DistanceOptions>) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let model = solver::distance_model(distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        solver::validate_problem(&problem, &model)?;
+        let mut ctx = SolverContext::from_problem(problem, model);
+        memo.import_into(&mut ctx);
+        let solution = solver::run(&mut ctx);
+        memo.export_from(&ctx);
+        Ok(solution.rescaled(scale))
+    })
+}
+
+/// Routes each vehicle optimally over exactly the orders given for it (vehicle id -> order ids),
+/// skipping the assignment search.
+#[napi]
+pub fn route_assignment(
+    mut problem: Problem,
+    assignments: HashMap<String, Vec<u32>>,
+    criterion: Criterion,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<ProblemSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        solver::routing::route_assignment(problem, assignments, criterion, distance.as_ref())
+    })
+}
+
+/// Re-sequences the stops of every route in `solution` without moving orders between vehicles.
+#[napi]
+pub fn reoptimize_routes(
+    mut problem: Problem,
+    solution: ProblemSolution,
+    criterion: Criterion,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<ReoptimizedSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        solver::routing::reoptimize_routes(problem, solution, criterion, distance.as_ref())
+    })
+}
+
+/// One plan from greedy cheapest insertion, for problems too large for `solveBruteForce`.
+/// The same plan is reported under every criterion.
+#[napi]
+pub fn solve_greedy(mut problem: Problem, distance: Option<DistanceOptions>) -> napi::Result<AlgorithmSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        Ok(heuristics::greedy::solve_greedy(&problem, &solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// Improves the greedy plan by simulated annealing, see `AnnealingOptions` for the knobs.
+#[napi]
+pub fn solve_simulated_annealing(mut problem: Problem, options: Option<AnnealingOptions>) -> napi::Result<AnnealingResult, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        heuristics::annealing::solve_simulated_annealing(&problem, options.unwrap_or_default())
+    })
+}
+
+/// Plans `problem` with the exact search or a heuristic depending on its size, and says which
+/// ran. See `AutoOptions` for the thresholds.
+#[napi]
+pub fn solve_auto(mut problem: Problem, options: Option<AutoOptions>) -> napi::Result<AutoSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        solver::auto::solve_auto(problem, options.unwrap_or_default())
+    })
+}
+
+/// One plan from a beam search over the assignments, between `solveGreedy` and the exact search.
+/// Takes up to 64 orders, at most 8 per vehicle.
+#[napi]
+pub fn solve_beam(mut problem: Problem, options: Option<BeamOptions>) -> napi::Result<ProblemSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        solver::beam::solve_beam(problem, options.unwrap_or_default())
+    })
+}
+
+/// One plan from regret insertion, usually a better start than `solveGreedy` for `improveSolution`.
+/// See `RegretOptions`.
+#[napi]
+pub fn solve_regret_insertion(mut problem: Problem, options: Option<RegretOptions>) -> napi::Result<ProblemSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        heuristics::regret::solve_regret_insertion(&problem, options.unwrap_or_default())
+    })
+}
+
+/// One plan from the sweep heuristic, e.g. as the `previousSolution` of `solveBruteForceWarm`.
+/// Takes up to 64 orders, at most 8 per vehicle.
+#[napi]
+pub fn solve_sweep(mut problem: Problem, distance: Option<DistanceOptions>) -> napi::Result<ProblemSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        Ok(solver::sweep::solve_sweep(problem, solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// Improves `solution`, e.g. from `solveGreedy` or `solveBeam`, by large neighbourhood search.
+/// See `LnsOptions` for the knobs.
+#[napi]
+pub fn improve_solution(mut problem: Problem, solution: ProblemSolution, options: Option<LnsOptions>) -> napi::Result<LnsResult, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        heuristics::lns::improve_solution(&problem, &solution, options.unwrap_or_default())
+    })
+}
+
+/// Plans `problem` cluster first, route second, for hundreds of orders. See `ClusterOptions`.
+#[napi]
+pub fn solve_clustered(mut problem: Problem, options: Option<ClusterOptions>) -> napi::Result<ClusteredSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        heuristics::cluster::solve_clustered(&problem, options.unwrap_or_default())
+    })
+}
+
+/// Polishes `solution` with relocate, swap and 2-opt* moves until none shortens it, logging each
+/// move applied. See `LocalSearchOptions`.
+#[napi]
+pub fn local_search(mut problem: Problem, solution: ProblemSolution, options: Option<LocalSearchOptions>) -> napi::Result<LocalSearchResult, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        heuristics::local_search::local_search(&problem, &solution, options.unwrap_or_default())
+    })
+}
+
+/// Quick vehicle suggestions for a batch of orders based on insertion estimates, without exact routing.
+#[napi]
+pub fn suggest_assignments(mut problem: Problem, criterion: Criterion, distance: Option<DistanceOptions>) -> napi::Result<AssignmentSuggestion, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        Ok(heuristics::suggest::suggest_assignments(&problem, criterion, &solver::distance_model(distance.as_ref())?).rescaled(scale))
+    })
+}
+
+/// Per-vehicle cost of adding `new_order` to an existing plan, for live quotes.
+#[napi]
+pub fn insertion_cost(
+    mut problem: Problem,
+    solution: ProblemSolution,
+    mut new_order: Order,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<InsertionCost, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut().chain(new_order.locations_mut()), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let (problem, solution) = (units::problem_to_base(problem, scale), solution.rescaled(1.0 / scale));
+        Ok(solver::marginal::insertion_cost(problem, &solution, new_order, &solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// Cheapest insertion of `new_order` into the existing routes of `solution`, without re-optimizing them.
+#[napi]
+pub fn evaluate_insertion(
+    mut problem: Problem,
+    solution: ProblemSolution,
+    mut new_order: Order,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<InsertionEvaluation, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut().chain(new_order.locations_mut()), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let (problem, solution) = (units::problem_to_base(problem, scale), solution.rescaled(1.0 / scale));
+        Ok(solver::marginal::evaluate_insertion(problem, &solution, new_order, &solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// What cancelling `order_id` saves with its stops spliced out of the route and nothing re-optimized.
+#[napi]
+pub fn evaluate_removal(
+    mut problem: Problem,
+    solution: ProblemSolution,
+    order_id: u32,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<RemovalEvaluation, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let (problem, solution) = (units::problem_to_base(problem, scale), solution.rescaled(1.0 / scale));
+        Ok(solver::marginal::evaluate_removal(&problem, &solution, order_id, &solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// How much distance, price and empty distance dropping `order_id` from `solution` saves.
+#[napi]
+pub fn removal_saving(
+    mut problem: Problem,
+    solution: ProblemSolution,
+    order_id: u32,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<RemovalSaving, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let (problem, solution) = (units::problem_to_base(problem, scale), solution.rescaled(1.0 / scale));
+        Ok(solver::marginal::removal_saving(&problem, &solution, order_id, &solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// Vehicles ranked by the cost of serving exactly `order_ids`, for building loads by hand.
+#[napi]
+pub fn rank_vehicles(
+    mut problem: Problem,
+    order_ids: Vec<u32>,
+    criterion: Criterion,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<Vec<VehicleRanking>, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        solver::routing::rank_vehicles(problem, &order_ids, criterion, distance.as_ref())
+    })
+}
+
+/// Solves `base_problem` and each what-if scenario in one call, with objective deltas against the base.
+#[napi]
+pub fn solve_scenarios(
+    mut base_problem: Problem,
+    mut scenarios: Vec<Scenario>,
+    options: Option<SolveOptions>,
+) -> napi::Result<ScenarioComparison, ErrorCode> {
+    guarded(|| {
+        let patches = scenarios.iter_mut().flat_map(Scenario::locations_mut);
+        solver::normalize_locations(base_problem.locations_mut().chain(patches), options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        solver::scenarios::solve_scenarios(base_problem, scenarios, options.unwrap_or_default())
+    })
+}
+
+/// Plan minimizing `weights.distance * km + weights.price * price + weights.empty * empty km`,
+/// with the weighted cost broken down per objective. Unset weights are 0.
+#[napi]
+pub fn solve_weighted(mut problem: Problem, weights: ObjectiveWeights, distance: Option<DistanceOptions>) -> napi::Result<WeightedSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        solver::weighted::solve_weighted(problem, weights, distance.as_ref())
+    })
+}
+
+/// Complete plans not dominated in distance, price and empty distance, sorted by distance.
+/// Keeps at most `max_solutions` (default 20), dropping those in the most crowded part of the front.
+#[napi]
+pub fn solve_pareto_front(
+    mut problem: Problem,
+    max_solutions: Option<u32>,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<Vec<ProblemSolution>, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        solver::pareto::solve_pareto_front(problem, max_solutions, distance.as_ref())
+    })
+}
+
+/// Whether `a` is no worse than `b` in distance, price and empty distance, and better in at least one.
+#[napi]
+pub fn solution_dominates(a: ProblemSolution, b: ProblemSolution) -> napi::Result<bool, ErrorCode> {
+    guarded(|| Ok(solver::pareto::solution_dominates(&a, &b)))
+}
+
+/// How plan `b` fares against plan `a` of `problem` under every criterion, e.g. a heuristic
+/// against the exact optimum.
+#[napi]
+pub fn compare_solutions(problem: Problem, a: ProblemSolution, b: ProblemSolution) -> napi::Result<SolutionComparison, ErrorCode> {
+    guarded(|| solver::gap::compare_solutions(&problem, &a, &b))
+}
+
+/// A value no plan serving every order of `problem` can beat under `criterion`, to report a
+/// heuristic's gap where the exact search cannot run.
+#[napi]
+pub fn lower_bound(mut problem: Problem, criterion: Criterion, distance: Option<DistanceOptions>) -> napi::Result<f64, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        let bound = solver::gap::lower_bound(&problem, criterion, &solver::distance_model(distance.as_ref())?)?;
+        Ok(units::criterion_from_base(bound, criterion, scale))
+    })
+}
+
+/// A random problem for tests and benchmarks, the same for the same seed and options.
+#[napi]
+pub fn generate_problem(options: GeneratorOptions) -> napi::Result<Problem, ErrorCode> {
+    guarded(|| generator::generate_problem(options))
+}
+
+/// Distance, empty distance, price and duration of routes built outside the solver, measured the way it measures its own.
+#[napi]
+pub fn evaluate_solution(
+    mut problem: Problem,
+    routes: HashMap<String, Vec<RouteStop>>,
+    distance: Option<DistanceOptions>,
+) -> napi::Result<ProblemSolution, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let problem = units::problem_to_base(problem, scale);
+        Ok(solver::audit::evaluate_solution(&problem, &routes, &solver::distance_model(distance.as_ref())?)?.rescaled(scale))
+    })
+}
+
+/// Every rule a hand-edited plan breaks, with its totals recomputed the way the solver measures them.
+#[napi]
+pub fn validate_solution(mut problem: Problem, solution: ProblemSolution, distance: Option<DistanceOptions>) -> napi::Result<SolutionReport, ErrorCode> {
+    guarded(|| {
+        solver::normalize_locations(problem.locations_mut(), distance.as_ref())?;
+        let scale = units::distance_scale(distance.as_ref());
+        let (problem, solution) = (units::problem_to_base(problem, scale), solution.rescaled(1.0 / scale));
+        Ok(solver::audit::validate_solution(&problem, &solution, &solver::distance_model(distance.as_ref())?).rescaled(scale))
+    })
+}
+
+/// Optimal pickup/delivery sequence when the vehicle and its orders are already decided.
 #[napi]
-pub fn solve_brute_force(problem: Problem) -> AlgorithmSolution {
-    solver::solve(problem)
+pub fn solve_single_vehicle(
+    mut vehicle: Vehicle,
+    mut orders: Vec<Order>,
+    criterion: Criterion,
+    options: Option<SolveOptions>,
+) -> napi::Result<VehicleRoute, ErrorCode> {
+    guarded(|| {
+        let locations = vehicle.locations_mut().chain(orders.iter_mut().flat_map(Order::locations_mut));
+        solver::normalize_locations(locations, options.as_ref().and_then(|o| o.distance.as_ref()))?;
+        solver::routing::solve_single_vehicle(vehicle, orders, criterion, options.unwrap_or_default())
+    })
 }
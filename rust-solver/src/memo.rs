@@ -0,0 +1,185 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use napi_derive::napi;
+
+use crate::models::{ForbiddenLeg, Location, Order, Vehicle};
+use crate::solver::context::SolverContext;
+use crate::solver::types::{InternalBestResults, OrderMask, RELOAD_NODE};
+use crate::utils::DistanceModel;
+
+fn hash_location(location: &Location, hasher: &mut DefaultHasher) {
+    location.hash.hash(hasher);
+    location.latitude.to_bits().hash(hasher);
+    location.longitude.to_bits().hash(hasher);
+}
+
+// Everything a memo entry depends on, so an entry can only be reused for identical input
+fn order_fingerprint(order: &Order) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    order.id.hash(&mut hasher);
+    hash_location(&order.pickup_location, &mut hasher);
+    hash_location(&order.delivery_location, &mut hasher);
+    order.load_factor.to_bits().hash(&mut hasher);
+    order.demand.map(f64::to_bits).hash(&mut hasher);
+    for pickup in [true, false] {
+        let (earliest, latest) = order.window_bounds(pickup);
+        (earliest.to_bits(), latest.to_bits()).hash(&mut hasher);
+        order.service_minutes(pickup).to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn vehicle_fingerprint(vehicle: &Vehicle) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    vehicle.id.hash(&mut hasher);
+    hash_location(&vehicle.start_location, &mut hasher);
+    vehicle.price_km.to_bits().hash(&mut hasher);
+    vehicle.price_km_empty.map(f64::to_bits).hash(&mut hasher);
+    vehicle.stop_fee.map(f64::to_bits).hash(&mut hasher);
+    vehicle.co2_per_km.map(f64::to_bits).hash(&mut hasher);
+    vehicle.co2_per_km_empty.map(f64::to_bits).hash(&mut hasher);
+    vehicle.trips().hash(&mut hasher);
+    vehicle.speed_kmh.map(f64::to_bits).hash(&mut hasher);
+    vehicle.capacity.map(f64::to_bits).hash(&mut hasher);
+    vehicle.max_distance_km.map(f64::to_bits).hash(&mut hasher);
+    vehicle.max_duration_min.map(f64::to_bits).hash(&mut hasher);
+    vehicle.onboard_order_ids.hash(&mut hasher);
+    if let Some(end) = &vehicle.end_location {
+        hash_location(end, &mut hasher);
+    }
+    hasher.finish()
+}
+
+// Routes change whenever the set of forbidden legs does, whatever the orders
+fn legs_fingerprint(legs: &[ForbiddenLeg]) -> u64 {
+    let mut pairs: Vec<(&str, &str)> = legs.iter().map(|l| (l.from_hash.as_str(), l.to_hash.as_str())).collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    let mut hasher = DefaultHasher::new();
+    pairs.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct MemoSnapshot {
+    // Routes measured any other way are all different
+    model: DistanceModel,
+    forbidden_legs: u64,
+    orders: Vec<u64>,
+    vehicles: Vec<u64>,
+    // (vehicle index, order mask) in the indexing of the solve that produced them
+    entries: Vec<(usize, OrderMask, InternalBestResults)>,
+}
+
+/// Per-(vehicle, order set) routing results carried from one solve to the next.
+///
+/// Entries are matched by fingerprints of the orders and vehicles they were computed for,
+/// so orders may be added, removed or reordered between solves. Entries touching anything
+/// that changed are discarded.
+#[napi]
+pub struct TspMemo {
+    snapshot: Mutex<Option<MemoSnapshot>>,
+}
+
+impl Default for TspMemo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[napi]
+impl TspMemo {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        TspMemo {
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    #[napi]
+    pub fn clear(&self) {
+        *self.snapshot.lock().unwrap() = None;
+    }
+
+    /// Number of stored (vehicle, order set) entries.
+    #[napi(getter)]
+    pub fn size(&self) -> u32 {
+        self.snapshot.lock().unwrap().as_ref().map_or(0, |s| s.entries.len() as u32)
+    }
+
+    /// Seeds `ctx.memo` with every stored entry whose orders and vehicle are unchanged.
+    pub(crate) fn import_into(&self, ctx: &mut SolverContext) {
+        let guard = self.snapshot.lock().unwrap();
+        let Some(snapshot) = guard.as_ref() else {
+            return;
+        };
+        if snapshot.model != ctx.shared.distance_model || snapshot.forbidden_legs != legs_fingerprint(&ctx.shared.forbidden_legs) {
+            return;
+        }
+
+        // Old index -> new index, matching each new order/vehicle at most once
+        let remap = |old: &[u64], new: Vec<u64>| -> Vec<Option<usize>> {
+            let mut available: HashMap<u64, Vec<usize>> = HashMap::new();
+            for (idx, fp) in new.into_iter().enumerate().rev() {
+                available.entry(fp).or_default().push(idx);
+            }
+            old.iter().map(|fp| available.get_mut(fp).and_then(|idxs| idxs.pop())).collect()
+        };
+        let order_map = remap(&snapshot.orders, ctx.shared.orders.iter().map(order_fingerprint).collect());
+        let vehicle_map = remap(&snapshot.vehicles, ctx.shared.vehicles.iter().map(vehicle_fingerprint).collect());
+
+        'entries: for (old_v, old_mask, res) in &snapshot.entries {
+            let Some(new_v) = vehicle_map[*old_v] else {
+                continue;
+            };
+
+            let mut new_mask = 0 as OrderMask;
+            let mut remaining = *old_mask;
+            while remaining != 0 {
+                let old_o = remaining.trailing_zeros() as usize;
+                match order_map[old_o] {
+                    Some(new_o) => new_mask |= 1 << new_o,
+                    None => continue 'entries,
+                }
+                remaining &= remaining - 1;
+            }
+
+            let mut remapped = *res;
+            for path in [
+                &mut remapped.min_dist.path,
+                &mut remapped.min_price.path,
+                &mut remapped.min_empty.path,
+                &mut remapped.min_duration.path,
+            ] {
+                for node in path.nodes.iter_mut().take(path.len as usize).filter(|n| **n != RELOAD_NODE) {
+                    let new_o = order_map[(*node / 2) as usize].unwrap_or_default();
+                    *node = (2 * new_o) as u8 + (*node % 2);
+                }
+            }
+
+            ctx.memo.insert(ctx.memo_rows[new_v], new_mask, remapped);
+        }
+    }
+
+    /// Replaces the stored entries with everything `ctx` has computed.
+    pub(crate) fn export_from(&self, ctx: &SolverContext) {
+        // Shared rows are stored once, under the first vehicle using them
+        let row_vehicle = |row: usize| ctx.memo_rows.iter().position(|&r| r == row).unwrap_or_default();
+        let entries = ctx
+            .memo
+            .entries()
+            .into_iter()
+            .map(|(row, mask, res)| (row_vehicle(row), mask, res))
+            .collect();
+
+        *self.snapshot.lock().unwrap() = Some(MemoSnapshot {
+            model: ctx.shared.distance_model.clone(),
+            forbidden_legs: legs_fingerprint(&ctx.shared.forbidden_legs),
+            orders: ctx.shared.orders.iter().map(order_fingerprint).collect(),
+            vehicles: ctx.shared.vehicles.iter().map(vehicle_fingerprint).collect(),
+            entries,
+        });
+    }
+}
@@ -1,6 +1,8 @@
 use napi_derive::napi;
 use std::collections::HashMap;
 
+use crate::solver::types::MAX_ROUTE_ORDERS;
+
 #[napi(object)]
 #[derive(Clone, Debug)]
 pub struct Location {
@@ -15,6 +17,108 @@ pub struct Vehicle {
     pub id: u32,
     pub start_location: Location,
     pub price_km: f64,
+    /// Price per km driven empty (repositioning, the leg to `end_location`), defaults to `price_km`
+    pub price_km_empty: Option<f64>,
+    /// Flat handling fee of every pickup and delivery the vehicle makes, added to the route price
+    pub stop_fee: Option<f64>,
+    /// Grams of CO2 per km, emissions are reported as 0 without it
+    pub co2_per_km: Option<f64>,
+    /// Grams of CO2 per km driven empty, defaults to `co2_per_km`
+    pub co2_per_km_empty: Option<f64>,
+    /// Trips per route, each one after the first starts with a reload stop back at
+    /// `start_location`. Defaults to 1
+    pub max_trips: Option<u32>,
+    /// Lets the vehicle run as many trips as it needs when `max_trips` is unset, e.g. a van
+    /// doing several loops per day with its capacity reset on each
+    pub allow_multi_trip: Option<bool>,
+    /// Average travel speed, defaults to `DEFAULT_SPEED_KMH`
+    pub speed_kmh: Option<f64>,
+    /// Smallest route price the vehicle is dispatched for, otherwise it stays unused. Compared
+    /// against the `routeCostFn` price when a solve is given one
+    pub min_route_price: Option<f64>,
+    /// Room for order `demand`, in whatever unit the orders use (pallets, kg). Orders without a
+    /// demand still take `1 / load_factor` of it
+    pub capacity: Option<f64>,
+    /// Where the route has to finish, the leg there from the last delivery is empty distance.
+    /// Without one routes end at their last delivery
+    pub end_location: Option<Location>,
+    /// Equipment the vehicle has, e.g. "tail-lift" or "refrigerated"
+    pub tags: Option<Vec<String>>,
+    /// Longest route the driver may cover, including the leg to `end_location`
+    pub max_distance_km: Option<f64>,
+    /// Longest route in minutes: travel at `speed_kmh`, waiting for windows and service time
+    pub max_duration_min: Option<f64>,
+    /// Most orders one route may serve, however short it is. At least 1
+    pub max_orders: Option<u32>,
+    /// Orders already picked up and still on board, e.g. when re-planning mid-day from the live
+    /// position given as `start_location`. Only their deliveries are routed, by this vehicle
+    pub onboard_order_ids: Option<Vec<u32>>,
+}
+
+pub const DEFAULT_SPEED_KMH: f64 = 60.0;
+
+impl Vehicle {
+    /// The start and, if set, the end location.
+    pub fn locations_mut(&mut self) -> impl Iterator<Item = &mut Location> {
+        std::iter::once(&mut self.start_location).chain(self.end_location.as_mut())
+    }
+
+    pub fn minutes_per_km(&self) -> f64 {
+        60.0 / self.speed_kmh.unwrap_or(DEFAULT_SPEED_KMH)
+    }
+
+    pub fn empty_price_km(&self) -> f64 {
+        self.price_km_empty.unwrap_or(self.price_km)
+    }
+
+    /// Grams of CO2 per km driven loaded, or empty.
+    pub fn emission_rate(&self, empty: bool) -> f64 {
+        let loaded = self.co2_per_km.unwrap_or(0.0);
+        if empty { self.co2_per_km_empty.unwrap_or(loaded) } else { loaded }
+    }
+
+    /// Price of driving `km` loaded or empty.
+    pub fn leg_price(&self, km: f64, empty: bool) -> f64 {
+        km * if empty { self.empty_price_km() } else { self.price_km }
+    }
+
+    pub fn fee_per_stop(&self) -> f64 {
+        self.stop_fee.unwrap_or(0.0)
+    }
+
+    /// Price of `dist` km of which `empty` were driven empty, with `stops` pickups and deliveries.
+    pub fn price_of(&self, dist: f64, empty: f64, stops: usize) -> CostBreakdown {
+        CostBreakdown {
+            loaded_cost: (dist - empty) * self.price_km,
+            empty_cost: empty * self.empty_price_km(),
+            stop_fees: stops as f64 * self.fee_per_stop(),
+            custom_adjustment: 0.0,
+        }
+    }
+
+    /// Trips the vehicle may run. Each trip serves at least one order, so with `allow_multi_trip`
+    /// one per order is never a limit.
+    pub fn trips(&self) -> u32 {
+        match self.max_trips {
+            Some(trips) => trips,
+            None if self.allow_multi_trip.unwrap_or(false) => MAX_ROUTE_ORDERS as u32,
+            None => 1,
+        }
+    }
+
+    /// `max_distance_km` and `max_duration_min`, infinite when unset.
+    pub fn route_limits(&self) -> (f64, f64) {
+        (self.max_distance_km.unwrap_or(f64::INFINITY), self.max_duration_min.unwrap_or(f64::INFINITY))
+    }
+
+    /// Whether a route serving `n_orders` orders stays within `max_orders`.
+    pub fn takes_orders(&self, n_orders: u32) -> bool {
+        self.max_orders.is_none_or(|cap| n_orders <= cap)
+    }
+
+    pub fn has_route_limits(&self) -> bool {
+        self.max_distance_km.is_some() || self.max_duration_min.is_some()
+    }
 }
 
 #[napi(object)]
@@ -23,7 +127,60 @@ pub struct Order {
     pub id: u32,
     pub pickup_location: Location,
     pub delivery_location: Location,
+    /// How many such orders fill a vehicle, the order takes `1 / load_factor` of its capacity.
+    /// Must be a finite number of at least 1
     pub load_factor: f64,
+    /// Room the order takes on vehicles with a `capacity`, on any other vehicle `load_factor` applies
+    pub demand: Option<f64>,
+    /// When the pickup may be served, a vehicle arriving early waits for it to open
+    pub pickup_window: Option<TimeWindow>,
+    /// When the delivery may be served, same rules as `pickup_window`
+    pub delivery_window: Option<TimeWindow>,
+    /// Minutes spent loading at the pickup, 0 when missing
+    pub pickup_service_min: Option<f64>,
+    /// Minutes spent unloading at the delivery, 0 when missing
+    pub delivery_service_min: Option<f64>,
+    /// Tags a vehicle needs all of to be given the order
+    pub required_tags: Option<Vec<String>>,
+}
+
+impl Order {
+    pub fn locations_mut(&mut self) -> impl Iterator<Item = &mut Location> {
+        [&mut self.pickup_location, &mut self.delivery_location].into_iter()
+    }
+
+    /// Whether `vehicle` has every tag the order requires.
+    pub fn servable_by(&self, vehicle: &Vehicle) -> bool {
+        let tags = vehicle.tags.as_deref().unwrap_or_default();
+        self.required_tags.iter().flatten().all(|required| tags.contains(required))
+    }
+
+    /// Share of `vehicle`'s capacity the order takes while on board.
+    pub fn load_on(&self, vehicle: &Vehicle) -> f64 {
+        match (self.demand, vehicle.capacity) {
+            (Some(demand), Some(capacity)) => demand / capacity,
+            _ => 1.0 / self.load_factor,
+        }
+    }
+
+    /// Earliest and latest start of service at the pickup or delivery, infinite where open.
+    pub fn window_bounds(&self, pickup: bool) -> (f64, f64) {
+        let window = if pickup { self.pickup_window } else { self.delivery_window }.unwrap_or_default();
+        (window.earliest.unwrap_or(f64::NEG_INFINITY), window.latest.unwrap_or(f64::INFINITY))
+    }
+
+    /// Minutes the vehicle stays at the pickup or delivery once service starts.
+    pub fn service_minutes(&self, pickup: bool) -> f64 {
+        if pickup { self.pickup_service_min } else { self.delivery_service_min }.unwrap_or(0.0)
+    }
+}
+
+/// Minutes since the vehicles leave their starts, which they all do at 0. A missing bound is open.
+#[napi(object)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimeWindow {
+    pub earliest: Option<f64>,
+    pub latest: Option<f64>,
 }
 
 #[napi(object)]
@@ -31,37 +188,848 @@ pub struct Order {
 pub struct Problem {
     pub vehicles: Vec<Vehicle>,
     pub orders: Vec<Order>,
+    /// Direct moves between locations that are never allowed, whatever they would save
+    pub forbidden_legs: Option<Vec<ForbiddenLeg>>,
+    /// Order id -> vehicle id. A pinned order is only ever given to that vehicle
+    pub fixed_assignments: Option<HashMap<String, u32>>,
+    /// Order ids that must all ride on the same vehicle, groups sharing an order are merged
+    pub same_vehicle_groups: Option<Vec<Vec<u32>>>,
+    /// Pairs of orders that may never share a vehicle
+    pub conflict_pairs: Option<Vec<OrderConflict>>,
+}
+
+impl Problem {
+    /// Every vehicle's and order's locations.
+    pub fn locations_mut(&mut self) -> impl Iterator<Item = &mut Location> {
+        let vehicles = self.vehicles.iter_mut().flat_map(Vehicle::locations_mut);
+        vehicles.chain(self.orders.iter_mut().flat_map(Order::locations_mut))
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct ForbiddenLeg {
+    pub from_hash: String,
+    pub to_hash: String,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct OrderConflict {
+    pub first_order_id: u32,
+    pub second_order_id: u32,
 }
 
 #[napi(object)]
 #[derive(Clone, Debug)]
 pub struct RouteStop {
+    /// 0 for reload stops
     pub order_id: u32,
+    /// "pickup", "delivery" or "reload"
     #[napi(js_name = "type")] // "type" is a reserved keyword in Rust
     pub type_: String,
+    /// Minutes since the vehicle left its start when the stop is served, after any wait for its window
+    pub arrival_time: f64,
+    /// Minutes since the vehicle left its start when it moves on, `arrival_time` plus the service time
+    pub departure_time: f64,
+    /// Where the stop is served, the vehicle's start for reload stops
+    pub location_hash: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// 0-based position of the stop in its route, reloads included
+    pub sequence: u32,
+    /// Share of capacity on board once the stop is served
+    pub load_after: f64,
+}
+
+impl RouteStop {
+    pub fn new(
+        order_id: u32,
+        type_: &str,
+        location: &Location,
+        sequence: usize,
+        arrival_time: f64,
+        departure_time: f64,
+        load_after: f64,
+    ) -> Self {
+        RouteStop {
+            order_id,
+            type_: type_.to_string(),
+            arrival_time,
+            departure_time,
+            location_hash: location.hash.clone(),
+            latitude: location.latitude,
+            longitude: location.longitude,
+            sequence: sequence as u32,
+            load_after,
+        }
+    }
+}
+
+/// One drive of a route, from the vehicle's start to its first stop, between stops, back to the
+/// start for a reload or on to the end location.
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct RouteLeg {
+    pub from: Location,
+    pub to: Location,
+    /// The legs of a route add up to exactly its `total_distance`
+    pub distance: f64,
+    /// Nothing was on board for the drive
+    pub empty: bool,
+    /// Share of capacity on board once the stop at `to` is served
+    pub load_after: f64,
+    /// The drive at `price_km` or `price_km_empty`, plus the `stop_fee` of a pickup or delivery at `to`
+    pub price: f64,
 }
 
 #[napi(object)]
 #[derive(Clone, Debug, Default)]
 pub struct VehicleRoute {
+    /// Id of the vehicle driving the route
+    pub vehicle_id: u32,
     pub stops: Vec<RouteStop>,
+    /// Every drive of the route in order, the stops between them
+    pub legs: Vec<RouteLeg>,
     pub total_distance: f64,
     pub empty_distance: f64,
     pub total_price: f64,
+    /// Minutes, travel time at the vehicle's speed, waiting for windows and service time at the stops
+    pub total_duration_min: f64,
+    /// Minutes spent waiting for time windows to open, included in `total_duration_min`
+    pub waiting_time: f64,
+    /// Last delivery to the vehicle's `end_location`, already part of the distance totals
+    pub return_distance: f64,
+    /// Switches between picking up and delivering along the route, reloads start a fresh count
+    pub interleavings: u32,
+    /// Built-in price split by `price_km`, `price_km_empty` and `stop_fee`, before any route cost callback
+    pub cost_breakdown: CostBreakdown,
+    /// Grams of CO2 at the vehicle's `co2_per_km`, 0 without it
+    pub total_co2: f64,
+    /// Highest share of capacity ever on board
+    pub max_load: f64,
+    /// Share of capacity on board on average over the km driven, 0 for a route that never moves
+    pub avg_utilization: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CostBreakdown {
+    /// Loaded km at `price_km`
+    pub loaded_cost: f64,
+    /// Empty km at `price_km_empty`
+    pub empty_cost: f64,
+    /// `stop_fee` times the pickups and deliveries served
+    pub stop_fees: f64,
+    /// What the `routeCostFn` price of the route adds to the other parts, which with the leg prices
+    /// stay at the vehicle's own rates. 0 without a callback
+    pub custom_adjustment: f64,
 }
 
 #[napi(object)]
 #[derive(Clone, Debug, Default)]
 pub struct ProblemSolution {
+    /// Vehicle id as a string -> route, kept for compatibility with `route_list`
     pub routes: HashMap<String, VehicleRoute>,
+    /// The same routes ordered by vehicle id, the canonical output
+    pub route_list: Vec<VehicleRouteEntry>,
     pub total_distance: f64,
     pub empty_distance: f64,
     pub total_price: f64,
+    pub total_duration: f64,
+    /// Grams of CO2 over every route
+    pub total_co2: f64,
+    /// Longest minus shortest route distance, for auditing `max_route_spread_km`
+    pub route_spread: f64,
+    /// Longest single route distance, what the makespan criterion minimizes
+    pub max_route_distance: f64,
+    /// Orders no route serves, only non-empty when the fleet cannot cover every order
+    pub unassigned_order_ids: Vec<u32>,
+}
+
+impl ProblemSolution {
+    /// Sets the route of `vehicle_id` in both `routes` and `route_list`.
+    pub fn insert_route(&mut self, vehicle_id: u32, route: VehicleRoute) {
+        match self.route_list.binary_search_by_key(&vehicle_id, |entry| entry.vehicle_id) {
+            Ok(at) => self.route_list[at].route = route.clone(),
+            Err(at) => self.route_list.insert(at, VehicleRouteEntry { vehicle_id, route: route.clone() }),
+        }
+        self.routes.insert(vehicle_id.to_string(), route);
+    }
+
+    pub fn remove_route(&mut self, vehicle_id: u32) {
+        self.route_list.retain(|entry| entry.vehicle_id != vehicle_id);
+        self.routes.remove(&vehicle_id.to_string());
+    }
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct VehicleRouteEntry {
+    pub vehicle_id: u32,
+    pub route: VehicleRoute,
 }
 
 #[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct RouteSavings {
+    pub vehicle_id: u32,
+    pub distance: f64,
+    pub empty_distance: f64,
+    pub price: f64,
+}
+
+#[napi(object)]
+pub struct ReoptimizedSolution {
+    pub solution: ProblemSolution,
+    /// Per-vehicle improvement over the input routes, positive values are savings
+    pub savings: Vec<RouteSavings>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct AssignmentSuggestion {
+    /// Vehicle id -> order ids, in the shape `routeAssignment` accepts
+    pub assignments: HashMap<String, Vec<u32>>,
+    pub unassigned_order_ids: Vec<u32>,
+    pub estimated_distance: f64,
+    pub estimated_empty_distance: f64,
+    pub estimated_price: f64,
+    /// Always true, metrics are estimates rather than optimal routes
+    pub approximate: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct InsertionOption {
+    pub vehicle_id: u32,
+    pub feasible: bool,
+    /// Why the order cannot be added to this vehicle
+    pub reason: Option<String>,
+    /// Whether the route was re-solved exactly or the order was inserted heuristically
+    pub exact: bool,
+    pub delta_distance: f64,
+    pub delta_empty_distance: f64,
+    pub delta_price: f64,
+    pub route: Option<VehicleRoute>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct InsertionCost {
+    pub feasible: bool,
+    /// Vehicle with the lowest price delta
+    pub best_vehicle_id: Option<u32>,
+    pub options: Vec<InsertionOption>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct InsertionEvaluation {
+    pub feasible: bool,
+    pub vehicle_id: Option<u32>,
+    /// Index of the new pickup among the vehicle's updated stops
+    pub pickup_position: Option<u32>,
+    /// Index of the new delivery among the vehicle's updated stops
+    pub delivery_position: Option<u32>,
+    pub delta_distance: f64,
+    pub delta_empty_distance: f64,
+    pub delta_price: f64,
+    /// The plan with the order inserted, the input plan when infeasible
+    pub solution: ProblemSolution,
+    /// Constraint that ruled out the closest insertion, only set when infeasible
+    pub binding_constraint: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct RemovalEvaluation {
+    pub vehicle_id: u32,
+    pub saved_distance: f64,
+    pub saved_empty_distance: f64,
+    pub saved_price: f64,
+    /// The plan without the order, its vehicle's route gone if it served nothing else
+    pub solution: ProblemSolution,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct RemovalSaving {
+    pub vehicle_id: u32,
+    pub saved_distance: f64,
+    pub saved_empty_distance: f64,
+    pub saved_price: f64,
+    /// Whether the reduced route was re-solved exactly or only had the order's stops spliced out
+    pub exact: bool,
+    /// The vehicle's route without the order, empty if it was the only one
+    pub route: VehicleRoute,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct VehicleRanking {
+    pub vehicle_id: u32,
+    pub feasible: bool,
+    /// Value of the requested criterion, Infinity when infeasible
+    pub cost: f64,
+    pub reason: Option<String>,
+    pub route: Option<VehicleRoute>,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Criterion {
+    Distance,
+    Price,
+    Empty,
+    /// Longest single route distance, also when speeds, service times or windows are set
+    Makespan,
+    /// Sum of route durations
+    Duration,
+    /// Fewest vehicles used, ties broken by total distance
+    Fleet,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
 pub struct AlgorithmSolution {
+    /// Solutions of criteria left out of `SolveOptions::compute` are empty
     pub best_distance_solution: ProblemSolution,
     pub best_price_solution: ProblemSolution,
     pub best_empty_solution: ProblemSolution,
+    /// Assignment minimizing the longest route distance
+    pub best_makespan_solution: ProblemSolution,
+    pub best_duration_solution: ProblemSolution,
+    /// Assignment using the fewest vehicles, the shortest total distance among those
+    pub best_fleet_solution: ProblemSolution,
+    pub stats: SolveStats,
+    /// False when `time_limit_ms` or `max_nodes` stopped the search early, the solutions are then
+    /// the best found so far
+    pub optimal: bool,
+    /// Whether any complete assignment meeting every constraint was found
+    pub feasible: bool,
+    /// Orders no vehicle can serve even on its own, with the reason. When each one can, the orders
+    /// the plans leave out because no route takes them alongside the others
+    pub unassignable_orders: Vec<UnassignableOrder>,
+    /// The `top_k` best distance plans, best first, none unless `top_k` is above 1. Plans only
+    /// swapping the orders of interchangeable vehicles count once
+    pub alternatives: Vec<ProblemSolution>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct UnassignableOrder {
+    pub order_id: u32,
+    pub reason: String,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SolveStats {
+    /// Vehicle-order pairs excluded by `max_candidate_radius_km`
+    pub filtered_candidate_pairs: u32,
+    /// Partial assignments expanded by the search
+    pub assignment_nodes: i64,
+    /// Partial routes expanded while routing candidate order sets
+    pub route_nodes: i64,
+    /// Partial routes cut because even a lower bound on their remaining distance was too much
+    pub route_bound_prunes: i64,
+    /// Partial assignments cut because they could no longer beat the incumbents
+    pub assignment_bound_prunes: i64,
+    /// Partial assignments cut because the same orders were already reached more cheaply
+    pub assignment_revisit_prunes: i64,
+    /// Order sets offered to a vehicle by the assignment search
+    pub submasks: i64,
+    /// Order sets whose routes were already in the memo
+    pub memo_hits: i64,
+    /// Order sets routed from scratch
+    pub memo_misses: i64,
+    /// Routing results held by the largest worker's memo at the end of the search
+    pub memo_entries: i64,
+    /// Milliseconds the search ran, rebuilding the reported routes excluded
+    pub elapsed_ms: f64,
+    /// How far above the optimum the reported plans may be at most, as a fraction. 0 for an exact
+    /// search, at most `gap_tolerance` otherwise, none when the search was stopped early
+    pub achieved_gap: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SolveOptions {
+    /// Added to every criterion's cost per pickup/delivery switch, so simpler routes win
+    /// unless the zig-zag saves more than this
+    pub interleaving_penalty: Option<f64>,
+    /// No used route may be longer than the shortest used route by more than this. When no
+    /// assignment complies, each solution is the one with the smallest spread
+    pub max_route_spread_km: Option<f64>,
+    /// Every vehicle must serve at least one order, infeasible when there are fewer orders than vehicles
+    pub require_all_vehicles: Option<bool>,
+    /// Vehicles whose start is farther than this from an order's pickup are not considered for it.
+    /// Grows automatically when it would leave some order without any vehicle
+    pub max_candidate_radius_km: Option<f64>,
+    /// Stop searching after this long and return the best solutions found so far. The first
+    /// complete assignment is always evaluated before the limit is enforced
+    pub time_limit_ms: Option<u32>,
+    /// Stop searching after expanding this many assignment and route nodes, as counted in `stats`,
+    /// and return the best solutions found so far. The search then runs on one thread, so a budget
+    /// always gives the same result. Like `time_limit_ms`, enforced once a complete assignment was evaluated
+    pub max_nodes: Option<i64>,
+    /// Start the search from the greedy plan's costs, so pruning has a bound from the first
+    /// branch. Defaults to true, turn off to compare node counts
+    pub heuristic_seed: Option<bool>,
+    /// Extend routes with the nearest next stop first, which finds good routes early and prunes
+    /// more. Results are the same either way. Defaults to true, turn off to compare node counts
+    pub nearest_first: Option<bool>,
+    /// Prune partial routes on a lower bound of their remaining distance. Results are the same
+    /// either way. Defaults to true, turn off to compare node counts
+    pub route_lower_bound: Option<bool>,
+    /// Route order sets by dynamic programming instead of DFS. Results are the same either way.
+    /// Unset picks the DP for sets of 7 or more orders. Multi-trip vehicles and time windows always use the DFS
+    pub route_dp: Option<bool>,
+    /// Assign orders by dynamic programming over covered order sets instead of the recursive
+    /// search, far faster with many vehicles. Same optimal values, ignored with `max_route_spread_km`
+    pub assignment_dp: Option<bool>,
+    /// Minimize these metrics in order instead, each of "distance", "price", "empty" or "co2" at most
+    /// once, "co2" only when every vehicle has `co2_per_km`.
+    /// A later one only breaks ties of the earlier ones, and the one plan is reported under every
+    /// criterion. Searched by a single sequential search, `assignment_dp` and the spread cap do not apply
+    pub criteria: Option<Vec<String>>,
+    /// Earth radius and unit of every distance in and out, defaults to km on a 6371 km sphere
+    pub distance: Option<DistanceOptions>,
+    /// Minimize `distance * km + price * price + empty * empty km` instead, reported under every
+    /// criterion like `criteria`, which it cannot be combined with
+    pub weights: Option<ObjectiveWeights>,
+    /// Problems with more orders than this, at most 8, get the greedy plan of `solve_greedy` instead
+    /// of the exact search. Only the distance options apply to it. Unset, larger problems are rejected
+    pub max_orders_exact: Option<u32>,
+    /// Worker threads of the search, defaults to one per core
+    pub threads: Option<u32>,
+    /// Accept a plan within this fraction above the optimum, pruning every branch that cannot beat
+    /// the incumbent by more. Such plans are not `optimal`. Not with `criteria`, `weights` or `assignment_dp`
+    pub epsilon: Option<f64>,
+    /// `epsilon` by the name of what it guarantees: the plans are at most this fraction above the
+    /// optimum, `stats.achieved_gap` telling how close they provably came. Not together with `epsilon`
+    pub gap_tolerance: Option<f64>,
+    /// Report each route's legs, defaults to true. Turn off to keep results small
+    pub return_legs: Option<bool>,
+    /// Only search for the best plans of these criteria, the others come back empty. Fewer
+    /// criteria prune far more, e.g. `["price"]` when only the price plan is used. Defaults to all,
+    /// not with `criteria` or `weights`
+    pub compute: Option<Vec<Criterion>>,
+    /// Pass the improved plan to every `onImprovement` call. Rebuilding it costs about as much
+    /// as the final result, so it is off by default
+    pub improvement_solutions: Option<bool>,
+    /// Also list this many of the best distinct distance plans as `alternatives`, defaults to 1
+    /// (none). Prunes far less, as the runners-up have to be searched for too. Not with `criteria`,
+    /// `weights` or `assignment_dp`
+    pub top_k: Option<u32>,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistanceUnit {
+    Km,
+    Miles,
+}
+
+/// `GreatCircle` measures on a sphere. `Euclidean` reads latitude as y and longitude as x on a
+/// plane, for synthetic benchmarks, with distances in whatever unit the coordinates are in.
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistanceMode {
+    GreatCircle,
+    Euclidean,
+}
+
+/// How distances are measured and reported. With another unit every distance-like input is read
+/// in it too: `price_km` is the price per unit, `speed_kmh` units per hour, and `max_distance_km`,
+/// `max_route_spread_km` and `max_candidate_radius_km` are in units. Unset speeds keep the default in km/h.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct DistanceOptions {
+    /// Defaults to 6371.0, e.g. 6371.0088 for the IUGG mean radius
+    pub radius_km: Option<f64>,
+    /// Defaults to km
+    pub unit: Option<DistanceUnit>,
+    /// Defaults to great circle. Euclidean distances are in coordinate units already, so
+    /// `radius_km` and `unit` are ignored and inputs are read per coordinate unit
+    pub mode: Option<DistanceMode>,
+    /// Road detour factor every distance is multiplied by, e.g. 1.3. Defaults to 1.0
+    pub circuity: Option<f64>,
+    /// Circuity of locations whose hash starts with the key, the longest matching prefix wins.
+    /// A leg between two locations takes the mean of their factors
+    pub circuity_by_prefix: Option<HashMap<String, f64>>,
+}
+
+/// What a JS route cost callback is told about one vehicle's route.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct RouteSummary {
+    pub vehicle_id: u32,
+    pub order_ids: Vec<u32>,
+    pub distance: f64,
+    pub empty_distance: f64,
+    /// Built-in price, `distance * price_km`
+    pub price: f64,
+    pub duration: f64,
+}
+
+/// A named what-if patch on a base problem: removals first, then replacements by id, then additions.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct Scenario {
+    pub name: String,
+    pub add_vehicles: Option<Vec<Vehicle>>,
+    pub remove_vehicle_ids: Option<Vec<u32>>,
+    /// Replace the vehicle with the same id
+    pub update_vehicles: Option<Vec<Vehicle>>,
+    pub add_orders: Option<Vec<Order>>,
+    pub remove_order_ids: Option<Vec<u32>>,
+    /// Replace the order with the same id
+    pub update_orders: Option<Vec<Order>>,
+}
+
+impl Scenario {
+    /// Locations of the vehicles and orders the scenario adds or replaces.
+    pub fn locations_mut(&mut self) -> impl Iterator<Item = &mut Location> {
+        let vehicles = self.add_vehicles.iter_mut().chain(self.update_vehicles.iter_mut()).flatten();
+        let orders = self.add_orders.iter_mut().chain(self.update_orders.iter_mut()).flatten();
+        vehicles.flat_map(Vehicle::locations_mut).chain(orders.flat_map(Order::locations_mut))
+    }
+}
+
+/// Scenario minus base for each objective's best solution, negative values are improvements.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioDelta {
+    pub distance: f64,
+    pub price: f64,
+    pub empty_distance: f64,
+    pub makespan: f64,
+    pub duration: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub solution: AlgorithmSolution,
+    pub delta: ScenarioDelta,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioComparison {
+    pub base: AlgorithmSolution,
+    pub scenarios: Vec<ScenarioResult>,
+}
+
+/// Relative weight of each objective in a heuristic's single cost, unset weights are 0.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ObjectiveWeights {
+    pub distance: Option<f64>,
+    pub price: Option<f64>,
+    pub empty: Option<f64>,
+}
+
+/// Assignment minimizing one weighted cost, with that cost split per objective.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct WeightedSolution {
+    pub solution: ProblemSolution,
+    /// Weighted cost of `solution`, the sum of the three parts below
+    pub cost: f64,
+    pub distance_cost: f64,
+    pub price_cost: f64,
+    pub empty_cost: f64,
+    /// Whether an assignment covering every order was found
+    pub feasible: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct AnnealingOptions {
+    /// Defaults to 20000
+    pub iterations: Option<u32>,
+    /// Defaults to 10
+    pub initial_temperature: Option<f64>,
+    /// Temperature multiplier per iteration, defaults to 0.9995
+    pub cooling_rate: Option<f64>,
+    /// Same seed and problem always give the same result, defaults to 1
+    pub seed: Option<u32>,
+    /// Defaults to total distance only
+    pub weights: Option<ObjectiveWeights>,
+    /// How distances are measured, great-circle km by default
+    pub distance: Option<DistanceOptions>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct LnsOptions {
+    /// Destroy and repair rounds, defaults to 1000
+    pub iterations: Option<u32>,
+    /// Same seed, problem and solution always give the same result, defaults to 1
+    pub seed: Option<u32>,
+    /// Stop after this long with the best plan so far, even before `iterations` rounds
+    pub time_limit_ms: Option<u32>,
+    /// How distances are measured, great-circle km by default
+    pub distance: Option<DistanceOptions>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct LnsResult {
+    pub solution: ProblemSolution,
+    /// Rounds whose repaired plan replaced the current one
+    pub accepted_moves: u32,
+}
+
+#[napi(string_enum = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalSearchMove {
+    /// Move one order to another vehicle
+    Relocate,
+    /// Exchange two orders between vehicles
+    Swap,
+    /// Exchange the tails of two routes
+    TwoOptStar,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct LocalSearchOptions {
+    /// Moves to try, in this order, defaults to all of them
+    pub moves: Option<Vec<LocalSearchMove>>,
+    /// How distances are measured, great-circle km by default
+    pub distance: Option<DistanceOptions>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct AppliedMove {
+    pub kind: LocalSearchMove,
+    /// Orders that changed vehicle, those leaving `from_vehicle_id` first
+    pub order_ids: Vec<u32>,
+    pub from_vehicle_id: u32,
+    pub to_vehicle_id: u32,
+    /// Total distance the move saved
+    pub saved_distance: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct LocalSearchResult {
+    pub solution: ProblemSolution,
+    /// Moves applied, in order
+    pub moves: Vec<AppliedMove>,
+    pub saved_distance: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ClusterOptions {
+    /// Groups the orders are split into, each routed by its own vehicle. At most the number of
+    /// vehicles, which is also the default
+    pub clusters: Option<u32>,
+    /// How distances are measured, great-circle km by default
+    pub distance: Option<DistanceOptions>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct OrderCluster {
+    /// Vehicle routing the cluster
+    pub vehicle_id: u32,
+    /// Orders grouped into the cluster, spilled ones included
+    pub order_ids: Vec<u32>,
+    /// Whether the cluster was routed exactly, or greedily for being too large or not fitting one route
+    pub exact: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ClusteredSolution {
+    pub solution: ProblemSolution,
+    pub clusters: Vec<OrderCluster>,
+    /// Orders their cluster's route could not take, served on another vehicle's route instead
+    pub spilled_order_ids: Vec<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct RegretOptions {
+    /// Vehicles compared per order: the regret is what the order loses over its next
+    /// `regret_k - 1` cheapest vehicles. 1 is plain cheapest insertion, defaults to 2
+    pub regret_k: Option<u32>,
+    /// How distances are measured, great-circle km by default
+    pub distance: Option<DistanceOptions>,
+}
+
+/// Algorithm `solve_auto` picked for a problem.
+#[napi(string_enum = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoAlgorithm {
+    /// The exact search alone
+    Exact,
+    /// The exact search, pruning from the greedy plan's costs
+    SeededExact,
+    /// Large neighbourhood search from a regret insertion plan
+    Lns,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct AutoOptions {
+    /// Largest problem searched exactly without seeding, defaults to 6 orders
+    pub exact_max_orders: Option<u32>,
+    /// Largest problem searched exactly from the greedy plan, at most and by default 8 orders
+    pub seeded_max_orders: Option<u32>,
+    /// Beyond this many vehicles the exact search is never tried, defaults to 8
+    pub exact_max_vehicles: Option<u32>,
+    /// Stop after this long with the best plan so far, whichever algorithm runs
+    pub time_limit_ms: Option<u32>,
+    /// Earth radius and unit of every distance in and out, defaults to km on a 6371 km sphere
+    pub distance: Option<DistanceOptions>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct AutoSolution {
+    pub algorithm: AutoAlgorithm,
+    /// Whether the plans are proven optimal: the exact search ran to the end
+    pub optimal: bool,
+    pub solution: AlgorithmSolution,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct BeamOptions {
+    /// Partial assignments kept per vehicle, defaults to 50. Infinity keeps every one, which is exact
+    pub beam_width: Option<f64>,
+    /// Criterion the kept assignments are ranked by and the routes optimized for, defaults to distance
+    pub criterion: Option<Criterion>,
+    /// How distances are measured, great-circle km by default
+    pub distance: Option<DistanceOptions>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct AnnealingStats {
+    pub iterations: u32,
+    /// Moves that kept every route feasible
+    pub feasible_moves: u32,
+    pub accepted_moves: u32,
+    /// Iteration at which the returned solution was found, 0 for the starting plan
+    pub best_iteration: u32,
+    pub final_temperature: f64,
+    pub initial_cost: f64,
+    pub best_cost: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct AnnealingResult {
+    pub solution: ProblemSolution,
+    pub stats: AnnealingStats,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct CriterionDelta {
+    pub criterion: Criterion,
+    pub a: f64,
+    pub b: f64,
+    /// `b - a`, positive when `b` is worse
+    pub delta: f64,
+    /// `delta` as a percentage of `a`, none when `a` is 0
+    pub percent: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SolutionComparison {
+    /// One per criterion, fleet counting the vehicles used
+    pub deltas: Vec<CriterionDelta>,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Copy, Debug)]
+pub struct NumberRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct GeneratorOptions {
+    pub n_orders: u32,
+    pub n_vehicles: u32,
+    /// Same seed, same problem. Defaults to 1
+    pub seed: Option<u32>,
+    /// Where every location is drawn, defaults to latitudes 54-56 and longitudes 23-26
+    pub bbox: Option<BoundingBox>,
+    /// Load factors are whole numbers within this range, defaults to 1-4
+    pub load_factor_range: Option<NumberRange>,
+    /// Price per km of each vehicle, rounded to cents, defaults to 0.5-1.5
+    pub price_range: Option<NumberRange>,
+    /// Gather pickups around this many random centres instead of spreading them over the box
+    pub clustering: Option<u32>,
+}
+
+/// What is wrong with a route, one code per rule `validateSolution` checks.
+#[napi(string_enum = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViolationCode {
+    UnknownVehicle,
+    UnknownOrder,
+    UnknownStopType,
+    DeliveryBeforePickup,
+    CapacityExceeded,
+    /// Picked up or delivered twice, on one route or on two
+    ServedTwice,
+    MissingDelivery,
+    /// Neither on any route nor listed as unassigned
+    MissingOrder,
+    TimeWindowMissed,
+    MissingTags,
+    RouteLimitExceeded,
+    TooManyOrders,
+    TooManyTrips,
+    ReloadWhileLoaded,
+    /// Claimed distance, empty distance, price or duration differs from the recomputed one
+    TotalsMismatch,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct SolutionViolation {
+    /// Key of the route in `solution.routes`, absent for orders no route serves and plan totals
+    pub route_key: Option<String>,
+    /// Index into the route's stops, absent when the route as a whole is at fault
+    pub stop_index: Option<u32>,
+    pub order_id: Option<u32>,
+    pub code: ViolationCode,
+    pub message: String,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SolutionReport {
+    pub valid: bool,
+    pub violations: Vec<SolutionViolation>,
+    /// Totals recomputed from the stops of every route, with the same leg rules as the solver
+    pub total_distance: f64,
+    pub empty_distance: f64,
+    pub total_price: f64,
+    pub total_duration: f64,
 }
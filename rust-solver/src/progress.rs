@@ -0,0 +1,247 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+
+use crate::error::{catch_panic, ErrorCode};
+use crate::models::{AlgorithmSolution, Criterion, Problem, ProblemSolution, RouteSummary, SolveOptions};
+use crate::solver;
+use crate::solver::context::{ImprovementFn, RouteCostFn, SharedContext, SolverContext};
+use crate::solver::types::PartialCost;
+use crate::utils::DistanceModel;
+
+pub type RouteCostCallback = ThreadsafeFunction<RouteSummary, f64, RouteSummary, Status, false>;
+
+/// A new best value of one criterion, as passed to an `onImprovement` callback.
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct Improvement {
+    pub criterion: Criterion,
+    /// The criterion's new best value, in the distance unit of the solve for distances
+    pub value: f64,
+    /// Milliseconds since the solve started
+    pub elapsed_ms: f64,
+    /// The improved plan, only with `improvement_solutions`
+    pub solution: Option<ProblemSolution>,
+}
+
+pub type ImprovementCallback = ThreadsafeFunction<Improvement, (), Improvement, Status, false>;
+
+// Criteria improvements are reported for, the same ones `SolveProgress` tracks
+const REPORTED: [Criterion; 5] = [Criterion::Distance, Criterion::Price, Criterion::Empty, Criterion::Makespan, Criterion::Duration];
+
+/// Best objective values found so far, None until a complete assignment is reached.
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SolveProgress {
+    pub best_distance: Option<f64>,
+    pub best_price: Option<f64>,
+    pub best_empty: Option<f64>,
+    pub best_makespan: Option<f64>,
+    pub best_duration: Option<f64>,
+    pub finished: bool,
+    /// Set together with `finished` when `cancel` stopped the search, `solution` stays empty
+    pub cancelled: bool,
+    /// Only set once the search has finished
+    pub solution: Option<AlgorithmSolution>,
+    /// Set together with `finished` when the search failed, as `"<code>: <message>"` like a thrown error's message
+    pub error: Option<String>,
+}
+
+pub type SharedProgress = Arc<Mutex<SolveProgress>>;
+
+/// Copies the incumbents of `ctx` into its progress snapshot, if anyone is watching.
+pub(crate) fn publish(ctx: &SolverContext) {
+    if let Some(progress) = &ctx.progress {
+        let finite = |value: f64| value.is_finite().then_some(value);
+        let scale = solver::units::unit_scale(&ctx.shared.options);
+        let best = solver::incumbent(ctx);
+        let mut progress = progress.lock().unwrap();
+        progress.best_distance = finite(best.dist * scale);
+        progress.best_price = finite(best.price);
+        progress.best_empty = finite(best.empty * scale);
+        progress.best_makespan = finite(best.makespan * scale);
+        progress.best_duration = finite(best.duration);
+    }
+}
+
+/// Reports every criterion `ctx` has improved on since `before` to its improvement callback.
+pub(crate) fn report_improvements(ctx: &mut SolverContext, before: &PartialCost) {
+    let Some(on_improvement) = ctx.shared.on_improvement.clone() else {
+        return;
+    };
+    let scale = solver::units::unit_scale(&ctx.shared.options);
+    let with_solution = ctx.shared.options.improvement_solutions.unwrap_or(false);
+    let after = solver::local_incumbent(ctx);
+    let criteria = ctx.shared.criteria;
+    for criterion in REPORTED.into_iter().filter(|&c| criteria.contains(c) && after.get(c) < before.get(c)) {
+        let solution = with_solution.then(|| {
+            let assignments = ctx.incumbent_assignments(criterion).to_vec();
+            let mut solution = solver::reconstruct_solution(ctx, &assignments, criterion);
+            solver::units::solution_from_base(&mut solution, scale);
+            solution
+        });
+        let value = match criterion {
+            Criterion::Distance | Criterion::Empty | Criterion::Makespan => after.get(criterion) * scale,
+            _ => after.get(criterion),
+        };
+        on_improvement(criterion, value, solution);
+    }
+}
+
+/// Queues improvements for `callback` without waiting for the JS thread. Values no better than
+/// one already queued for their criterion are dropped, so whichever worker finds them the
+/// callback sees every criterion improve monotonically.
+pub fn non_blocking_improvements(callback: ImprovementCallback) -> ImprovementFn {
+    let started = Instant::now();
+    let sent = Mutex::new(PartialCost::UNREACHED);
+    Arc::new(move |criterion, value, solution| {
+        // Held while queueing, so improvements are queued in the order they were accepted
+        let mut sent = sent.lock().unwrap();
+        if value >= sent.get(criterion) {
+            return;
+        }
+        sent.set(criterion, value);
+        let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        callback.call(Improvement { criterion, value, elapsed_ms, solution }, ThreadsafeFunctionCallMode::NonBlocking);
+    })
+}
+
+/// A solve running on a background thread, whose incumbents can be read while it searches.
+#[napi]
+pub struct SolveHandle {
+    progress: SharedProgress,
+    cancel: Arc<AtomicBool>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SolveHandle {
+    /// A handle with nothing left to search, for plans that need no background thread.
+    fn finished(solution: AlgorithmSolution) -> Self {
+        let progress = SolveProgress {
+            best_distance: Some(solution.best_distance_solution.total_distance),
+            best_price: Some(solution.best_price_solution.total_price),
+            best_empty: Some(solution.best_empty_solution.empty_distance),
+            best_makespan: Some(solution.best_makespan_solution.max_route_distance),
+            best_duration: Some(solution.best_duration_solution.total_duration),
+            finished: true,
+            cancelled: false,
+            solution: Some(solution),
+            error: None,
+        };
+        SolveHandle {
+            progress: Arc::new(Mutex::new(progress)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            worker: Mutex::new(None),
+        }
+    }
+}
+
+#[napi]
+impl SolveHandle {
+    /// Starts solving `problem` on a background thread.
+    ///
+    /// `route_cost_fn` optionally replaces the price of each candidate route used by the price
+    /// objective. Each search worker calls it once per distinct (vehicle, order set) it routes, which can
+    /// be up to vehicles * 2^orders calls per worker, each a round trip to the JS thread while the worker waits,
+    /// so expect price solves to be far slower with it. Prices are cached per worker, not per solve, so with
+    /// several threads the same route may be priced once by each. If it throws or returns anything but a
+    /// finite number the search stops and finishes with `error` set. Without it the built-in pricing is used.
+    /// Routes it priced keep their leg prices and report the difference in `cost_breakdown.custom_adjustment`.
+    #[napi(factory)]
+    pub fn start(mut problem: Problem, options: Option<SolveOptions>, route_cost_fn: Option<RouteCostCallback>) -> Result<Self, ErrorCode> {
+        let options = options.unwrap_or_default();
+        solver::normalize_locations(problem.locations_mut(), options.distance.as_ref())?;
+        solver::validate_options(&problem, &options)?;
+        if solver::plans_greedily(&problem, &options) {
+            return Ok(SolveHandle::finished(solver::greedy_plan(problem, options)));
+        }
+        let (problem, options) = solver::units::to_base(problem, options);
+        let pool = solver::thread_pool(&options)?;
+        let route_cost = route_cost_fn.map(blocking_route_cost);
+
+        let progress = SharedProgress::default();
+        let worker_progress = progress.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        let worker = std::thread::spawn(move || {
+            // A panic would otherwise end the thread without ever setting `finished`
+            let outcome = catch_panic(|| {
+                let search = || {
+                    let shared = SharedContext {
+                        route_cost,
+                        cancel: Some(worker_cancel),
+                        ..SharedContext::from_problem(problem, None, DistanceModel::from_options(options.distance.as_ref())).with_options(options.clone())
+                    };
+                    solver::parallel::search(Arc::new(shared), Some(worker_progress.clone()))
+                };
+                let mut ctx = match &pool {
+                    Some(pool) => pool.install(search),
+                    None => search(),
+                };
+                publish(&ctx);
+                if let Some(err) = ctx.route_cost_error.take() {
+                    return Err(err);
+                }
+
+                Ok((!ctx.cancelled).then(|| {
+                    let solution = solver::units::algorithm_from_base(solver::collect(&mut ctx), &options);
+                    solver::finish(solution, &options)
+                }))
+            });
+            // A panicking worker may have poisoned the lock mid-publish, the snapshot is still usable
+            let mut progress = worker_progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match outcome {
+                Ok(solution) => {
+                    progress.cancelled = solution.is_none();
+                    progress.solution = solution;
+                }
+                Err(err) => progress.error = Some(format!("{}: {}", err.code(), err)),
+            }
+            progress.finished = true;
+        });
+
+        Ok(SolveHandle {
+            progress,
+            cancel,
+            worker: Mutex::new(Some(worker)),
+        })
+    }
+
+    /// Stops the search, which then finishes as `cancelled`. Does nothing once it has finished.
+    #[napi]
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Snapshot of the search, the final result once `finished` is set.
+    #[napi]
+    pub fn current(&self) -> SolveProgress {
+        let progress = self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        if progress.finished {
+            // Reap the worker so its thread does not linger
+            if let Some(worker) = self.worker.lock().unwrap().take() {
+                let _ = worker.join();
+            }
+        }
+        progress
+    }
+}
+
+// The worker blocks until the JS thread has run the callback and handed back its result
+fn blocking_route_cost(callback: RouteCostCallback) -> RouteCostFn {
+    Arc::new(move |summary| {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        callback.call_with_return_value(summary, ThreadsafeFunctionCallMode::Blocking, move |price, _env| {
+            let _ = sender.send(price);
+            Ok(())
+        });
+        receiver
+            .recv()
+            .map_err(|_| Error::new(Status::GenericFailure, "Route cost callback did not return".to_string()))?
+    })
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use super::context::SharedContext;
+use super::types::{wins_tie_break, OrderMask};
+
+/// The `top_k` best distinct complete assignments by distance, best first.
+///
+/// Two assignments are the same plan when they only swap the order sets of interchangeable
+/// vehicles, so each is keyed by its order sets sorted within every group of such vehicles.
+#[derive(Clone, Debug)]
+pub struct Alternatives {
+    k: usize,
+    // Per vehicle, the group of vehicles it is interchangeable with
+    groups: Vec<usize>,
+    // (distance, canonical key, assignments), sorted by distance then key
+    kept: Vec<(f64, Vec<OrderMask>, Vec<OrderMask>)>,
+}
+
+impl Alternatives {
+    /// Keeps nothing unless `top_k` is above 1, the single best plan is the incumbent's job.
+    pub fn new(shared: &SharedContext, memo_rows: &[usize]) -> Self {
+        let k = shared.options.top_k.unwrap_or(1) as usize;
+        // Vehicles route alike when they share a memo row, they are interchangeable when they
+        // may also take the same orders under the same limits
+        let mut keys = HashMap::new();
+        let groups = (0..shared.vehicles.len())
+            .map(|v_idx| {
+                let vehicle = &shared.vehicles[v_idx];
+                let key = (
+                    memo_rows[v_idx],
+                    shared.vehicle_allowed[v_idx],
+                    shared.fixed[v_idx],
+                    vehicle.max_orders,
+                    vehicle.min_route_price.map(f64::to_bits),
+                );
+                let next = keys.len();
+                *keys.entry(key).or_insert(next)
+            })
+            .collect();
+        Alternatives { k: if k > 1 { k } else { 0 }, groups, kept: Vec::new() }
+    }
+
+    #[inline(always)]
+    pub fn active(&self) -> bool {
+        self.k > 0
+    }
+
+    /// Distance a branch has to beat to still make the list, infinite until it is full.
+    #[inline(always)]
+    pub fn threshold(&self) -> f64 {
+        if self.kept.len() < self.k {
+            f64::INFINITY
+        } else {
+            self.kept.last().map_or(f64::INFINITY, |(dist, _, _)| *dist)
+        }
+    }
+
+    /// Order sets grouped by vehicle group, sorted within each group.
+    fn canonical(&self, assignments: &[OrderMask]) -> Vec<OrderMask> {
+        let mut keyed: Vec<(usize, OrderMask)> = self.groups.iter().copied().zip(assignments.iter().copied()).collect();
+        keyed.sort_unstable();
+        keyed.into_iter().map(|(_, mask)| mask).collect()
+    }
+
+    /// Keeps `assignments` if it is among the `k` best so far. Of two assignments forming the same
+    /// plan the cheaper one stays, ties going by `wins_tie_break`.
+    pub fn offer(&mut self, dist: f64, assignments: &[OrderMask]) {
+        if !self.active() || dist > self.threshold() {
+            return;
+        }
+        let key = self.canonical(assignments);
+        if let Some(at) = self.kept.iter().position(|(_, other, _)| *other == key) {
+            let (kept_dist, _, kept_assignments) = &self.kept[at];
+            if !wins_tie_break(dist, assignments, *kept_dist, kept_assignments) {
+                return;
+            }
+            self.kept.remove(at);
+        }
+        let at = self
+            .kept
+            .iter()
+            .position(|(kept_dist, _, kept_assignments)| wins_tie_break(dist, assignments, *kept_dist, kept_assignments))
+            .unwrap_or(self.kept.len());
+        self.kept.insert(at, (dist, key, assignments.to_vec()));
+        self.kept.truncate(self.k);
+    }
+
+    /// Folds in another worker's list.
+    pub fn merge(&mut self, other: &Alternatives) {
+        for (dist, _, assignments) in &other.kept {
+            self.offer(*dist, assignments);
+        }
+    }
+
+    /// Assignments kept, best first.
+    pub fn assignments(&self) -> Vec<Vec<OrderMask>> {
+        self.kept.iter().map(|(_, _, assignments)| assignments.clone()).collect()
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use crate::error::{Result, SolverError};
+use crate::models::{Problem, ProblemSolution, RouteStop, SolutionReport, SolutionViolation, ViolationCode};
+use crate::utils::DistanceModel;
+use super::evaluate::{audit_stops, route_from_stops, Stop, ViolationKind};
+use super::marginal::order_index;
+use super::{max_route_distance, route_spread};
+
+// Claimed totals come from summing the same legs in another order
+const TOTALS_EPSILON: f64 = 1e-6;
+
+fn totals_agree(claimed: f64, recomputed: f64) -> bool {
+    (claimed - recomputed).abs() <= TOTALS_EPSILON * (1.0 + recomputed.abs())
+}
+
+fn describe(kind: ViolationKind, order_id: Option<u32>) -> (ViolationCode, String) {
+    let order = order_id.map_or_else(|| "The route".to_string(), |id| format!("Order {}", id));
+    match kind {
+        ViolationKind::DeliveryBeforePickup => (ViolationCode::DeliveryBeforePickup, format!("{} is delivered before it is picked up", order)),
+        ViolationKind::CapacityExceeded => (ViolationCode::CapacityExceeded, format!("{} does not fit on the vehicle", order)),
+        ViolationKind::DuplicateStop => (ViolationCode::ServedTwice, format!("{} is served twice on the route", order)),
+        ViolationKind::MissingDelivery => (ViolationCode::MissingDelivery, format!("{} is never delivered", order)),
+        ViolationKind::TimeWindowMissed => (ViolationCode::TimeWindowMissed, format!("{} is reached after its window closes", order)),
+        ViolationKind::MissingTags => (ViolationCode::MissingTags, format!("{} needs tags the vehicle lacks", order)),
+        ViolationKind::RouteLimitExceeded => (ViolationCode::RouteLimitExceeded, "The route exceeds the vehicle's max_distance_km or max_duration_min".to_string()),
+        ViolationKind::TooManyOrders => (ViolationCode::TooManyOrders, format!("{} is beyond the vehicle's max_orders", order)),
+        ViolationKind::ReloadWhileLoaded => (ViolationCode::ReloadWhileLoaded, "The vehicle reloads with orders still on board".to_string()),
+        ViolationKind::TooManyTrips => (ViolationCode::TooManyTrips, "The reload starts more trips than the vehicle may run".to_string()),
+    }
+}
+
+fn parse_stop(stop: &RouteStop, order_idx: &HashMap<u32, usize>) -> std::result::Result<Stop, (ViolationCode, String)> {
+    match (stop.type_.as_str(), order_idx.get(&stop.order_id)) {
+        ("reload", _) => Ok(Stop::RELOAD),
+        ("pickup" | "delivery", Some(&o_idx)) => Ok(Stop { order_idx: o_idx, pickup: stop.type_ == "pickup" }),
+        ("pickup" | "delivery", None) => Err((ViolationCode::UnknownOrder, format!("No order has id {}", stop.order_id))),
+        (other, _) => Err((ViolationCode::UnknownStopType, format!("Unknown stop type '{}'", other))),
+    }
+}
+
+/// Checks a possibly hand-edited plan against `problem`: every stop must name a known order, each
+/// route must pass the checks of `evaluate_stops` and every order must be served exactly once or
+/// listed as unassigned. Totals are recomputed with the solver's leg rules and compared with
+/// the claimed ones, route by route and for the whole plan, every leg measured by `model`.
+pub fn validate_solution(problem: &Problem, solution: &ProblemSolution, model: &DistanceModel) -> SolutionReport {
+    let orders = &problem.orders;
+    let order_idx = order_index(orders);
+    let mut report = SolutionReport::default();
+    let mut violations = Vec::new();
+    let mut served_on: Vec<Option<&str>> = vec![None; orders.len()];
+
+    let mut keys: Vec<&String> = solution.routes.keys().collect();
+    keys.sort();
+    for key in keys {
+        let route = &solution.routes[key];
+        let mut violation = |stop_index: Option<usize>, order_id: Option<u32>, code: ViolationCode, message: String| {
+            violations.push(SolutionViolation {
+                route_key: Some(key.clone()),
+                stop_index: stop_index.map(|i| i as u32),
+                order_id,
+                code,
+                message,
+            })
+        };
+
+        let Some(vehicle) = problem.vehicles.iter().find(|v| v.id.to_string() == *key) else {
+            violation(None, None, ViolationCode::UnknownVehicle, format!("No vehicle has id {}", key));
+            continue;
+        };
+
+        // Stops that parse, with their index among the route's stops
+        let mut stops = Vec::new();
+        let mut positions = Vec::new();
+        for (i, stop) in route.stops.iter().enumerate() {
+            match parse_stop(stop, &order_idx) {
+                Ok(parsed) => {
+                    stops.push(parsed);
+                    positions.push(i);
+                }
+                Err((code, message)) => violation(Some(i), Some(stop.order_id), code, message),
+            }
+        }
+
+        let metrics = audit_stops(model, vehicle, orders, &stops, |index, kind| {
+            // Reported per order below, the walk only knows something is still on board
+            if kind == ViolationKind::MissingDelivery {
+                return;
+            }
+            let at = stops.get(index).filter(|s| !s.is_reload());
+            let (code, message) = describe(kind, at.map(|s| orders[s.order_idx].id));
+            violation(positions.get(index).copied(), at.map(|s| orders[s.order_idx].id), code, message);
+        });
+
+        let delivered = |o_idx: usize| stops.iter().any(|s| s.order_idx == o_idx && !s.pickup);
+        for (k, stop) in stops.iter().enumerate() {
+            if stop.pickup && !delivered(stop.order_idx) {
+                let (code, message) = describe(ViolationKind::MissingDelivery, Some(orders[stop.order_idx].id));
+                violation(Some(positions[k]), Some(orders[stop.order_idx].id), code, message);
+            }
+        }
+        for &id in vehicle.onboard_order_ids.iter().flatten() {
+            if order_idx.get(&id).is_some_and(|&o_idx| !delivered(o_idx)) {
+                let (code, message) = describe(ViolationKind::MissingDelivery, Some(id));
+                violation(None, Some(id), code, message);
+            }
+        }
+
+        for (k, stop) in stops.iter().enumerate().filter(|(_, s)| !s.is_reload()) {
+            match served_on[stop.order_idx] {
+                Some(other) if other != key.as_str() => {
+                    let id = orders[stop.order_idx].id;
+                    violation(Some(positions[k]), Some(id), ViolationCode::ServedTwice, format!("Order {} is also served by route {}", id, other));
+                    // One report per order and route is enough
+                    served_on[stop.order_idx] = Some(key.as_str());
+                }
+                _ => served_on[stop.order_idx] = Some(key.as_str()),
+            }
+        }
+
+        let mismatched: Vec<&str> = [
+            ("total_distance", route.total_distance, metrics.dist),
+            ("empty_distance", route.empty_distance, metrics.empty),
+            ("total_price", route.total_price, metrics.price),
+            ("total_duration_min", route.total_duration_min, metrics.duration),
+        ]
+        .into_iter()
+        .filter(|&(_, claimed, recomputed)| !totals_agree(claimed, recomputed))
+        .map(|(field, _, _)| field)
+        .collect();
+        if !mismatched.is_empty() {
+            violation(None, None, ViolationCode::TotalsMismatch, format!("Route {} differs from its stops", mismatched.join(", ")));
+        }
+
+        report.total_distance += metrics.dist;
+        report.empty_distance += metrics.empty;
+        report.total_price += metrics.price;
+        report.total_duration += metrics.duration;
+    }
+
+    let plan_violation = |order_id: Option<u32>, code: ViolationCode, message: String| SolutionViolation {
+        route_key: None,
+        stop_index: None,
+        order_id,
+        code,
+        message,
+    };
+    for (order, served) in orders.iter().zip(&served_on) {
+        if served.is_none() && !solution.unassigned_order_ids.contains(&order.id) {
+            violations.push(plan_violation(
+                Some(order.id),
+                ViolationCode::MissingOrder,
+                format!("Order {} is on no route and not listed as unassigned", order.id),
+            ));
+        }
+    }
+    let mismatched: Vec<&str> = [
+        ("total_distance", solution.total_distance, report.total_distance),
+        ("empty_distance", solution.empty_distance, report.empty_distance),
+        ("total_price", solution.total_price, report.total_price),
+        ("total_duration", solution.total_duration, report.total_duration),
+    ]
+    .into_iter()
+    .filter(|&(_, claimed, recomputed)| !totals_agree(claimed, recomputed))
+    .map(|(field, _, _)| field)
+    .collect();
+    if !mismatched.is_empty() {
+        violations.push(plan_violation(None, ViolationCode::TotalsMismatch, format!("Plan {} differs from its routes", mismatched.join(", "))));
+    }
+
+    report.valid = violations.is_empty();
+    report.violations = violations;
+    report
+}
+
+/// Exact metrics of an externally built plan, every route measured with the solver's leg rules so
+/// human and solver plans compare in the same units. Capacity, windows and the vehicle's limits are
+/// not enforced, `validate_solution` reports those. Stops naming unknown vehicles or orders, and
+/// stops that cannot be driven in the given order, are errors. Legs are measured by `model`.
+pub fn evaluate_solution(problem: &Problem, routes: &HashMap<String, Vec<RouteStop>>, model: &DistanceModel) -> Result<ProblemSolution> {
+    let orders = &problem.orders;
+    let order_idx = order_index(orders);
+    let mut solution = ProblemSolution::default();
+    let mut served = vec![false; orders.len()];
+
+    let mut keys: Vec<&String> = routes.keys().collect();
+    keys.sort();
+    for key in keys {
+        let route_error = |msg: String| SolverError::InvalidArg(format!("Route {}: {}", key, msg));
+        let vehicle = problem
+            .vehicles
+            .iter()
+            .find(|v| v.id.to_string() == *key)
+            .ok_or_else(|| route_error("no vehicle has this id".to_string()))?;
+        let stops = routes[key]
+            .iter()
+            .enumerate()
+            .map(|(i, stop)| parse_stop(stop, &order_idx).map_err(|(_, message)| route_error(format!("stop {}: {}", i, message))))
+            .collect::<Result<Vec<_>>>()?;
+        if stops.is_empty() {
+            continue;
+        }
+
+        let mut undrivable = None;
+        let metrics = audit_stops(model, vehicle, orders, &stops, |index, kind| {
+            if matches!(kind, ViolationKind::DuplicateStop | ViolationKind::DeliveryBeforePickup) {
+                undrivable.get_or_insert((index, kind));
+            }
+        });
+        if let Some((index, kind)) = undrivable {
+            let (_, message) = describe(kind, Some(orders[stops[index].order_idx].id));
+            return Err(route_error(format!("stop {}: {}", index, message)));
+        }
+
+        for stop in stops.iter().filter(|s| !s.is_reload()) {
+            served[stop.order_idx] = true;
+        }
+        let route = route_from_stops(model, vehicle, orders, &stops, &metrics);
+        solution.total_distance += route.total_distance;
+        solution.empty_distance += route.empty_distance;
+        solution.total_price += route.total_price;
+        solution.total_co2 += route.total_co2;
+        solution.total_duration += route.total_duration_min;
+        solution.insert_route(vehicle.id, route);
+    }
+
+    solution.route_spread = route_spread(&solution);
+    solution.max_route_distance = max_route_distance(&solution);
+    solution.unassigned_order_ids = orders.iter().zip(served).filter(|(_, served)| !served).map(|(o, _)| o.id).collect();
+    Ok(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random_problem;
+
+    #[test]
+    fn accepts_solver_plans() {
+        for seed in 0..5 {
+            let problem = random_problem(seed, 2, 5);
+            let plan = crate::solver::solve(problem.clone()).best_price_solution;
+            let report = validate_solution(&problem, &plan, &DistanceModel::default());
+            assert!(report.valid);
+            assert!(report.violations.is_empty());
+            assert!(totals_agree(plan.total_price, report.total_price));
+        }
+    }
+
+    #[test]
+    fn counts_waiting_towards_the_duration() {
+        let mut problem = random_problem(4, 1, 2);
+        problem.orders[0].pickup_window = Some(crate::models::TimeWindow { earliest: Some(10_000.0), latest: None });
+        let plan = crate::solver::solve(problem.clone()).best_duration_solution;
+        let route = &plan.routes["1"];
+        assert!(route.waiting_time > 0.0);
+        assert!(route.total_duration_min >= 10_000.0);
+        let report = validate_solution(&problem, &plan, &DistanceModel::default());
+        assert!(report.valid);
+        assert!(totals_agree(plan.total_duration, report.total_duration));
+    }
+
+    #[test]
+    fn flags_a_delivery_moved_before_its_pickup() {
+        let problem = random_problem(0, 1, 2);
+        let mut plan = crate::solver::solve(problem.clone()).best_distance_solution;
+        let stops = &mut plan.routes.get_mut("1").unwrap().stops;
+        let first = stops[0].order_id;
+        let delivery = stops.iter().position(|stop| stop.type_ == "delivery" && stop.order_id == first).unwrap();
+        let moved = stops.remove(delivery);
+        stops.insert(0, moved);
+
+        let report = validate_solution(&problem, &plan, &DistanceModel::default());
+        assert!(!report.valid);
+        assert!(report.violations.iter().any(|violation| {
+            violation.route_key.as_deref() == Some("1")
+                && violation.stop_index == Some(0)
+                && violation.code == ViolationCode::DeliveryBeforePickup
+        }));
+    }
+}
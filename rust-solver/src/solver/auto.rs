@@ -0,0 +1,69 @@
+use std::time::Instant;
+
+use crate::error::{Result, SolverError};
+use crate::heuristics::greedy::single_plan;
+use crate::heuristics::lns::improve_solution;
+use crate::heuristics::regret::solve_regret_insertion;
+use crate::models::{AutoAlgorithm, AutoOptions, AutoSolution, LnsOptions, Problem, RegretOptions, SolveOptions};
+use super::solve_with_options;
+use super::types::MAX_ROUTE_ORDERS;
+
+const DEFAULT_EXACT_MAX_ORDERS: u32 = 6;
+// The exact search takes no more orders than fit one route, seeded or not
+const DEFAULT_SEEDED_MAX_ORDERS: u32 = MAX_ROUTE_ORDERS as u32;
+const DEFAULT_EXACT_MAX_VEHICLES: u32 = 8;
+
+/// Algorithm `solve_auto` runs on a problem of `n_orders` orders and `n_vehicles` vehicles.
+pub fn select_algorithm(n_orders: usize, n_vehicles: usize, options: &AutoOptions) -> AutoAlgorithm {
+    let exact_max = options.exact_max_orders.unwrap_or(DEFAULT_EXACT_MAX_ORDERS) as usize;
+    let seeded_max = options.seeded_max_orders.unwrap_or(DEFAULT_SEEDED_MAX_ORDERS) as usize;
+    if n_vehicles > options.exact_max_vehicles.unwrap_or(DEFAULT_EXACT_MAX_VEHICLES) as usize {
+        AutoAlgorithm::Lns
+    } else if n_orders <= exact_max {
+        AutoAlgorithm::Exact
+    } else if n_orders <= seeded_max {
+        AutoAlgorithm::SeededExact
+    } else {
+        AutoAlgorithm::Lns
+    }
+}
+
+/// Plans `problem` with whatever suits its size: the exact search for small problems, seeded by
+/// the greedy plan in a middle band, and large neighbourhood search on distance from a regret
+/// insertion plan beyond. `time_limit_ms` bounds every one of them, a stopped exact search is
+/// reported as not optimal. The heuristic plan stands for every criterion.
+pub fn solve_auto(problem: Problem, options: AutoOptions) -> Result<AutoSolution> {
+    let exact_max = options.exact_max_orders.unwrap_or(DEFAULT_EXACT_MAX_ORDERS);
+    let seeded_max = options.seeded_max_orders.unwrap_or(DEFAULT_SEEDED_MAX_ORDERS);
+    if seeded_max < exact_max {
+        return Err(SolverError::InvalidArg(format!(
+            "Invalid options: seeded_max_orders ({}) must be at least exact_max_orders ({})",
+            seeded_max, exact_max
+        )));
+    }
+    if seeded_max as usize > MAX_ROUTE_ORDERS {
+        return Err(SolverError::InvalidArg(format!("Invalid options: seeded_max_orders must be at most {}, got {}", MAX_ROUTE_ORDERS, seeded_max)));
+    }
+
+    let algorithm = select_algorithm(problem.orders.len(), problem.vehicles.len(), &options);
+    let solution = match algorithm {
+        AutoAlgorithm::Exact | AutoAlgorithm::SeededExact => {
+            let solve_options = SolveOptions {
+                heuristic_seed: Some(algorithm == AutoAlgorithm::SeededExact),
+                time_limit_ms: options.time_limit_ms,
+                distance: options.distance,
+                ..Default::default()
+            };
+            solve_with_options(problem, solve_options)?
+        }
+        AutoAlgorithm::Lns => {
+            let started = Instant::now();
+            let regret_options = RegretOptions { distance: options.distance.clone(), ..Default::default() };
+            let start = solve_regret_insertion(&problem, regret_options)?;
+            let time_limit_ms = options.time_limit_ms.map(|limit| limit.saturating_sub(started.elapsed().as_millis() as u32));
+            let lns_options = LnsOptions { time_limit_ms, distance: options.distance, ..Default::default() };
+            single_plan(improve_solution(&problem, &start, lns_options)?.solution)
+        }
+    };
+    Ok(AutoSolution { algorithm, optimal: solution.optimal, solution })
+}
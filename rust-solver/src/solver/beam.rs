@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use crate::error::{Result, SolverError};
+use crate::models::{BeamOptions, Criterion, Problem, ProblemSolution};
+use super::context::SolverContext;
+use super::tsp::solve_tsp;
+use super::units::{distance_scale, problem_to_base, Rescale};
+use super::types::{OrderMask, PartialCost, FLEET_VEHICLE_WEIGHT, MAX_MASK_ORDERS, MAX_ROUTE_ORDERS};
+use super::{distance_model, meets_min_route_price, next_submask, reconstruct_solution, validate_rules};
+
+const DEFAULT_BEAM_WIDTH: f64 = 50.0;
+
+/// Vehicles decided so far, the orders they cover and what their routes cost.
+#[derive(Clone)]
+struct State {
+    mask: OrderMask,
+    cost: PartialCost,
+    assignments: Vec<OrderMask>,
+}
+
+/// Distance any route has to drive to take in each order: every stop is entered by some leg, none
+/// cheaper than the cheapest leg into it. Per vehicle, then the least over all vehicles.
+struct Floors {
+    // Vehicle index * n_orders + order index
+    per_vehicle: Vec<f64>,
+    any_vehicle: Vec<f64>,
+}
+
+impl Floors {
+    fn new(ctx: &mut SolverContext) -> Self {
+        let shared = ctx.shared.clone();
+        let n_orders = shared.n_orders;
+        let leg = |from: usize, to: usize| shared.dist_mat[from * shared.num_nodes + to];
+        // Cheapest leg into each stop from any other stop, a pickup never from its own delivery
+        let into: Vec<f64> = (0..2 * n_orders)
+            .map(|to| {
+                (0..2 * n_orders)
+                    .filter(|&from| from != to && !(to % 2 == 0 && from == to + 1))
+                    .map(|from| leg(from, to))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+
+        let mut per_vehicle = Vec::with_capacity(shared.vehicles.len() * n_orders);
+        for vehicle_idx in 0..shared.vehicles.len() {
+            ctx.veh_start.ensure(vehicle_idx, shared.full_mask);
+            for o_idx in 0..n_orders {
+                let pickup = into[2 * o_idx].min(ctx.veh_start.values[vehicle_idx * n_orders + o_idx]);
+                per_vehicle.push(pickup + into[2 * o_idx + 1]);
+            }
+        }
+        let any_vehicle = (0..n_orders)
+            .map(|o_idx| {
+                (0..shared.vehicles.len())
+                    .map(|vehicle_idx| per_vehicle[vehicle_idx * n_orders + o_idx])
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        Floors { per_vehicle, any_vehicle }
+    }
+}
+
+/// Beam search over the assignment tree, one level per vehicle.
+///
+/// Each level extends every kept state by each order set the vehicle may take, ranks the
+/// extensions by their cost so far under `criterion` plus an optimistic estimate of the new route
+/// and of the orders still left, and routes them with `solve_tsp` best first until `beam_width`
+/// states covering distinct order sets are kept. Of two states covering the same orders the cheaper
+/// stays, so an unbounded width is the exact partition DP.
+pub fn solve_beam(problem: Problem, options: BeamOptions) -> Result<ProblemSolution> {
+    let width = options.beam_width.unwrap_or(DEFAULT_BEAM_WIDTH);
+    if width.is_nan() || width < 1.0 {
+        return Err(SolverError::InvalidArg(format!("beam_width must be at least 1, got {}", width)));
+    }
+    if problem.orders.len() > MAX_MASK_ORDERS {
+        return Err(SolverError::TooManyOrders(format!("Problem exceeds max {} orders ({} given)", MAX_MASK_ORDERS, problem.orders.len())));
+    }
+    let model = distance_model(options.distance.as_ref())?;
+    let scale = distance_scale(options.distance.as_ref());
+    let problem = problem_to_base(problem, scale);
+    validate_rules(&problem, &model)?;
+    // Saturates, an infinite width keeps every state
+    let width = width as usize;
+    let criterion = options.criterion.unwrap_or(Criterion::Distance);
+
+    let mut ctx = SolverContext::from_problem(problem, model);
+    let shared = ctx.shared.clone();
+    let n_orders = shared.n_orders;
+    let n_vehicles = shared.vehicles.len();
+    let require_all = shared.options.require_all_vehicles.unwrap_or(false);
+    let floors = Floors::new(&mut ctx);
+
+    // Cost per km of route floor, the cheapest rate of any vehicle for the orders left over
+    let rate = |vehicle_idx: usize| {
+        let vehicle = &shared.vehicles[vehicle_idx];
+        match criterion {
+            Criterion::Price => vehicle.price_km.min(vehicle.empty_price_km()),
+            Criterion::Empty => 0.0,
+            Criterion::Duration => vehicle.minutes_per_km(),
+            Criterion::Distance | Criterion::Makespan | Criterion::Fleet => 1.0,
+        }
+    };
+    let rest_rate = (0..n_vehicles).map(rate).fold(f64::INFINITY, f64::min);
+    let floor_of = |mask: OrderMask, floor: &dyn Fn(usize) -> f64| {
+        let mut sum = 0.0;
+        let mut bits = mask;
+        while bits != 0 {
+            sum += floor(bits.trailing_zeros() as usize);
+            bits &= bits - 1;
+        }
+        sum
+    };
+    // Optimistic final cost of `state` once `vehicle_idx` takes `submask`
+    let score = |state: &State, vehicle_idx: usize, submask: OrderMask| {
+        let route = floor_of(submask, &|o_idx| floors.per_vehicle[vehicle_idx * n_orders + o_idx]);
+        let left = shared.full_mask ^ state.mask ^ submask;
+        let rest = floor_of(left, &|o_idx| floors.any_vehicle[o_idx]);
+        let current = state.cost.get(criterion);
+        match criterion {
+            Criterion::Makespan => current.max(route),
+            Criterion::Fleet => {
+                let used = if submask != 0 { FLEET_VEHICLE_WEIGHT + route } else { 0.0 };
+                let more = if left != 0 { FLEET_VEHICLE_WEIGHT + rest } else { 0.0 };
+                current + used + more
+            }
+            _ => current + route * rate(vehicle_idx) + rest * rest_rate,
+        }
+    };
+
+    let mut beam = vec![State { mask: 0, cost: PartialCost::default(), assignments: vec![0; n_vehicles] }];
+    for vehicle_idx in 0..n_vehicles {
+        let fixed = shared.fixed[vehicle_idx];
+        let mut candidates: Vec<(f64, usize, OrderMask)> = Vec::new();
+        for (state_idx, state) in beam.iter().enumerate() {
+            if !require_all && fixed == 0 {
+                candidates.push((score(state, vehicle_idx, 0), state_idx, 0));
+            }
+            let remaining = (shared.full_mask ^ state.mask) & shared.vehicle_allowed[vehicle_idx];
+            let mut submask = next_submask(0, remaining);
+            while submask != 0 {
+                if submask & fixed == fixed
+                    && submask.count_ones() as usize <= MAX_ROUTE_ORDERS
+                    && shared.within_order_cap(vehicle_idx, submask)
+                {
+                    candidates.push((score(state, vehicle_idx, submask), state_idx, submask));
+                }
+                submask = next_submask(submask, remaining);
+            }
+        }
+        // Ties go to the earlier state and the smaller set, so runs are reproducible
+        candidates.sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+        let mut next: Vec<State> = Vec::new();
+        let mut kept: HashMap<OrderMask, usize> = HashMap::new();
+        for (_, state_idx, submask) in candidates {
+            let state = &beam[state_idx];
+            let cost = if submask == 0 {
+                state.cost
+            } else {
+                let res = solve_tsp(&mut ctx, vehicle_idx, submask);
+                if !res.valid || !meets_min_route_price(&ctx, vehicle_idx, &res) {
+                    continue;
+                }
+                state.cost.with_route(&res)
+            };
+            let mask = state.mask | submask;
+            match kept.get(&mask) {
+                Some(&at) if cost.get(criterion) < next[at].cost.get(criterion) => {
+                    next[at].cost = cost;
+                    next[at].assignments.clone_from(&state.assignments);
+                    next[at].assignments[vehicle_idx] = submask;
+                }
+                Some(_) => {}
+                None => {
+                    let mut assignments = state.assignments.clone();
+                    assignments[vehicle_idx] = submask;
+                    kept.insert(mask, next.len());
+                    next.push(State { mask, cost, assignments });
+                    if next.len() >= width {
+                        break;
+                    }
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        beam = next;
+    }
+
+    // The cheapest state covering every order, or else the most orders
+    let best = beam
+        .iter()
+        .min_by(|a, b| {
+            b.mask
+                .count_ones()
+                .cmp(&a.mask.count_ones())
+                .then(a.cost.get(criterion).total_cmp(&b.cost.get(criterion)))
+        })
+        .map(|state| state.assignments.clone())
+        .unwrap_or_else(|| vec![0; n_vehicles]);
+    Ok(reconstruct_solution(&mut ctx, &best, criterion).rescaled(scale))
+}
@@ -1,85 +1,565 @@
-use crate::models::{Order, Vehicle};
-use crate::utils::calculate_distance;
-use super::types::InternalBestResults;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-pub struct SolverContext<'a> {
-    pub orders: &'a Vec<Order>,
-    pub vehicles: &'a Vec<Vehicle>,
-    
-    // Flattened matrices for cache locality
-    pub dist_mat: Vec<f64>, 
+use crate::cache::SharedDistanceStore;
+use crate::error::SolverError;
+use crate::progress::SharedProgress;
+use crate::models::{Criterion, ForbiddenLeg, Location, Order, Problem, ProblemSolution, RouteSummary, SolveOptions, Vehicle};
+use crate::utils::{prepared_distance, DistanceModel, PreparedLocation};
+use super::alternatives::Alternatives;
+use super::matrix::{DistanceMatrices, VehicleStartDistances};
+use super::objective::Objective;
+use super::parallel::SharedBounds;
+use super::route_memo::{fits_dense, RouteMemo};
+use super::types::{full_mask, CriteriaSet, OrderMask, PartialCost, PartialIncumbents, SolverStats};
+
+/// Caller-supplied price of a route, replacing the built-in `distance * price_km` at the assignment level.
+pub type RouteCostFn = Arc<dyn Fn(RouteSummary) -> napi::Result<f64> + Send + Sync>;
+
+/// Told each new best value of a criterion, with its plan when `improvement_solutions` is set.
+/// Must not block, it runs on the search thread.
+pub type ImprovementFn = Arc<dyn Fn(Criterion, f64, Option<ProblemSolution>) + Send + Sync>;
+
+/// Read-only problem data, shared between every search running on the same problem.
+pub struct SharedContext {
+    pub orders: Vec<Order>,
+    pub vehicles: Vec<Vehicle>,
+
+    // Flattened matrix for cache locality
+    pub dist_mat: Vec<f64>,
     pub num_nodes: usize,
-    pub veh_start_mat: Vec<f64>,
+    // How `dist_mat` and every other distance of this search are measured
+    pub distance_model: DistanceModel,
 
-    // Memoization table
-    pub memo: Vec<Option<InternalBestResults>>,
     pub n_orders: usize,
+    pub full_mask: OrderMask,
 
-    // Best solutions found so far
-    pub best_dist: f64,
-    pub best_dist_assignments: Vec<u32>,
-    
-    pub best_price: f64,
-    pub best_price_assignments: Vec<u32>,
-    
-    pub best_empty: f64,
-    pub best_empty_assignments: Vec<u32>,
+    // Per vehicle, orders whose first stop (the pickup, or the delivery when on board) may not be
+    // reached straight from the start and orders whose delivery may not return straight to it
+    pub forbidden_from_start: Vec<OrderMask>,
+    pub forbidden_to_start: Vec<OrderMask>,
+    // Per vehicle, orders whose delivery may not be followed straight by its end location
+    pub forbidden_to_end: Vec<OrderMask>,
+    pub forbidden_legs: Vec<ForbiddenLeg>,
+
+    // Per node, like `dist_mat`, the window its service must start in. Open bounds are infinite
+    pub windows: Vec<(f64, f64)>,
+    // Per node, minutes spent at the stop once service starts
+    pub service: Vec<f64>,
+    // vehicles x orders, the share of the vehicle's capacity each order takes
+    pub loads: Vec<f64>,
+    pub has_time_windows: bool,
+
+    // Per vehicle, the orders it may be assigned at all
+    pub vehicle_allowed: Vec<OrderMask>,
+    // Per vehicle, orders pinned to it by `fixed_assignments` or on board, every set it takes includes them
+    pub fixed: Vec<OrderMask>,
+    // Per vehicle, orders already on board at its start, routed from their delivery on
+    pub onboard: Vec<OrderMask>,
+    // Orders that must share a vehicle, one mask per merged group, and pairs that may not
+    pub same_vehicle_groups: Vec<OrderMask>,
+    pub conflicts: Vec<OrderMask>,
+    // Vehicle-order pairs removed by `max_candidate_radius_km`
+    pub filtered_candidate_pairs: u32,
+
+    // Cross-call distance cache, also consulted by each thread's lazy vehicle start distances
+    pub cache: Option<SharedDistanceStore>,
+
+    pub options: SolveOptions,
+
+    pub route_cost: Option<RouteCostFn>,
 
-    pub full_mask: u32,
+    // Called from `record_complete` whenever an incumbent improves
+    pub on_improvement: Option<ImprovementFn>,
+
+    // Set for a single-objective solve, routes then minimize it instead of each criterion
+    pub objective: Option<Objective>,
+
+    // Criteria the search keeps incumbents for, from `options.compute`
+    pub criteria: CriteriaSet,
+
+    // Per vehicle, a previous plan repaired for this problem, tried as incumbent before the search
+    pub warm_start: Option<Vec<OrderMask>>,
+
+    // Raised from JS to abandon the search, polled by both search levels
+    pub cancel: Option<Arc<AtomicBool>>,
 }
 
-impl<'a> SolverContext<'a> {
-    pub fn new(orders: &'a Vec<Order>, vehicles: &'a Vec<Vehicle>) -> Self {
+impl SharedContext {
+    /// Context for `problem` measured by `model`, with its forbidden legs applied.
+    pub fn from_problem(problem: Problem, cache: Option<SharedDistanceStore>, model: DistanceModel) -> Self {
+        let mut shared = Self::with_cache(problem.orders, problem.vehicles, cache, model);
+        if let Some(legs) = problem.forbidden_legs {
+            shared.forbid_legs(&legs);
+            shared.forbidden_legs = legs;
+        }
+        if let Some(fixed) = problem.fixed_assignments {
+            shared.fix_assignments(&fixed);
+        }
+        if let Some(groups) = problem.same_vehicle_groups {
+            shared.same_vehicle_groups = group_masks(&shared.orders, &groups);
+        }
+        if let Some(pairs) = problem.conflict_pairs {
+            shared.conflicts = pairs
+                .iter()
+                .map(|pair| order_mask(&shared.orders, &[pair.first_order_id, pair.second_order_id]))
+                .filter(|mask| mask.count_ones() == 2)
+                .collect();
+        }
+        shared
+    }
+
+    /// The distance cache holds plain great-circle distances, so any other `model` leaves it unused.
+    pub fn with_cache(orders: Vec<Order>, vehicles: Vec<Vehicle>, cache: Option<SharedDistanceStore>, model: DistanceModel) -> Self {
         let n_orders = orders.len();
-        let num_nodes = n_orders * 2;
-
-        // 1. Build Order-Order Matrix
-        let mut dist_mat = vec![0.0; num_nodes * num_nodes];
-        
-        let get_loc = |idx: usize| -> &crate::models::Location {
-            let order_idx = idx / 2;
-            if idx % 2 == 0 { &orders[order_idx].pickup_location } 
-            else { &orders[order_idx].delivery_location }
+        let n_vehicles = vehicles.len();
+        let cache = cache.filter(|_| model.is_great_circle());
+        let DistanceMatrices { dist_mat, num_nodes } = DistanceMatrices::build(&orders, cache.as_ref(), &model);
+        let windows: Vec<(f64, f64)> = orders
+            .iter()
+            .flat_map(|o| [o.window_bounds(true), o.window_bounds(false)])
+            .collect();
+        let service: Vec<f64> = orders.iter().flat_map(|o| [o.service_minutes(true), o.service_minutes(false)]).collect();
+        let has_time_windows = orders.iter().any(|o| o.pickup_window.is_some() || o.delivery_window.is_some());
+        let loads: Vec<f64> = vehicles.iter().flat_map(|v| orders.iter().map(|o| o.load_on(v))).collect();
+        let onboard: Vec<OrderMask> =
+            vehicles.iter().map(|v| order_mask(&orders, v.onboard_order_ids.as_deref().unwrap_or_default())).collect();
+        let all_onboard = onboard.iter().fold(0, |all, &mask| all | mask);
+        // Orders that cannot fit a vehicle even alone, or need tags it lacks, are never handed to it.
+        // Orders on board stay with their vehicle whatever else holds
+        let vehicle_allowed = (0..n_vehicles)
+            .map(|v_idx| {
+                let fitting = (0..n_orders)
+                    .filter(|&o_idx| loads[v_idx * n_orders + o_idx] <= 1.000001 && orders[o_idx].servable_by(&vehicles[v_idx]))
+                    .fold(0, |mask: OrderMask, o_idx| mask | (1 << o_idx));
+                (fitting & !all_onboard) | onboard[v_idx]
+            })
+            .collect();
+
+        SharedContext {
+            orders,
+            vehicles,
+            dist_mat,
+            num_nodes,
+            distance_model: model,
+            n_orders,
+            full_mask: full_mask(n_orders),
+            forbidden_from_start: vec![0; n_vehicles],
+            forbidden_to_start: vec![0; n_vehicles],
+            forbidden_to_end: vec![0; n_vehicles],
+            forbidden_legs: Vec::new(),
+            windows,
+            service,
+            loads,
+            has_time_windows,
+            vehicle_allowed,
+            fixed: onboard.clone(),
+            onboard,
+            same_vehicle_groups: Vec::new(),
+            conflicts: Vec::new(),
+            filtered_candidate_pairs: 0,
+            cache,
+            options: SolveOptions::default(),
+            route_cost: None,
+            on_improvement: None,
+            objective: None,
+            criteria: CriteriaSet::ALL,
+            warm_start: None,
+            cancel: None,
+        }
+    }
+
+    /// Whether `mask` stays within the vehicle's `max_orders`.
+    #[inline(always)]
+    pub fn within_order_cap(&self, vehicle_idx: usize, mask: OrderMask) -> bool {
+        self.vehicles[vehicle_idx].takes_orders(mask.count_ones())
+    }
+
+    /// Whether one vehicle may take exactly `mask`: no same-vehicle group is split and no
+    /// conflicting pair rides together.
+    #[inline(always)]
+    pub fn keeps_pairings(&self, mask: OrderMask) -> bool {
+        self.same_vehicle_groups.iter().all(|&group| mask & group == 0 || mask & group == group)
+            && self.conflicts.iter().all(|&pair| mask & pair != pair)
+    }
+
+    /// Whether the caller asked to abandon the search.
+    #[inline(always)]
+    pub fn cancel_requested(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    /// Applies `options`, including any candidate filtering they ask for. The context must already
+    /// be measured by their distance options, see `from_problem`.
+    pub fn with_options(mut self, options: SolveOptions) -> Self {
+        debug_assert_eq!(self.distance_model, DistanceModel::from_options(options.distance.as_ref()));
+        if let Some(radius) = options.max_candidate_radius_km {
+            self.filter_candidates_by_radius(radius);
+        }
+        // Criteria were checked by `validate_options`
+        self.objective = match (&options.criteria, &options.weights) {
+            (Some(criteria), _) => Objective::lexicographic(criteria).ok(),
+            (None, Some(weights)) => Some(Objective::weighted(
+                weights.distance.unwrap_or(0.0),
+                weights.price.unwrap_or(0.0),
+                weights.empty.unwrap_or(0.0),
+            )),
+            (None, None) => None,
         };
+        self.criteria = options.compute.as_deref().map_or(CriteriaSet::ALL, CriteriaSet::of);
+        self.options = options;
+        self
+    }
+
+    /// `location` prepared for this context's distance model.
+    pub fn place(&self, location: &Location) -> PreparedLocation {
+        self.distance_model.prepare(location)
+    }
+
+    /// Disallows vehicle-order pairs whose start-to-pickup distance exceeds `radius`. If that would
+    /// leave an order without any vehicle, the radius grows to the farthest order's nearest vehicle.
+    fn filter_candidates_by_radius(&mut self, radius: f64) {
+        let starts: Vec<PreparedLocation> = self.vehicles.iter().map(|v| self.place(&v.start_location)).collect();
+        let distances: Vec<Vec<f64>> = self
+            .orders
+            .iter()
+            .map(|o| {
+                let pickup = self.place(&o.pickup_location);
+                starts.iter().map(|start| prepared_distance(start, &pickup)).collect()
+            })
+            .collect();
+
+        let nearest_needed = distances
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::INFINITY, f64::min))
+            .filter(|d| d.is_finite())
+            .fold(0.0, f64::max);
+        let radius = radius.max(nearest_needed);
 
-        for i in 0..num_nodes {
-            for j in 0..num_nodes {
-                if i != j {
-                    dist_mat[i * num_nodes + j] = calculate_distance(get_loc(i), get_loc(j));
+        for (o_idx, row) in distances.iter().enumerate() {
+            for (v_idx, &distance) in row.iter().enumerate() {
+                // An order on board is past its pickup, wherever that was
+                let onboard = self.onboard[v_idx] & (1 << o_idx) != 0;
+                if distance > radius && !onboard && self.vehicle_allowed[v_idx] & (1 << o_idx) != 0 {
+                    self.vehicle_allowed[v_idx] &= !(1 << o_idx);
+                    self.filtered_candidate_pairs += 1;
                 }
             }
         }
+    }
 
-        // 2. Build Vehicle-Order Matrix
-        let mut veh_start_mat = vec![0.0; vehicles.len() * n_orders];
-        for (v_idx, vehicle) in vehicles.iter().enumerate() {
-            for (o_idx, order) in orders.iter().enumerate() {
-                veh_start_mat[v_idx * n_orders + o_idx] = calculate_distance(&vehicle.start_location, &order.pickup_location);
+    /// Takes each pinned order away from every vehicle but its own. Pins naming an order or
+    /// vehicle outside this context are skipped, `validate_problem` reports them.
+    fn fix_assignments(&mut self, fixed: &HashMap<String, u32>) {
+        for (order_id, vehicle_id) in fixed {
+            let o_idx = self.orders.iter().position(|o| order_id.parse() == Ok(o.id));
+            let v_idx = self.vehicles.iter().position(|v| v.id == *vehicle_id);
+            if let (Some(o_idx), Some(v_idx)) = (o_idx, v_idx) {
+                for (other_idx, allowed) in self.vehicle_allowed.iter_mut().enumerate() {
+                    if other_idx != v_idx {
+                        *allowed &= !(1 << o_idx);
+                    }
+                }
+                self.fixed[v_idx] |= 1 << o_idx;
             }
         }
-        
-        // Size: vehicles * 2^orders
-        let cache_size = vehicles.len() * (1 << n_orders);
+    }
+
+    /// Marks every leg in `legs` as impassable: matrix entries become infinite and
+    /// start legs are flagged, so `solve_tsp` never expands across them.
+    fn forbid_legs(&mut self, legs: &[ForbiddenLeg]) {
+        let forbidden: HashSet<(&str, &str)> = legs.iter().map(|l| (l.from_hash.as_str(), l.to_hash.as_str())).collect();
+        let node_hash = |node: usize| {
+            let order = &self.orders[node / 2];
+            if node.is_multiple_of(2) { order.pickup_location.hash.as_str() } else { order.delivery_location.hash.as_str() }
+        };
+
+        for i in 0..self.num_nodes {
+            for j in 0..self.num_nodes {
+                if i != j && forbidden.contains(&(node_hash(i), node_hash(j))) {
+                    self.dist_mat[i * self.num_nodes + j] = f64::INFINITY;
+                }
+            }
+        }
+
+        for (v_idx, vehicle) in self.vehicles.iter().enumerate() {
+            let start = vehicle.start_location.hash.as_str();
+            for (o_idx, order) in self.orders.iter().enumerate() {
+                let first_stop = if self.onboard[v_idx] & (1 << o_idx) != 0 { &order.delivery_location } else { &order.pickup_location };
+                if forbidden.contains(&(start, first_stop.hash.as_str())) {
+                    self.forbidden_from_start[v_idx] |= 1 << o_idx;
+                }
+                if forbidden.contains(&(order.delivery_location.hash.as_str(), start)) {
+                    self.forbidden_to_start[v_idx] |= 1 << o_idx;
+                }
+                if vehicle
+                    .end_location
+                    .as_ref()
+                    .is_some_and(|end| forbidden.contains(&(order.delivery_location.hash.as_str(), end.hash.as_str())))
+                {
+                    self.forbidden_to_end[v_idx] |= 1 << o_idx;
+                }
+            }
+        }
+    }
+}
+
+/// Mask of the orders among `ids`, unknown ids are skipped.
+pub(crate) fn order_mask(orders: &[Order], ids: &[u32]) -> OrderMask {
+    orders
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| ids.contains(&o.id))
+        .fold(0, |mask, (o_idx, _)| mask | (1 << o_idx))
+}
+
+/// Masks of `groups`, merged wherever groups share an order, so the masks are disjoint.
+pub(crate) fn group_masks(orders: &[Order], groups: &[Vec<u32>]) -> Vec<OrderMask> {
+    let mut masks: Vec<OrderMask> = Vec::new();
+    for group in groups {
+        let mut mask = order_mask(orders, group);
+        // The kept masks are disjoint, so absorbing one never makes the group overlap another
+        masks.retain(|&other| {
+            let overlaps = other & mask != 0;
+            if overlaps {
+                mask |= other;
+            }
+            !overlaps
+        });
+        if mask.count_ones() > 1 {
+            masks.push(mask);
+        }
+    }
+    masks
+}
+
+/// Mutable search state of one solver thread. Each thread owns its memo, incumbents and
+/// stats, so several contexts can search the same problem concurrently.
+pub struct SolverContext {
+    pub shared: Arc<SharedContext>,
+
+    // Filled lazily, so it lives with the thread that fills it
+    pub veh_start: VehicleStartDistances,
+
+    // Routing results, one row of order sets per distinct routing behaviour
+    pub memo: RouteMemo,
+    // Vehicle index -> memo row, vehicles that route identically share one
+    pub memo_rows: Vec<usize>,
+
+    // Lowest cost per criterion seen on entering each (vehicle_idx, assignment_mask) state,
+    // the subtree below a state only depends on its key so dominated re-entries are pruned.
+    // Empty when too large to allocate up front, see `fits_dense`
+    pub assignment_visits: Vec<PartialCost>,
+
+    pub stats: SolverStats,
+    // When the context was created, `collect` reports the time since as the search time
+    pub started: Instant,
+
+    // Set from `time_limit_ms` when the search starts, `stopped` once it has passed
+    pub deadline: Option<Instant>,
+    // Assignment plus route nodes to expand at most, from `max_nodes`, `stopped` once spent
+    pub node_budget: u64,
+    // Per criterion the cheapest branch the gap tolerance pruned, what the unexplored rest of the
+    // tree may still reach at best
+    pub gap_floor: PartialCost,
+    pub stopped: bool,
+    // The search saw `SharedContext::cancel` raised, its results are meaningless
+    pub cancelled: bool,
+    // Why `route_cost` could not price a route, which stopped the search
+    pub route_cost_error: Option<SolverError>,
+    // Off while routes are rebuilt after the search, which must not be cut short
+    pub watch_cancel: bool,
+
+    // Incumbents of every worker in a parallel search
+    pub bounds: Option<Arc<SharedBounds>>,
+
+    // Incumbent snapshot read by a `SolveHandle` while the search runs
+    pub progress: Option<SharedProgress>,
+
+    // Best solutions found so far
+    pub best_dist: f64,
+    pub best_dist_assignments: Vec<OrderMask>,
+    
+    pub best_price: f64,
+    pub best_price_assignments: Vec<OrderMask>,
+    
+    pub best_empty: f64,
+    pub best_empty_assignments: Vec<OrderMask>,
+
+    pub best_makespan: f64,
+    pub best_makespan_assignments: Vec<OrderMask>,
+
+    pub best_duration: f64,
+    pub best_duration_assignments: Vec<OrderMask>,
+    pub best_fleet: f64,
+    pub best_fleet_assignments: Vec<OrderMask>,
+
+    // Fallback when no assignment covers every order
+    pub partial: PartialIncumbents,
+
+    // Runners-up of the distance criterion, see `SolveOptions::top_k`
+    pub alternatives: Alternatives,
+}
+
+impl SolverContext {
+    pub fn new(orders: Vec<Order>, vehicles: Vec<Vehicle>, model: DistanceModel) -> Self {
+        Self::from_shared(Arc::new(SharedContext::with_cache(orders, vehicles, None, model)))
+    }
+
+    pub fn from_problem(problem: Problem, model: DistanceModel) -> Self {
+        Self::from_shared(Arc::new(SharedContext::from_problem(problem, None, model)))
+    }
+
+    pub fn from_shared(shared: Arc<SharedContext>) -> Self {
+        let n_vehicles = shared.vehicles.len();
+        let memo_rows = memo_rows(&shared);
+        let n_rows = memo_rows.iter().max().map_or(0, |&row| row + 1);
+        let alternatives = Alternatives::new(&shared, &memo_rows);
 
         SolverContext {
-            orders,
-            vehicles,
-            dist_mat,
-            num_nodes,
-            veh_start_mat,
-            memo: vec![None; cache_size],
-            n_orders,
+            veh_start: VehicleStartDistances::new(
+                &shared.orders,
+                &shared.vehicles,
+                &shared.onboard,
+                shared.cache.clone(),
+                &shared.distance_model,
+            ),
+            memo: RouteMemo::new(n_rows, shared.n_orders),
+            memo_rows,
+
+            // vehicle_idx runs up to and including vehicles.len(). Only the exact search reads it,
+            // and without it only prunes less
+            assignment_visits: if fits_dense::<PartialCost>(n_vehicles + 1, shared.n_orders) {
+                vec![PartialCost::UNREACHED; (n_vehicles + 1) * (1 << shared.n_orders)]
+            } else {
+                Vec::new()
+            },
+
+            stats: SolverStats::default(),
+            started: Instant::now(),
+            deadline: None,
+            node_budget: shared.options.max_nodes.map_or(u64::MAX, |max| max as u64),
+            gap_floor: PartialCost::UNREACHED,
+            stopped: false,
+            cancelled: false,
+            route_cost_error: None,
+            watch_cancel: true,
+            bounds: None,
+            progress: None,
             
             best_dist: f64::INFINITY,
-            best_dist_assignments: vec![0; vehicles.len()],
+            best_dist_assignments: vec![0; n_vehicles],
             
             best_price: f64::INFINITY,
-            best_price_assignments: vec![0; vehicles.len()],
+            best_price_assignments: vec![0; n_vehicles],
             
             best_empty: f64::INFINITY,
-            best_empty_assignments: vec![0; vehicles.len()],
-            
-            full_mask: (1 << n_orders) - 1,
+            best_empty_assignments: vec![0; n_vehicles],
+
+            best_makespan: f64::INFINITY,
+            best_makespan_assignments: vec![0; n_vehicles],
+
+            best_duration: f64::INFINITY,
+            best_duration_assignments: vec![0; n_vehicles],
+            best_fleet: f64::INFINITY,
+            best_fleet_assignments: vec![0; n_vehicles],
+
+            partial: PartialIncumbents::new(n_vehicles),
+            alternatives,
+
+            shared,
         }
     }
-}
\ No newline at end of file
+
+    /// Assignment behind the incumbent of `criterion`.
+    pub fn incumbent_assignments(&self, criterion: Criterion) -> &[OrderMask] {
+        match criterion {
+            Criterion::Distance => &self.best_dist_assignments,
+            Criterion::Price => &self.best_price_assignments,
+            Criterion::Empty => &self.best_empty_assignments,
+            Criterion::Makespan => &self.best_makespan_assignments,
+            Criterion::Duration => &self.best_duration_assignments,
+            Criterion::Fleet => &self.best_fleet_assignments,
+        }
+    }
+}
+
+/// Memo row of each vehicle. Routes only depend on where a vehicle starts, how it is priced and
+/// timed, how many trips it makes and which start legs it may not use, so vehicles agreeing on all
+/// of that share the row of the first one. A custom route cost sees the vehicle id, so it keeps
+/// every vehicle apart.
+fn memo_rows(shared: &SharedContext) -> Vec<usize> {
+    if shared.route_cost.is_some() {
+        return (0..shared.vehicles.len()).collect();
+    }
+
+    let mut rows = HashMap::new();
+    shared
+        .vehicles
+        .iter()
+        .enumerate()
+        .map(|(v_idx, v)| {
+            let key = (
+                v.start_location.hash.as_str(),
+                v.start_location.latitude.to_bits(),
+                v.start_location.longitude.to_bits(),
+                (
+                    v.price_km.to_bits(),
+                    v.price_km_empty.map(f64::to_bits),
+                    v.stop_fee.map(f64::to_bits),
+                    v.co2_per_km.map(f64::to_bits),
+                    v.co2_per_km_empty.map(f64::to_bits),
+                ),
+                v.trips(),
+                v.speed_kmh.map(f64::to_bits),
+                v.capacity.map(f64::to_bits),
+                (v.max_distance_km.map(f64::to_bits), v.max_duration_min.map(f64::to_bits), shared.onboard[v_idx]),
+                v.end_location.as_ref().map(|end| (end.hash.as_str(), end.latitude.to_bits(), end.longitude.to_bits())),
+                shared.forbidden_from_start[v_idx],
+                shared.forbidden_to_start[v_idx],
+                shared.forbidden_to_end[v_idx],
+            );
+            let next = rows.len();
+            *rows.entry(key).or_insert(next)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tsp::solve_tsp;
+    use crate::testing::random_problem;
+
+    // Validity, then distance and stops of each criterion's route
+    type Routed = (bool, Vec<(f64, Vec<u8>)>);
+
+    // Every non-empty order set routed for one vehicle
+    fn route_every_set(ctx: &mut SolverContext, vehicle_idx: usize) -> Vec<Routed> {
+        (1..full_mask(ctx.shared.n_orders) + 1)
+            .map(|mask| {
+                let res = solve_tsp(ctx, vehicle_idx, mask);
+                let routes = [res.min_dist, res.min_empty, res.min_price, res.min_duration];
+                (res.valid, routes.iter().map(|route| (route.total_dist, route.path.stops().to_vec())).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threads_route_disjoint_vehicles_against_one_shared_context() {
+        let shared = Arc::new(SharedContext::from_problem(random_problem(1, 2, 5), None, DistanceModel::default()));
+        let mut ctx = SolverContext::from_shared(shared.clone());
+        let sequential = [route_every_set(&mut ctx, 0), route_every_set(&mut ctx, 1)];
+        let threaded = std::thread::scope(|scope| {
+            [0, 1].map(|vehicle_idx| {
+                let shared = shared.clone();
+                scope.spawn(move || route_every_set(&mut SolverContext::from_shared(shared), vehicle_idx))
+            })
+            .map(|handle| handle.join().unwrap())
+        });
+        assert_eq!(sequential, threaded);
+        assert!(sequential.iter().flatten().any(|(valid, _)| *valid));
+    }
+}
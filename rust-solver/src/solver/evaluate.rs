@@ -0,0 +1,339 @@
+use crate::models::{Location, Order, RouteLeg, RouteStop, Vehicle, VehicleRoute};
+use crate::utils::{prepared_distance, DistanceModel};
+use super::tsp::{DIST_EPSILON, TIME_EPSILON};
+
+/// One stop of an explicit route: the pickup or delivery node of `orders[order_idx]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stop {
+    pub order_idx: usize,
+    pub pickup: bool,
+}
+
+impl Stop {
+    /// Return to the vehicle's start between two trips, only valid once the vehicle is empty.
+    pub const RELOAD: Stop = Stop { order_idx: usize::MAX, pickup: false };
+
+    pub fn is_reload(&self) -> bool {
+        self.order_idx == usize::MAX
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RouteMetrics {
+    pub dist: f64,
+    pub empty: f64,
+    pub price: f64,
+    pub duration: f64,
+    pub co2: f64,
+    pub waiting: f64,
+    pub return_dist: f64,
+    pub interleavings: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    DeliveryBeforePickup,
+    CapacityExceeded,
+    DuplicateStop,
+    MissingDelivery,
+    TimeWindowMissed,
+    MissingTags,
+    RouteLimitExceeded,
+    TooManyOrders,
+    ReloadWhileLoaded,
+    TooManyTrips,
+}
+
+/// Metrics of driving `stops` in the given order, using exactly the DFS rules in `solve_tsp`:
+/// a leg is empty when nothing is on board before it, the load may not exceed 1.0 and no stop
+/// may be reached after its window closes. Orders needing tags the vehicle lacks are rejected
+/// too, the search never hands them to it, as are routes beyond the vehicle's distance or
+/// duration limit or its `max_orders`. Orders on board start out picked up, so only their
+/// delivery appears in `stops`. A `Stop::RELOAD` drives the empty vehicle back to its start for
+/// another trip. Errors carry the index of the offending stop, `stops.len()` for the route.
+/// Every leg is measured by `model`.
+pub fn evaluate_stops(
+    model: &DistanceModel,
+    vehicle: &Vehicle,
+    orders: &[Order],
+    stops: &[Stop],
+) -> Result<RouteMetrics, (usize, ViolationKind)> {
+    walk_stops(model, vehicle, orders, stops, |_| {}, |_| {}, |index, kind| Err((index, kind)))
+}
+
+/// Every violation `evaluate_stops` would stop at, in route order, with the metrics of driving the
+/// stops anyway. Stops that cannot be served at all (duplicates, deliveries before their pickup)
+/// are skipped, every other violation is driven through.
+pub fn audit_stops(
+    model: &DistanceModel,
+    vehicle: &Vehicle,
+    orders: &[Order],
+    stops: &[Stop],
+    mut violation: impl FnMut(usize, ViolationKind),
+) -> RouteMetrics {
+    walk_stops(model, vehicle, orders, stops, |_| {}, |_| {}, |index, kind| {
+        violation(index, kind);
+        Ok(())
+    })
+    .unwrap_or_default()
+}
+
+/// Minutes since leaving the start at which each stop is served, for stops `evaluate_stops` accepts.
+fn service_times(model: &DistanceModel, vehicle: &Vehicle, orders: &[Order], stops: &[Stop]) -> Vec<f64> {
+    let mut times = Vec::with_capacity(stops.len());
+    let _ = walk_stops(model, vehicle, orders, stops, |time| times.push(time), |_| {}, |index, kind| {
+        Err((index, kind))
+    });
+    times
+}
+
+/// Every drive of stops `evaluate_stops` accepts, their distances adding up to its `dist`.
+fn route_legs(model: &DistanceModel, vehicle: &Vehicle, orders: &[Order], stops: &[Stop]) -> Vec<RouteLeg> {
+    let mut legs = Vec::with_capacity(stops.len() + 1);
+    let _ = walk_stops(
+        model,
+        vehicle,
+        orders,
+        stops,
+        |_| {},
+        |leg| {
+            legs.push(RouteLeg {
+                from: leg.from.clone(),
+                to: leg.to.clone(),
+                distance: leg.distance,
+                empty: leg.empty,
+                load_after: leg.load_after,
+                price: vehicle.leg_price(leg.distance, leg.empty) + leg.fee,
+            })
+        },
+        |index, kind| Err((index, kind)),
+    );
+    legs
+}
+
+/// Highest share of capacity on board along `legs` and the average share over the km driven,
+/// `initial_load` being on board when the vehicle leaves its start.
+pub fn load_profile(initial_load: f64, legs: &[RouteLeg]) -> (f64, f64) {
+    let mut load = initial_load;
+    let (mut max_load, mut loaded_km, mut km) = (initial_load, 0.0, 0.0);
+    for leg in legs {
+        loaded_km += leg.distance * load;
+        km += leg.distance;
+        load = leg.load_after;
+        max_load = max_load.max(load);
+    }
+    (max_load, if km > 0.0 { loaded_km / km } else { 0.0 })
+}
+
+/// A drive of `walk_stops`, `fee` being what the stop at `to` adds to the price.
+struct Leg<'a> {
+    from: &'a Location,
+    to: &'a Location,
+    distance: f64,
+    empty: bool,
+    load_after: f64,
+    fee: f64,
+}
+
+/// Drives `stops`, handing each violation to `violated`, which either ends the walk with an error
+/// or lets it carry on past the offending stop.
+fn walk_stops(
+    model: &DistanceModel,
+    vehicle: &Vehicle,
+    orders: &[Order],
+    stops: &[Stop],
+    mut served_at: impl FnMut(f64),
+    mut drove: impl FnMut(Leg),
+    mut violated: impl FnMut(usize, ViolationKind) -> Result<(), (usize, ViolationKind)>,
+) -> Result<RouteMetrics, (usize, ViolationKind)> {
+    let mut metrics = RouteMetrics::default();
+    let start = model.prepare(&vehicle.start_location);
+    let mut trip = 1;
+    let mut time = 0.0;
+    let mut picked = vec![false; orders.len()];
+    let mut delivered = vec![false; orders.len()];
+    let mut on_board = 0usize;
+    let mut load = 0.0;
+    let mut position = start;
+    let mut position_location = &vehicle.start_location;
+
+    // Orders the vehicle starts with are picked up already, a pickup stop for one is a duplicate
+    for id in vehicle.onboard_order_ids.iter().flatten() {
+        if let Some(o_idx) = orders.iter().position(|o| o.id == *id) {
+            picked[o_idx] = true;
+            on_board += 1;
+            load += orders[o_idx].load_on(vehicle);
+        }
+    }
+    if load > 1.000001 {
+        violated(0, ViolationKind::CapacityExceeded)?;
+    }
+
+    for (stop_index, stop) in stops.iter().enumerate() {
+        let mut violation = |kind| violated(stop_index, kind);
+
+        // Like the DFS, the leg back to the start is empty and the next trip starts a fresh count of switches
+        if stop.is_reload() {
+            if on_board > 0 {
+                violation(ViolationKind::ReloadWhileLoaded)?;
+            }
+            trip += 1;
+            if trip > vehicle.trips() {
+                violation(ViolationKind::TooManyTrips)?;
+            }
+            let leg_dist = prepared_distance(&position, &start);
+            time += leg_dist * vehicle.minutes_per_km();
+            served_at(time);
+            metrics.dist += leg_dist;
+            metrics.empty += leg_dist;
+            metrics.price += leg_dist * vehicle.empty_price_km();
+            metrics.duration += leg_dist * vehicle.minutes_per_km();
+            metrics.co2 += leg_dist * vehicle.emission_rate(true);
+            drove(Leg { from: position_location, to: &vehicle.start_location, distance: leg_dist, empty: true, load_after: 0.0, fee: 0.0 });
+            position = start;
+            position_location = &vehicle.start_location;
+            continue;
+        }
+
+        let order = &orders[stop.order_idx];
+        let load_val = order.load_on(vehicle);
+
+        let location = if stop.pickup {
+            if picked[stop.order_idx] {
+                violation(ViolationKind::DuplicateStop)?;
+                continue;
+            }
+            if !order.servable_by(vehicle) {
+                violation(ViolationKind::MissingTags)?;
+            }
+            if !vehicle.takes_orders(picked.iter().filter(|&&p| p).count() as u32 + 1) {
+                violation(ViolationKind::TooManyOrders)?;
+            }
+            if load + load_val > 1.000001 {
+                violation(ViolationKind::CapacityExceeded)?;
+            }
+            &order.pickup_location
+        } else {
+            if delivered[stop.order_idx] {
+                violation(ViolationKind::DuplicateStop)?;
+                continue;
+            }
+            if !picked[stop.order_idx] {
+                violation(ViolationKind::DeliveryBeforePickup)?;
+                continue;
+            }
+            &order.delivery_location
+        };
+
+        let next = model.prepare(location);
+        let leg_dist = prepared_distance(&position, &next);
+        let arrival = time + leg_dist * vehicle.minutes_per_km();
+        let (earliest, latest) = order.window_bounds(stop.pickup);
+        if arrival > latest + TIME_EPSILON {
+            violation(ViolationKind::TimeWindowMissed)?;
+        }
+        time = arrival.max(earliest);
+        let waited = time - arrival;
+        metrics.waiting += waited;
+        served_at(time);
+        let service = order.service_minutes(stop.pickup);
+        time += service;
+
+        metrics.dist += leg_dist;
+        metrics.price += vehicle.fee_per_stop();
+        metrics.duration += leg_dist * vehicle.minutes_per_km() + waited + service;
+        metrics.co2 += leg_dist * vehicle.emission_rate(on_board == 0);
+        if on_board == 0 {
+            metrics.empty += leg_dist;
+            metrics.price += leg_dist * vehicle.empty_price_km();
+        } else {
+            metrics.price += leg_dist * vehicle.price_km;
+        }
+        if stop_index > 0 && !stops[stop_index - 1].is_reload() && stops[stop_index - 1].pickup != stop.pickup {
+            metrics.interleavings += 1;
+        }
+        let empty = on_board == 0;
+
+        if stop.pickup {
+            picked[stop.order_idx] = true;
+            on_board += 1;
+            load += load_val;
+        } else {
+            delivered[stop.order_idx] = true;
+            on_board -= 1;
+            load -= load_val;
+        }
+        let load_after = if on_board == 0 { 0.0 } else { load };
+        drove(Leg { from: position_location, to: location, distance: leg_dist, empty, load_after, fee: vehicle.fee_per_stop() });
+        position = next;
+        position_location = location;
+    }
+
+    if on_board > 0 {
+        violated(stops.len(), ViolationKind::MissingDelivery)?;
+    }
+
+    // An unused vehicle never leaves its start, so it has nothing to return from
+    if let (Some(end), false) = (&vehicle.end_location, stops.is_empty()) {
+        let leg_dist = prepared_distance(&position, &model.prepare(end));
+        metrics.return_dist = leg_dist;
+        metrics.dist += leg_dist;
+        metrics.empty += leg_dist;
+        metrics.price += leg_dist * vehicle.empty_price_km();
+        metrics.duration += leg_dist * vehicle.minutes_per_km();
+        metrics.co2 += leg_dist * vehicle.emission_rate(true);
+        drove(Leg { from: position_location, to: end, distance: leg_dist, empty: true, load_after: 0.0, fee: 0.0 });
+    }
+
+    let (max_dist, max_duration) = vehicle.route_limits();
+    if metrics.dist > max_dist + DIST_EPSILON || metrics.duration > max_duration + TIME_EPSILON {
+        violated(stops.len(), ViolationKind::RouteLimitExceeded)?;
+    }
+
+    Ok(metrics)
+}
+
+/// The route of driving `stops`, for `metrics` measured by `model`.
+pub fn route_from_stops(
+    model: &DistanceModel,
+    vehicle: &Vehicle,
+    orders: &[Order],
+    stops: &[Stop],
+    metrics: &RouteMetrics,
+) -> VehicleRoute {
+    let legs = route_legs(model, vehicle, orders, stops);
+    let onboard = vehicle.onboard_order_ids.iter().flatten();
+    let initial_load = onboard.filter_map(|id| orders.iter().find(|o| o.id == *id)).map(|o| o.load_on(vehicle)).sum();
+    let (max_load, avg_utilization) = load_profile(initial_load, &legs);
+    VehicleRoute {
+        vehicle_id: vehicle.id,
+        stops: stops
+            .iter()
+            .zip(service_times(model, vehicle, orders, stops))
+            .zip(&legs)
+            .enumerate()
+            .map(|(sequence, ((s, arrival_time), leg))| match s.is_reload() {
+                true => RouteStop::new(0, "reload", &vehicle.start_location, sequence, arrival_time, arrival_time, 0.0),
+                false => {
+                    let order = &orders[s.order_idx];
+                    let location = if s.pickup { &order.pickup_location } else { &order.delivery_location };
+                    let type_ = if s.pickup { "pickup" } else { "delivery" };
+                    let departure_time = arrival_time + order.service_minutes(s.pickup);
+                    RouteStop::new(order.id, type_, location, sequence, arrival_time, departure_time, leg.load_after)
+                }
+            })
+            .collect(),
+        legs,
+        max_load,
+        avg_utilization,
+        total_distance: metrics.dist,
+        empty_distance: metrics.empty,
+        total_price: metrics.price,
+        total_duration_min: metrics.duration,
+        cost_breakdown: vehicle.price_of(metrics.dist, metrics.empty, stops.iter().filter(|s| !s.is_reload()).count()),
+        total_co2: metrics.co2,
+        waiting_time: metrics.waiting,
+        return_distance: metrics.return_dist,
+        interleavings: metrics.interleavings,
+    }
+}
@@ -0,0 +1,207 @@
+use crate::error::{Result, SolverError};
+use crate::models::{Criterion, CriterionDelta, Problem, ProblemSolution, SolutionComparison, Vehicle};
+use crate::utils::{prepared_distance, DistanceModel};
+use super::matrix::DistanceMatrices;
+
+const CRITERIA: [Criterion; 6] =
+    [Criterion::Distance, Criterion::Price, Criterion::Empty, Criterion::Makespan, Criterion::Duration, Criterion::Fleet];
+
+/// What `solution` scores under `criterion`, fleet counting the vehicles with a route.
+fn criterion_value(solution: &ProblemSolution, criterion: Criterion) -> f64 {
+    match criterion {
+        Criterion::Distance => solution.total_distance,
+        Criterion::Price => solution.total_price,
+        Criterion::Empty => solution.empty_distance,
+        Criterion::Makespan => solution.max_route_distance,
+        Criterion::Duration => solution.total_duration,
+        Criterion::Fleet => solution.routes.values().filter(|route| !route.stops.is_empty()).count() as f64,
+    }
+}
+
+/// Rejects a plan naming vehicles or orders `problem` does not have.
+fn check_belongs(problem: &Problem, solution: &ProblemSolution, name: &str) -> Result<()> {
+    for (vehicle_id, route) in &solution.routes {
+        if !problem.vehicles.iter().any(|v| v.id.to_string() == *vehicle_id) {
+            return Err(SolverError::InvalidArg(format!("Solution {} routes unknown vehicle id {}", name, vehicle_id)));
+        }
+        if let Some(stop) = route.stops.iter().find(|s| s.type_ != "reload" && !problem.orders.iter().any(|o| o.id == s.order_id)) {
+            return Err(SolverError::InvalidArg(format!("Solution {} serves unknown order id {}", name, stop.order_id)));
+        }
+    }
+    Ok(())
+}
+
+/// How `b` fares against `a` under every criterion, e.g. a heuristic plan against the exact
+/// optimum. Deltas are `b - a`, so positive when `b` is worse, and as a percentage of `a` unless
+/// `a` is 0. Values are taken from the plans as they are, not re-measured.
+pub fn compare_solutions(problem: &Problem, a: &ProblemSolution, b: &ProblemSolution) -> Result<SolutionComparison> {
+    check_belongs(problem, a, "a")?;
+    check_belongs(problem, b, "b")?;
+    let deltas = CRITERIA
+        .iter()
+        .map(|&criterion| {
+            let (a, b) = (criterion_value(a, criterion), criterion_value(b, criterion));
+            let delta = b - a;
+            CriterionDelta { criterion, a, b, delta, percent: (a != 0.0).then(|| delta / a * 100.0) }
+        })
+        .collect();
+    Ok(SolutionComparison { deltas })
+}
+
+/// A value no plan serving every order can beat under `criterion`, computed without searching.
+///
+/// Every stop is entered by some leg, none shorter than the shortest leg into it from another stop
+/// or a vehicle start, so their sum bounds the distance. Price and duration price that distance at
+/// the cheapest and fastest vehicle's rates, adding the least stop fees and every service time.
+/// Unless a vehicle starts loaded, some route begins empty with at least the shortest approach to
+/// a pickup. The longest route is at least an equal share of the distance, and at least as long as
+/// the shortest way to serve the farthest order alone. Fleet counts the vehicles that must be used.
+/// Distances are measured by `model`.
+pub fn lower_bound(problem: &Problem, criterion: Criterion, model: &DistanceModel) -> Result<f64> {
+    let (orders, vehicles) = (&problem.orders, &problem.vehicles);
+    if orders.is_empty() {
+        return Ok(0.0);
+    }
+    if vehicles.is_empty() {
+        return Err(SolverError::Infeasible("No vehicle can serve the orders".to_string()));
+    }
+    let DistanceMatrices { dist_mat, num_nodes } = DistanceMatrices::build(orders, None, model);
+    let leg = |from: usize, to: usize| dist_mat[from * num_nodes + to];
+    let nodes: Vec<_> = orders.iter().flat_map(|o| [model.prepare(&o.pickup_location), model.prepare(&o.delivery_location)]).collect();
+    let starts: Vec<_> = vehicles.iter().map(|v| model.prepare(&v.start_location)).collect();
+    let from_start = |to: usize| starts.iter().map(|start| prepared_distance(start, &nodes[to])).fold(f64::INFINITY, f64::min);
+    let on_board: Vec<bool> = orders
+        .iter()
+        .map(|o| vehicles.iter().any(|v| v.onboard_order_ids.iter().flatten().any(|id| *id == o.id)))
+        .collect();
+
+    // Shortest leg into each stop that a route may drive. Orders on board have no pickup stop
+    let into = |to: usize| {
+        let o_idx = to / 2;
+        let from_stops = (0..num_nodes)
+            .filter(|&from| from / 2 != o_idx || (!to.is_multiple_of(2) && from == to - 1))
+            .filter(|&from| !(from.is_multiple_of(2) && on_board[from / 2]))
+            .map(|from| leg(from, to))
+            .fold(f64::INFINITY, f64::min);
+        if to.is_multiple_of(2) || on_board[o_idx] { from_stops.min(from_start(to)) } else { from_stops }
+    };
+    let stops: Vec<usize> = (0..num_nodes).filter(|&node| !(node.is_multiple_of(2) && on_board[node / 2])).collect();
+    let distance: f64 = stops.iter().map(|&to| into(to)).sum();
+
+    let cheapest = |rate: &dyn Fn(&Vehicle) -> f64| vehicles.iter().map(rate).fold(f64::INFINITY, f64::min);
+    Ok(match criterion {
+        Criterion::Distance => distance,
+        Criterion::Price => {
+            distance * cheapest(&|v| v.price_km.min(v.empty_price_km())) + stops.len() as f64 * cheapest(&|v| v.fee_per_stop())
+        }
+        Criterion::Duration => {
+            let service: f64 = stops.iter().map(|&node| orders[node / 2].service_minutes(node.is_multiple_of(2))).sum();
+            distance * cheapest(&|v| v.minutes_per_km()) + service
+        }
+        Criterion::Empty => {
+            if on_board.iter().any(|&carried| carried) {
+                0.0
+            } else {
+                (0..orders.len()).map(|o_idx| from_start(2 * o_idx)).fold(f64::INFINITY, f64::min)
+            }
+        }
+        Criterion::Makespan => {
+            let alone = (0..orders.len())
+                .map(|o_idx| {
+                    let (pickup, delivery) = (2 * o_idx, 2 * o_idx + 1);
+                    if on_board[o_idx] { from_start(delivery) } else { from_start(pickup) + leg(pickup, delivery) }
+                })
+                .fold(0.0, f64::max);
+            alone.max(distance / vehicles.len() as f64)
+        }
+        Criterion::Fleet => {
+            let loaded = vehicles.iter().filter(|v| v.onboard_order_ids.as_ref().is_some_and(|ids| !ids.is_empty())).count();
+            let most = vehicles.iter().map(|v| v.max_orders.map_or(orders.len(), |cap| cap as usize)).max().unwrap_or(0);
+            loaded.max(orders.len().div_ceil(most.max(1))).max(1) as f64
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DistanceMode;
+    use crate::testing::{order, place, problem, random_problem, vehicle};
+    use crate::utils::PreparedLocation;
+
+    fn degree() -> f64 {
+        prepared_distance(&PreparedLocation::new(&place("a", 0.0, 0.0)), &PreparedLocation::new(&place("b", 0.0, 1.0)))
+    }
+
+    // Along the equator one vehicle at 0 carries order 1 from 1 to 2 and order 2 from 3 to 4,
+    // every leg of the optimal route is one degree
+    fn line() -> Problem {
+        let at = |hash: &str, longitude: f64| place(hash, 0.0, longitude);
+        problem(
+            vec![Vehicle { price_km: 2.0, ..vehicle(1, at("v1", 0.0)) }],
+            vec![order(1, at("p1", 1.0), at("d1", 2.0)), order(2, at("p2", 3.0), at("d2", 4.0))],
+        )
+    }
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 1e-6
+    }
+
+    #[test]
+    fn bounds_a_hand_computed_instance() {
+        let problem = line();
+        let bound = |criterion| lower_bound(&problem, criterion, &DistanceModel::default()).unwrap();
+        assert!(close(bound(Criterion::Distance), 4.0 * degree()));
+        assert!(close(bound(Criterion::Price), 8.0 * degree()));
+        assert!(close(bound(Criterion::Empty), degree()));
+        assert!(close(bound(Criterion::Makespan), 4.0 * degree()));
+        assert_eq!(bound(Criterion::Fleet), 1.0);
+        let optimum = crate::solver::solve(problem.clone()).best_distance_solution.total_distance;
+        assert!(close(bound(Criterion::Distance), optimum));
+    }
+
+    #[test]
+    fn bounds_follow_the_fleet() {
+        let mut problem = line();
+        problem.vehicles.push(Vehicle { max_orders: Some(1), ..vehicle(2, place("v2", 0.0, 10.0)) });
+        // Order 2 alone takes 3 degrees to reach and 1 to deliver, more than half of 4 degrees
+        assert!(close(lower_bound(&problem, Criterion::Makespan, &DistanceModel::default()).unwrap(), 4.0 * degree()));
+        assert!(close(lower_bound(&problem, Criterion::Price, &DistanceModel::default()).unwrap(), 4.0 * degree()));
+        assert_eq!(lower_bound(&problem, Criterion::Fleet, &DistanceModel::default()).unwrap(), 1.0);
+        problem.vehicles[0].max_orders = Some(1);
+        assert_eq!(lower_bound(&problem, Criterion::Fleet, &DistanceModel::default()).unwrap(), 2.0);
+        problem.vehicles[0].onboard_order_ids = Some(vec![1]);
+        assert_eq!(lower_bound(&problem, Criterion::Empty, &DistanceModel::default()).unwrap(), 0.0);
+        problem.orders.clear();
+        assert_eq!(lower_bound(&problem, Criterion::Distance, &DistanceModel::default()).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn measures_by_the_model() {
+        let euclidean = DistanceModel { mode: DistanceMode::Euclidean, ..Default::default() };
+        assert!(close(lower_bound(&line(), Criterion::Distance, &euclidean).unwrap(), 4.0));
+        let circuitous = DistanceModel { circuity: 1.5, ..Default::default() };
+        assert!(close(lower_bound(&line(), Criterion::Distance, &circuitous).unwrap(), 6.0 * degree()));
+    }
+
+    #[test]
+    fn rejects_orders_without_vehicles() {
+        let mut problem = line();
+        problem.vehicles.clear();
+        assert!(matches!(lower_bound(&problem, Criterion::Distance, &DistanceModel::default()), Err(SolverError::Infeasible(_))));
+    }
+
+    #[test]
+    fn no_plan_beats_the_bound() {
+        for seed in 0..5 {
+            let problem = random_problem(seed, 2, 5);
+            let exact = crate::solver::solve(problem.clone());
+            let bound = |criterion| lower_bound(&problem, criterion, &DistanceModel::default()).unwrap() - 1e-6;
+            assert!(bound(Criterion::Distance) <= exact.best_distance_solution.total_distance);
+            assert!(bound(Criterion::Price) <= exact.best_price_solution.total_price);
+            assert!(bound(Criterion::Empty) <= exact.best_empty_solution.empty_distance);
+            assert!(bound(Criterion::Makespan) <= exact.best_makespan_solution.max_route_distance);
+            assert!(bound(Criterion::Duration) <= exact.best_duration_solution.total_duration);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::models::Criterion;
+use super::context::SolverContext;
+use super::types::{wins_tie_break, InternalBestResults, InternalTspResult, OrderMask, PathBuffer, TIE_EPSILON};
+
+/// Smallest order set routed with the DP when `route_dp` is left unset. Below it the pruned
+/// DFS expands fewer nodes than the DP has states.
+pub const AUTO_DP_MIN_ORDERS: u32 = 7;
+
+const CRITERIA: [Criterion; 4] = [Criterion::Distance, Criterion::Empty, Criterion::Price, Criterion::Duration];
+
+// Parent of a state that was entered straight from the vehicle's start
+const FROM_START: u8 = u8::MAX;
+
+/// Optimal routes of one vehicle over `target_mask`, by dynamic programming over
+/// (order progress, last stop) states instead of enumerating stop sequences.
+///
+/// Each order of the set is not picked up, on board or delivered, encoded as one ternary digit,
+/// so picking up or delivering order `i` always adds `3^i` and states can be filled in index
+/// order. The load only depends on which orders are on board, so capacity is checked per state.
+/// One table per criterion keeps the four optima independent, exactly as the DFS does. Routes
+/// are single trip only, without time windows, which make a state's future depend on its clock.
+pub fn route(ctx: &mut SolverContext, vehicle_idx: usize, target_mask: OrderMask) -> InternalBestResults {
+    let shared = &ctx.shared;
+    let vehicle = &shared.vehicles[vehicle_idx];
+    let n_orders = shared.n_orders;
+
+    // Local order index -> global order index
+    let members: Vec<usize> = (0..n_orders).filter(|o| target_mask & (1 << o) != 0).collect();
+    let k = members.len();
+    let pow3: Vec<usize> = (0..=k).map(|i| 3usize.pow(i as u32)).collect();
+    let n_states = pow3[k];
+    // Local stop index 2i / 2i + 1 is member i's pickup / delivery
+    let n_last = 2 * k;
+    let global_node = |local: usize| 2 * members[local / 2] + local % 2;
+    let leg = |from: usize, to: usize| shared.dist_mat[global_node(from) * shared.num_nodes + global_node(to)];
+
+    let loads: Vec<f64> = members.iter().map(|&o| shared.loads[vehicle_idx * n_orders + o]).collect();
+    let start_dist = &ctx.veh_start.values[vehicle_idx * n_orders..(vehicle_idx + 1) * n_orders];
+    let start_forbidden = shared.forbidden_from_start[vehicle_idx];
+    let end_forbidden = shared.forbidden_to_end[vehicle_idx];
+    let has_end = vehicle.end_location.is_some();
+    // Leg from the last delivery to the end location, none without one
+    let end_leg = |last: usize| {
+        if has_end { ctx.veh_start.to_end[vehicle_idx * n_orders + members[last / 2]] } else { 0.0 }
+    };
+    let (v_price, v_price_empty, stop_fee) = (vehicle.price_km, vehicle.empty_price_km(), vehicle.fee_per_stop());
+    let v_minutes_per_km = vehicle.minutes_per_km();
+    let interleaving_penalty = shared.options.interleaving_penalty.unwrap_or(0.0);
+
+    // Load on board and whether anything is, per state
+    let mut load = vec![0.0; n_states];
+    let mut on_board = vec![false; n_states];
+    for state in 0..n_states {
+        for (i, &order_load) in loads.iter().enumerate() {
+            if (state / pow3[i]) % 3 == 1 {
+                load[state] += order_load;
+                on_board[state] = true;
+            }
+        }
+    }
+
+    // Every step but the one to the end location serves a stop
+    let step_cost = |criterion: Criterion, leg_dist: f64, empty_before: bool, switched: bool, stop: bool| {
+        let penalty = if switched { interleaving_penalty } else { 0.0 };
+        penalty
+            + match criterion {
+                Criterion::Distance | Criterion::Makespan | Criterion::Fleet => leg_dist,
+                Criterion::Empty => if empty_before { leg_dist } else { 0.0 },
+                Criterion::Price => {
+                    leg_dist * if empty_before { v_price_empty } else { v_price } + if stop { stop_fee } else { 0.0 }
+                }
+                Criterion::Duration => leg_dist * v_minutes_per_km,
+            }
+    };
+
+    let mut best: Vec<Vec<f64>> = vec![vec![f64::INFINITY; n_states * n_last]; CRITERIA.len()];
+    let mut parent: Vec<Vec<u8>> = vec![vec![FROM_START; n_states * n_last]; CRITERIA.len()];
+
+    // First stops, straight from the start
+    for i in 0..k {
+        if start_forbidden & (1 << members[i]) != 0 || loads[i] > 1.000001 {
+            continue;
+        }
+        let leg_dist = start_dist[members[i]];
+        if leg_dist == f64::INFINITY {
+            continue;
+        }
+        let idx = pow3[i] * n_last + 2 * i;
+        for (c, &criterion) in CRITERIA.iter().enumerate() {
+            best[c][idx] = step_cost(criterion, leg_dist, true, false, true);
+        }
+    }
+
+    let mut expanded = 0u64;
+    for state in 0..n_states {
+        for last in 0..n_last {
+            let idx = state * n_last + last;
+            if CRITERIA.iter().enumerate().all(|(c, _)| best[c][idx] == f64::INFINITY) {
+                continue;
+            }
+            expanded += 1;
+
+            for i in 0..k {
+                let digit = (state / pow3[i]) % 3;
+                let next_local = match digit {
+                    0 if load[state] + loads[i] <= 1.000001 => 2 * i,
+                    1 => 2 * i + 1,
+                    _ => continue,
+                };
+                let leg_dist = leg(last, next_local);
+                if leg_dist == f64::INFINITY {
+                    continue;
+                }
+                let switched = last % 2 != next_local % 2;
+                let next_idx = (state + pow3[i]) * n_last + next_local;
+
+                for (c, &criterion) in CRITERIA.iter().enumerate() {
+                    let value = best[c][idx] + step_cost(criterion, leg_dist, !on_board[state], switched, true);
+                    let incumbent = best[c][next_idx];
+                    // Both ways in leave `state` for the same stop, so within `TIE_EPSILON` the
+                    // smaller path up to `state` is the smaller route, as the DFS picks it
+                    let wins = if (value - incumbent).abs() <= TIE_EPSILON {
+                        let other = parent[c][next_idx] as usize;
+                        let key = path_to(&parent[c], &pow3, n_last, state, last);
+                        wins_tie_break(value, &key, incumbent, &path_to(&parent[c], &pow3, n_last, state, other))
+                    } else {
+                        value < incumbent
+                    };
+                    if wins {
+                        best[c][next_idx] = value;
+                        parent[c][next_idx] = last as u8;
+                    }
+                }
+            }
+        }
+    }
+    ctx.stats.route_nodes += expanded;
+
+    let done = n_states - 1;
+    let mut results = [InternalBestResults::INVALID.min_dist; CRITERIA.len()];
+    for (c, result) in results.iter_mut().enumerate() {
+        let criterion = CRITERIA[c];
+        let finish = |last: usize| best[c][done * n_last + last] + step_cost(criterion, end_leg(last), true, false, false);
+        // Routes tied within `TIE_EPSILON` go to the lexicographically smaller path
+        let mut chosen: Option<(f64, Vec<usize>)> = None;
+        for last in (0..n_last)
+            .filter(|&last| best[c][done * n_last + last] < f64::INFINITY)
+            .filter(|&last| !has_end || end_forbidden & (1 << members[last / 2]) == 0)
+        {
+            let (cost, path) = (finish(last), path_to(&parent[c], &pow3, n_last, done, last));
+            if chosen.as_ref().is_none_or(|(best_cost, best_path)| wins_tie_break(cost, &path, *best_cost, best_path)) {
+                chosen = Some((cost, path));
+            }
+        }
+        let Some((_, path)) = chosen else {
+            return InternalBestResults::INVALID;
+        };
+
+        *result = replay(ctx, vehicle_idx, &path.iter().map(|&local| global_node(local)).collect::<Vec<_>>());
+    }
+
+    let [min_dist, min_empty, min_price, min_duration] = results;
+    InternalBestResults { min_dist, min_empty, min_price, min_duration, valid: true }
+}
+
+/// Local stops of the best way into (`state`, `last`), walking the parents back to the start. Local
+/// stops sort like the global nodes they stand for, so comparing two paths compares the routes.
+fn path_to(parent: &[u8], pow3: &[usize], n_last: usize, mut state: usize, mut last: usize) -> Vec<usize> {
+    let mut path = Vec::with_capacity(n_last);
+    loop {
+        path.push(last);
+        let prev = parent[state * n_last + last];
+        state -= pow3[last / 2];
+        if prev == FROM_START {
+            break;
+        }
+        last = prev as usize;
+    }
+    path.reverse();
+    path
+}
+
+/// Metrics of driving `nodes` from the vehicle's start, accumulated in the same order as the DFS.
+fn replay(ctx: &SolverContext, vehicle_idx: usize, nodes: &[usize]) -> InternalTspResult {
+    let shared = &ctx.shared;
+    let vehicle = &shared.vehicles[vehicle_idx];
+    let v_minutes_per_km = vehicle.minutes_per_km();
+    let interleaving_penalty = shared.options.interleaving_penalty.unwrap_or(0.0);
+
+    let mut result = InternalTspResult {
+        path: PathBuffer::default(),
+        total_dist: 0.0,
+        total_empty: 0.0,
+        total_price: 0.0,
+        total_duration: 0.0,
+        total_co2: 0.0,
+        interleavings: 0,
+        penalty: 0.0,
+        waiting: 0.0,
+        return_dist: 0.0,
+    };
+    let mut on_board = 0;
+    let mut time = 0.0;
+    let mut prev: Option<usize> = None;
+
+    for &node in nodes {
+        let leg_dist = match prev {
+            None => ctx.veh_start.values[vehicle_idx * shared.n_orders + node / 2],
+            Some(prev) => shared.dist_mat[prev * shared.num_nodes + node],
+        };
+        result.total_dist += leg_dist;
+        if on_board == 0 {
+            result.total_empty += leg_dist;
+            result.total_price += leg_dist * vehicle.empty_price_km();
+        } else {
+            result.total_price += leg_dist * vehicle.price_km;
+        }
+        result.total_price += vehicle.fee_per_stop();
+        result.total_co2 += leg_dist * vehicle.emission_rate(on_board == 0);
+        let arrival = time + leg_dist * v_minutes_per_km;
+        let served = arrival.max(shared.windows[node].0);
+        time = served + shared.service[node];
+        result.waiting += served - arrival;
+        result.total_duration += leg_dist * v_minutes_per_km + (served - arrival) + shared.service[node];
+        if prev.is_some_and(|prev| prev % 2 != node % 2) {
+            result.interleavings += 1;
+            result.penalty += interleaving_penalty;
+        }
+        if node.is_multiple_of(2) { on_board += 1 } else { on_board -= 1 }
+
+        result.path.nodes[result.path.len as usize] = node as u8;
+        result.path.len += 1;
+        prev = Some(node);
+    }
+    if let (Some(_), Some(last)) = (&vehicle.end_location, prev) {
+        let leg_dist = ctx.veh_start.to_end[vehicle_idx * shared.n_orders + last / 2];
+        result.return_dist = leg_dist;
+        result.total_dist += leg_dist;
+        result.total_empty += leg_dist;
+        result.total_price += leg_dist * vehicle.empty_price_km();
+        result.total_duration += leg_dist * v_minutes_per_km;
+        result.total_co2 += leg_dist * vehicle.emission_rate(true);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{ProblemSolution, SolveOptions};
+    use crate::solver::solve_with_options;
+    use crate::testing::{order, place, problem, vehicle};
+
+    fn stops(solution: &ProblemSolution) -> Vec<(u32, String)> {
+        solution.routes["1"].stops.iter().map(|stop| (stop.order_id, stop.type_.clone())).collect()
+    }
+
+    #[test]
+    fn breaks_ties_like_the_dfs() {
+        // Orders back and forth between two shared places, so many stop sequences cost the same
+        let (a, b) = (place("a", 54.0, 23.0), place("b", 54.0, 23.1));
+        let orders = (1..=7)
+            .map(|id| {
+                let mut order = if id % 2 == 0 { order(id, a.clone(), b.clone()) } else { order(id, b.clone(), a.clone()) };
+                order.load_factor = 4.0;
+                order
+            })
+            .collect();
+        let problem = problem(vec![vehicle(1, place("s", 54.1, 23.05))], orders);
+        let solve = |route_dp| solve_with_options(problem.clone(), SolveOptions { route_dp: Some(route_dp), ..Default::default() }).unwrap();
+        let (dp, dfs) = (solve(true), solve(false));
+        for (a, b) in [
+            (&dp.best_distance_solution, &dfs.best_distance_solution),
+            (&dp.best_empty_solution, &dfs.best_empty_solution),
+            (&dp.best_price_solution, &dfs.best_price_solution),
+            (&dp.best_duration_solution, &dfs.best_duration_solution),
+        ] {
+            assert_eq!(stops(a), stops(b));
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+
+use crate::error::{Result, SolverError};
+use crate::models::{InsertionCost, InsertionEvaluation, InsertionOption, Order, Problem, ProblemSolution, RemovalEvaluation, RemovalSaving, Vehicle, VehicleRoute};
+use super::{build_route, max_route_distance, route_spread};
+use crate::utils::DistanceModel;
+use super::context::SolverContext;
+use super::evaluate::{evaluate_stops, route_from_stops, RouteMetrics, Stop, ViolationKind};
+use super::tsp::solve_tsp;
+use super::types::MAX_ROUTE_ORDERS;
+
+pub(crate) fn order_index(orders: &[Order]) -> HashMap<u32, usize> {
+    orders.iter().enumerate().map(|(i, o)| (o.id, i)).collect()
+}
+
+pub(crate) fn stops_from_route(route: &VehicleRoute, order_idx: &HashMap<u32, usize>) -> Result<Vec<Stop>> {
+    route
+        .stops
+        .iter()
+        .map(|s| {
+            if s.type_ == "reload" {
+                return Err(SolverError::InvalidArg("Routes with reload stops cannot be edited incrementally".to_string()));
+            }
+            let idx = *order_idx
+                .get(&s.order_id)
+                .ok_or_else(|| SolverError::InvalidArg(format!("Route references unknown order id {}", s.order_id)))?;
+            match s.type_.as_str() {
+                "pickup" => Ok(Stop { order_idx: idx, pickup: true }),
+                "delivery" => Ok(Stop { order_idx: idx, pickup: false }),
+                other => Err(SolverError::InvalidArg(format!("Unknown stop type '{}'", other))),
+            }
+        })
+        .collect()
+}
+
+/// Exact routing of one vehicle over all of `orders`, in a context holding just that vehicle.
+pub(crate) fn route_exactly(vehicle: &Vehicle, orders: Vec<Order>, model: &DistanceModel) -> Option<VehicleRoute> {
+    let mut ctx = SolverContext::new(orders, vec![vehicle.clone()], model.clone());
+    let full_mask = ctx.shared.full_mask;
+    let res = solve_tsp(&mut ctx, 0, full_mask);
+    res.valid.then(|| build_route(&ctx, 0, &res.min_price))
+}
+
+/// How close a rejected insertion got, later checks in `evaluate_stops` rank higher.
+fn violation_rank(kind: ViolationKind) -> u8 {
+    match kind {
+        ViolationKind::MissingTags => 0,
+        ViolationKind::TooManyOrders => 1,
+        ViolationKind::CapacityExceeded => 2,
+        ViolationKind::TimeWindowMissed => 3,
+        ViolationKind::RouteLimitExceeded => 4,
+        _ => 5,
+    }
+}
+
+/// Cheapest-price placement of `new_idx` into an existing stop sequence, trying every position pair.
+/// Without any feasible pair, the violation of the candidate that got furthest.
+fn insert_heuristically(
+    model: &DistanceModel,
+    vehicle: &Vehicle,
+    orders: &[Order],
+    stops: &[Stop],
+    new_idx: usize,
+) -> std::result::Result<(Vec<Stop>, RouteMetrics), ViolationKind> {
+    let mut best: Option<(Vec<Stop>, RouteMetrics)> = None;
+    let mut closest = ViolationKind::MissingTags;
+
+    for i in 0..=stops.len() {
+        for j in i + 1..=stops.len() + 1 {
+            let mut candidate = stops.to_vec();
+            candidate.insert(i, Stop { order_idx: new_idx, pickup: true });
+            candidate.insert(j, Stop { order_idx: new_idx, pickup: false });
+
+            match evaluate_stops(model, vehicle, orders, &candidate) {
+                Ok(metrics) => {
+                    if best.as_ref().is_none_or(|(_, b)| metrics.price < b.price) {
+                        best = Some((candidate, metrics));
+                    }
+                }
+                Err((_, kind)) => {
+                    if violation_rank(kind) > violation_rank(closest) {
+                        closest = kind;
+                    }
+                }
+            }
+        }
+    }
+
+    best.ok_or(closest)
+}
+
+/// Additional cost of serving `new_order` on each vehicle of an existing plan.
+///
+/// Vehicles whose order count stays within the exact solver's limit are re-routed optimally,
+/// larger routes get the cheapest insertion into their current stop sequence. Deltas are
+/// measured against the routes in `solution` and the best option is the one with the lowest price delta.
+/// Every route is measured by `model`, as are those of the other functions here.
+pub fn insertion_cost(problem: Problem, solution: &ProblemSolution, new_order: Order, model: &DistanceModel) -> Result<InsertionCost> {
+    if problem.orders.iter().any(|o| o.id == new_order.id) {
+        return Err(SolverError::InvalidArg(format!("Order {} is already part of the problem", new_order.id)));
+    }
+
+    let mut orders = problem.orders;
+    orders.push(new_order);
+    let new_idx = orders.len() - 1;
+    let order_idx = order_index(&orders);
+
+    let mut result = InsertionCost::default();
+
+    for vehicle in &problem.vehicles {
+        let current = solution.routes.get(&vehicle.id.to_string()).cloned().unwrap_or_default();
+        let stops = stops_from_route(&current, &order_idx)?;
+        let route_orders = stops.iter().filter(|s| s.pickup).count() + 1;
+
+        let mut option = InsertionOption {
+            vehicle_id: vehicle.id,
+            exact: route_orders <= MAX_ROUTE_ORDERS,
+            ..Default::default()
+        };
+
+        let inserted = if orders[new_idx].load_on(vehicle) > 1.000001 {
+            option.reason = Some("Order load exceeds vehicle capacity on its own".to_string());
+            None
+        } else if !orders[new_idx].servable_by(vehicle) {
+            option.reason = Some("Vehicle lacks tags the order requires".to_string());
+            None
+        } else if option.exact {
+            let mut sub_orders: Vec<Order> = stops.iter().filter(|s| s.pickup).map(|s| orders[s.order_idx].clone()).collect();
+            sub_orders.push(orders[new_idx].clone());
+            route_exactly(vehicle, sub_orders, model)
+        } else {
+            insert_heuristically(model, vehicle, &orders, &stops, new_idx)
+                .ok()
+                .map(|(stops, metrics)| route_from_stops(model, vehicle, &orders, &stops, &metrics))
+        };
+
+        match inserted {
+            Some(route) => {
+                option.feasible = true;
+                option.delta_distance = route.total_distance - current.total_distance;
+                option.delta_empty_distance = route.empty_distance - current.empty_distance;
+                option.delta_price = route.total_price - current.total_price;
+                option.route = Some(route);
+            }
+            None => {
+                option.reason.get_or_insert_with(|| "No insertion position respects vehicle capacity, time windows and route limits".to_string());
+            }
+        }
+
+        result.options.push(option);
+    }
+
+    result.best_vehicle_id = result
+        .options
+        .iter()
+        .filter(|o| o.feasible)
+        .min_by(|a, b| a.delta_price.total_cmp(&b.delta_price))
+        .map(|o| o.vehicle_id);
+    result.feasible = result.best_vehicle_id.is_some();
+
+    Ok(result)
+}
+
+/// Cheapest single insertion of `new_order` into the routes of `solution`, keeping every existing
+/// stop sequence as it is and trying each pickup/delivery position pair on each vehicle.
+///
+/// Unlike `insertion_cost` no route is re-optimized, so the returned plan is `solution` with exactly
+/// two stops added. Without a feasible position the binding constraint is the one that rejected the
+/// candidate which got furthest through the route checks.
+pub fn evaluate_insertion(problem: Problem, solution: &ProblemSolution, new_order: Order, model: &DistanceModel) -> Result<InsertionEvaluation> {
+    if problem.orders.iter().any(|o| o.id == new_order.id) {
+        return Err(SolverError::InvalidArg(format!("Order {} is already part of the problem", new_order.id)));
+    }
+
+    let mut orders = problem.orders;
+    orders.push(new_order);
+    let new_idx = orders.len() - 1;
+    let order_idx = order_index(&orders);
+
+    // (vehicle, its current route, its route with the order, position of the new pickup and delivery)
+    let mut best: Option<(&Vehicle, VehicleRoute, VehicleRoute, (usize, usize))> = None;
+    let mut closest: Option<ViolationKind> = None;
+
+    for vehicle in &problem.vehicles {
+        let current = solution.routes.get(&vehicle.id.to_string()).cloned().unwrap_or_default();
+        let stops = stops_from_route(&current, &order_idx)?;
+        if evaluate_stops(model, vehicle, &orders, &stops).is_err() {
+            return Err(SolverError::Infeasible(format!("Route of vehicle {} is infeasible", vehicle.id)));
+        }
+
+        match insert_heuristically(model, vehicle, &orders, &stops, new_idx) {
+            Ok((inserted, metrics)) => {
+                let delta_price = metrics.price - current.total_price;
+                if best.as_ref().is_none_or(|(_, before, after, _)| delta_price < after.total_price - before.total_price) {
+                    let position = |pickup| inserted.iter().position(|s| s.order_idx == new_idx && s.pickup == pickup).unwrap_or_default();
+                    let route = route_from_stops(model, vehicle, &orders, &inserted, &metrics);
+                    best = Some((vehicle, current, route, (position(true), position(false))));
+                }
+            }
+            Err(kind) => {
+                if closest.is_none_or(|c| violation_rank(kind) > violation_rank(c)) {
+                    closest = Some(kind);
+                }
+            }
+        }
+    }
+
+    let Some((vehicle, current, route, (pickup_at, delivery_at))) = best else {
+        let binding = match closest {
+            None => "No vehicles are available",
+            Some(ViolationKind::MissingTags) => "No vehicle has every tag the order requires",
+            Some(ViolationKind::TooManyOrders) => "Every vehicle already serves its max_orders",
+            Some(ViolationKind::CapacityExceeded) => "No insertion position respects vehicle capacity",
+            Some(ViolationKind::TimeWindowMissed) => "No insertion position respects the time windows",
+            Some(ViolationKind::RouteLimitExceeded) => "Every insertion exceeds the vehicle's max_distance_km or max_duration_min",
+            Some(_) => "No insertion position keeps the routes valid",
+        };
+        return Ok(InsertionEvaluation {
+            solution: solution.clone(),
+            binding_constraint: Some(binding.to_string()),
+            ..Default::default()
+        });
+    };
+
+    let delta_distance = route.total_distance - current.total_distance;
+    let delta_empty_distance = route.empty_distance - current.empty_distance;
+    let delta_price = route.total_price - current.total_price;
+
+    let mut updated = solution.clone();
+    updated.total_distance += delta_distance;
+    updated.empty_distance += delta_empty_distance;
+    updated.total_price += delta_price;
+    updated.total_duration += route.total_duration_min - current.total_duration_min;
+    updated.total_co2 += route.total_co2 - current.total_co2;
+    updated.insert_route(vehicle.id, route);
+    updated.route_spread = route_spread(&updated);
+    updated.max_route_distance = max_route_distance(&updated);
+
+    Ok(InsertionEvaluation {
+        feasible: true,
+        vehicle_id: Some(vehicle.id),
+        pickup_position: Some(pickup_at as u32),
+        delivery_position: Some(delivery_at as u32),
+        delta_distance,
+        delta_empty_distance,
+        delta_price,
+        solution: updated,
+        binding_constraint: None,
+    })
+}
+
+/// Route of `vehicle` once `remove_idx` is dropped from `stops`, re-solved exactly when small enough,
+/// otherwise with the order's two stops spliced out. None if the remaining stops are infeasible.
+pub(crate) fn route_without(
+    model: &DistanceModel,
+    vehicle: &Vehicle,
+    orders: &[Order],
+    stops: &[Stop],
+    remove_idx: usize,
+) -> Option<(VehicleRoute, bool)> {
+    let remaining: Vec<Stop> = stops.iter().copied().filter(|s| s.order_idx != remove_idx).collect();
+    if remaining.is_empty() {
+        return Some((VehicleRoute::default(), true));
+    }
+
+    let remaining_orders = remaining.iter().filter(|s| s.pickup).count();
+    if remaining_orders <= MAX_ROUTE_ORDERS {
+        let sub_orders = remaining.iter().filter(|s| s.pickup).map(|s| orders[s.order_idx].clone()).collect();
+        route_exactly(vehicle, sub_orders, model).map(|route| (route, true))
+    } else {
+        evaluate_stops(model, vehicle, orders, &remaining)
+            .ok()
+            .map(|metrics| (route_from_stops(model, vehicle, orders, &remaining, &metrics), false))
+    }
+}
+
+/// The vehicle whose route in `solution` serves `order_id`, that route and its stops.
+fn serving_route<'a>(
+    problem: &'a Problem,
+    solution: &'a ProblemSolution,
+    order_idx: &HashMap<u32, usize>,
+    order_id: u32,
+) -> Result<(&'a Vehicle, &'a VehicleRoute, Vec<Stop>)> {
+    let (vehicle_key, current) = solution
+        .routes
+        .iter()
+        .find(|(_, route)| route.stops.iter().any(|s| s.order_id == order_id))
+        .ok_or_else(|| SolverError::InvalidArg(format!("Order {} is not served by any route", order_id)))?;
+    let vehicle = problem
+        .vehicles
+        .iter()
+        .find(|v| v.id.to_string() == *vehicle_key)
+        .ok_or_else(|| SolverError::InvalidArg(format!("Unknown vehicle id {}", vehicle_key)))?;
+
+    Ok((vehicle, current, stops_from_route(current, order_idx)?))
+}
+
+/// What dropping `order_id` from its route saves, with that route re-optimized for price afterwards.
+pub fn removal_saving(problem: &Problem, solution: &ProblemSolution, order_id: u32, model: &DistanceModel) -> Result<RemovalSaving> {
+    let order_idx = order_index(&problem.orders);
+    let remove_idx = *order_idx
+        .get(&order_id)
+        .ok_or_else(|| SolverError::InvalidArg(format!("Unknown order id {}", order_id)))?;
+
+    let (vehicle, current, stops) = serving_route(problem, solution, &order_idx, order_id)?;
+    let (route, exact) = route_without(model, vehicle, &problem.orders, &stops, remove_idx)
+        .ok_or_else(|| SolverError::Infeasible(format!("Route of vehicle {} is infeasible", vehicle.id)))?;
+
+    Ok(RemovalSaving {
+        vehicle_id: vehicle.id,
+        saved_distance: current.total_distance - route.total_distance,
+        saved_empty_distance: current.empty_distance - route.empty_distance,
+        saved_price: current.total_price - route.total_price,
+        exact,
+        route,
+    })
+}
+
+/// What cancelling `order_id` saves when its two stops are spliced out of their route and every
+/// other stop keeps its place, the route's legs and empty distance re-measured from scratch.
+///
+/// A route left without stops is dropped from the returned plan. Orders already on board have no
+/// pickup to splice out and cannot be cancelled this way.
+pub fn evaluate_removal(problem: &Problem, solution: &ProblemSolution, order_id: u32, model: &DistanceModel) -> Result<RemovalEvaluation> {
+    let order_idx = order_index(&problem.orders);
+    let remove_idx = *order_idx
+        .get(&order_id)
+        .ok_or_else(|| SolverError::InvalidArg(format!("Unknown order id {}", order_id)))?;
+
+    let (vehicle, current, stops) = serving_route(problem, solution, &order_idx, order_id)?;
+    if vehicle.onboard_order_ids.iter().flatten().any(|&id| id == order_id) {
+        return Err(SolverError::InvalidArg(format!("Order {} is on board vehicle {} and cannot be removed", order_id, vehicle.id)));
+    }
+
+    let remaining: Vec<Stop> = stops.iter().copied().filter(|s| s.order_idx != remove_idx).collect();
+    let route = if remaining.is_empty() {
+        VehicleRoute::default()
+    } else {
+        let metrics = evaluate_stops(model, vehicle, &problem.orders, &remaining)
+            .map_err(|_| SolverError::Infeasible(format!("Route of vehicle {} is infeasible", vehicle.id)))?;
+        route_from_stops(model, vehicle, &problem.orders, &remaining, &metrics)
+    };
+
+    let saved_distance = current.total_distance - route.total_distance;
+    let saved_empty_distance = current.empty_distance - route.empty_distance;
+    let saved_price = current.total_price - route.total_price;
+
+    let mut updated = solution.clone();
+    updated.total_distance -= saved_distance;
+    updated.empty_distance -= saved_empty_distance;
+    updated.total_price -= saved_price;
+    updated.total_duration -= current.total_duration_min - route.total_duration_min;
+    updated.total_co2 -= current.total_co2 - route.total_co2;
+    if remaining.is_empty() {
+        updated.remove_route(vehicle.id);
+    } else {
+        updated.insert_route(vehicle.id, route);
+    }
+    updated.route_spread = route_spread(&updated);
+    updated.max_route_distance = max_route_distance(&updated);
+
+    Ok(RemovalEvaluation {
+        vehicle_id: vehicle.id,
+        saved_distance,
+        saved_empty_distance,
+        saved_price,
+        solution: updated,
+    })
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use crate::cache::SharedDistanceStore;
+use crate::models::{Order, Vehicle};
+use crate::utils::{prepared_distance, DistanceModel, PreparedLocation};
+use super::types::OrderMask;
+use rayon::prelude::*;
+
+// Below this many locations spawning work on the thread pool costs more than the matrix itself
+const PARALLEL_THRESHOLD: usize = 256;
+
+pub struct DistanceMatrices {
+    // Flattened, row-major: node i is order i/2's pickup (even) or delivery (odd)
+    pub dist_mat: Vec<f64>,
+    pub num_nodes: usize,
+}
+
+/// Vehicle start to order pickup distances, filled in on first use. For orders already on board
+/// the vehicle, whose pickup is behind it, the distance to their delivery instead.
+///
+/// Most vehicle x order pairs are never routed once orders are restricted to a subset of the fleet,
+/// so entries are only computed when `solve_tsp` first asks for a (vehicle, order set) combination.
+/// Multi-trip vehicles also get the distance from each delivery back to their start, and vehicles
+/// with an end location the distance from each delivery to it.
+pub struct VehicleStartDistances {
+    n_orders: usize,
+    starts: Vec<PreparedLocation>,
+    ends: Vec<Option<PreparedLocation>>,
+    pickups: Vec<PreparedLocation>,
+    deliveries: Vec<PreparedLocation>,
+    multi_trip: Vec<bool>,
+    onboard: Vec<OrderMask>,
+    // vehicles x orders, only meaningful where the matching bit in `known` is set
+    pub values: Vec<f64>,
+    // Same layout as `values`, delivery to start, only filled for multi-trip vehicles
+    pub returns: Vec<f64>,
+    // Same layout as `values`, delivery to end location, only filled for vehicles with one
+    pub to_end: Vec<f64>,
+    known: Vec<OrderMask>,
+    cache: Option<CacheLink>,
+}
+
+struct CacheLink {
+    store: SharedDistanceStore,
+    start_hashes: Vec<String>,
+    end_hashes: Vec<Option<String>>,
+    pickup_hashes: Vec<String>,
+    delivery_hashes: Vec<String>,
+}
+
+impl VehicleStartDistances {
+    pub fn new(
+        orders: &[Order],
+        vehicles: &[Vehicle],
+        onboard: &[OrderMask],
+        cache: Option<SharedDistanceStore>,
+        model: &DistanceModel,
+    ) -> Self {
+        let prepare = |location| model.prepare(location);
+        VehicleStartDistances {
+            n_orders: orders.len(),
+            starts: vehicles.iter().map(|v| prepare(&v.start_location)).collect(),
+            ends: vehicles.iter().map(|v| v.end_location.as_ref().map(prepare)).collect(),
+            pickups: orders.iter().map(|o| prepare(&o.pickup_location)).collect(),
+            deliveries: orders.iter().map(|o| prepare(&o.delivery_location)).collect(),
+            multi_trip: vehicles.iter().map(|v| v.trips() > 1).collect(),
+            onboard: onboard.to_vec(),
+            values: vec![0.0; vehicles.len() * orders.len()],
+            returns: vec![0.0; vehicles.len() * orders.len()],
+            to_end: vec![0.0; vehicles.len() * orders.len()],
+            known: vec![0; vehicles.len()],
+            cache: cache.map(|store| CacheLink {
+                store,
+                start_hashes: vehicles.iter().map(|v| v.start_location.hash.clone()).collect(),
+                end_hashes: vehicles.iter().map(|v| v.end_location.as_ref().map(|end| end.hash.clone())).collect(),
+                pickup_hashes: orders.iter().map(|o| o.pickup_location.hash.clone()).collect(),
+                delivery_hashes: orders.iter().map(|o| o.delivery_location.hash.clone()).collect(),
+            }),
+        }
+    }
+
+    /// Computes any missing entries for `vehicle_idx` and the orders in `mask`.
+    #[inline(always)]
+    pub fn ensure(&mut self, vehicle_idx: usize, mask: OrderMask) {
+        let mut missing = mask & !self.known[vehicle_idx];
+        if missing == 0 {
+            return;
+        }
+        self.known[vehicle_idx] |= missing;
+
+        let row = vehicle_idx * self.n_orders;
+        let mut store = self.cache.as_ref().map(|link| (link, link.store.lock().unwrap()));
+        let mut lookup = |from: &PreparedLocation, to: &PreparedLocation, hashes: Option<(&str, &str)>| {
+            match (store.as_mut(), hashes) {
+                (Some((_, store)), Some((from_hash, to_hash))) => store.get(from_hash, to_hash).unwrap_or_else(|| {
+                    let distance = prepared_distance(from, to);
+                    store.insert(from_hash, to_hash, distance);
+                    distance
+                }),
+                _ => prepared_distance(from, to),
+            }
+        };
+        while missing != 0 {
+            let o_idx = missing.trailing_zeros() as usize;
+            let link = self.cache.as_ref();
+            let start = &self.starts[vehicle_idx];
+
+            if self.onboard[vehicle_idx] & (1 << o_idx) != 0 {
+                let hashes = link.map(|l| (l.start_hashes[vehicle_idx].as_str(), l.delivery_hashes[o_idx].as_str()));
+                self.values[row + o_idx] = lookup(start, &self.deliveries[o_idx], hashes);
+            } else {
+                let hashes = link.map(|l| (l.start_hashes[vehicle_idx].as_str(), l.pickup_hashes[o_idx].as_str()));
+                self.values[row + o_idx] = lookup(start, &self.pickups[o_idx], hashes);
+            }
+
+            if self.multi_trip[vehicle_idx] {
+                let hashes = link.map(|l| (l.delivery_hashes[o_idx].as_str(), l.start_hashes[vehicle_idx].as_str()));
+                self.returns[row + o_idx] = lookup(&self.deliveries[o_idx], start, hashes);
+            }
+            if let Some(end) = &self.ends[vehicle_idx] {
+                let hashes = link.and_then(|l| Some((l.delivery_hashes[o_idx].as_str(), l.end_hashes[vehicle_idx].as_deref()?)));
+                self.to_end[row + o_idx] = lookup(&self.deliveries[o_idx], end, hashes);
+            }
+            missing &= missing - 1;
+        }
+    }
+}
+
+impl DistanceMatrices {
+    /// Measures each pair of distinct location hashes once, orders sharing a warehouse or a
+    /// customer reuse its row, and copies the result out to the node x node matrix `solve_tsp` reads.
+    /// Large matrices are filled on the rayon pool the caller runs in: the exact search builds them
+    /// in the one `SolveOptions.threads` sizes, the heuristics in the global pool.
+    pub fn build(orders: &[Order], cache: Option<&SharedDistanceStore>, model: &DistanceModel) -> Self {
+        let num_nodes = orders.len() * 2;
+
+        // Unique locations in first-seen order, and which one each node stands at
+        let mut place_of: HashMap<&str, usize> = HashMap::new();
+        let mut places = Vec::new();
+        let node_place: Vec<usize> = orders
+            .iter()
+            .flat_map(|o| [&o.pickup_location, &o.delivery_location])
+            .map(|location| {
+                *place_of.entry(location.hash.as_str()).or_insert_with(|| {
+                    places.push(location);
+                    places.len() - 1
+                })
+            })
+            .collect();
+        let num_places = places.len();
+        let prepared: Vec<PreparedLocation> = places.iter().map(|l| model.prepare(l)).collect();
+
+        let mut place_mat = vec![0.0; num_places * num_places];
+
+        let fill_place_row = |i: usize, row: &mut [f64]| {
+            for (j, cell) in row.iter_mut().enumerate() {
+                if i != j {
+                    *cell = prepared_distance(&prepared[i], &prepared[j]);
+                }
+            }
+        };
+
+        // Chunk size must be non-zero even for empty problems
+        let place_chunk = num_places.max(1);
+
+        if let Some(cache) = cache {
+            // Cache lookups are cheap next to what they replace, one lock for the whole matrix
+            let mut store = cache.lock().unwrap();
+            store.measure_by(model);
+            for (i, row) in place_mat.chunks_mut(place_chunk).enumerate() {
+                for (j, cell) in row.iter_mut().enumerate() {
+                    if i != j {
+                        *cell = store.get(&places[i].hash, &places[j].hash).unwrap_or_else(|| {
+                            let distance = prepared_distance(&prepared[i], &prepared[j]);
+                            store.insert(&places[i].hash, &places[j].hash, distance);
+                            distance
+                        });
+                    }
+                }
+            }
+        } else if num_places < PARALLEL_THRESHOLD {
+            place_mat.chunks_mut(place_chunk).enumerate().for_each(|(i, row)| fill_place_row(i, row));
+        } else {
+            place_mat.par_chunks_mut(place_chunk).enumerate().for_each(|(i, row)| fill_place_row(i, row));
+        }
+
+        let mut dist_mat = vec![0.0; num_nodes * num_nodes];
+        for (i, row) in dist_mat.chunks_mut(num_nodes.max(1)).enumerate() {
+            let place_row = &place_mat[node_place[i] * num_places..][..num_places];
+            for (cell, &place) in row.iter_mut().zip(&node_place) {
+                *cell = place_row[place];
+            }
+        }
+
+        DistanceMatrices {
+            dist_mat,
+            num_nodes,
+        }
+    }
+}
@@ -1,151 +1,1180 @@
+pub mod alternatives;
+pub mod audit;
+pub mod auto;
+pub mod beam;
 pub mod context;
+pub mod evaluate;
+pub mod gap;
+pub mod held_karp;
+pub mod marginal;
+pub mod matrix;
+pub mod objective;
+pub mod parallel;
+pub mod pareto;
+pub mod partition;
+pub mod route_memo;
+pub mod routing;
+pub mod scenarios;
+pub mod seed;
+pub mod spread;
+pub mod sweep;
 pub mod tsp;
 pub mod types;
+pub mod units;
+pub mod weighted;
 
-use std::collections::HashMap;
-use crate::models::{Problem, AlgorithmSolution, ProblemSolution, VehicleRoute, RouteStop};
-use context::SolverContext;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::error::{Result, SolverError};
+use crate::heuristics::greedy;
+use crate::progress;
+use crate::utils::{prepared_distance, DistanceModel};
+use crate::models::{
+    DistanceMode, DistanceOptions, Location, Problem, AlgorithmSolution, Criterion, ProblemSolution, VehicleRoute, RouteLeg, RouteStop, SolveOptions, SolveStats, UnassignableOrder, Vehicle,
+};
+use context::{group_masks, order_mask, ImprovementFn, SharedContext, SolverContext};
+use evaluate::{evaluate_stops, Stop, ViolationKind};
+use objective::Objective;
 use tsp::solve_tsp;
+use spread::{exceeds_cap_everywhere, nearest_feasible, within_cap, RouteSpan};
+use types::{wins_tie_break, InternalBestResults, InternalTspResult, OrderMask, PartialCost, MAX_ROUTE_ORDERS, RELOAD_NODE, TIE_EPSILON};
+use units::Rescale;
 
 fn solve_recursive(
     ctx: &mut SolverContext, 
     vehicle_idx: usize, 
-    assignment_mask: u32,
-    current_dist: f64,
-    current_price: f64,
-    current_empty: f64,
-    assignments: &mut Vec<u32>,
+    assignment_mask: OrderMask,
+    current: PartialCost,
+    span: RouteSpan,
+    assignments: &mut Vec<OrderMask>,
 ) {
-    // Top level pruning
-    if current_dist >= ctx.best_dist && current_price >= ctx.best_price && current_empty >= ctx.best_empty {
+    if ctx.stopped {
         return;
     }
 
-    // Base Case: All orders assigned
-    if assignment_mask == ctx.full_mask {
-        if current_dist < ctx.best_dist {
-            ctx.best_dist = current_dist;
-            ctx.best_dist_assignments.copy_from_slice(assignments);
-        }
-        if current_price < ctx.best_price {
-            ctx.best_price = current_price;
-            ctx.best_price_assignments.copy_from_slice(assignments);
+    // Top level pruning
+    if current.dominated_by(&pruning_bound(ctx)) {
+        ctx.stats.assignment_bound_prunes += 1;
+        if gap_tolerance(&ctx.shared.options) > 0.0 {
+            ctx.gap_floor = ctx.gap_floor.min(&current);
         }
-        if current_empty < ctx.best_empty {
-            ctx.best_empty = current_empty;
-            ctx.best_empty_assignments.copy_from_slice(assignments);
+        return;
+    }
+
+    let spread_cap = ctx.shared.options.max_route_spread_km;
+    if exceeds_cap_everywhere(&span, spread_cap) {
+        return;
+    }
+
+    // Base Case: All orders assigned. Required vehicles are never skipped, so reaching this
+    // before the last vehicle means some went unused
+    if assignment_mask == ctx.shared.full_mask {
+        if ctx.shared.options.require_all_vehicles.unwrap_or(false) && vehicle_idx < ctx.shared.vehicles.len() {
+            return;
         }
+        record_complete(ctx, current, span, assignments);
+        return;
+    }
+
+    // Every vehicle considered and some orders left over
+    if vehicle_idx >= ctx.shared.vehicles.len() {
+        ctx.partial.offer(assignment_mask.count_ones(), &current, assignments);
+        return;
+    }
+
+    // Every remaining vehicle needs at least one of the remaining orders
+    let require_all = ctx.shared.options.require_all_vehicles.unwrap_or(false);
+    let remaining_orders = (ctx.shared.full_mask ^ assignment_mask).count_ones() as usize;
+    if require_all && remaining_orders < ctx.shared.vehicles.len() - vehicle_idx {
         return;
     }
 
-    if vehicle_idx >= ctx.vehicles.len() {
+    ctx.stats.assignment_nodes += 1;
+    if out_of_nodes(ctx) {
+        ctx.stopped = true;
         return;
     }
+    if ctx.stats.assignment_nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+        if ctx.shared.cancel_requested() {
+            ctx.cancelled = true;
+            ctx.stopped = true;
+            return;
+        }
+        if past_deadline(ctx) {
+            ctx.stopped = true;
+            return;
+        }
+    }
+
+    // Same orders covered by the same vehicle prefix, reached through a different interleaving.
+    // With a spread cap the subtree also depends on the routes used so far, so nothing is shared
+    // Listing alternatives needs the worse re-entries too, their subtrees hold the runners-up
+    if spread_cap.is_none() && !ctx.assignment_visits.is_empty() && !ctx.alternatives.active() {
+        let visit_idx = vehicle_idx * (1 << ctx.shared.n_orders) + assignment_mask as usize;
+        let current = current.only(ctx.shared.criteria);
+        let seen = &mut ctx.assignment_visits[visit_idx];
+        if current.dominated_by(seen) {
+            ctx.stats.assignment_revisits_pruned += 1;
+            return;
+        }
+        // Each component is only ever compared per criterion, so keeping the minimum of each is safe
+        seen.dist = seen.dist.min(current.dist);
+        seen.price = seen.price.min(current.price);
+        seen.empty = seen.empty.min(current.empty);
+        seen.makespan = seen.makespan.min(current.makespan);
+        seen.duration = seen.duration.min(current.duration);
+        seen.fleet = seen.fleet.min(current.fleet);
+    }
+
+    // Assignment vectors are visited in lexicographic order, so of two tied ones the one
+    // `wins_tie_break` prefers is always found first. Skipping this vehicle comes first
+    if !require_all {
+        solve_recursive(ctx, vehicle_idx + 1, assignment_mask, current, span, assignments);
+    }
+
+    let remaining_mask = (ctx.shared.full_mask ^ assignment_mask) & ctx.shared.vehicle_allowed[vehicle_idx];
+    let fixed = ctx.shared.fixed[vehicle_idx];
+
+    // Then every submask of the remaining orders this vehicle may serve in ascending order, no
+    // other vehicle can take the orders pinned to it. Sets beyond `max_orders` are skipped before routing
+    let mut submask = next_submask(0, remaining_mask);
+    while submask != 0 {
+        ctx.stats.submasks += 1;
+        if submask & fixed == fixed && ctx.shared.within_order_cap(vehicle_idx, submask) {
+            assign_and_recurse(ctx, vehicle_idx, assignment_mask, submask, current, span, assignments);
+        }
+        submask = next_submask(submask, remaining_mask);
+    }
+}
+
+/// The smallest submask of `mask` above `submask`, 0 once `submask` is `mask` itself.
+#[inline(always)]
+pub(crate) fn next_submask(submask: OrderMask, mask: OrderMask) -> OrderMask {
+    (submask | !mask).wrapping_add(1) & mask
+}
+
+/// Takes a complete assignment as the incumbent of every criterion it wins by `wins_tie_break`,
+/// unless that criterion's routes break the spread cap.
+pub(crate) fn record_complete(ctx: &mut SolverContext, current: PartialCost, span: RouteSpan, assignments: &[OrderMask]) {
+    ctx.stats.complete_assignments += 1;
+    let spread_cap = ctx.shared.options.max_route_spread_km;
+    let spread = span.spread();
+    let criteria = ctx.shared.criteria;
+    let before_cost = local_incumbent(ctx);
+    let before = (ctx.best_dist, ctx.best_price, ctx.best_empty, ctx.best_makespan, ctx.best_duration, ctx.best_fleet);
+    if ctx.alternatives.active() && within_cap(spread.dist, spread_cap) {
+        ctx.alternatives.offer(current.dist, assignments);
+    }
+    if criteria.contains(Criterion::Distance)
+        && wins_tie_break(current.dist, assignments, ctx.best_dist, &ctx.best_dist_assignments)
+        && within_cap(spread.dist, spread_cap)
+    {
+        ctx.best_dist = current.dist;
+        ctx.best_dist_assignments.copy_from_slice(assignments);
+    }
+    if criteria.contains(Criterion::Price)
+        && wins_tie_break(current.price, assignments, ctx.best_price, &ctx.best_price_assignments)
+        && within_cap(spread.price, spread_cap)
+    {
+        ctx.best_price = current.price;
+        ctx.best_price_assignments.copy_from_slice(assignments);
+    }
+    if criteria.contains(Criterion::Empty)
+        && wins_tie_break(current.empty, assignments, ctx.best_empty, &ctx.best_empty_assignments)
+        && within_cap(spread.empty, spread_cap)
+    {
+        ctx.best_empty = current.empty;
+        ctx.best_empty_assignments.copy_from_slice(assignments);
+    }
+    if criteria.contains(Criterion::Makespan)
+        && wins_tie_break(current.makespan, assignments, ctx.best_makespan, &ctx.best_makespan_assignments)
+        && within_cap(spread.makespan, spread_cap)
+    {
+        ctx.best_makespan = current.makespan;
+        ctx.best_makespan_assignments.copy_from_slice(assignments);
+    }
+    if criteria.contains(Criterion::Duration)
+        && wins_tie_break(current.duration, assignments, ctx.best_duration, &ctx.best_duration_assignments)
+        && within_cap(spread.duration, spread_cap)
+    {
+        ctx.best_duration = current.duration;
+        ctx.best_duration_assignments.copy_from_slice(assignments);
+    }
+    if criteria.contains(Criterion::Fleet)
+        && wins_tie_break(current.fleet, assignments, ctx.best_fleet, &ctx.best_fleet_assignments)
+        && within_cap(spread.fleet, spread_cap)
+    {
+        ctx.best_fleet = current.fleet;
+        ctx.best_fleet_assignments.copy_from_slice(assignments);
+    }
+    if before != (ctx.best_dist, ctx.best_price, ctx.best_empty, ctx.best_makespan, ctx.best_duration, ctx.best_fleet) {
+        if let Some(bounds) = &ctx.bounds {
+            bounds.offer(&local_incumbent(ctx));
+        }
+        progress::publish(ctx);
+        progress::report_improvements(ctx, &before_cost);
+    }
+}
+
+/// Gives `submask` to the vehicle and searches the remaining vehicles, if it can serve that set.
+#[inline(always)]
+fn assign_and_recurse(
+    ctx: &mut SolverContext,
+    vehicle_idx: usize,
+    assignment_mask: OrderMask,
+    submask: OrderMask,
+    current: PartialCost,
+    span: RouteSpan,
+    assignments: &mut Vec<OrderMask>,
+) {
+    let res = solve_tsp(ctx, vehicle_idx, submask);
+
+    if res.valid && meets_min_route_price(ctx, vehicle_idx, &res) {
+        assignments[vehicle_idx] = submask;
+
+        let spread_cap = ctx.shared.options.max_route_spread_km;
+        solve_recursive(
+            ctx,
+            vehicle_idx + 1,
+            assignment_mask | submask,
+            current.with_route(&res),
+            if spread_cap.is_some() { span.with_route(&res) } else { span },
+            assignments
+        );
+
+        assignments[vehicle_idx] = 0;
+    }
+}
+
+/// Best costs this context has found itself.
+#[inline(always)]
+pub(crate) fn local_incumbent(ctx: &SolverContext) -> PartialCost {
+    PartialCost {
+        dist: ctx.best_dist,
+        price: ctx.best_price,
+        empty: ctx.best_empty,
+        makespan: ctx.best_makespan,
+        duration: ctx.best_duration,
+        fleet: ctx.best_fleet,
+    }
+}
+
+/// Best costs found so far by this context or, in a parallel search, by any other worker.
+#[inline(always)]
+pub(crate) fn incumbent(ctx: &SolverContext) -> PartialCost {
+    let local = local_incumbent(ctx);
+    match &ctx.bounds {
+        Some(bounds) => local.min(&bounds.get()),
+        None => local,
+    }
+}
+
+/// What a partial assignment is pruned against: `incumbent`, but with the other workers' bounds
+/// loosened by `TIE_EPSILON`. A tie another worker reached first is still searched here, so the
+/// merge gets to break it the same way however the workers were scheduled.
+/// With `options.top_k` the distance bound is the worst plan still listed, each worker keeping its
+/// own list. With `options.gap_tolerance` only branches beating it by more than that fraction are
+/// left. Criteria left out of `options.compute` never keep a branch alive.
+#[inline(always)]
+fn pruning_bound(ctx: &SolverContext) -> PartialCost {
+    let local = local_incumbent(ctx);
+    let bound = match &ctx.bounds {
+        Some(bounds) => {
+            let shared = bounds.get();
+            local.min(&PartialCost::from_fn(|criterion| shared.get(criterion) + TIE_EPSILON))
+        }
+        None => local,
+    };
+    let bound = if ctx.alternatives.active() { PartialCost { dist: ctx.alternatives.threshold(), ..bound } } else { bound };
+    let gap = gap_tolerance(&ctx.shared.options);
+    let bound = if gap > 0.0 { PartialCost::from_fn(|criterion| bound.get(criterion) / (1.0 + gap)) } else { bound };
+    bound.only(ctx.shared.criteria)
+}
+
+/// `options.gap_tolerance` or its older name `options.epsilon`, 0 for an exact search.
+pub(crate) fn gap_tolerance(options: &SolveOptions) -> f64 {
+    options.gap_tolerance.or(options.epsilon).unwrap_or(0.0)
+}
+
+/// Largest fraction by which a plan of a requested criterion may exceed its optimum, going by the
+/// branches the gap tolerance pruned. None after an early stop, the unexplored branches bound nothing.
+fn achieved_gap(ctx: &SolverContext) -> Option<f64> {
+    if ctx.stopped {
+        return None;
+    }
+    let best = local_incumbent(ctx);
+    let all = [Criterion::Distance, Criterion::Price, Criterion::Empty, Criterion::Makespan, Criterion::Duration, Criterion::Fleet];
+    let gap = all
+        .into_iter()
+        .filter(|&criterion| ctx.shared.criteria.contains(criterion) && best.get(criterion) < f64::INFINITY)
+        .map(|criterion| {
+            let floor = ctx.gap_floor.get(criterion);
+            if floor < best.get(criterion) && floor > 0.0 { best.get(criterion) / floor - 1.0 } else { 0.0 }
+        })
+        .fold(0.0, f64::max);
+    Some(gap)
+}
 
-    let remaining_mask = ctx.full_mask ^ assignment_mask;
-    let mut submask = remaining_mask;
-    
-    // Iterate over all submasks of the remaining orders
-    loop {
-        if submask == 0 { break; } 
-        
-        let res = solve_tsp(ctx, vehicle_idx, submask);
-        
-        if res.valid {
-            assignments[vehicle_idx] = submask;
+// Assignment nodes between clock and cancel flag reads, keeps `Instant::now` out of the hot path
+pub(crate) const DEADLINE_CHECK_INTERVAL: u64 = 1024;
 
-            solve_recursive(
-                ctx,
-                vehicle_idx + 1,
-                assignment_mask | submask,
-                current_dist + res.min_dist.total_dist,
-                current_price + res.min_price.total_price,
-                current_empty + res.min_empty.total_empty,
-                assignments
-            );
+/// Only true once a complete assignment was evaluated, so a tight limit still yields a solution
+/// whenever one is reached quickly.
+pub(crate) fn past_deadline(ctx: &SolverContext) -> bool {
+    ctx.stats.complete_assignments > 0 && ctx.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
 
-            assignments[vehicle_idx] = 0;
+/// Whether `options.max_nodes` is spent, only once a complete assignment was evaluated like `past_deadline`.
+#[inline(always)]
+pub(crate) fn out_of_nodes(ctx: &SolverContext) -> bool {
+    ctx.stats.assignment_nodes + ctx.stats.route_nodes >= ctx.node_budget && ctx.stats.complete_assignments > 0
+}
+
+/// Whether the order set behind `res` is worth dispatching the vehicle for. Its cheapest route
+/// is checked, so no route the search picks for that set falls below the minimum. With a
+/// `route_cost` callback that is the price the callback gave it.
+#[inline(always)]
+pub(crate) fn meets_min_route_price(ctx: &SolverContext, vehicle_idx: usize, res: &InternalBestResults) -> bool {
+    ctx.shared.vehicles[vehicle_idx]
+        .min_route_price
+        .is_none_or(|min_price| res.min_price.total_price >= min_price)
+}
+
+pub(crate) fn build_route(ctx: &SolverContext, vehicle_idx: usize, internal_res: &InternalTspResult) -> VehicleRoute {
+    let shared = &ctx.shared;
+    let vehicle = &shared.vehicles[vehicle_idx];
+    let start = shared.place(&vehicle.start_location);
+    let minutes_per_km = vehicle.minutes_per_km();
+    let loads = &shared.loads[vehicle_idx * shared.n_orders..(vehicle_idx + 1) * shared.n_orders];
+    let onboard = shared.onboard[vehicle_idx];
+
+    // Replays the path on the clock, waiting wherever a window has not opened yet. Legs are
+    // measured and summed like the search does, so they add up to its total bit for bit
+    let mut time = 0.0;
+    let mut prev: Option<usize> = None;
+    let mut position = &vehicle.start_location;
+    let mut on_board = onboard.count_ones();
+    let initial_load: f64 = (0..shared.n_orders).filter(|o_idx| onboard & (1 << o_idx) != 0).map(|o_idx| loads[o_idx]).sum();
+    let mut load = initial_load;
+    let mut stops = Vec::new();
+    let mut legs = Vec::new();
+    let mut drive = |from: &Location, to: &Location, distance: f64, empty: bool, load_after: f64, fee: f64| {
+        legs.push(RouteLeg {
+            from: from.clone(),
+            to: to.clone(),
+            distance,
+            empty,
+            load_after,
+            price: vehicle.leg_price(distance, empty) + fee,
+        });
+    };
+    for i in 0..internal_res.path.len {
+        let node = internal_res.path.nodes[i as usize];
+        if node == RELOAD_NODE {
+            let delivery = &shared.orders[prev.unwrap_or_default() / 2].delivery_location;
+            let leg_dist = prepared_distance(&shared.place(delivery), &start);
+            drive(delivery, &vehicle.start_location, leg_dist, true, 0.0, 0.0);
+            time += leg_dist * minutes_per_km;
+            prev = None;
+            position = &vehicle.start_location;
+            stops.push(RouteStop::new(0, "reload", &vehicle.start_location, stops.len(), time, time, 0.0));
+            continue;
+        }
+        let node = node as usize;
+        let order = &shared.orders[node / 2];
+        let pickup = node.is_multiple_of(2);
+        let location = if pickup { &order.pickup_location } else { &order.delivery_location };
+        let leg_dist = match prev {
+            Some(prev) => shared.dist_mat[prev * shared.num_nodes + node],
+            // Straight to a delivery only for orders already on board
+            None => prepared_distance(&start, &shared.place(location)),
+        };
+        let empty = on_board == 0;
+        if pickup {
+            on_board += 1;
+            load += loads[node / 2];
+        } else {
+            on_board -= 1;
+            load -= loads[node / 2];
         }
+        let load_after = if on_board == 0 { 0.0 } else { load };
+        drive(position, location, leg_dist, empty, load_after, vehicle.fee_per_stop());
+        let served = (time + leg_dist * minutes_per_km).max(order.window_bounds(pickup).0);
+        time = served + order.service_minutes(pickup);
+        prev = Some(node);
+        position = location;
+        let type_ = if pickup { "pickup" } else { "delivery" };
+        stops.push(RouteStop::new(order.id, type_, location, stops.len(), served, time, load_after));
+    }
+    if let (Some(end), false) = (&vehicle.end_location, stops.is_empty()) {
+        drive(position, end, internal_res.return_dist, true, 0.0, 0.0);
+    }
 
-        submask = (submask - 1) & remaining_mask;
-        if submask == 0 { break; }
+    let served = stops.iter().filter(|stop| stop.type_ != "reload").count();
+    let (max_load, avg_utilization) = evaluate::load_profile(initial_load, &legs);
+    let mut cost_breakdown = vehicle.price_of(internal_res.total_dist, internal_res.total_empty, served);
+    if shared.route_cost.is_some() {
+        // Only the price-optimal path carries the callback's price, the others match their legs
+        let adjustment = internal_res.total_price - (cost_breakdown.loaded_cost + cost_breakdown.empty_cost + cost_breakdown.stop_fees);
+        if adjustment.abs() > TIE_EPSILON {
+            cost_breakdown.custom_adjustment = adjustment;
+        }
     }
+    VehicleRoute {
+        vehicle_id: vehicle.id,
+        stops,
+        legs,
+        max_load,
+        avg_utilization,
+        total_distance: internal_res.total_dist,
+        empty_distance: internal_res.total_empty,
+        total_price: internal_res.total_price,
+        total_duration_min: internal_res.total_duration,
+        waiting_time: internal_res.waiting,
+        return_distance: internal_res.return_dist,
+        interleavings: internal_res.interleavings,
+        cost_breakdown,
+        total_co2: internal_res.total_co2,
+    }
+}
 
-    // Recursive step: Try skipping this vehicle
-    solve_recursive(ctx, vehicle_idx + 1, assignment_mask, current_dist, current_price, current_empty, assignments);
+pub(crate) fn reconstruct_solution(ctx: &mut SolverContext, assignments: &[OrderMask], criterion: Criterion) -> ProblemSolution {
+    reconstruct_with(ctx, assignments, |_| criterion)
 }
 
-fn reconstruct_solution(ctx: &mut SolverContext, assignments: &Vec<u32>, criterion: &str) -> ProblemSolution {
+/// Same as `reconstruct_solution`, routing each vehicle for its own criterion.
+pub(crate) fn reconstruct_with(
+    ctx: &mut SolverContext,
+    assignments: &[OrderMask],
+    criterion: impl Fn(usize) -> Criterion,
+) -> ProblemSolution {
     let mut solution = ProblemSolution {
         routes: HashMap::new(),
+        route_list: Vec::new(),
         total_distance: 0.0,
         empty_distance: 0.0,
         total_price: 0.0,
+        total_duration: 0.0,
+        total_co2: 0.0,
+        route_spread: 0.0,
+        max_route_distance: 0.0,
+        unassigned_order_ids: Vec::new(),
     };
 
+    let covered = assignments.iter().fold(0, |all, &mask| all | mask);
+    solution.unassigned_order_ids = ctx
+        .shared
+        .orders
+        .iter()
+        .enumerate()
+        .filter(|&(o_idx, _)| covered & (1 << o_idx) == 0)
+        .map(|(_, order)| order.id)
+        .collect();
+
     for (v_idx, &mask) in assignments.iter().enumerate() {
         if mask > 0 {
             let res = solve_tsp(ctx, v_idx, mask);
             if res.valid {
-                let internal_res = match criterion {
-                    "dist" => res.min_dist,
-                    "price" => res.min_price,
-                    _ => res.min_empty,
-                };
-
-                let mut stops = Vec::new();
-                for i in 0..internal_res.path.len {
-                    let node = internal_res.path.nodes[i as usize];
-                    let order_id = ctx.orders[(node / 2) as usize].id;
-                    let type_str = if node % 2 == 0 { "pickup" } else { "delivery" };
-                    stops.push(RouteStop {
-                        order_id,
-                        type_: type_str.to_string(),
-                    });
-                }
-
-                let route = VehicleRoute {
-                    stops,
-                    total_distance: internal_res.total_dist,
-                    empty_distance: internal_res.total_empty,
-                    total_price: internal_res.total_price,
-                };
+                let route = build_route(ctx, v_idx, res.for_criterion(criterion(v_idx)));
 
                 solution.total_distance += route.total_distance;
                 solution.total_price += route.total_price;
                 solution.empty_distance += route.empty_distance;
-                solution.routes.insert(ctx.vehicles[v_idx].id.to_string(), route);
+                solution.total_duration += route.total_duration_min;
+                solution.total_co2 += route.total_co2;
+                solution.insert_route(ctx.shared.vehicles[v_idx].id, route);
             }
         }
     }
+    solution.route_spread = route_spread(&solution);
+    solution.max_route_distance = max_route_distance(&solution);
     solution
 }
 
+/// Longest minus shortest route distance, 0 with fewer than two routes.
+pub(crate) fn route_spread(solution: &ProblemSolution) -> f64 {
+    let distances = solution.routes.values().map(|r| r.total_distance);
+    let longest = distances.clone().fold(f64::NEG_INFINITY, f64::max);
+    let shortest = distances.fold(f64::INFINITY, f64::min);
+    (longest - shortest).max(0.0)
+}
+
+/// Longest route distance, 0 without routes.
+pub(crate) fn max_route_distance(solution: &ProblemSolution) -> f64 {
+    solution.routes.values().map(|r| r.total_distance).fold(0.0, f64::max)
+}
+
 pub fn solve(problem: Problem) -> AlgorithmSolution {
-    let mut ctx = SolverContext::new(&problem.orders, &problem.vehicles);
-    let mut assignments = vec![0; problem.vehicles.len()];
-
-    solve_recursive(&mut ctx, 0, 0, 0.0, 0.0, 0.0, &mut assignments);
-
-    let best_dist_vec = ctx.best_dist_assignments.clone();
-    let best_price_vec = ctx.best_price_assignments.clone();
-    let best_empty_vec = ctx.best_empty_assignments.clone();
-    
-    let dist_sol = if ctx.best_dist < f64::INFINITY {
-        reconstruct_solution(&mut ctx, &best_dist_vec, "dist")
-    } else { ProblemSolution::default() };
-
-    let price_sol = if ctx.best_price < f64::INFINITY {
-        reconstruct_solution(&mut ctx, &best_price_vec, "price")
-    } else { ProblemSolution::default() };
-    
-    let empty_sol = if ctx.best_empty < f64::INFINITY {
-        reconstruct_solution(&mut ctx, &best_empty_vec, "empty")
-    } else { ProblemSolution::default() };
+    solve_shared(Arc::new(SharedContext::from_problem(problem, None, DistanceModel::default())))
+}
+
+/// Rejects problems the exact search cannot represent. Any vehicle may be handed every order,
+/// so the whole problem has to fit in one route's `PathBuffer`. Fixed assignments are checked
+/// with distances measured by `model`.
+pub fn validate_problem(problem: &Problem, model: &DistanceModel) -> Result<()> {
+    if problem.orders.len() > MAX_ROUTE_ORDERS {
+        return Err(SolverError::TooManyOrders(format!(
+            "Problem exceeds max {} orders per solve ({} given)",
+            MAX_ROUTE_ORDERS,
+            problem.orders.len()
+        )));
+    }
+    validate_rules(problem, model)
+}
+
+/// Everything `validate_problem` checks but the order count, for searches taking larger problems.
+pub(crate) fn validate_rules(problem: &Problem, model: &DistanceModel) -> Result<()> {
+    validate_ids(problem)?;
+    validate_hashes(problem)?;
+    validate_load_factors(problem)?;
+    validate_vehicles(problem)?;
+    validate_tags(problem)?;
+    validate_fixed_assignments(problem, model)?;
+    validate_onboard(problem)?;
+    validate_pairings(problem)
+}
+
+/// Ids used more than once, each listed once in ascending order.
+fn duplicates(ids: impl Iterator<Item = u32>) -> Vec<u32> {
+    let mut seen = HashSet::new();
+    let mut repeated: Vec<u32> = ids.filter(|&id| !seen.insert(id)).collect();
+    repeated.sort_unstable();
+    repeated.dedup();
+    repeated
+}
+
+/// Orders and vehicles are looked up by id, each id has to name exactly one of them.
+pub(crate) fn validate_ids(problem: &Problem) -> Result<()> {
+    let orders = duplicates(problem.orders.iter().map(|o| o.id));
+    if !orders.is_empty() {
+        return Err(SolverError::DuplicateOrderId(orders));
+    }
+    let vehicles = duplicates(problem.vehicles.iter().map(|v| v.id));
+    if !vehicles.is_empty() {
+        return Err(SolverError::DuplicateVehicleId(vehicles));
+    }
+    Ok(())
+}
+
+/// Callers deduplicate stops by location hash, an order needs at least one to be told apart.
+fn validate_hashes(problem: &Problem) -> Result<()> {
+    let unnamed: Vec<String> = problem
+        .orders
+        .iter()
+        .filter(|o| o.pickup_location.hash.is_empty() && o.delivery_location.hash.is_empty())
+        .map(|o| o.id.to_string())
+        .collect();
+    if unnamed.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::InvalidArg(format!("Empty pickup and delivery location hashes on orders {}", unnamed.join(", "))))
+    }
+}
+
+/// Orders on board must be known, on a single vehicle, not pinned to another one and fit it
+/// together, they are already loaded.
+fn validate_onboard(problem: &Problem) -> Result<()> {
+    let invalid = |msg: String| Err(SolverError::InvalidArg(format!("Invalid onboard orders: {}", msg)));
+    let mut carriers: HashMap<u32, u32> = HashMap::new();
+    for vehicle in &problem.vehicles {
+        let mut load = 0.0;
+        for id in vehicle.onboard_order_ids.iter().flatten() {
+            let Some(order) = problem.orders.iter().find(|o| o.id == *id) else {
+                return invalid(format!("unknown order id {} on vehicle {}", id, vehicle.id));
+            };
+            if let Some(other) = carriers.insert(*id, vehicle.id) {
+                return invalid(format!("order {} is on board vehicles {} and {}", id, other, vehicle.id));
+            }
+            if let Some(&pinned) = problem.fixed_assignments.as_ref().and_then(|fixed| fixed.get(&id.to_string())) {
+                if pinned != vehicle.id {
+                    return invalid(format!("order {} is on board vehicle {} but fixed to vehicle {}", id, vehicle.id, pinned));
+                }
+            }
+            load += order.load_on(vehicle);
+        }
+        if load > 1.000001 {
+            return invalid(format!("orders on board vehicle {} exceed its capacity", vehicle.id));
+        }
+    }
+    Ok(())
+}
+
+/// Groups and conflicts must name known orders and must not contradict each other or the pins.
+fn validate_pairings(problem: &Problem) -> Result<()> {
+    let invalid = |msg: String| Err(SolverError::InvalidArg(format!("Invalid order pairing: {}", msg)));
+    let groups = problem.same_vehicle_groups.as_deref().unwrap_or_default();
+    let pairs = problem.conflict_pairs.as_deref().unwrap_or_default();
+
+    let ids = groups.iter().flatten().chain(pairs.iter().flat_map(|p| [&p.first_order_id, &p.second_order_id]));
+    for id in ids {
+        if !problem.orders.iter().any(|o| o.id == *id) {
+            return invalid(format!("unknown order id {}", id));
+        }
+    }
+
+    let masks = group_masks(&problem.orders, groups);
+    let group_of = |id: u32| masks.iter().copied().find(|&mask| mask & order_mask(&problem.orders, &[id]) != 0);
+    for pair in pairs {
+        if pair.first_order_id == pair.second_order_id {
+            return invalid(format!("order {} conflicts with itself", pair.first_order_id));
+        }
+        if group_of(pair.first_order_id).is_some_and(|group| group_of(pair.second_order_id) == Some(group)) {
+            return invalid(format!(
+                "orders {} and {} must share a vehicle and may not",
+                pair.first_order_id, pair.second_order_id
+            ));
+        }
+    }
+
+    // Pinned members of one group have to be pinned to the same vehicle
+    let mut pins: Vec<(u32, u32)> = problem
+        .fixed_assignments
+        .iter()
+        .flatten()
+        .filter_map(|(order_id, &vehicle_id)| order_id.parse().ok().map(|order_id| (order_id, vehicle_id)))
+        .collect();
+    pins.sort();
+    for (i, &(a, vehicle_a)) in pins.iter().enumerate() {
+        for &(b, vehicle_b) in &pins[i + 1..] {
+            if vehicle_a != vehicle_b && group_of(a).is_some_and(|group| group_of(b) == Some(group)) {
+                return invalid(format!(
+                    "orders {} and {} must share a vehicle but are fixed to vehicles {} and {}",
+                    a, b, vehicle_a, vehicle_b
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fails fast on orders whose required tags no single vehicle has, they could never be served.
+/// Orders without tags or already on board are left alone, an empty fleet merely leaves them unassigned.
+fn validate_tags(problem: &Problem) -> Result<()> {
+    let unservable: Vec<String> = problem
+        .orders
+        .iter()
+        .filter(|o| o.required_tags.as_ref().is_some_and(|tags| !tags.is_empty()))
+        .filter(|o| !problem.vehicles.iter().any(|v| o.servable_by(v) || v.onboard_order_ids.iter().flatten().any(|id| *id == o.id)))
+        .map(|o| o.id.to_string())
+        .collect();
+    if unservable.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::InvalidArg(format!("No vehicle has every tag required by orders {}", unservable.join(", "))))
+    }
+}
+
+/// Every pin must name a known order and vehicle, and the vehicle must be able to serve the
+/// order on its own and all orders pinned to it together, otherwise no plan could honour them.
+fn validate_fixed_assignments(problem: &Problem, model: &DistanceModel) -> Result<()> {
+    let invalid = |msg: String| Err(SolverError::InvalidArg(format!("Invalid fixed assignment: {}", msg)));
+    let mut pins: Vec<_> = problem.fixed_assignments.iter().flatten().collect();
+    pins.sort();
+    for (order_id, vehicle_id) in pins {
+        let Some(o_idx) = problem.orders.iter().position(|o| order_id.parse() == Ok(o.id)) else {
+            return invalid(format!("unknown order id '{}'", order_id));
+        };
+        let Some(vehicle) = problem.vehicles.iter().find(|v| v.id == *vehicle_id) else {
+            return invalid(format!("order {} is fixed to unknown vehicle {}", order_id, vehicle_id));
+        };
+        let stops = [Stop { order_idx: o_idx, pickup: true }, Stop { order_idx: o_idx, pickup: false }];
+        let failed = match evaluate_stops(model, vehicle, &problem.orders, &stops) {
+            Err((_, ViolationKind::CapacityExceeded)) => "exceeds its capacity",
+            Err((_, ViolationKind::TimeWindowMissed)) => "cannot reach it within its time windows",
+            Err((_, ViolationKind::MissingTags)) => "lacks tags it requires",
+            Err((_, ViolationKind::RouteLimitExceeded)) => "cannot serve it within its route limits",
+            _ => continue,
+        };
+        return invalid(format!("order {} is fixed to vehicle {}, which {}", order_id, vehicle_id, failed));
+    }
+    for vehicle in &problem.vehicles {
+        let pinned = problem.fixed_assignments.iter().flatten().filter(|&(_, &v_id)| v_id == vehicle.id).count() as u32;
+        if !vehicle.takes_orders(pinned) {
+            return invalid(format!(
+                "{} orders are fixed to vehicle {}, more than its max_orders of {}",
+                pinned,
+                vehicle.id,
+                vehicle.max_orders.unwrap_or_default()
+            ));
+        }
+    }
+    Ok(())
+}
+
+type LoadFactorCheck = (&'static str, fn(f64) -> bool);
+type VehicleLimitCheck = (&'static str, fn(&Vehicle) -> Option<f64>);
+
+/// A load factor is how many such orders fill the vehicle, so the order's load is its inverse.
+/// Anything that is not a finite number of at least 1 can never be loaded correctly. Explicit
+/// demands are checked alongside, an order larger than every vehicle is merely unassigned.
+pub(crate) fn validate_load_factors(problem: &Problem) -> Result<()> {
+    let offending = |pred: fn(f64) -> bool| -> Vec<String> {
+        problem.orders.iter().filter(|o| pred(o.load_factor)).map(|o| o.id.to_string()).collect()
+    };
+    let checks: [LoadFactorCheck; 4] = [
+        ("non-finite load_factor", |f| !f.is_finite()),
+        ("zero load_factor", |f| f == 0.0),
+        ("negative load_factor", |f| f < 0.0),
+        ("load_factor below 1 (over capacity on its own)", |f| f > 0.0 && f < 1.0),
+    ];
+
+    let mut problems: Vec<String> = checks
+        .iter()
+        .filter_map(|&(label, pred)| {
+            let ids = offending(pred);
+            (!ids.is_empty()).then(|| format!("{} on orders {}", label, ids.join(", ")))
+        })
+        .collect();
+    let bad_demands: Vec<String> = problem
+        .orders
+        .iter()
+        .filter(|o| o.demand.is_some_and(|d| !d.is_finite() || d < 0.0))
+        .map(|o| o.id.to_string())
+        .collect();
+    if !bad_demands.is_empty() {
+        problems.push(format!("negative or non-finite demand on orders {}", bad_demands.join(", ")));
+    }
+    if !problems.is_empty() {
+        return Err(SolverError::InvalidLoadFactor(format!("Invalid orders: {}", problems.join("; "))));
+    }
+    Ok(())
+}
+
+/// Capacities and route limits have to be positive and finite where set, and an order cap of
+/// zero leaves the vehicle nothing to serve.
+pub(crate) fn validate_vehicles(problem: &Problem) -> Result<()> {
+    let zero_caps: Vec<String> =
+        problem.vehicles.iter().filter(|v| v.max_orders == Some(0)).map(|v| v.id.to_string()).collect();
+    let vehicle_checks: [VehicleLimitCheck; 3] = [
+        ("capacity", |v| v.capacity),
+        ("max_distance_km", |v| v.max_distance_km),
+        ("max_duration_min", |v| v.max_duration_min),
+    ];
+    let mut problems: Vec<String> = vehicle_checks
+        .iter()
+        .filter_map(|&(label, value)| {
+            let ids: Vec<String> = problem
+                .vehicles
+                .iter()
+                .filter(|v| value(v).is_some_and(|x| !x.is_finite() || x <= 0.0))
+                .map(|v| v.id.to_string())
+                .collect();
+            (!ids.is_empty()).then(|| format!("non-positive or non-finite {} on vehicles {}", label, ids.join(", ")))
+        })
+        .collect();
+    if !zero_caps.is_empty() {
+        problems.push(format!("zero max_orders on vehicles {}", zero_caps.join(", ")));
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::InvalidArg(format!("Invalid vehicles: {}", problems.join("; "))))
+    }
+}
+
+/// Rejects option combinations no assignment can satisfy.
+pub fn validate_options(problem: &Problem, options: &SolveOptions) -> Result<()> {
+    validate_search_options(options)?;
+    validate_distance_options(options.distance.as_ref())?;
+    if plans_greedily(problem, options) {
+        validate_ids(problem)?;
+        validate_load_factors(problem)?;
+        validate_vehicles(problem)?;
+    } else {
+        // Fixed routes are measured against the route limits in solver km
+        let base = units::problem_to_base(problem.clone(), units::unit_scale(options));
+        validate_problem(&base, &DistanceModel::from_options(options.distance.as_ref()))?;
+    }
+    if let Some(criteria) = &options.criteria {
+        let objective = Objective::lexicographic(criteria)?;
+        if let Some(vehicle) = problem.vehicles.iter().find(|v| v.co2_per_km.is_none()).filter(|_| objective.weighs_co2()) {
+            return Err(SolverError::InvalidArg(format!("Invalid criteria: 'co2' needs co2_per_km on every vehicle, vehicle {} has none", vehicle.id)));
+        }
+    }
+    if options.require_all_vehicles.unwrap_or(false) && problem.orders.len() < problem.vehicles.len() {
+        return Err(SolverError::Infeasible(format!(
+            "Every vehicle must be used, but there are {} orders for {} vehicles",
+            problem.orders.len(),
+            problem.vehicles.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Knobs that are out of range or contradict each other.
+fn validate_search_options(options: &SolveOptions) -> Result<()> {
+    let invalid = |message: String| Err(SolverError::InvalidArg(format!("Invalid options: {}", message)));
+    if let Some(weights) = &options.weights {
+        if options.criteria.is_some() {
+            return invalid("criteria and weights cannot be combined".to_string());
+        }
+        weighted::validate_weights(weights)?;
+    }
+    if let Some(max) = options.max_orders_exact.filter(|&max| max as usize > MAX_ROUTE_ORDERS) {
+        return invalid(format!("max_orders_exact must be at most {}, got {}", MAX_ROUTE_ORDERS, max));
+    }
+    if options.threads == Some(0) {
+        return invalid("threads must be at least 1".to_string());
+    }
+    if options.epsilon.is_some() && options.gap_tolerance.is_some() {
+        return invalid("gap_tolerance is the new name of epsilon, give only one of them".to_string());
+    }
+    for (name, gap) in [("epsilon", options.epsilon), ("gap_tolerance", options.gap_tolerance)] {
+        let Some(gap) = gap else { continue };
+        if !gap.is_finite() || gap < 0.0 {
+            return invalid(format!("{} must be non-negative and finite, got {}", name, gap));
+        }
+        if options.criteria.is_some() || options.weights.is_some() || options.assignment_dp == Some(true) {
+            return invalid(format!("{} only applies to the recursive search, not with criteria, weights or assignment_dp", name));
+        }
+    }
+    if let Some(max_nodes) = options.max_nodes {
+        if max_nodes < 1 {
+            return invalid(format!("max_nodes must be at least 1, got {}", max_nodes));
+        }
+        if options.assignment_dp == Some(true) {
+            return invalid("max_nodes only applies to the recursive search, not with assignment_dp".to_string());
+        }
+    }
+    if let Some(top_k) = options.top_k {
+        if top_k == 0 {
+            return invalid("top_k must be at least 1".to_string());
+        }
+        if options.criteria.is_some() || options.weights.is_some() || options.assignment_dp == Some(true) {
+            return invalid("top_k only applies to the recursive search, not with criteria, weights or assignment_dp".to_string());
+        }
+        if options.compute.as_ref().is_some_and(|compute| !compute.contains(&Criterion::Distance)) {
+            return invalid("top_k lists distance plans, compute has to include distance".to_string());
+        }
+    }
+    if let Some(compute) = &options.compute {
+        if compute.is_empty() {
+            return invalid("compute needs at least one criterion".to_string());
+        }
+        if options.criteria.is_some() || options.weights.is_some() {
+            return invalid("compute cannot be combined with criteria or weights, they search a single plan".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Whether `options.max_orders_exact` hands `problem` to the greedy heuristic instead of the exact search.
+pub(crate) fn plans_greedily(problem: &Problem, options: &SolveOptions) -> bool {
+    options.max_orders_exact.is_some_and(|max| problem.orders.len() > max as usize)
+}
+
+/// The greedy plan of a problem too large for `options.max_orders_exact`, measured by its distance options.
+pub(crate) fn greedy_plan(problem: Problem, options: SolveOptions) -> AlgorithmSolution {
+    let (problem, options) = units::to_base(problem, options);
+    let model = DistanceModel::from_options(options.distance.as_ref());
+    let solution = greedy::greedy_solution(&problem, &model);
+    finish(units::algorithm_from_base(solution, &options), &options)
+}
+
+/// Applies the options shaping the result rather than the search.
+pub(crate) fn finish(mut solution: AlgorithmSolution, options: &SolveOptions) -> AlgorithmSolution {
+    if !options.return_legs.unwrap_or(true) {
+        for plan in [
+            &mut solution.best_distance_solution,
+            &mut solution.best_price_solution,
+            &mut solution.best_empty_solution,
+            &mut solution.best_makespan_solution,
+            &mut solution.best_duration_solution,
+            &mut solution.best_fleet_solution,
+        ]
+        .into_iter()
+        .chain(&mut solution.alternatives)
+        {
+            for route in plan.routes.values_mut().chain(plan.route_list.iter_mut().map(|entry| &mut entry.route)) {
+                route.legs = Vec::new();
+            }
+        }
+    }
+    solution
+}
+
+/// A pool of `options.threads` search workers, None to search on rayon's global pool.
+pub(crate) fn thread_pool(options: &SolveOptions) -> Result<Option<rayon::ThreadPool>> {
+    let Some(threads) = options.threads else {
+        return Ok(None);
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads as usize)
+        .build()
+        .map(Some)
+        .map_err(|err| SolverError::Internal(format!("Could not start {} search threads: {}", threads, err)))
+}
+
+/// Runs `search` on the workers `thread_pool` picks for `options`.
+pub(crate) fn with_threads<T: Send>(options: &SolveOptions, search: impl FnOnce() -> T + Send) -> Result<T> {
+    Ok(match thread_pool(options)? {
+        Some(pool) => pool.install(search),
+        None => search(),
+    })
+}
+
+/// A radius has to be a real length for distances to mean anything.
+pub(crate) fn validate_distance_options(distance: Option<&DistanceOptions>) -> Result<()> {
+    let Some(distance) = distance else {
+        return Ok(());
+    };
+    let invalid = |message: String| Err(SolverError::InvalidArg(format!("Invalid distance options: {}", message)));
+    if let Some(radius) = distance.radius_km.filter(|r| !r.is_finite() || *r <= 0.0) {
+        return invalid(format!("radius_km must be positive and finite, got {}", radius));
+    }
+    if let Some(factor) = distance.circuity.filter(|f| !f.is_finite() || *f <= 0.0) {
+        return invalid(format!("circuity must be positive and finite, got {}", factor));
+    }
+    let mut regions: Vec<_> = distance.circuity_by_prefix.iter().flatten().collect();
+    regions.sort_by_key(|(prefix, _)| prefix.as_str());
+    match regions.into_iter().find(|(_, f)| !f.is_finite() || **f <= 0.0) {
+        Some((prefix, factor)) => invalid(format!("circuity for prefix '{}' must be positive and finite, got {}", prefix, factor)),
+        None => Ok(()),
+    }
+}
+
+/// The model of `distance` for the heuristics and plan checks, which measure in solver km: callers
+/// convert with `units::distance_scale` of the same options.
+pub(crate) fn distance_model(distance: Option<&DistanceOptions>) -> Result<DistanceModel> {
+    validate_distance_options(distance)?;
+    Ok(DistanceModel::from_options(distance))
+}
+
+/// Rejects coordinates no distance can be measured between, naming their location hashes. On the
+/// sphere latitudes must lie within ±90 and longitudes are wrapped into (-180, 180], plane
+/// coordinates only have to be finite.
+pub fn normalize_locations<'a>(locations: impl IntoIterator<Item = &'a mut Location>, distance: Option<&DistanceOptions>) -> Result<()> {
+    let mode = distance.and_then(|d| d.mode).unwrap_or(DistanceMode::GreatCircle);
+    let sphere = mode == DistanceMode::GreatCircle;
+    let mut invalid: Vec<String> = Vec::new();
+    for location in locations {
+        let finite = location.latitude.is_finite() && location.longitude.is_finite();
+        if !finite || (sphere && location.latitude.abs() > 90.0) {
+            if !invalid.contains(&location.hash) {
+                invalid.push(location.hash.clone());
+            }
+        } else if sphere {
+            location.longitude = wrap_longitude(location.longitude);
+        }
+    }
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::InvalidCoordinate(format!("Invalid coordinates at locations {}", invalid.join(", "))))
+    }
+}
+
+/// The same meridian in (-180, 180], in range longitudes are kept as they are.
+pub(crate) fn wrap_longitude(longitude: f64) -> f64 {
+    if longitude > -180.0 && longitude <= 180.0 {
+        return longitude;
+    }
+    let wrapped = (longitude + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 { 180.0 } else { wrapped }
+}
+
+/// Same as `solve`, tuned by `options`.
+pub fn solve_with_options(problem: Problem, options: SolveOptions) -> Result<AlgorithmSolution> {
+    solve_cancellable(problem, options, None, None)
+}
+
+/// Same as `solve_with_options`, abandoned with a `Cancelled` error once `cancel` is raised.
+/// Raising it after the search has finished has no effect. `on_improvement` hears of every
+/// incumbent the search improves.
+pub fn solve_cancellable(
+    problem: Problem,
+    options: SolveOptions,
+    cancel: Option<Arc<AtomicBool>>,
+    on_improvement: Option<ImprovementFn>,
+) -> Result<AlgorithmSolution> {
+    validate_options(&problem, &options)?;
+    if plans_greedily(&problem, &options) {
+        return Ok(greedy_plan(problem, options));
+    }
+
+    let (problem, options) = units::to_base(problem, options);
+    // The matrix is built inside the pool too, so `threads` bounds every thread the solve uses
+    let mut ctx = with_threads(&options, || {
+        let shared = SharedContext {
+            cancel,
+            on_improvement,
+            ..SharedContext::from_problem(problem, None, DistanceModel::from_options(options.distance.as_ref())).with_options(options.clone())
+        };
+        parallel::search(Arc::new(shared), None)
+    })?;
+    if ctx.cancelled {
+        return Err(SolverError::Cancelled);
+    }
+    Ok(finish(units::algorithm_from_base(collect(&mut ctx), &options), &options))
+}
+
+/// Same as `solve`, starting from `previous` repaired for `problem` as incumbent. Only pruning
+/// gets faster, the result is as optimal as a cold solve.
+pub fn solve_warm(problem: Problem, previous: &ProblemSolution, distance: Option<&DistanceOptions>) -> Result<AlgorithmSolution> {
+    validate_distance_options(distance)?;
+    let model = DistanceModel::from_options(distance);
+    let scale = units::distance_scale(distance);
+    let problem = units::problem_to_base(problem, scale);
+    validate_problem(&problem, &model)?;
+    let shared = Arc::new(SharedContext::from_problem(problem, None, model));
+    let warm_start = seed::warm_assignments(&mut SolverContext::from_shared(shared.clone()), previous);
+    let shared = SharedContext { warm_start: Some(warm_start), ..Arc::into_inner(shared).expect("the repair context is dropped") };
+    Ok(solve_shared(Arc::new(shared)).rescaled(scale))
+}
+
+pub fn solve_shared(shared: Arc<SharedContext>) -> AlgorithmSolution {
+    collect(&mut parallel::search(shared, None))
+}
+
+/// Runs the full search on a prepared context, e.g. one whose memo was seeded from an earlier solve.
+/// Single-threaded, so everything it routes ends up in that context's memo.
+pub fn run(ctx: &mut SolverContext) -> AlgorithmSolution {
+    let mut assignments = vec![0; ctx.shared.vehicles.len()];
+
+    if partition::applies(&ctx.shared.options) {
+        partition::solve(ctx);
+        return collect(ctx);
+    }
+
+    ctx.deadline = deadline(&ctx.shared);
+    for seed in seed::starting_points(&ctx.shared) {
+        seed::seed_incumbents(ctx, &seed);
+    }
+    solve_recursive(ctx, 0, 0, PartialCost::default(), RouteSpan::NONE, &mut assignments);
+
+    collect(ctx)
+}
+
+pub(crate) fn deadline(shared: &SharedContext) -> Option<Instant> {
+    shared
+        .options
+        .time_limit_ms
+        .map(|limit| Instant::now() + Duration::from_millis(limit as u64))
+}
+
+/// Builds the solutions of a finished search from its incumbents.
+pub fn collect(ctx: &mut SolverContext) -> AlgorithmSolution {
+    // Routes are rebuilt from finished searches, a late cancel must not drop any of them
+    ctx.watch_cancel = false;
+    // Rebuilding the routes is not part of the search
+    let elapsed_ms = ctx.started.elapsed().as_secs_f64() * 1000.0;
+    let stats = ctx.stats;
+
+    let incumbents = [
+        (ctx.best_dist, ctx.best_dist_assignments.clone(), Criterion::Distance),
+        (ctx.best_price, ctx.best_price_assignments.clone(), Criterion::Price),
+        (ctx.best_empty, ctx.best_empty_assignments.clone(), Criterion::Empty),
+        (ctx.best_makespan, ctx.best_makespan_assignments.clone(), Criterion::Makespan),
+        (ctx.best_duration, ctx.best_duration_assignments.clone(), Criterion::Duration),
+        (ctx.best_fleet, ctx.best_fleet_assignments.clone(), Criterion::Fleet),
+    ];
+    let feasible = incumbents.iter().any(|(best, _, _)| *best < f64::INFINITY);
+    let [dist_sol, price_sol, empty_sol, makespan_sol, duration_sol, fleet_sol] = incumbents.map(|(best, assignments, criterion)| {
+        if !ctx.shared.criteria.contains(criterion) {
+            ProblemSolution::default()
+        } else if best < f64::INFINITY {
+            reconstruct_solution(ctx, &assignments, criterion)
+        } else if ctx.shared.options.max_route_spread_km.is_some() && !ctx.stopped {
+            // Nothing meets the spread cap, report the closest assignment instead of nothing.
+            // Skipped after a timeout, the fallback is another exhaustive search
+            nearest_feasible(ctx, criterion)
+                .map(|assignments| reconstruct_solution(ctx, &assignments, criterion))
+                .unwrap_or_default()
+        } else {
+            // No assignment covers every order, report the one covering the most
+            let assignments = ctx.partial.for_criterion(criterion).to_vec();
+            reconstruct_solution(ctx, &assignments, criterion)
+        }
+    });
+
+    let unassignable_orders = if feasible {
+        Vec::new()
+    } else {
+        let reported = [
+            (Criterion::Distance, &dist_sol),
+            (Criterion::Price, &price_sol),
+            (Criterion::Empty, &empty_sol),
+            (Criterion::Makespan, &makespan_sol),
+            (Criterion::Duration, &duration_sol),
+            (Criterion::Fleet, &fleet_sol),
+        ];
+        let left_out = reported.iter().find(|(criterion, _)| ctx.shared.criteria.contains(*criterion)).map(|(_, plan)| plan);
+        unassignable_orders(ctx, left_out.map_or(&[][..], |plan| &plan.unassigned_order_ids))
+    };
+    let listed = ctx.alternatives.assignments();
+    let alternatives = listed.iter().map(|assignments| reconstruct_solution(ctx, assignments, Criterion::Distance)).collect();
 
     AlgorithmSolution {
         best_distance_solution: dist_sol,
         best_price_solution: price_sol,
         best_empty_solution: empty_sol,
+        best_makespan_solution: makespan_sol,
+        best_duration_solution: duration_sol,
+        best_fleet_solution: fleet_sol,
+        stats: SolveStats {
+            filtered_candidate_pairs: ctx.shared.filtered_candidate_pairs,
+            assignment_nodes: stats.assignment_nodes as i64,
+            route_nodes: stats.route_nodes as i64,
+            route_bound_prunes: stats.route_bound_prunes as i64,
+            assignment_bound_prunes: stats.assignment_bound_prunes as i64,
+            assignment_revisit_prunes: stats.assignment_revisits_pruned as i64,
+            submasks: stats.submasks as i64,
+            memo_hits: stats.memo_hits as i64,
+            memo_misses: stats.memo_misses as i64,
+            memo_entries: stats.memo_entries as i64,
+            elapsed_ms,
+            achieved_gap: achieved_gap(ctx),
+        },
+        optimal: !ctx.stopped && gap_tolerance(&ctx.shared.options) == 0.0,
+        feasible,
+        unassignable_orders,
+        alternatives,
+    }
+}
+
+/// Orders no vehicle can route even on its own, together with the orders it must share a
+/// vehicle with. The reason comes from the vehicle that got furthest with the order. When every
+/// order routes on its own, the orders `left_out` of the reported plan are listed instead.
+fn unassignable_orders(ctx: &mut SolverContext, left_out: &[u32]) -> Vec<UnassignableOrder> {
+    let mut unassignable = Vec::new();
+    for o_idx in 0..ctx.shared.n_orders {
+        let bit: OrderMask = 1 << o_idx;
+        let mask = ctx.shared.same_vehicle_groups.iter().copied().find(|&group| group & bit != 0).unwrap_or(bit);
+        let routable = (0..ctx.shared.vehicles.len())
+            .any(|v_idx| mask & !ctx.shared.vehicle_allowed[v_idx] == 0 && solve_tsp(ctx, v_idx, mask).valid);
+        if routable {
+            continue;
+        }
+
+        let stops = [Stop { order_idx: o_idx, pickup: true }, Stop { order_idx: o_idx, pickup: false }];
+        let closest = ctx
+            .shared
+            .vehicles
+            .iter()
+            .map(|v| match evaluate_stops(&ctx.shared.distance_model, v, &ctx.shared.orders, &stops) {
+                Err((_, ViolationKind::MissingTags)) => 0,
+                Err((_, ViolationKind::CapacityExceeded)) => 1,
+                Err((_, ViolationKind::TimeWindowMissed)) => 2,
+                Err((_, ViolationKind::RouteLimitExceeded)) => 3,
+                _ => 4,
+            })
+            .max();
+        let reason = match closest {
+            None => "No vehicles are available",
+            Some(0) => "No vehicle has every tag the order requires",
+            Some(1) => "Order load exceeds every vehicle's capacity",
+            Some(2) => "No vehicle can reach the order within its time windows",
+            Some(3) => "Serving the order exceeds every vehicle's max_distance_km or max_duration_min",
+            _ => "No vehicle may serve the order under the problem's other constraints",
+        };
+        unassignable.push(UnassignableOrder { order_id: ctx.shared.orders[o_idx].id, reason: reason.to_string() });
+    }
+    if unassignable.is_empty() {
+        // Each order fits some route on its own, so they fail together, e.g. across forbidden legs
+        let reason = "No vehicle can serve the order together with the other orders";
+        unassignable = left_out.iter().map(|&order_id| UnassignableOrder { order_id, reason: reason.to_string() }).collect();
     }
+    unassignable
 }
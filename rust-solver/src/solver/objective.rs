@@ -0,0 +1,264 @@
+use crate::error::{Result, SolverError};
+use super::context::SolverContext;
+use super::seed::greedy_assignments;
+use super::tsp::solve_tsp;
+use super::types::{InternalBestResults, OrderMask, PartialCost, TIE_EPSILON};
+use super::{meets_min_route_price, out_of_nodes, past_deadline, DEADLINE_CHECK_INTERVAL};
+
+// Metrics an objective weighs: distance, price, empty distance and CO2
+const METRICS: usize = 4;
+
+/// Cost of a route or plan under each weighting of an `Objective`, unused entries stay 0.
+pub type ObjectiveCost = [f64; METRICS];
+
+/// Ordered weightings of (distance, price, empty distance, CO2). Costs are compared lexicographically,
+/// so each weighting only breaks ties of the ones before it. A single weighting is a plain weighted sum.
+#[derive(Clone, Copy, Debug)]
+pub struct Objective {
+    weightings: [[f64; METRICS]; METRICS],
+    len: usize,
+}
+
+impl Objective {
+    pub fn weighted(distance: f64, price: f64, empty: f64) -> Self {
+        let mut weightings = [[0.0; METRICS]; METRICS];
+        weightings[0] = [distance, price, empty, 0.0];
+        Objective { weightings, len: 1 }
+    }
+
+    /// Minimizes the named metrics in order, each one of "distance", "price", "empty" or "co2" at most once.
+    pub fn lexicographic(criteria: &[String]) -> Result<Self> {
+        let invalid = |msg: String| SolverError::InvalidArg(format!("Invalid criteria: {}", msg));
+        if criteria.is_empty() {
+            return Err(invalid("at least one criterion is required".to_string()));
+        }
+        let mut objective = Objective { weightings: [[0.0; METRICS]; METRICS], len: 0 };
+        for name in criteria {
+            let metric = match name.as_str() {
+                "distance" => 0,
+                "price" => 1,
+                "empty" => 2,
+                "co2" => 3,
+                _ => return Err(invalid(format!("unknown criterion '{}', expected distance, price, empty or co2", name))),
+            };
+            if objective.weightings[..objective.len].iter().any(|w| w[metric] != 0.0) {
+                return Err(invalid(format!("'{}' is listed twice", name)));
+            }
+            objective.weightings[objective.len][metric] = 1.0;
+            objective.len += 1;
+        }
+        Ok(objective)
+    }
+
+    /// Cost of the given totals, `penalty` is added under every weighting like for the criteria.
+    #[inline(always)]
+    pub fn cost(&self, dist: f64, price: f64, empty: f64, co2: f64, penalty: f64) -> ObjectiveCost {
+        let mut cost = [0.0; METRICS];
+        for (c, [w_dist, w_price, w_empty, w_co2]) in cost.iter_mut().zip(&self.weightings[..self.len]) {
+            *c = w_dist * dist + w_price * price + w_empty * empty + w_co2 * co2 + penalty;
+        }
+        cost
+    }
+
+    /// Whether CO2 is weighed at all, it needs emission rates on every vehicle to mean anything.
+    pub fn weighs_co2(&self) -> bool {
+        self.weightings[..self.len].iter().any(|w| w[3] != 0.0)
+    }
+
+    /// Whether `a` is strictly better than `b`, ties within `TIE_EPSILON` going to the next weighting.
+    #[inline(always)]
+    pub fn better(&self, a: &ObjectiveCost, b: &ObjectiveCost) -> bool {
+        for i in 0..self.len {
+            if a[i] < b[i] - TIE_EPSILON {
+                return true;
+            }
+            if a[i] > b[i] + TIE_EPSILON {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Cost of the single route the DFS kept for an order set, reported as `min_dist`.
+    fn route_cost(&self, res: &InternalBestResults) -> ObjectiveCost {
+        let route = &res.min_dist;
+        self.cost(route.total_dist, route.total_price, route.total_empty, route.total_co2, route.penalty)
+    }
+}
+
+pub const UNREACHED: ObjectiveCost = [f64::INFINITY; METRICS];
+
+fn add(a: ObjectiveCost, b: ObjectiveCost) -> ObjectiveCost {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+}
+
+/// Single incumbent of the objective search, plus the best assignment covering the most orders.
+struct ObjectiveSearch {
+    objective: Objective,
+    best: ObjectiveCost,
+    best_assignments: Vec<OrderMask>,
+    partial_covered: u32,
+    partial_cost: ObjectiveCost,
+    partial_assignments: Vec<OrderMask>,
+}
+
+fn recurse(
+    ctx: &mut SolverContext,
+    search: &mut ObjectiveSearch,
+    vehicle_idx: usize,
+    assignment_mask: OrderMask,
+    cost: ObjectiveCost,
+    assignments: &mut Vec<OrderMask>,
+) {
+    if ctx.stopped {
+        return;
+    }
+    if !search.objective.better(&cost, &search.best) {
+        ctx.stats.assignment_bound_prunes += 1;
+        return;
+    }
+
+    let require_all = ctx.shared.options.require_all_vehicles.unwrap_or(false);
+    if assignment_mask == ctx.shared.full_mask {
+        if require_all && vehicle_idx < ctx.shared.vehicles.len() {
+            return;
+        }
+        ctx.stats.complete_assignments += 1;
+        search.best = cost;
+        search.best_assignments.copy_from_slice(assignments);
+        return;
+    }
+
+    if vehicle_idx >= ctx.shared.vehicles.len() {
+        let covered = assignment_mask.count_ones();
+        if covered > search.partial_covered
+            || (covered == search.partial_covered && search.objective.better(&cost, &search.partial_cost))
+        {
+            search.partial_covered = covered;
+            search.partial_cost = cost;
+            search.partial_assignments.copy_from_slice(assignments);
+        }
+        return;
+    }
+
+    let remaining_orders = (ctx.shared.full_mask ^ assignment_mask).count_ones() as usize;
+    if require_all && remaining_orders < ctx.shared.vehicles.len() - vehicle_idx {
+        return;
+    }
+
+    ctx.stats.assignment_nodes += 1;
+    if out_of_nodes(ctx) {
+        ctx.stopped = true;
+        return;
+    }
+    if ctx.stats.assignment_nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+        if ctx.shared.cancel_requested() {
+            ctx.cancelled = true;
+            ctx.stopped = true;
+            return;
+        }
+        if past_deadline(ctx) {
+            ctx.stopped = true;
+            return;
+        }
+    }
+
+    // Same submask order as `solve_recursive`, so equal costs resolve to the same assignment
+    let remaining_mask = (ctx.shared.full_mask ^ assignment_mask) & ctx.shared.vehicle_allowed[vehicle_idx];
+    let mut submask = remaining_mask;
+    while submask != 0 {
+        ctx.stats.submasks += 1;
+        let res = solve_tsp(ctx, vehicle_idx, submask);
+        if res.valid && meets_min_route_price(ctx, vehicle_idx, &res) {
+            assignments[vehicle_idx] = submask;
+            let route_cost = search.objective.route_cost(&res);
+            recurse(ctx, search, vehicle_idx + 1, assignment_mask | submask, add(cost, route_cost), assignments);
+            assignments[vehicle_idx] = 0;
+        }
+        submask = (submask - 1) & remaining_mask;
+    }
+
+    if !require_all {
+        recurse(ctx, search, vehicle_idx + 1, assignment_mask, cost, assignments);
+    }
+}
+
+/// Cost of the greedy plan when every set in it routes feasibly, a first incumbent.
+fn seed_cost(ctx: &mut SolverContext, objective: &Objective, assignments: &[OrderMask]) -> Option<ObjectiveCost> {
+    let require_all = ctx.shared.options.require_all_vehicles.unwrap_or(false);
+    let covered = assignments.iter().fold(0, |all, &mask| all | mask);
+    if covered != ctx.shared.full_mask || (require_all && assignments.contains(&0)) {
+        return None;
+    }
+    let mut cost = [0.0; METRICS];
+    for (v_idx, &mask) in assignments.iter().enumerate().filter(|&(_, &mask)| mask != 0) {
+        if mask & !ctx.shared.vehicle_allowed[v_idx] != 0 {
+            return None;
+        }
+        let res = solve_tsp(ctx, v_idx, mask);
+        if !res.valid || !meets_min_route_price(ctx, v_idx, &res) {
+            return None;
+        }
+        cost = add(cost, objective.route_cost(&res));
+    }
+    Some(cost)
+}
+
+/// Exact search for the assignment minimizing `ctx.shared.objective`, keeping a single incumbent
+/// at both levels. Returns it with its cost; without any assignment covering every order, the
+/// best one covering the most orders is returned instead, without a cost.
+pub fn search(ctx: &mut SolverContext) -> (Vec<OrderMask>, Option<ObjectiveCost>) {
+    let objective = ctx.shared.objective.expect("objective search needs an objective");
+    let n_vehicles = ctx.shared.vehicles.len();
+    let mut search = ObjectiveSearch {
+        objective,
+        best: UNREACHED,
+        best_assignments: vec![0; n_vehicles],
+        partial_covered: 0,
+        partial_cost: UNREACHED,
+        partial_assignments: vec![0; n_vehicles],
+    };
+
+    ctx.deadline = super::deadline(&ctx.shared);
+    if ctx.shared.options.heuristic_seed.unwrap_or(true) {
+        let seed = greedy_assignments(&ctx.shared);
+        if let Some(cost) = seed_cost(ctx, &objective, &seed) {
+            ctx.stats.complete_assignments += 1;
+            search.best = cost;
+            search.best_assignments.copy_from_slice(&seed);
+        }
+    }
+
+    let mut assignments = vec![0; n_vehicles];
+    recurse(ctx, &mut search, 0, 0, [0.0; METRICS], &mut assignments);
+
+    if search.best[0] < f64::INFINITY {
+        (search.best_assignments, Some(search.best))
+    } else {
+        (search.partial_assignments, None)
+    }
+}
+
+/// Runs the objective search and takes its plan as the incumbent of every criterion, so `collect`
+/// reports the one plan under each of them.
+pub fn solve(ctx: &mut SolverContext) {
+    let (assignments, cost) = search(ctx);
+    let Some(cost) = cost else {
+        let covered = assignments.iter().fold(0, |all: OrderMask, &mask| all | mask);
+        ctx.partial.offer(covered.count_ones(), &PartialCost::default(), &assignments);
+        return;
+    };
+    // Only marks the incumbents as found, `collect` rebuilds every figure from the routes
+    let found = cost[0];
+    for (best, best_assignments) in [
+        (&mut ctx.best_dist, &mut ctx.best_dist_assignments),
+        (&mut ctx.best_price, &mut ctx.best_price_assignments),
+        (&mut ctx.best_empty, &mut ctx.best_empty_assignments),
+        (&mut ctx.best_makespan, &mut ctx.best_makespan_assignments),
+        (&mut ctx.best_duration, &mut ctx.best_duration_assignments),
+        (&mut ctx.best_fleet, &mut ctx.best_fleet_assignments),
+    ] {
+        *best = found;
+        best_assignments.copy_from_slice(&assignments);
+    }
+}
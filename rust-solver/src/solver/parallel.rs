@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::models::Criterion;
+use crate::progress::SharedProgress;
+use super::context::{SharedContext, SolverContext};
+use super::objective;
+use super::partition;
+use super::seed::{seed_incumbents, starting_points};
+use super::spread::RouteSpan;
+use super::types::{wins_tie_break, OrderMask, PartialCost};
+use super::{assign_and_recurse, deadline, next_submask, solve_recursive};
+
+const CRITERIA: [Criterion; 6] = [
+    Criterion::Distance,
+    Criterion::Price,
+    Criterion::Empty,
+    Criterion::Makespan,
+    Criterion::Duration,
+    Criterion::Fleet,
+];
+
+/// Best cost per criterion over every worker, as f64 bits, so each worker prunes against
+/// what the others have already found.
+pub struct SharedBounds {
+    values: [AtomicU64; CRITERIA.len()],
+}
+
+impl SharedBounds {
+    fn new() -> Self {
+        SharedBounds {
+            values: std::array::from_fn(|_| AtomicU64::new(f64::INFINITY.to_bits())),
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self) -> PartialCost {
+        PartialCost::from_fn(|criterion| f64::from_bits(self.values[slot(criterion)].load(Ordering::Relaxed)))
+    }
+
+    /// Lowers every bound `cost` improves on.
+    pub fn offer(&self, cost: &PartialCost) {
+        for criterion in CRITERIA {
+            let value = cost.get(criterion);
+            let _ = self.values[slot(criterion)].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                (value < f64::from_bits(bits)).then_some(value.to_bits())
+            });
+        }
+    }
+}
+
+#[inline(always)]
+fn slot(criterion: Criterion) -> usize {
+    CRITERIA.iter().position(|&c| c == criterion).unwrap()
+}
+
+/// Runs the assignment search with the first vehicle's branches spread over the rayon pool.
+///
+/// Every order set vehicle 0 may take, and leaving it unused, is an independent subtree. Each
+/// worker explores its share with its own memo and incumbents, pruning against the shared bounds,
+/// and the workers are merged into one context holding the overall incumbents. Route costs are
+/// summed in vehicle order whichever worker finds them, so the optimal values match a sequential search.
+pub fn search(shared: Arc<SharedContext>, progress: Option<SharedProgress>) -> SolverContext {
+    // A single objective keeps one incumbent, searched sequentially
+    if shared.objective.is_some() {
+        let mut ctx = SolverContext::from_shared(shared);
+        ctx.progress = progress;
+        objective::solve(&mut ctx);
+        return ctx;
+    }
+
+    // The partition DP has no subtrees to hand out
+    if partition::applies(&shared.options) {
+        let mut ctx = SolverContext::from_shared(shared);
+        ctx.progress = progress;
+        partition::solve(&mut ctx);
+        return ctx;
+    }
+
+    let deadline = deadline(&shared);
+    let bounds = Arc::new(SharedBounds::new());
+    let seeds = starting_points(&shared);
+    // Every worker starts from the seeds, so they survive the merge even if no worker beats them
+    let worker = || {
+        let mut ctx = SolverContext::from_shared(shared.clone());
+        ctx.deadline = deadline;
+        ctx.progress = progress.clone();
+        for seed in &seeds {
+            seed_incumbents(&mut ctx, seed);
+        }
+        ctx
+    };
+
+    // Nothing to split, or a node budget that has to be spent the same way every run: the root
+    // is the whole search
+    if shared.vehicles.is_empty() || shared.n_orders == 0 || shared.options.max_nodes.is_some() {
+        let mut ctx = worker();
+        let mut assignments = vec![0; shared.vehicles.len()];
+        solve_recursive(&mut ctx, 0, 0, PartialCost::default(), RouteSpan::NONE, &mut assignments);
+        return ctx;
+    }
+
+    // In the order `solve_recursive` visits them, which the merge relies on to break ties
+    let first_mask = shared.full_mask & shared.vehicle_allowed[0];
+    let mut branches: Vec<Option<OrderMask>> = Vec::new();
+    if !shared.options.require_all_vehicles.unwrap_or(false) {
+        branches.push(None);
+    }
+    let mut submask = next_submask(0, first_mask);
+    while submask != 0 {
+        branches.push(Some(submask));
+        submask = next_submask(submask, first_mask);
+    }
+    let root_submasks = branches.iter().flatten().count() as u64;
+
+    let mut ctx = branches
+        .into_par_iter()
+        .fold(
+            || {
+                let mut ctx = worker();
+                ctx.bounds = Some(bounds.clone());
+                ctx
+            },
+            |mut ctx, branch| {
+                let mut assignments = vec![0; ctx.shared.vehicles.len()];
+                match branch {
+                    Some(submask) => assign_and_recurse(
+                        &mut ctx,
+                        0,
+                        0,
+                        submask,
+                        PartialCost::default(),
+                        RouteSpan::NONE,
+                        &mut assignments,
+                    ),
+                    None => solve_recursive(&mut ctx, 1, 0, PartialCost::default(), RouteSpan::NONE, &mut assignments),
+                }
+                ctx
+            },
+        )
+        .reduce_with(merge)
+        .unwrap_or_else(worker);
+
+    // The root node itself
+    ctx.stats.assignment_nodes += 1;
+    ctx.stats.submasks += root_submasks;
+    ctx.bounds = None;
+    ctx
+}
+
+/// Keeps the better incumbent of each criterion, ties going by `wins_tie_break` like within a worker.
+fn merge(mut into: SolverContext, from: SolverContext) -> SolverContext {
+    let pick = |best: &mut f64, assignments: &mut Vec<OrderMask>, other: f64, other_assignments: &[OrderMask]| {
+        if wins_tie_break(other, other_assignments, *best, assignments) {
+            *best = other;
+            assignments.copy_from_slice(other_assignments);
+        }
+    };
+    pick(&mut into.best_dist, &mut into.best_dist_assignments, from.best_dist, &from.best_dist_assignments);
+    pick(&mut into.best_price, &mut into.best_price_assignments, from.best_price, &from.best_price_assignments);
+    pick(&mut into.best_empty, &mut into.best_empty_assignments, from.best_empty, &from.best_empty_assignments);
+    pick(&mut into.best_makespan, &mut into.best_makespan_assignments, from.best_makespan, &from.best_makespan_assignments);
+    pick(&mut into.best_duration, &mut into.best_duration_assignments, from.best_duration, &from.best_duration_assignments);
+    pick(&mut into.best_fleet, &mut into.best_fleet_assignments, from.best_fleet, &from.best_fleet_assignments);
+
+    into.partial.merge(&from.partial);
+    into.alternatives.merge(&from.alternatives);
+
+    into.stats.assignment_nodes += from.stats.assignment_nodes;
+    into.stats.assignment_revisits_pruned += from.stats.assignment_revisits_pruned;
+    into.stats.complete_assignments += from.stats.complete_assignments;
+    into.stats.route_nodes += from.stats.route_nodes;
+    into.stats.route_bound_prunes += from.stats.route_bound_prunes;
+    into.stats.assignment_bound_prunes += from.stats.assignment_bound_prunes;
+    into.stats.submasks += from.stats.submasks;
+    into.stats.memo_hits += from.stats.memo_hits;
+    into.stats.memo_misses += from.stats.memo_misses;
+    into.stats.memo_entries = into.stats.memo_entries.max(from.stats.memo_entries);
+    into.started = into.started.min(from.started);
+    into.gap_floor = into.gap_floor.min(&from.gap_floor);
+    into.stopped |= from.stopped;
+    into.cancelled |= from.cancelled;
+    into.route_cost_error = into.route_cost_error.or(from.route_cost_error);
+    into
+}
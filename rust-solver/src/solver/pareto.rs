@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use crate::error::{Result, SolverError};
+use crate::models::{Criterion, DistanceOptions, Problem, ProblemSolution};
+use crate::utils::DistanceModel;
+use super::context::{SharedContext, SolverContext};
+use super::tsp::solve_tsp;
+use super::types::OrderMask;
+use super::units::{self, Rescale};
+use super::{meets_min_route_price, reconstruct_with, validate_distance_options, validate_problem};
+
+pub const DEFAULT_MAX_SOLUTIONS: u32 = 20;
+
+// Costs closer than this count as equal when checking dominance
+const DOMINANCE_EPSILON: f64 = 1e-9;
+
+// Route alternatives tried for each order set, its optimal route for each of these
+const ROUTE_CRITERIA: [Criterion; 3] = [Criterion::Distance, Criterion::Price, Criterion::Empty];
+
+/// Total (distance, price, empty distance) of a plan.
+type Cost = [f64; 3];
+
+/// Whether `a` is no worse than `b` in every objective.
+#[inline(always)]
+fn weakly_dominates(a: &Cost, b: &Cost) -> bool {
+    a.iter().zip(b).all(|(a, b)| *a <= b + DOMINANCE_EPSILON)
+}
+
+/// Whether `a` is no worse than `b` in every objective and better in at least one.
+fn dominates(a: &Cost, b: &Cost) -> bool {
+    weakly_dominates(a, b) && a.iter().zip(b).any(|(a, b)| *a < b - DOMINANCE_EPSILON)
+}
+
+fn solution_cost(solution: &ProblemSolution) -> Cost {
+    [solution.total_distance, solution.total_price, solution.empty_distance]
+}
+
+/// Whether solution `a` is no worse than `b` in distance, price and empty distance, and
+/// strictly better in at least one of them.
+pub fn solution_dominates(a: &ProblemSolution, b: &ProblemSolution) -> bool {
+    dominates(&solution_cost(a), &solution_cost(b))
+}
+
+/// A complete assignment on the front, with the route criterion each vehicle was routed for.
+struct Member {
+    cost: Cost,
+    assignments: Vec<OrderMask>,
+    routes: Vec<Criterion>,
+}
+
+/// Non-dominated complete assignments found so far, at most `cap` of them.
+struct Archive {
+    members: Vec<Member>,
+    cap: usize,
+}
+
+impl Archive {
+    /// Whether some member is at least as good as `cost` everywhere. Costs only grow along a
+    /// branch, so nothing below such a partial assignment can join the front.
+    fn covers(&self, cost: &Cost) -> bool {
+        self.members.iter().any(|m| weakly_dominates(&m.cost, cost))
+    }
+
+    fn offer(&mut self, cost: Cost, assignments: &[OrderMask], routes: &[Criterion]) {
+        if self.covers(&cost) {
+            return;
+        }
+        self.members.retain(|m| !dominates(&cost, &m.cost));
+        self.members.push(Member { cost, assignments: assignments.to_vec(), routes: routes.to_vec() });
+        if self.members.len() > self.cap {
+            let crowded = most_crowded(&self.members);
+            self.members.swap_remove(crowded);
+        }
+    }
+}
+
+/// Member whose neighbours along each objective are closest, by NSGA-II crowding distance.
+/// The best member of every objective counts as infinitely far, so it is never picked. The worst
+/// has no neighbour above it and counts the gap below it twice instead.
+fn most_crowded(members: &[Member]) -> usize {
+    let mut crowding = vec![0.0; members.len()];
+    let mut order: Vec<usize> = (0..members.len()).collect();
+    for objective in 0..3 {
+        order.sort_by(|&a, &b| members[a].cost[objective].total_cmp(&members[b].cost[objective]));
+        let (first, last) = (order[0], order[order.len() - 1]);
+        let range = members[last].cost[objective] - members[first].cost[objective];
+        crowding[first] = f64::INFINITY;
+        if range <= 0.0 {
+            continue;
+        }
+        for w in order.windows(3) {
+            crowding[w[1]] += (members[w[2]].cost[objective] - members[w[0]].cost[objective]) / range;
+        }
+        let below = order[order.len() - 2];
+        crowding[last] += 2.0 * (members[last].cost[objective] - members[below].cost[objective]) / range;
+    }
+    (0..members.len()).min_by(|&a, &b| crowding[a].total_cmp(&crowding[b])).unwrap_or(0)
+}
+
+fn recurse(
+    ctx: &mut SolverContext,
+    archive: &mut Archive,
+    vehicle_idx: usize,
+    assignment_mask: OrderMask,
+    cost: Cost,
+    assignments: &mut Vec<OrderMask>,
+    routes: &mut Vec<Criterion>,
+) {
+    if archive.covers(&cost) {
+        return;
+    }
+    if assignment_mask == ctx.shared.full_mask {
+        ctx.stats.complete_assignments += 1;
+        archive.offer(cost, assignments, routes);
+        return;
+    }
+    if vehicle_idx >= ctx.shared.vehicles.len() {
+        return;
+    }
+    ctx.stats.assignment_nodes += 1;
+
+    let remaining_mask = (ctx.shared.full_mask ^ assignment_mask) & ctx.shared.vehicle_allowed[vehicle_idx];
+    let mut submask = remaining_mask;
+    while submask != 0 {
+        let res = solve_tsp(ctx, vehicle_idx, submask);
+        if res.valid && meets_min_route_price(ctx, vehicle_idx, &res) {
+            assignments[vehicle_idx] = submask;
+            for (i, &criterion) in ROUTE_CRITERIA.iter().enumerate() {
+                let route = res.for_criterion(criterion);
+                // Skip a route an earlier criterion already tried
+                let seen = ROUTE_CRITERIA[..i].iter().any(|&c| {
+                    let other = res.for_criterion(c);
+                    other.total_dist == route.total_dist
+                        && other.total_price == route.total_price
+                        && other.total_empty == route.total_empty
+                });
+                if seen {
+                    continue;
+                }
+                routes[vehicle_idx] = criterion;
+                let next = [
+                    cost[0] + route.total_dist + route.penalty,
+                    cost[1] + route.total_price + route.penalty,
+                    cost[2] + route.total_empty + route.penalty,
+                ];
+                recurse(ctx, archive, vehicle_idx + 1, assignment_mask | submask, next, assignments, routes);
+            }
+            assignments[vehicle_idx] = 0;
+        }
+        submask = (submask - 1) & remaining_mask;
+    }
+
+    recurse(ctx, archive, vehicle_idx + 1, assignment_mask, cost, assignments, routes);
+}
+
+/// Every complete assignment not dominated in (distance, price, empty distance), sorted by
+/// distance, then price. Empty when no assignment covers every order.
+///
+/// Each vehicle's order set is routed by its distance-, price- and empty-optimal route, so the
+/// front spans the trade-offs between those routes and between assignments. At most
+/// `max_solutions` points are kept: once the archive is full, each new point pushes out the
+/// member with the smallest crowding distance, i.e. the one in the densest part of the front.
+/// With room for at least 3, the best point of each objective is always kept.
+pub fn solve_pareto_front(problem: Problem, max_solutions: Option<u32>, distance: Option<&DistanceOptions>) -> Result<Vec<ProblemSolution>> {
+    validate_distance_options(distance)?;
+    let model = DistanceModel::from_options(distance);
+    let scale = units::distance_scale(distance);
+    let problem = units::problem_to_base(problem, scale);
+    validate_problem(&problem, &model)?;
+    let cap = max_solutions.unwrap_or(DEFAULT_MAX_SOLUTIONS);
+    if cap == 0 {
+        return Err(SolverError::InvalidArg("max_solutions must be at least 1".to_string()));
+    }
+
+    let mut ctx = SolverContext::from_shared(Arc::new(SharedContext::from_problem(problem, None, model)));
+    let n_vehicles = ctx.shared.vehicles.len();
+    let mut archive = Archive { members: Vec::new(), cap: cap as usize };
+    recurse(
+        &mut ctx,
+        &mut archive,
+        0,
+        0,
+        [0.0; 3],
+        &mut vec![0; n_vehicles],
+        &mut vec![Criterion::Distance; n_vehicles],
+    );
+
+    archive.members.sort_by(|a, b| {
+        a.cost[0]
+            .total_cmp(&b.cost[0])
+            .then(a.cost[1].total_cmp(&b.cost[1]))
+            .then(a.cost[2].total_cmp(&b.cost[2]))
+    });
+    Ok(archive
+        .members
+        .iter()
+        .map(|m| reconstruct_with(&mut ctx, &m.assignments, |v_idx| m.routes[v_idx]).rescaled(scale))
+        .collect())
+}
@@ -0,0 +1,125 @@
+use crate::models::{Criterion, SolveOptions};
+use super::context::SolverContext;
+use super::spread::RouteSpan;
+use super::tsp::solve_tsp;
+use super::types::{OrderMask, PartialCost};
+use super::{meets_min_route_price, record_complete};
+
+const CRITERIA: [Criterion; 6] = [
+    Criterion::Distance,
+    Criterion::Price,
+    Criterion::Empty,
+    Criterion::Makespan,
+    Criterion::Duration,
+    Criterion::Fleet,
+];
+
+/// Whether `options` ask for the partition DP and it can honour all of them. A spread cap
+/// depends on every route used so far, not just the covered orders, so it stays with the recursion.
+pub fn applies(options: &SolveOptions) -> bool {
+    options.assignment_dp.unwrap_or(false) && options.max_route_spread_km.is_none()
+}
+
+/// Optimal assignment of every criterion by dynamic programming over (vehicle prefix, covered orders).
+///
+/// `best[v][mask]` is the cheapest way for the first `v` vehicles to serve exactly `mask`, so each
+/// state is expanded once however many assignments reach it, vehicles * 3^orders transitions in all.
+/// Sums (and the makespan's maximum) only depend on the state, which keeps it exact. The optimum of
+/// each criterion is recorded as that criterion's incumbent.
+pub fn solve(ctx: &mut SolverContext) {
+    let shared = ctx.shared.clone();
+    let n_vehicles = shared.vehicles.len();
+    let n_masks = 1usize << shared.n_orders;
+    let require_all = shared.options.require_all_vehicles.unwrap_or(false);
+
+    let mut best = vec![PartialCost::UNREACHED; n_masks];
+    best[0] = PartialCost::default();
+    // Order set handed to each vehicle on the way to each state, per criterion
+    let mut choices: Vec<Vec<[OrderMask; CRITERIA.len()]>> = Vec::with_capacity(n_vehicles);
+
+    for vehicle_idx in 0..n_vehicles {
+        if shared.cancel_requested() {
+            ctx.cancelled = true;
+            ctx.stopped = true;
+            return;
+        }
+
+        let mut next = vec![PartialCost::UNREACHED; n_masks];
+        let mut choice = vec![[0; CRITERIA.len()]; n_masks];
+        for mask in 0..n_masks {
+            let current = best[mask];
+            // Every criterion reaches the same states
+            if current.dist == f64::INFINITY {
+                continue;
+            }
+            ctx.stats.assignment_nodes += 1;
+
+            if !require_all {
+                relax(&mut next[mask], &mut choice[mask], current, 0);
+            }
+
+            let remaining = (shared.full_mask ^ mask as OrderMask) & shared.vehicle_allowed[vehicle_idx];
+            let mut submask = remaining;
+            while submask != 0 {
+                ctx.stats.submasks += 1;
+                let res = solve_tsp(ctx, vehicle_idx, submask);
+                if res.valid && meets_min_route_price(ctx, vehicle_idx, &res) {
+                    let target = mask | submask as usize;
+                    relax(&mut next[target], &mut choice[target], current.with_route(&res), submask);
+                }
+                submask = (submask - 1) & remaining;
+            }
+        }
+
+        best = next;
+        choices.push(choice);
+    }
+
+    // Order sets each vehicle takes on the way to `mask`
+    let assignments_for = |mut mask: usize, slot: usize| {
+        let mut assignments = vec![0; n_vehicles];
+        for vehicle_idx in (0..n_vehicles).rev() {
+            let submask = choices[vehicle_idx][mask][slot];
+            assignments[vehicle_idx] = submask;
+            mask ^= submask as usize;
+        }
+        assignments
+    };
+    // Only one criterion's value, the assignment may be worse under the others
+    let only = |criterion: Criterion, value: f64| PartialCost::from_fn(|c| if c == criterion { value } else { f64::INFINITY });
+
+    let complete = best[shared.full_mask as usize];
+    if complete.dist == f64::INFINITY {
+        // No assignment covers everything, keep those covering the most orders instead
+        let reachable = (0..n_masks).filter(|&mask| best[mask].dist < f64::INFINITY);
+        let covered = reachable.clone().map(|mask| mask.count_ones()).max().unwrap_or(0);
+        for (slot, criterion) in CRITERIA.into_iter().enumerate() {
+            let cheapest = reachable
+                .clone()
+                .filter(|mask| mask.count_ones() == covered)
+                .min_by(|&a, &b| best[a].get(criterion).total_cmp(&best[b].get(criterion)));
+            if let Some(mask) = cheapest {
+                ctx.partial.offer(covered, &only(criterion, best[mask].get(criterion)), &assignments_for(mask, slot));
+            }
+        }
+        return;
+    }
+
+    for (slot, criterion) in CRITERIA.into_iter().enumerate() {
+        let value = complete.get(criterion);
+        if value == f64::INFINITY {
+            continue;
+        }
+        record_complete(ctx, only(criterion, value), RouteSpan::NONE, &assignments_for(shared.full_mask as usize, slot));
+    }
+}
+
+/// Lowers each criterion of `next` that `cost` improves on, remembering `submask` as the reason.
+fn relax(next: &mut PartialCost, choice: &mut [OrderMask; CRITERIA.len()], cost: PartialCost, submask: OrderMask) {
+    for (slot, criterion) in CRITERIA.into_iter().enumerate() {
+        if cost.get(criterion) < next.get(criterion) {
+            next.set(criterion, cost.get(criterion));
+            choice[slot] = submask;
+        }
+    }
+}
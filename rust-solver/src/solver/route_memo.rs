@@ -0,0 +1,92 @@
+use rustc_hash::FxHashMap;
+
+use super::types::{InternalBestResults, OrderMask};
+
+/// Largest order count whose memo is allocated up front, one slot per (row, order set).
+/// Beyond it the search only ever routes a small share of the 2^orders sets per row.
+pub const DENSE_MEMO_MAX_ORDERS: usize = 12;
+/// Most memory a table allocated up front may take, many vehicles make even 12 orders too many.
+const DENSE_MAX_BYTES: usize = 64 << 20;
+
+/// Whether a table of `n_rows << n_orders` slots of `T` is small enough to allocate up front.
+pub fn fits_dense<T>(n_rows: usize, n_orders: usize) -> bool {
+    n_orders <= DENSE_MEMO_MAX_ORDERS && (n_rows << n_orders).saturating_mul(std::mem::size_of::<T>()) <= DENSE_MAX_BYTES
+}
+
+/// Routing results per (memo row, order set), see `SolverContext::memo_rows`.
+pub enum RouteMemo {
+    Dense { n_orders: usize, slots: Vec<Option<InternalBestResults>> },
+    Sparse(FxHashMap<(u32, OrderMask), InternalBestResults>),
+}
+
+impl RouteMemo {
+    pub fn new(n_rows: usize, n_orders: usize) -> Self {
+        if fits_dense::<Option<InternalBestResults>>(n_rows, n_orders) {
+            RouteMemo::Dense { n_orders, slots: vec![None; n_rows << n_orders] }
+        } else {
+            RouteMemo::Sparse(FxHashMap::default())
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, row: usize, mask: OrderMask) -> Option<&InternalBestResults> {
+        match self {
+            RouteMemo::Dense { n_orders, slots } => slots[(row << n_orders) + mask as usize].as_ref(),
+            RouteMemo::Sparse(entries) => entries.get(&(row as u32, mask)),
+        }
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, row: usize, mask: OrderMask, result: InternalBestResults) {
+        match self {
+            RouteMemo::Dense { n_orders, slots } => slots[(row << *n_orders) + mask as usize] = Some(result),
+            RouteMemo::Sparse(entries) => {
+                entries.insert((row as u32, mask), result);
+            }
+        }
+    }
+
+    /// Every stored (row, order set, result).
+    pub fn entries(&self) -> Vec<(usize, OrderMask, InternalBestResults)> {
+        match self {
+            RouteMemo::Dense { n_orders, slots } => slots
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, res)| res.map(|res| (idx >> n_orders, (idx & ((1 << n_orders) - 1)) as OrderMask, res)))
+                .collect(),
+            RouteMemo::Sparse(entries) => entries.iter().map(|(&(row, mask), &res)| (row as usize, mask, res)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::context::SolverContext;
+    use crate::testing::random_problem;
+    use crate::utils::DistanceModel;
+
+    fn memo_of(n_vehicles: u32, n_orders: u32) -> RouteMemo {
+        SolverContext::from_problem(random_problem(0, n_vehicles, n_orders), DistanceModel::default()).memo
+    }
+
+    #[test]
+    fn allocates_every_slot_only_for_few_orders() {
+        assert!(matches!(memo_of(5, DENSE_MEMO_MAX_ORDERS as u32), RouteMemo::Dense { .. }));
+        // 5 x 2^16 slots up front would be tens of MB before a single route is searched
+        let mut memo = memo_of(5, 16);
+        assert!(matches!(&memo, RouteMemo::Sparse(entries) if entries.is_empty()));
+        memo.insert(4, 0xffff, InternalBestResults::INVALID);
+        assert!(memo.get(4, 0xffff).is_some_and(|res| !res.valid));
+        assert!(memo.get(3, 0xffff).is_none());
+        assert_eq!(memo.entries().len(), 1);
+    }
+
+    #[test]
+    fn allocates_by_slots_not_just_orders() {
+        // 100 rows x 2^12 slots would be well over 100 MB
+        assert!(matches!(memo_of(100, DENSE_MEMO_MAX_ORDERS as u32), RouteMemo::Sparse(_)));
+        assert!(fits_dense::<InternalBestResults>(8, DENSE_MEMO_MAX_ORDERS));
+        assert!(!fits_dense::<InternalBestResults>(8, DENSE_MEMO_MAX_ORDERS + 1));
+    }
+}
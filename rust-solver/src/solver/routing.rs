@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::{Result, SolverError};
+use crate::models::{
+    Criterion, DistanceOptions, Order, Problem, ProblemSolution, ReoptimizedSolution, RouteSavings, SolveOptions, Vehicle, VehicleRanking,
+    VehicleRoute,
+};
+use crate::utils::DistanceModel;
+use super::context::{SharedContext, SolverContext};
+use super::tsp::solve_tsp;
+use super::units::Rescale;
+use super::types::{OrderMask, MAX_MASK_ORDERS, MAX_ROUTE_ORDERS};
+use super::{build_route, max_route_distance, reconstruct_solution, route_spread, units, validate_distance_options};
+
+// Re-sequenced routes must beat the current one by more than this to replace it
+const IMPROVEMENT_EPSILON: f64 = 1e-9;
+
+fn criterion_value(route: &VehicleRoute, criterion: Criterion) -> f64 {
+    match criterion {
+        Criterion::Distance | Criterion::Makespan | Criterion::Fleet => route.total_distance,
+        Criterion::Price => route.total_price,
+        Criterion::Empty => route.empty_distance,
+        Criterion::Duration => route.total_duration_min,
+    }
+}
+
+/// Optimal stop sequences for a fixed assignment of orders to vehicles,
+/// keyed by vehicle id like `ProblemSolution.routes`.
+pub fn route_assignment(
+    problem: Problem,
+    assignments: HashMap<String, Vec<u32>>,
+    criterion: Criterion,
+    distance: Option<&DistanceOptions>,
+) -> Result<ProblemSolution> {
+    validate_distance_options(distance)?;
+    if problem.orders.len() > MAX_MASK_ORDERS {
+        return Err(SolverError::TooManyOrders(format!("Problem exceeds max {} orders ({} given)", MAX_MASK_ORDERS, problem.orders.len())));
+    }
+    let order_idx: HashMap<u32, usize> = problem.orders.iter().enumerate().map(|(i, o)| (o.id, i)).collect();
+    let mut masks = vec![0 as OrderMask; problem.vehicles.len()];
+    let mut assigned = 0 as OrderMask;
+
+    for (vehicle_id, order_ids) in &assignments {
+        let v_idx = problem
+            .vehicles
+            .iter()
+            .position(|v| v.id.to_string() == *vehicle_id)
+            .ok_or_else(|| SolverError::InvalidArg(format!("Unknown vehicle id {}", vehicle_id)))?;
+
+        for order_id in order_ids {
+            let o_idx = *order_idx
+                .get(order_id)
+                .ok_or_else(|| SolverError::InvalidArg(format!("Unknown order id {}", order_id)))?;
+            let bit = 1 << o_idx;
+            if assigned & bit != 0 {
+                return Err(SolverError::InvalidArg(format!("Order {} is assigned more than once", order_id)));
+            }
+            assigned |= bit;
+            masks[v_idx] |= bit;
+        }
+    }
+
+    for (v_idx, &mask) in masks.iter().enumerate() {
+        if mask.count_ones() as usize > MAX_ROUTE_ORDERS {
+            return Err(SolverError::TooManyOrders(format!("Vehicle {} is assigned more than {} orders", problem.vehicles[v_idx].id, MAX_ROUTE_ORDERS)));
+        }
+    }
+
+    let scale = units::distance_scale(distance);
+    let mut ctx = SolverContext::from_problem(units::problem_to_base(problem, scale), DistanceModel::from_options(distance));
+
+    for (v_idx, &mask) in masks.iter().enumerate() {
+        if mask != 0 && !solve_tsp(&mut ctx, v_idx, mask).valid {
+            return Err(SolverError::Infeasible(format!("Vehicle {} cannot serve its assigned orders within capacity", ctx.shared.vehicles[v_idx].id)));
+        }
+    }
+
+    Ok(reconstruct_solution(&mut ctx, &masks, criterion).rescaled(scale))
+}
+
+/// Keeps every order on its current vehicle and re-sequences each route optimally for `criterion`.
+/// Routes that are already optimal are returned unchanged.
+pub fn reoptimize_routes(
+    problem: Problem,
+    solution: ProblemSolution,
+    criterion: Criterion,
+    distance: Option<&DistanceOptions>,
+) -> Result<ReoptimizedSolution> {
+    let assignments: HashMap<String, Vec<u32>> = solution
+        .routes
+        .iter()
+        .map(|(vehicle_id, route)| {
+            let mut order_ids: Vec<u32> = route.stops.iter().map(|s| s.order_id).collect();
+            order_ids.sort_unstable();
+            order_ids.dedup();
+            (vehicle_id.clone(), order_ids)
+        })
+        .collect();
+    let optimal = route_assignment(problem, assignments, criterion, distance)?;
+
+    let mut result = ProblemSolution::default();
+    let mut savings = Vec::new();
+
+    // In key order, so the totals are summed the same way every time
+    let mut routes: Vec<_> = solution.routes.into_iter().collect();
+    routes.sort_by(|a, b| a.0.cmp(&b.0));
+    for (key, current) in routes {
+        let vehicle_id = key.parse().unwrap_or_default();
+        let best = match optimal.routes.get(&key) {
+            Some(best) if criterion_value(best, criterion) < criterion_value(&current, criterion) - IMPROVEMENT_EPSILON => best.clone(),
+            _ => current.clone(),
+        };
+
+        savings.push(RouteSavings {
+            vehicle_id,
+            distance: current.total_distance - best.total_distance,
+            empty_distance: current.empty_distance - best.empty_distance,
+            price: current.total_price - best.total_price,
+        });
+
+        result.total_distance += best.total_distance;
+        result.empty_distance += best.empty_distance;
+        result.total_price += best.total_price;
+        result.total_co2 += best.total_co2;
+        result.total_duration += best.total_duration_min;
+        result.insert_route(vehicle_id, best);
+    }
+
+    savings.sort_by_key(|s| s.vehicle_id);
+    result.route_spread = route_spread(&result);
+    result.max_route_distance = max_route_distance(&result);
+
+    Ok(ReoptimizedSolution { solution: result, savings })
+}
+
+/// Every vehicle ranked by the cost of serving exactly `order_ids`, cheapest first,
+/// with vehicles that cannot serve the set listed last.
+pub fn rank_vehicles(problem: Problem, order_ids: &[u32], criterion: Criterion, distance: Option<&DistanceOptions>) -> Result<Vec<VehicleRanking>> {
+    validate_distance_options(distance)?;
+    let mut orders = Vec::with_capacity(order_ids.len());
+    for order_id in order_ids {
+        let order = problem
+            .orders
+            .iter()
+            .find(|o| o.id == *order_id)
+            .ok_or_else(|| SolverError::InvalidArg(format!("Unknown order id {}", order_id)))?;
+        if orders.iter().any(|o: &Order| o.id == *order_id) {
+            return Err(SolverError::InvalidArg(format!("Order {} is listed more than once", order_id)));
+        }
+        orders.push(order.clone());
+    }
+    if orders.is_empty() || orders.len() > MAX_ROUTE_ORDERS {
+        return Err(SolverError::InvalidArg(format!("Order set must contain between 1 and {} orders", MAX_ROUTE_ORDERS)));
+    }
+
+    let scale = units::distance_scale(distance);
+    let problem = units::problem_to_base(Problem { orders, ..problem }, scale);
+    let mut ctx = SolverContext::from_problem(problem, DistanceModel::from_options(distance));
+    let full_mask = ctx.shared.full_mask;
+
+    let mut ranking: Vec<VehicleRanking> = (0..ctx.shared.vehicles.len())
+        .map(|v_idx| {
+            let res = solve_tsp(&mut ctx, v_idx, full_mask);
+            let vehicle_id = ctx.shared.vehicles[v_idx].id;
+            if res.valid {
+                let route = build_route(&ctx, v_idx, res.for_criterion(criterion)).rescaled(scale);
+                VehicleRanking {
+                    vehicle_id,
+                    feasible: true,
+                    cost: criterion_value(&route, criterion),
+                    reason: None,
+                    route: Some(route),
+                }
+            } else {
+                VehicleRanking {
+                    vehicle_id,
+                    feasible: false,
+                    cost: f64::INFINITY,
+                    reason: Some("Orders cannot be combined within vehicle capacity".to_string()),
+                    route: None,
+                }
+            }
+        })
+        .collect();
+
+    ranking.sort_by(|a, b| a.cost.total_cmp(&b.cost).then(a.vehicle_id.cmp(&b.vehicle_id)));
+    Ok(ranking)
+}
+
+/// Optimal stop sequence for `vehicle` over all of `orders`, without any assignment search.
+pub fn solve_single_vehicle(mut vehicle: Vehicle, orders: Vec<Order>, criterion: Criterion, options: SolveOptions) -> Result<VehicleRoute> {
+    validate_distance_options(options.distance.as_ref())?;
+    if orders.len() > MAX_ROUTE_ORDERS {
+        return Err(SolverError::TooManyOrders(format!("A single route can hold at most {} orders", MAX_ROUTE_ORDERS)));
+    }
+    if orders.is_empty() {
+        return Ok(VehicleRoute::default());
+    }
+
+    let vehicle_id = vehicle.id;
+    let scale = units::unit_scale(&options);
+    units::vehicle_to_base(&mut vehicle, scale);
+    let model = DistanceModel::from_options(options.distance.as_ref());
+    let shared = SharedContext::with_cache(orders, vec![vehicle], None, model).with_options(units::options_to_base(options));
+    let mut ctx = SolverContext::from_shared(Arc::new(shared));
+    let full_mask = ctx.shared.full_mask;
+
+    let res = solve_tsp(&mut ctx, 0, full_mask);
+    if !res.valid {
+        return Err(SolverError::Infeasible(format!("Vehicle {} cannot serve the orders within capacity, time windows and route limits", vehicle_id)));
+    }
+    let mut route = build_route(&ctx, 0, res.for_criterion(criterion));
+    units::route_from_base(&mut route, scale);
+    Ok(route)
+}
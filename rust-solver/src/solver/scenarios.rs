@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::error::{Result, SolverError};
+use crate::cache::DistanceCache;
+use crate::models::{AlgorithmSolution, Problem, Scenario, ScenarioComparison, ScenarioDelta, ScenarioResult, SolveOptions};
+use crate::utils::DistanceModel;
+use super::context::SharedContext;
+use super::{finish, greedy_plan, plans_greedily, solve_shared, thread_pool, units, validate_options};
+
+/// `base` with `scenario`'s removals, replacements and additions applied, in that order.
+fn apply_patch(base: &Problem, scenario: &Scenario) -> Result<Problem> {
+    let mut problem = base.clone();
+    let patch_error = |what: String| SolverError::InvalidArg(format!("Scenario '{}': {}", scenario.name, what));
+
+    for id in scenario.remove_vehicle_ids.iter().flatten() {
+        let before = problem.vehicles.len();
+        problem.vehicles.retain(|v| v.id != *id);
+        if problem.vehicles.len() == before {
+            return Err(patch_error(format!("cannot remove unknown vehicle id {}", id)));
+        }
+    }
+    for id in scenario.remove_order_ids.iter().flatten() {
+        let before = problem.orders.len();
+        problem.orders.retain(|o| o.id != *id);
+        if problem.orders.len() == before {
+            return Err(patch_error(format!("cannot remove unknown order id {}", id)));
+        }
+        // A removed order's pin, pairings and place on board go with it
+        if let Some(fixed) = problem.fixed_assignments.as_mut() {
+            fixed.remove(&id.to_string());
+        }
+        for onboard in problem.vehicles.iter_mut().filter_map(|v| v.onboard_order_ids.as_mut()) {
+            onboard.retain(|order_id| order_id != id);
+        }
+        for group in problem.same_vehicle_groups.iter_mut().flatten() {
+            group.retain(|order_id| order_id != id);
+        }
+        if let Some(pairs) = problem.conflict_pairs.as_mut() {
+            pairs.retain(|pair| pair.first_order_id != *id && pair.second_order_id != *id);
+        }
+    }
+
+    for vehicle in scenario.update_vehicles.iter().flatten() {
+        let slot = problem
+            .vehicles
+            .iter_mut()
+            .find(|v| v.id == vehicle.id)
+            .ok_or_else(|| patch_error(format!("cannot update unknown vehicle id {}", vehicle.id)))?;
+        *slot = vehicle.clone();
+    }
+    for order in scenario.update_orders.iter().flatten() {
+        let slot = problem
+            .orders
+            .iter_mut()
+            .find(|o| o.id == order.id)
+            .ok_or_else(|| patch_error(format!("cannot update unknown order id {}", order.id)))?;
+        *slot = order.clone();
+    }
+
+    for vehicle in scenario.add_vehicles.iter().flatten() {
+        if problem.vehicles.iter().any(|v| v.id == vehicle.id) {
+            return Err(patch_error(format!("vehicle id {} already exists", vehicle.id)));
+        }
+        problem.vehicles.push(vehicle.clone());
+    }
+    for order in scenario.add_orders.iter().flatten() {
+        if problem.orders.iter().any(|o| o.id == order.id) {
+            return Err(patch_error(format!("order id {} already exists", order.id)));
+        }
+        problem.orders.push(order.clone());
+    }
+
+    Ok(problem)
+}
+
+fn delta(scenario: &AlgorithmSolution, base: &AlgorithmSolution) -> ScenarioDelta {
+    ScenarioDelta {
+        distance: scenario.best_distance_solution.total_distance - base.best_distance_solution.total_distance,
+        price: scenario.best_price_solution.total_price - base.best_price_solution.total_price,
+        empty_distance: scenario.best_empty_solution.empty_distance - base.best_empty_solution.empty_distance,
+        makespan: scenario.best_makespan_solution.max_route_distance - base.best_makespan_solution.max_route_distance,
+        duration: scenario.best_duration_solution.total_duration - base.best_duration_solution.total_duration,
+    }
+}
+
+/// Solves `base` and every scenario derived from it, sharing one distance cache so locations
+/// common to several scenarios are only measured once. Deltas are scenario minus base, per objective.
+pub fn solve_scenarios(base: Problem, scenarios: Vec<Scenario>, options: SolveOptions) -> Result<ScenarioComparison> {
+    let problems = scenarios
+        .iter()
+        .map(|scenario| apply_patch(&base, scenario))
+        .collect::<Result<Vec<_>>>()?;
+
+    validate_options(&base, &options)?;
+    for problem in &problems {
+        validate_options(problem, &options)?;
+    }
+
+    let cache = DistanceCache::new(None).store;
+    let pool = thread_pool(&options)?;
+    let solve = |problem: Problem| {
+        if plans_greedily(&problem, &options) {
+            return greedy_plan(problem, options.clone());
+        }
+        let (problem, options) = units::to_base(problem, options.clone());
+        let model = DistanceModel::from_options(options.distance.as_ref());
+        let search = || solve_shared(Arc::new(SharedContext::from_problem(problem, Some(cache.clone()), model).with_options(options.clone())));
+        let solution = match &pool {
+            Some(pool) => pool.install(search),
+            None => search(),
+        };
+        finish(units::algorithm_from_base(solution, &options), &options)
+    };
+
+    let base_solution = solve(base);
+    let results = scenarios
+        .into_iter()
+        .zip(problems)
+        .map(|(scenario, problem)| {
+            let solution = solve(problem);
+            ScenarioResult {
+                delta: delta(&solution, &base_solution),
+                name: scenario.name,
+                solution,
+            }
+        })
+        .collect();
+
+    Ok(ScenarioComparison {
+        base: base_solution,
+        scenarios: results,
+    })
+}
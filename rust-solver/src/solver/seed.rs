@@ -0,0 +1,105 @@
+use crate::heuristics::greedy::greedy_routes;
+use crate::models::ProblemSolution;
+use super::context::{SharedContext, SolverContext};
+use super::spread::RouteSpan;
+use super::tsp::solve_tsp;
+use super::types::{OrderMask, PartialCost};
+use super::{meets_min_route_price, record_complete};
+
+/// Order set per vehicle of the greedy plan, a cheap first guess at a good assignment.
+pub fn greedy_assignments(shared: &SharedContext) -> Vec<OrderMask> {
+    greedy_routes(&shared.orders, &shared.vehicles, &shared.distance_model)
+        .iter()
+        .map(|stops| stops.iter().fold(0, |mask, stop| mask | (1 << stop.order_idx)))
+        .collect()
+}
+
+/// Assignments to start the search from: the warm start if there is one, then the greedy plan
+/// unless `heuristic_seed` is off.
+pub fn starting_points(shared: &SharedContext) -> Vec<Vec<OrderMask>> {
+    let mut points: Vec<Vec<OrderMask>> = shared.warm_start.iter().cloned().collect();
+    if shared.options.heuristic_seed.unwrap_or(true) {
+        points.push(greedy_assignments(shared));
+    }
+    points
+}
+
+/// Order set per vehicle of `previous`, repaired for the current problem. Orders that are gone,
+/// served twice or no longer allowed on their vehicle are dropped, then every order left
+/// uncovered goes to the vehicle whose exact route grows the least by taking it.
+pub fn warm_assignments(ctx: &mut SolverContext, previous: &ProblemSolution) -> Vec<OrderMask> {
+    let shared = ctx.shared.clone();
+    let mut assignments = vec![0; shared.vehicles.len()];
+    let mut covered: OrderMask = 0;
+    // Sorted by vehicle id, so an order served twice always stays with the same vehicle
+    let mut routes: Vec<_> = previous.routes.iter().collect();
+    routes.sort_by_key(|(vehicle_id, _)| vehicle_id.as_str());
+    for (vehicle_id, route) in routes {
+        let Some(v_idx) = shared.vehicles.iter().position(|v| vehicle_id.parse() == Ok(v.id)) else {
+            continue;
+        };
+        for stop in route.stops.iter().filter(|s| s.type_ != "reload") {
+            let Some(o_idx) = shared.orders.iter().position(|o| o.id == stop.order_id) else {
+                continue;
+            };
+            let bit = 1 << o_idx;
+            if covered & bit == 0 && shared.vehicle_allowed[v_idx] & bit != 0 {
+                assignments[v_idx] |= bit;
+                covered |= bit;
+            }
+        }
+    }
+
+    let mut uncovered = shared.full_mask & !covered;
+    while uncovered != 0 {
+        let bit = 1 << uncovered.trailing_zeros();
+        uncovered &= uncovered - 1;
+        let mut best: Option<(f64, usize)> = None;
+        for v_idx in (0..shared.vehicles.len()).filter(|&v_idx| shared.vehicle_allowed[v_idx] & bit != 0) {
+            let before = if assignments[v_idx] == 0 { 0.0 } else { solve_tsp(ctx, v_idx, assignments[v_idx]).min_dist.total_dist };
+            let res = solve_tsp(ctx, v_idx, assignments[v_idx] | bit);
+            if res.valid && best.is_none_or(|(delta, _)| res.min_dist.total_dist - before < delta) {
+                best = Some((res.min_dist.total_dist - before, v_idx));
+            }
+        }
+        if let Some((_, v_idx)) = best {
+            assignments[v_idx] |= bit;
+        }
+    }
+    assignments
+}
+
+/// Starts the search from `assignments` as incumbent, so pruning has a bound from the first branch.
+///
+/// The greedy plan and warm starts ignore most constraints, so each set is routed exactly and the
+/// plan is dropped if any part of it is infeasible. Costs are summed in vehicle order like the search
+/// does, an equally good assignment found later therefore never displaces it.
+pub fn seed_incumbents(ctx: &mut SolverContext, assignments: &[OrderMask]) {
+    let require_all = ctx.shared.options.require_all_vehicles.unwrap_or(false);
+    let spread_cap = ctx.shared.options.max_route_spread_km;
+    let covered = assignments.iter().fold(0, |all, &mask| all | mask);
+    if covered != ctx.shared.full_mask || (require_all && assignments.contains(&0)) {
+        return;
+    }
+
+    let mut current = PartialCost::default();
+    let mut span = RouteSpan::NONE;
+    for (v_idx, &mask) in assignments.iter().enumerate() {
+        if mask == 0 {
+            continue;
+        }
+        if mask & !ctx.shared.vehicle_allowed[v_idx] != 0 {
+            return;
+        }
+        let res = solve_tsp(ctx, v_idx, mask);
+        if !res.valid || !meets_min_route_price(ctx, v_idx, &res) {
+            return;
+        }
+        current = current.with_route(&res);
+        if spread_cap.is_some() {
+            span = span.with_route(&res);
+        }
+    }
+
+    record_complete(ctx, current, span, assignments);
+}
@@ -0,0 +1,119 @@
+use crate::models::Criterion;
+use super::context::SolverContext;
+use super::meets_min_route_price;
+use super::tsp::solve_tsp;
+use super::types::{InternalBestResults, OrderMask, PartialCost};
+
+// Floating point slack when comparing a spread against the cap
+const SPREAD_EPSILON: f64 = 1e-9;
+
+/// Shortest and longest used route, in km, measured per criterion on the route that criterion picks.
+#[derive(Clone, Copy, Debug)]
+pub struct RouteSpan {
+    shortest: PartialCost,
+    longest: PartialCost,
+}
+
+impl RouteSpan {
+    pub const NONE: RouteSpan = RouteSpan {
+        shortest: PartialCost::UNREACHED,
+        longest: PartialCost {
+            dist: 0.0,
+            price: 0.0,
+            empty: 0.0,
+            makespan: 0.0,
+            duration: 0.0,
+            fleet: 0.0,
+        },
+    };
+
+    #[inline(always)]
+    pub fn with_route(&self, res: &InternalBestResults) -> RouteSpan {
+        RouteSpan {
+            shortest: PartialCost::from_fn(|c| self.shortest.get(c).min(res.for_criterion(c).total_dist)),
+            longest: PartialCost::from_fn(|c| self.longest.get(c).max(res.for_criterion(c).total_dist)),
+        }
+    }
+
+    /// Longest minus shortest route, 0 while fewer than two routes are used.
+    #[inline(always)]
+    pub fn spread(&self) -> PartialCost {
+        PartialCost::from_fn(|c| (self.longest.get(c) - self.shortest.get(c)).max(0.0))
+    }
+}
+
+#[inline(always)]
+pub fn within_cap(spread: f64, cap: Option<f64>) -> bool {
+    cap.is_none_or(|cap| spread <= cap + SPREAD_EPSILON)
+}
+
+/// Adding routes never narrows the span, so a partial assignment over the cap for every criterion is a dead end.
+#[inline(always)]
+pub fn exceeds_cap_everywhere(span: &RouteSpan, cap: Option<f64>) -> bool {
+    let spread = span.spread();
+    cap.is_some()
+        && [spread.dist, spread.price, spread.empty, spread.makespan, spread.duration, spread.fleet]
+            .into_iter()
+            .all(|value| !within_cap(value, cap))
+}
+
+struct FallbackSearch {
+    criterion: Criterion,
+    assignments: Vec<OrderMask>,
+    // (spread, cost, assignments)
+    best: Option<(f64, f64, Vec<OrderMask>)>,
+}
+
+/// Assignment with the smallest spread for `criterion`, cheapest among equal spreads.
+///
+/// Only used when nothing satisfies `max_route_spread_km`, so this enumerates every assignment
+/// without the main search's pruning.
+pub fn nearest_feasible(ctx: &mut SolverContext, criterion: Criterion) -> Option<Vec<OrderMask>> {
+    let mut search = FallbackSearch {
+        criterion,
+        assignments: vec![0; ctx.shared.vehicles.len()],
+        best: None,
+    };
+    fallback_recursive(ctx, &mut search, 0, 0, PartialCost::default(), RouteSpan::NONE);
+    search.best.map(|(_, _, assignments)| assignments)
+}
+
+fn fallback_recursive(
+    ctx: &mut SolverContext,
+    search: &mut FallbackSearch,
+    vehicle_idx: usize,
+    assignment_mask: OrderMask,
+    current: PartialCost,
+    span: RouteSpan,
+) {
+    let spread = span.spread().get(search.criterion);
+    let cost = current.get(search.criterion);
+    if let Some((best_spread, best_cost, _)) = &search.best {
+        if spread > best_spread + SPREAD_EPSILON || (spread >= best_spread - SPREAD_EPSILON && cost >= *best_cost) {
+            return;
+        }
+    }
+
+    if assignment_mask == ctx.shared.full_mask {
+        search.best = Some((spread, cost, search.assignments.clone()));
+        return;
+    }
+
+    if vehicle_idx >= ctx.shared.vehicles.len() {
+        return;
+    }
+
+    let remaining_mask = (ctx.shared.full_mask ^ assignment_mask) & ctx.shared.vehicle_allowed[vehicle_idx];
+    let mut submask = remaining_mask;
+    while submask != 0 {
+        let res = solve_tsp(ctx, vehicle_idx, submask);
+        if res.valid && meets_min_route_price(ctx, vehicle_idx, &res) {
+            search.assignments[vehicle_idx] = submask;
+            fallback_recursive(ctx, search, vehicle_idx + 1, assignment_mask | submask, current.with_route(&res), span.with_route(&res));
+            search.assignments[vehicle_idx] = 0;
+        }
+        submask = (submask - 1) & remaining_mask;
+    }
+
+    fallback_recursive(ctx, search, vehicle_idx + 1, assignment_mask, current, span);
+}
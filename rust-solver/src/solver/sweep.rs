@@ -0,0 +1,129 @@
+use std::f64::consts::PI;
+
+use crate::error::{Result, SolverError};
+use crate::models::{Criterion, Problem, ProblemSolution};
+use crate::utils::DistanceModel;
+use super::context::SolverContext;
+use super::tsp::solve_tsp;
+use super::types::{OrderMask, MAX_MASK_ORDERS, MAX_ROUTE_ORDERS};
+use super::{meets_min_route_price, reconstruct_solution, validate_rules};
+
+/// Whether vehicle `vehicle_idx` may take exactly `mask` and route it. `priced` also asks the
+/// route to reach the vehicle's minimum price, which a partly filled vehicle need not.
+fn fits(ctx: &mut SolverContext, vehicle_idx: usize, mask: OrderMask, priced: bool) -> bool {
+    let shared = ctx.shared.clone();
+    if mask & !shared.vehicle_allowed[vehicle_idx] != 0
+        || mask.count_ones() as usize > MAX_ROUTE_ORDERS
+        || !shared.within_order_cap(vehicle_idx, mask)
+        || !shared.keeps_pairings(mask)
+    {
+        return false;
+    }
+    let res = solve_tsp(ctx, vehicle_idx, mask);
+    res.valid && (!priced || meets_min_route_price(ctx, vehicle_idx, &res))
+}
+
+/// One plan by the sweep heuristic, a cheap upper bound for seeding and benchmarks.
+///
+/// Orders are sorted by the polar angle of their pickup around the centroid of all pickups and
+/// handed to the vehicles in turn: the current vehicle takes the next order while its route stays
+/// feasible within 8 orders, then the next vehicle takes over. When every pickup shares one
+/// location the orders go by how far their delivery lies from it instead. Orders sharing a
+/// same-vehicle group move together, pinned and on board orders stay with their vehicle. Orders
+/// the sweep could not place, or whose vehicle falls short of its minimum route price, go to the
+/// first vehicle that can still take them. Each route is then optimized for distance as `model` measures it.
+pub fn solve_sweep(problem: Problem, model: DistanceModel) -> Result<ProblemSolution> {
+    if problem.orders.len() > MAX_MASK_ORDERS {
+        return Err(SolverError::TooManyOrders(format!("Problem exceeds max {} orders ({} given)", MAX_MASK_ORDERS, problem.orders.len())));
+    }
+    validate_rules(&problem, &model)?;
+    let mut ctx = SolverContext::from_problem(problem, model);
+    let shared = ctx.shared.clone();
+    let n_vehicles = shared.vehicles.len();
+
+    // Pinned orders, along with the rest of their same-vehicle groups
+    let own: Vec<OrderMask> = (0..n_vehicles)
+        .map(|v_idx| {
+            let pins = shared.fixed[v_idx] | shared.onboard[v_idx];
+            shared.same_vehicle_groups.iter().filter(|&&group| group & pins != 0).fold(pins, |all, &group| all | group)
+        })
+        .collect();
+    let mut assignments = own.clone();
+    let pinned = own.iter().fold(0, |all, &mask| all | mask);
+    let free: Vec<usize> = (0..shared.n_orders).filter(|&o_idx| pinned & (1 << o_idx) == 0).collect();
+
+    let pickups: Vec<(f64, f64)> = free
+        .iter()
+        .map(|&o_idx| {
+            let at = &shared.orders[o_idx].pickup_location;
+            (at.latitude, at.longitude)
+        })
+        .collect();
+    let count = pickups.len().max(1) as f64;
+    let centroid = pickups.iter().fold((0.0, 0.0), |(lat, lon), p| (lat + p.0 / count, lon + p.1 / count));
+    let one_place = pickups.windows(2).all(|pair| pair[0] == pair[1]);
+    let key = |o_idx: usize| {
+        let order = &shared.orders[o_idx];
+        if one_place {
+            let to = &order.delivery_location;
+            (to.latitude - centroid.0).hypot(to.longitude - centroid.1)
+        } else {
+            let at = &order.pickup_location;
+            // Pickups on the centroid itself have no angle, they go first
+            if at.latitude == centroid.0 && at.longitude == centroid.1 {
+                -PI - 1.0
+            } else {
+                (at.latitude - centroid.0).atan2(at.longitude - centroid.1)
+            }
+        }
+    };
+    let mut sweep = free;
+    sweep.sort_by(|&a, &b| key(a).total_cmp(&key(b)).then(a.cmp(&b)));
+
+    let unit_of = |o_idx: usize| shared.same_vehicle_groups.iter().copied().find(|&group| group & (1 << o_idx) != 0).unwrap_or(1 << o_idx);
+    let mut placed: OrderMask = pinned;
+    let mut left: Vec<OrderMask> = Vec::new();
+    let mut vehicle_idx = 0;
+    for o_idx in sweep {
+        let unit = unit_of(o_idx);
+        if placed & unit != 0 {
+            continue;
+        }
+        placed |= unit;
+        // The vehicle filled up when it could take the order next to its pinned orders, just not
+        // on top of its route
+        while vehicle_idx < n_vehicles
+            && !fits(&mut ctx, vehicle_idx, assignments[vehicle_idx] | unit, false)
+            && fits(&mut ctx, vehicle_idx, own[vehicle_idx] | unit, false)
+        {
+            vehicle_idx += 1;
+        }
+        if vehicle_idx < n_vehicles && fits(&mut ctx, vehicle_idx, assignments[vehicle_idx] | unit, false) {
+            assignments[vehicle_idx] |= unit;
+        } else {
+            left.push(unit);
+        }
+    }
+
+    // Routes short of their minimum price give up what was swept into them
+    for (v_idx, mask) in assignments.iter_mut().enumerate() {
+        let swept = *mask & !pinned;
+        if swept != 0 && !fits(&mut ctx, v_idx, *mask, true) {
+            *mask &= pinned;
+            left.extend(shared.same_vehicle_groups.iter().copied().filter(|&group| group & swept == group));
+            let grouped = shared.same_vehicle_groups.iter().fold(0, |all, &group| all | group);
+            let mut bits = swept & !grouped;
+            while bits != 0 {
+                left.push(1 << bits.trailing_zeros());
+                bits &= bits - 1;
+            }
+        }
+    }
+    for unit in left {
+        if let Some(v_idx) = (0..n_vehicles).find(|&v_idx| fits(&mut ctx, v_idx, assignments[v_idx] | unit, true)) {
+            assignments[v_idx] |= unit;
+        }
+    }
+
+    Ok(reconstruct_solution(&mut ctx, &assignments, Criterion::Distance))
+}
@@ -1,132 +1,649 @@
-use crate::models::Order;
-use super::context::SolverContext;
-use super::types::{InternalBestResults, InternalTspResult, PathBuffer};
+use crate::error::{Result, SolverError};
+use crate::models::{Criterion, RouteSummary};
+use super::context::{RouteCostFn, SolverContext};
+use super::held_karp;
+use super::objective::{self, Objective, ObjectiveCost};
+use super::types::{wins_tie_break, CriteriaSet, InternalBestResults, InternalTspResult, OrderMask, PathBuffer, MAX_MASK_ORDERS, MAX_ROUTE_ORDERS, RELOAD_NODE, TIE_EPSILON};
+use super::units;
 use std::f64;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-pub fn solve_tsp(
-    ctx: &mut SolverContext, 
-    vehicle_idx: usize,
-    target_mask: u32,
-) -> InternalBestResults {
-    
-    let cache_idx = vehicle_idx * (1 << ctx.n_orders) + target_mask as usize;
-    
-    // Unsafe unchecked access is fine here due to strictly controlled bounds logic in Context::new
-    let cached_opt = unsafe { ctx.memo.get_unchecked(cache_idx) };
-    if let Some(cached) = cached_opt {
-        return *cached;
+/// Everything that stays constant while routing one (vehicle, order set) pair.
+struct SearchFrame<'a> {
+    n_orders: usize,
+    num_nodes: usize,
+    // Start-to-pickup distances of the routed vehicle, indexed by order, to the delivery for orders on board
+    veh_start: &'a [f64],
+    // Delivery-to-start distances, indexed by order, only filled when `max_trips > 1`
+    veh_return: &'a [f64],
+    // Orders that cannot be reached from / return to the start, see `SharedContext::forbid_legs`
+    start_forbidden: OrderMask,
+    return_forbidden: OrderMask,
+    max_trips: u32,
+    dist_mat: &'a [f64],
+    // Service windows and durations per node, see `SharedContext::windows`
+    windows: &'a [(f64, f64)],
+    service: &'a [f64],
+    // Per order, its share of this vehicle's capacity
+    loads: &'a [f64],
+    // Per order, delivery to the vehicle's end location, None if routes end at the last delivery
+    veh_end: Option<&'a [f64]>,
+    end_forbidden: OrderMask,
+    v_price: f64,
+    // Per km driven with nothing on board, the leg to the end location and reloads included
+    v_price_empty: f64,
+    // The lower of the two, what any remaining km costs at least
+    v_price_min: f64,
+    // Added to the price at every pickup and delivery
+    stop_fee: f64,
+    // Grams of CO2 per loaded and per empty km, and the lower of the two
+    v_co2: f64,
+    v_co2_empty: f64,
+    v_co2_min: f64,
+    v_minutes_per_km: f64,
+    // The vehicle's `max_distance_km` and `max_duration_min`, infinite when unset
+    max_dist: f64,
+    max_duration: f64,
+    interleaving_penalty: f64,
+    // See `SharedContext::objective`, None when routes are kept per criterion
+    objective: Option<Objective>,
+    // Route criteria that have to come out optimal, the others never stop a branch
+    criteria: CriteriaSet,
+    target_mask: OrderMask,
+    cancel: Option<&'a AtomicBool>,
+    // Expansions left of `max_nodes`
+    max_expansions: u64,
+    nearest_first: bool,
+    lower_bound: bool,
+    // Cheapest leg into each node of the target set by node index, so any order of the problem
+    // fits. Zero everywhere when the bound is off
+    min_incoming: [f64; 2 * MAX_MASK_ORDERS],
+}
+
+impl SearchFrame<'_> {
+    /// Distance still needed to visit every remaining stop: each one has to be entered by some
+    /// leg, and none is cheaper than its cheapest incoming one. Never overestimates.
+    #[inline(always)]
+    fn remaining_bound(&self, cur: &SearchNode) -> f64 {
+        let mut bound = 0.0;
+        let mut pickups = self.target_mask & !cur.pickup_mask;
+        while pickups != 0 {
+            bound += self.min_incoming[2 * pickups.trailing_zeros() as usize];
+            pickups &= pickups - 1;
+        }
+        let mut deliveries = self.target_mask & !cur.deliver_mask;
+        while deliveries != 0 {
+            bound += self.min_incoming[2 * deliveries.trailing_zeros() as usize + 1];
+            deliveries &= deliveries - 1;
+        }
+        bound
     }
 
-    let vehicle_price = ctx.vehicles[vehicle_idx].price_km;
-    
-    // Initialization of best trackers
-    let mut best_dist = (f64::INFINITY, PathBuffer::default(), 0.0, 0.0); // (val, path, empty, price)
-    let mut best_empty = (f64::INFINITY, PathBuffer::default(), 0.0, 0.0);
-    let mut best_price = (f64::INFINITY, PathBuffer::default(), 0.0, 0.0);
-    
-    let mut path_stack = PathBuffer::default();
-
-    // Helper to keep args clean
-    fn dfs(
-        n_orders: usize, num_nodes: usize,
-        veh_start: &Vec<f64>, dist_mat: &Vec<f64>, orders: &Vec<Order>,
-        v_idx: usize, v_price: f64, target_mask: u32,
-        
-        last_node: Option<usize>,
-        cur: (f64, f64, f64, f64), // (dist, empty, price, load)
-        path: &mut PathBuffer,
-        pickup_mask: u32, deliver_mask: u32,
-
-        b_dist: &mut (f64, PathBuffer, f64, f64),
-        b_empty: &mut (f64, PathBuffer, f64, f64),
-        b_price: &mut (f64, PathBuffer, f64, f64),
-    ) {
-        let (c_dist, c_empty, c_price, c_load) = cur;
-
-        // Pruning: if current path is worse than best found in ALL categories
-        if c_dist >= b_dist.0 && c_empty >= b_empty.0 && c_price >= b_price.0 {
-            return;
+    #[inline(always)]
+    fn within_limits(&self, dist: f64, duration: f64) -> bool {
+        dist <= self.max_dist + DIST_EPSILON && duration <= self.max_duration + TIME_EPSILON
+    }
+}
+
+/// Cheapest leg into each pickup and delivery of `target_mask` from anywhere it could be
+/// entered from: the start for pickups, or deliveries of orders on board, and any other stop of
+/// the set except the order's own delivery before its pickup.
+fn min_incoming(ctx: &SolverContext, vehicle_idx: usize, target_mask: OrderMask) -> [f64; 2 * MAX_MASK_ORDERS] {
+    let shared = &ctx.shared;
+    let n_orders = shared.n_orders;
+    let mut min_in = [0.0; 2 * MAX_MASK_ORDERS];
+    let in_set = |node: usize| target_mask & (1 << (node / 2)) != 0;
+
+    for to in (0..2 * n_orders).filter(|&to| in_set(to)) {
+        let o_idx = to / 2;
+        let mut best = f64::INFINITY;
+        let first_stop = if shared.onboard[vehicle_idx] & (1 << o_idx) != 0 { 2 * o_idx + 1 } else { 2 * o_idx };
+        if to == first_stop && shared.forbidden_from_start[vehicle_idx] & (1 << o_idx) == 0 {
+            best = ctx.veh_start.values[vehicle_idx * n_orders + o_idx];
+        }
+        for from in (0..2 * n_orders).filter(|&from| in_set(from) && from != to && from != to + 1) {
+            best = best.min(shared.dist_mat[from * shared.num_nodes + to]);
+        }
+        min_in[to] = best;
+    }
+    min_in
+}
+
+/// Per-node state of the DFS, copied into every child.
+#[derive(Clone, Copy)]
+struct SearchNode {
+    // None at the vehicle's start, before the first stop or after a reload
+    last_node: Option<usize>,
+    trip: u32,
+    dist: f64,
+    empty: f64,
+    price: f64,
+    duration: f64,
+    co2: f64,
+    interleavings: u32,
+    penalty: f64,
+    // Minutes since leaving the start, and how many of them were spent waiting for windows
+    time: f64,
+    waiting: f64,
+    load: f64,
+    pickup_mask: OrderMask,
+    deliver_mask: OrderMask,
+}
+
+/// Best route found for each criterion, plus the path being built.
+struct SearchState {
+    path: PathBuffer,
+    best_dist: InternalTspResult,
+    best_empty: InternalTspResult,
+    best_price: InternalTspResult,
+    best_duration: InternalTspResult,
+    // Objective cost of `best_dist` in a single-objective search
+    best_objective: ObjectiveCost,
+    expansions: u64,
+    bound_prunes: u64,
+    cancelled: bool,
+    // Past `SearchFrame::max_expansions`
+    out_of_nodes: bool,
+}
+
+// DFS nodes between reads of the cancel flag
+const CANCEL_CHECK_INTERVAL: u64 = 4096;
+
+// Minutes of slack when checking arrivals against a window's latest bound
+pub(crate) const TIME_EPSILON: f64 = 1e-9;
+
+// Kilometres of slack when checking a route against the vehicle's distance limit
+pub(crate) const DIST_EPSILON: f64 = 1e-9;
+
+impl SearchState {
+    fn new() -> Self {
+        let unreached = InternalTspResult {
+            path: PathBuffer::default(),
+            total_dist: f64::INFINITY,
+            total_empty: f64::INFINITY,
+            total_price: f64::INFINITY,
+            total_duration: f64::INFINITY,
+            total_co2: f64::INFINITY,
+            interleavings: 0,
+            penalty: 0.0,
+            waiting: 0.0,
+            return_dist: 0.0,
+        };
+        SearchState {
+            path: PathBuffer::default(),
+            best_dist: unreached,
+            best_empty: unreached,
+            best_price: unreached,
+            best_duration: unreached,
+            best_objective: objective::UNREACHED,
+            expansions: 0,
+            bound_prunes: 0,
+            cancelled: false,
+            out_of_nodes: false,
+        }
+    }
+}
+
+impl SearchNode {
+    // The node that follows stopping at `node`, counting a switch between picking up and delivering
+    #[inline(always)]
+    fn switch_to(&self, frame: &SearchFrame, node: usize) -> (u32, f64) {
+        match self.last_node {
+            Some(prev) if prev % 2 != node % 2 => (self.interleavings + 1, self.penalty + frame.interleaving_penalty),
+            _ => (self.interleavings, self.penalty),
         }
+    }
+}
+
+fn dfs(frame: &SearchFrame, state: &mut SearchState, cur: SearchNode) {
+    state.expansions += 1;
+    if state.expansions.is_multiple_of(CANCEL_CHECK_INTERVAL)
+        && frame.cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    {
+        state.cancelled = true;
+    }
+    if state.expansions > frame.max_expansions {
+        state.out_of_nodes = true;
+    }
+    if state.cancelled || state.out_of_nodes {
+        return;
+    }
 
-        if deliver_mask == target_mask {
-            if c_dist < b_dist.0 { *b_dist = (c_dist, *path, c_empty, c_price); }
-            if c_empty < b_empty.0 { *b_empty = (c_empty, *path, c_dist, c_price); }
-            if c_price < b_price.0 { *b_price = (c_price, *path, c_dist, c_empty); }
+    // Pruning: if current path is worse than best found in ALL categories, or no better than
+    // the single objective's incumbent
+    let dominated = |bound: f64| match &frame.objective {
+        Some(objective) => {
+            let cost = objective.cost(
+                cur.dist + bound,
+                cur.price + bound * frame.v_price_min,
+                cur.empty,
+                cur.co2 + bound * frame.v_co2_min,
+                cur.penalty,
+            );
+            !objective.better(&cost, &state.best_objective)
+        }
+        None => {
+            let criteria = frame.criteria;
+            // A tie within `TIE_EPSILON` is only beaten once its path so far sorts after the
+            // incumbent's, so the lexicographically smallest of tied routes is found whichever
+            // branch reaches a tie first
+            let prefix = state.path.stops();
+            let beaten = |cost: f64, best: &InternalTspResult, best_cost: f64| {
+                let (cost, best_cost) = (cost + cur.penalty, best_cost + best.penalty);
+                let best_prefix = &best.path.stops()[..prefix.len().min(best.path.len as usize)];
+                cost > best_cost + TIE_EPSILON || (cost >= best_cost - TIE_EPSILON && prefix > best_prefix)
+            };
+            (!criteria.contains(Criterion::Distance) || beaten(cur.dist + bound, &state.best_dist, state.best_dist.total_dist))
+                && (!criteria.contains(Criterion::Empty) || beaten(cur.empty, &state.best_empty, state.best_empty.total_empty))
+                && (!criteria.contains(Criterion::Price)
+                    || beaten(cur.price + bound * frame.v_price_min, &state.best_price, state.best_price.total_price))
+                && (!criteria.contains(Criterion::Duration)
+                    || beaten(cur.duration + bound * frame.v_minutes_per_km, &state.best_duration, state.best_duration.total_duration))
+        }
+    };
+    if dominated(0.0) {
+        return;
+    }
+    // Same, counting what the rest of the route must cost at least
+    if frame.lower_bound && dominated(frame.remaining_bound(&cur)) {
+        state.bound_prunes += 1;
+        return;
+    }
+    // Already past the vehicle's limits, or bound to end up there
+    if frame.max_dist < f64::INFINITY || frame.max_duration < f64::INFINITY {
+        let bound = if frame.lower_bound { frame.remaining_bound(&cur) } else { 0.0 };
+        if !frame.within_limits(cur.dist + bound, cur.duration + bound * frame.v_minutes_per_km) {
             return;
         }
+    }
 
-        for o_idx in 0..n_orders {
-            let order_bit = 1 << o_idx;
-            if (target_mask & order_bit) == 0 { continue; }
-
-            let order = &orders[o_idx];
-            let load_val = 1.0 / order.load_factor;
-
-            // PICKUP Logic
-            if (pickup_mask & order_bit) == 0 {
-                if c_load + load_val > 1.000001 { continue; }
-
-                let leg_dist = match last_node {
-                    None => veh_start[v_idx * n_orders + o_idx],
-                    Some(prev) => dist_mat[prev * num_nodes + (2 * o_idx)]
-                };
-
-                let is_empty = pickup_mask == deliver_mask;
-                let add_empty = if is_empty { leg_dist } else { 0.0 };
-                
-                path.nodes[path.len as usize] = (2 * o_idx) as u8;
-                path.len += 1;
-                
-                dfs(n_orders, num_nodes, veh_start, dist_mat, orders,
-                   v_idx, v_price, target_mask,
-                   Some(2 * o_idx), 
-                   (c_dist + leg_dist, c_empty + add_empty, c_price + (leg_dist * v_price), c_load + load_val),
-                   path, pickup_mask | order_bit, deliver_mask,
-                   b_dist, b_empty, b_price
-                );
-                
-                path.len -= 1;
+    if cur.deliver_mask == frame.target_mask {
+        // The empty leg on to the end location closes the route
+        let return_dist = match (frame.veh_end, cur.last_node) {
+            (Some(_), Some(last)) if frame.end_forbidden & (1 << (last / 2)) != 0 => return,
+            (Some(to_end), Some(last)) => to_end[last / 2],
+            _ => 0.0,
+        };
+        let found = InternalTspResult {
+            path: state.path,
+            total_dist: cur.dist + return_dist,
+            total_empty: cur.empty + return_dist,
+            total_price: cur.price + return_dist * frame.v_price_empty,
+            total_duration: cur.duration + return_dist * frame.v_minutes_per_km,
+            total_co2: cur.co2 + return_dist * frame.v_co2_empty,
+            interleavings: cur.interleavings,
+            penalty: cur.penalty,
+            waiting: cur.waiting,
+            return_dist,
+        };
+        if !frame.within_limits(found.total_dist, found.total_duration) {
+            return;
+        }
+        if let Some(objective) = &frame.objective {
+            let cost = objective.cost(found.total_dist, found.total_price, found.total_empty, found.total_co2, cur.penalty);
+            let tied = !objective.better(&state.best_objective, &cost) && found.path.stops() < state.best_dist.path.stops();
+            if objective.better(&cost, &state.best_objective) || tied {
+                state.best_objective = cost;
+                state.best_dist = found;
             }
-            // DELIVERY Logic
-            else if (pickup_mask & order_bit) != 0 && (deliver_mask & order_bit) == 0 {
-                let prev = last_node.unwrap_or(0); 
-                let leg_dist = dist_mat[prev * num_nodes + (2 * o_idx + 1)];
-
-                path.nodes[path.len as usize] = (2 * o_idx + 1) as u8;
-                path.len += 1;
-
-                dfs(n_orders, num_nodes, veh_start, dist_mat, orders,
-                    v_idx, v_price, target_mask,
-                    Some(2 * o_idx + 1), 
-                    (c_dist + leg_dist, c_empty, c_price + (leg_dist * v_price), c_load - load_val),
-                    path, pickup_mask, deliver_mask | order_bit,
-                    b_dist, b_empty, b_price
-                );
-
-                path.len -= 1;
+            return;
+        }
+        // Routes tied within `TIE_EPSILON` go to the lexicographically smaller path
+        let wins = |cost: f64, best: &InternalTspResult, best_cost: f64| {
+            wins_tie_break(cost + cur.penalty, found.path.stops(), best_cost + best.penalty, best.path.stops())
+        };
+        if wins(found.total_dist, &state.best_dist, state.best_dist.total_dist) { state.best_dist = found; }
+        if wins(found.total_empty, &state.best_empty, state.best_empty.total_empty) { state.best_empty = found; }
+        if wins(found.total_price, &state.best_price, state.best_price.total_price) { state.best_price = found; }
+        if wins(found.total_duration, &state.best_duration, state.best_duration.total_duration) {
+            state.best_duration = found;
+        }
+        return;
+    }
+
+    // RELOAD Logic: return to the start once the vehicle is empty, the whole leg is empty
+    if let Some(prev) = cur.last_node {
+        if cur.trip + 1 < frame.max_trips
+            && cur.pickup_mask == cur.deliver_mask
+            && frame.return_forbidden & (1 << (prev / 2)) == 0
+        {
+            let leg_dist = frame.veh_return[prev / 2];
+
+            state.path.nodes[state.path.len as usize] = RELOAD_NODE;
+            state.path.len += 1;
+
+            dfs(frame, state, SearchNode {
+                last_node: None,
+                trip: cur.trip + 1,
+                dist: cur.dist + leg_dist,
+                empty: cur.empty + leg_dist,
+                price: cur.price + (leg_dist * frame.v_price_empty),
+                duration: cur.duration + (leg_dist * frame.v_minutes_per_km),
+                co2: cur.co2 + (leg_dist * frame.v_co2_empty),
+                time: cur.time + (leg_dist * frame.v_minutes_per_km),
+                load: 0.0,
+                ..cur
+            });
+
+            state.path.len -= 1;
+        }
+    }
+
+    // Every order contributes at most one next stop, its pickup or its delivery
+    let mut candidates = [(0.0, 0usize); MAX_ROUTE_ORDERS];
+    let mut n_candidates = 0;
+
+    for o_idx in 0..frame.n_orders {
+        let order_bit = 1 << o_idx;
+        if (frame.target_mask & order_bit) == 0 { continue; }
+
+        let load_val = frame.loads[o_idx];
+
+        // PICKUP Logic
+        let (node, leg_dist) = if (cur.pickup_mask & order_bit) == 0 {
+            if cur.load + load_val > 1.000001 { continue; }
+
+            let leg_dist = match cur.last_node {
+                None if frame.start_forbidden & order_bit != 0 => continue,
+                None => frame.veh_start[o_idx],
+                Some(prev) => frame.dist_mat[prev * frame.num_nodes + (2 * o_idx)]
+            };
+            (2 * o_idx, leg_dist)
+        }
+        // DELIVERY Logic, straight from the start only for orders already on board
+        else if (cur.deliver_mask & order_bit) == 0 {
+            let leg_dist = match cur.last_node {
+                None if frame.start_forbidden & order_bit != 0 => continue,
+                None => frame.veh_start[o_idx],
+                Some(prev) => frame.dist_mat[prev * frame.num_nodes + (2 * o_idx + 1)],
+            };
+            (2 * o_idx + 1, leg_dist)
+        } else {
+            continue;
+        };
+        // Forbidden legs are infinite in the matrix
+        if leg_dist == f64::INFINITY { continue; }
+        // Arriving after the window closes, waiting cannot help
+        if cur.time + leg_dist * frame.v_minutes_per_km > frame.windows[node].1 + TIME_EPSILON { continue; }
+
+        candidates[n_candidates] = (leg_dist, node);
+        n_candidates += 1;
+    }
+
+    // Cheapest leg first, so good routes are found early and prune the rest. Stable, so equal
+    // legs keep index order
+    let candidates = &mut candidates[..n_candidates];
+    if frame.nearest_first {
+        for i in 1..candidates.len() {
+            let mut j = i;
+            while j > 0 && candidates[j - 1].0 > candidates[j].0 {
+                candidates.swap(j - 1, j);
+                j -= 1;
             }
         }
     }
 
-    dfs(ctx.n_orders, ctx.num_nodes, &ctx.veh_start_mat, &ctx.dist_mat, &ctx.orders,
-        vehicle_idx, vehicle_price, target_mask, 
-        None, (0.0, 0.0, 0.0, 0.0), &mut path_stack, 0, 0,
-        &mut best_dist, &mut best_empty, &mut best_price
-    );
+    for &(leg_dist, node) in candidates.iter() {
+        let o_idx = node / 2;
+        let order_bit = 1 << o_idx;
+        let load_val = frame.loads[o_idx];
+        let (interleavings, penalty) = cur.switch_to(frame, node);
+        let arrival = cur.time + leg_dist * frame.v_minutes_per_km;
+        let served = arrival.max(frame.windows[node].0);
+        let waiting = cur.waiting + (served - arrival);
+        let time = served + frame.service[node];
+        // Waiting for a window to open counts towards the route's duration like driving does
+        let duration = cur.duration + (leg_dist * frame.v_minutes_per_km) + (served - arrival) + frame.service[node];
+
+        state.path.nodes[state.path.len as usize] = node as u8;
+        state.path.len += 1;
+
+        let next = if node.is_multiple_of(2) {
+            let is_empty = cur.pickup_mask == cur.deliver_mask;
+            SearchNode {
+                last_node: Some(node),
+                trip: cur.trip,
+                dist: cur.dist + leg_dist,
+                empty: cur.empty + if is_empty { leg_dist } else { 0.0 },
+                price: cur.price + (leg_dist * if is_empty { frame.v_price_empty } else { frame.v_price }) + frame.stop_fee,
+                duration,
+                co2: cur.co2 + (leg_dist * if is_empty { frame.v_co2_empty } else { frame.v_co2 }),
+                interleavings,
+                penalty,
+                time,
+                waiting,
+                load: cur.load + load_val,
+                pickup_mask: cur.pickup_mask | order_bit,
+                deliver_mask: cur.deliver_mask,
+            }
+        } else {
+            SearchNode {
+                last_node: Some(node),
+                trip: cur.trip,
+                dist: cur.dist + leg_dist,
+                empty: cur.empty,
+                price: cur.price + (leg_dist * frame.v_price) + frame.stop_fee,
+                duration,
+                co2: cur.co2 + (leg_dist * frame.v_co2),
+                interleavings,
+                penalty,
+                time,
+                waiting,
+                load: cur.load - load_val,
+                pickup_mask: cur.pickup_mask,
+                deliver_mask: cur.deliver_mask | order_bit,
+            }
+        };
+        dfs(frame, state, next);
+
+        state.path.len -= 1;
+    }
+}
+
+pub fn solve_tsp(
+    ctx: &mut SolverContext, 
+    vehicle_idx: usize,
+    target_mask: OrderMask,
+) -> InternalBestResults {
+    // Rules the set out for every vehicle, whichever search is asking
+    if !ctx.shared.keeps_pairings(target_mask) {
+        return InternalBestResults::INVALID;
+    }
+    // `solve_recursive` never asks for such sets, the other searches may
+    if !ctx.shared.within_order_cap(vehicle_idx, target_mask) {
+        return InternalBestResults::INVALID;
+    }
+    // Whatever is on board has to be delivered by this vehicle
+    let onboard = ctx.shared.onboard[vehicle_idx];
+    if target_mask & onboard != onboard {
+        return InternalBestResults::INVALID;
+    }
+
+    let memo_row = ctx.memo_rows[vehicle_idx];
+    if let Some(cached) = ctx.memo.get(memo_row, target_mask) {
+        ctx.stats.memo_hits += 1;
+        return *cached;
+    }
+    ctx.stats.memo_misses += 1;
+
+    ctx.veh_start.ensure(vehicle_idx, target_mask);
 
-    let result = if best_dist.0 < f64::INFINITY {
-         InternalBestResults {
-             min_dist: InternalTspResult { path: best_dist.1, total_dist: best_dist.0, total_empty: best_dist.2, total_price: best_dist.3 },
-             min_empty: InternalTspResult { path: best_empty.1, total_dist: best_empty.2, total_empty: best_empty.0, total_price: best_empty.3 },
-             min_price: InternalTspResult { path: best_price.1, total_dist: best_price.2, total_empty: best_price.3, total_price: best_price.0 },
-             valid: true
-         }
+    let vehicle = &ctx.shared.vehicles[vehicle_idx];
+    let single_trip = vehicle.trips() <= 1;
+    // The DP keeps one route per criterion and has no notion of route limits or orders on board
+    let use_dp = single_trip
+        && !vehicle.has_route_limits()
+        && onboard == 0
+        && !ctx.shared.has_time_windows
+        && ctx.shared.objective.is_none()
+        && ctx.shared.options.route_dp.unwrap_or(target_mask.count_ones() >= held_karp::AUTO_DP_MIN_ORDERS);
+    let result = if use_dp {
+        held_karp::route(ctx, vehicle_idx, target_mask)
     } else {
-        let dummy = InternalTspResult { path: PathBuffer::default(), total_dist: 0.0, total_empty: 0.0, total_price: 0.0 };
-        InternalBestResults { min_dist: dummy, min_empty: dummy, min_price: dummy, valid: false }
+        match route_dfs(ctx, vehicle_idx, target_mask) {
+            Some(result) => result,
+            None => {
+                // The partial result is never memoized, the search unwinds from here
+                ctx.stopped = true;
+                return InternalBestResults::INVALID;
+            }
+        }
+    };
+
+    let result = match ctx.shared.route_cost.clone() {
+        Some(route_cost) if result.valid => match with_custom_price(ctx, &route_cost, vehicle_idx, target_mask, result) {
+            Ok(result) => result,
+            Err(err) => {
+                // Never memoized, a price the caller could not give must not pass for the built-in one
+                ctx.route_cost_error.get_or_insert(err);
+                ctx.stopped = true;
+                return InternalBestResults::INVALID;
+            }
+        },
+        _ => result,
     };
 
-    unsafe { *ctx.memo.get_unchecked_mut(cache_idx) = Some(result); }
+    ctx.memo.insert(memo_row, target_mask, result);
+    ctx.stats.memo_entries += 1;
 
     result
-}
\ No newline at end of file
+}
+
+/// Optimal routes by pruned DFS over stop sequences, None if the search was cancelled, which
+/// `ctx.cancelled` tells, or ran out of `max_nodes`.
+fn route_dfs(ctx: &mut SolverContext, vehicle_idx: usize, target_mask: OrderMask) -> Option<InternalBestResults> {
+    let n_orders = ctx.shared.n_orders;
+    let lower_bound = ctx.shared.options.route_lower_bound.unwrap_or(true);
+
+    let frame = SearchFrame {
+        n_orders,
+        num_nodes: ctx.shared.num_nodes,
+        veh_start: &ctx.veh_start.values[vehicle_idx * n_orders..(vehicle_idx + 1) * n_orders],
+        veh_return: &ctx.veh_start.returns[vehicle_idx * n_orders..(vehicle_idx + 1) * n_orders],
+        start_forbidden: ctx.shared.forbidden_from_start[vehicle_idx],
+        return_forbidden: ctx.shared.forbidden_to_start[vehicle_idx],
+        max_trips: ctx.shared.vehicles[vehicle_idx].trips(),
+        dist_mat: &ctx.shared.dist_mat,
+        windows: &ctx.shared.windows,
+        service: &ctx.shared.service,
+        loads: &ctx.shared.loads[vehicle_idx * ctx.shared.n_orders..(vehicle_idx + 1) * ctx.shared.n_orders],
+        veh_end: ctx.shared.vehicles[vehicle_idx]
+            .end_location
+            .is_some()
+            .then(|| &ctx.veh_start.to_end[vehicle_idx * n_orders..(vehicle_idx + 1) * n_orders]),
+        end_forbidden: ctx.shared.forbidden_to_end[vehicle_idx],
+        v_price: ctx.shared.vehicles[vehicle_idx].price_km,
+        v_price_empty: ctx.shared.vehicles[vehicle_idx].empty_price_km(),
+        v_price_min: ctx.shared.vehicles[vehicle_idx].price_km.min(ctx.shared.vehicles[vehicle_idx].empty_price_km()),
+        stop_fee: ctx.shared.vehicles[vehicle_idx].fee_per_stop(),
+        v_co2: ctx.shared.vehicles[vehicle_idx].emission_rate(false),
+        v_co2_empty: ctx.shared.vehicles[vehicle_idx].emission_rate(true),
+        v_co2_min: ctx.shared.vehicles[vehicle_idx].emission_rate(false).min(ctx.shared.vehicles[vehicle_idx].emission_rate(true)),
+        v_minutes_per_km: ctx.shared.vehicles[vehicle_idx].minutes_per_km(),
+        max_dist: ctx.shared.vehicles[vehicle_idx].route_limits().0,
+        max_duration: ctx.shared.vehicles[vehicle_idx].route_limits().1,
+        interleaving_penalty: ctx.shared.options.interleaving_penalty.unwrap_or(0.0),
+        objective: ctx.shared.objective,
+        // Minimum route prices are checked against the cheapest route, and vehicles sharing a
+        // memo row may differ in them
+        criteria: if ctx.shared.vehicles.iter().any(|v| v.min_route_price.is_some()) {
+            ctx.shared.criteria.route_criteria().with(Criterion::Price)
+        } else {
+            ctx.shared.criteria.route_criteria()
+        },
+        target_mask,
+        cancel: ctx.shared.cancel.as_deref().filter(|_| ctx.watch_cancel),
+        // Routes rebuilt after the search are never cut short
+        max_expansions: if ctx.watch_cancel && ctx.stats.complete_assignments > 0 {
+            ctx.node_budget.saturating_sub(ctx.stats.assignment_nodes + ctx.stats.route_nodes)
+        } else {
+            u64::MAX
+        },
+        nearest_first: ctx.shared.options.nearest_first.unwrap_or(true),
+        lower_bound,
+        min_incoming: if lower_bound { min_incoming(ctx, vehicle_idx, target_mask) } else { [0.0; 2 * MAX_MASK_ORDERS] },
+    };
+    let mut state = SearchState::new();
+    // Orders on board start out picked up, their load already taken
+    let onboard = ctx.shared.onboard[vehicle_idx];
+    let initial_load = (0..n_orders).filter(|o_idx| onboard & (1 << o_idx) != 0).map(|o_idx| frame.loads[o_idx]).sum();
+
+    dfs(&frame, &mut state, SearchNode {
+        last_node: None,
+        trip: 0,
+        dist: 0.0,
+        empty: 0.0,
+        price: 0.0,
+        duration: 0.0,
+        co2: 0.0,
+        interleavings: 0,
+        penalty: 0.0,
+        time: 0.0,
+        waiting: 0.0,
+        load: initial_load,
+        pickup_mask: onboard,
+        deliver_mask: 0,
+    });
+    ctx.stats.route_nodes += state.expansions;
+    ctx.stats.route_bound_prunes += state.bound_prunes;
+
+    if state.cancelled {
+        ctx.cancelled = true;
+        return None;
+    }
+    if state.out_of_nodes {
+        return None;
+    }
+
+    // A single-objective search keeps one route, reported under every criterion
+    if frame.objective.is_some() {
+        state.best_empty = state.best_dist;
+        state.best_price = state.best_dist;
+        state.best_duration = state.best_dist;
+    }
+
+    Some(if state.best_dist.total_dist < f64::INFINITY {
+        InternalBestResults {
+            min_dist: state.best_dist,
+            min_empty: state.best_empty,
+            min_price: state.best_price,
+            min_duration: state.best_duration,
+            valid: true
+        }
+    } else {
+        InternalBestResults::INVALID
+    })
+}
+
+// Replaces the price-optimal route's price with the caller's own pricing. A callback that throws
+// or returns something other than a finite number fails the solve
+fn with_custom_price(
+    ctx: &SolverContext,
+    route_cost: &RouteCostFn,
+    vehicle_idx: usize,
+    target_mask: OrderMask,
+    mut result: InternalBestResults,
+) -> Result<InternalBestResults> {
+    let route = &result.min_price;
+    let scale = units::unit_scale(&ctx.shared.options);
+    let summary = RouteSummary {
+        vehicle_id: ctx.shared.vehicles[vehicle_idx].id,
+        order_ids: (0..ctx.shared.n_orders)
+            .filter(|o_idx| target_mask & (1 << o_idx) != 0)
+            .map(|o_idx| ctx.shared.orders[o_idx].id)
+            .collect(),
+        distance: route.total_dist * scale,
+        empty_distance: route.total_empty * scale,
+        price: route.total_price,
+        duration: route.total_duration,
+    };
+    let route = format!("vehicle {} with orders {:?}", summary.vehicle_id, summary.order_ids);
+    match route_cost(summary) {
+        Ok(price) if price.is_finite() => {
+            result.min_price.total_price = price;
+            Ok(result)
+        }
+        Ok(price) => Err(SolverError::InvalidArg(format!("routeCostFn returned {} for {}", price, route))),
+        Err(err) => Err(SolverError::InvalidArg(format!("routeCostFn failed for {}: {}", route, err.reason))),
+    }
+}
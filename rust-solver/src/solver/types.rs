@@ -1,21 +1,46 @@
-#[derive(Clone, Copy, Debug)]
+use crate::models::Criterion;
+
+#[derive(Clone, Copy, Debug, Default)]
 pub struct PathBuffer {
-    pub nodes: [u8; 16],
+    // Two nodes per order plus a reload between each pair of trips
+    pub nodes: [u8; 3 * MAX_ROUTE_ORDERS - 1],
     pub len: u8,
 }
 
-impl Default for PathBuffer {
-    fn default() -> Self {
-        Self { nodes: [0; 16], len: 0 }
+impl PathBuffer {
+    pub fn stops(&self) -> &[u8] {
+        &self.nodes[..self.len as usize]
     }
 }
 
+// Costs closer than this are ties, broken by the assignment vector or the route's path
+pub const TIE_EPSILON: f64 = 1e-9;
+
+/// Whether a candidate costing `cost` replaces an incumbent costing `best`: it is cheaper by more
+/// than `TIE_EPSILON`, or ties with it and has the lexicographically smaller `key`. Ties then go
+/// the same way whichever of the two the search happens to reach first.
+#[inline(always)]
+pub fn wins_tie_break<K: PartialOrd + ?Sized>(cost: f64, key: &K, best: f64, best_key: &K) -> bool {
+    cost < best - TIE_EPSILON || (best.is_finite() && cost <= best + TIE_EPSILON && key < best_key)
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct InternalTspResult {
     pub path: PathBuffer,
     pub total_dist: f64,
     pub total_empty: f64,
     pub total_price: f64,
+    // Minutes
+    pub total_duration: f64,
+    // Grams
+    pub total_co2: f64,
+    pub interleavings: u32,
+    // Soft cost of the interleavings, added to every criterion when comparing routes
+    pub penalty: f64,
+    // Minutes spent waiting for time windows, included in `total_duration`
+    pub waiting: f64,
+    // Last delivery to the vehicle's end location, included in the totals
+    pub return_dist: f64,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -23,5 +48,337 @@ pub struct InternalBestResults {
     pub min_dist: InternalTspResult,
     pub min_price: InternalTspResult,
     pub min_empty: InternalTspResult,
+    pub min_duration: InternalTspResult,
     pub valid: bool,
-}
\ No newline at end of file
+}
+
+impl InternalBestResults {
+    pub const INVALID: InternalBestResults = {
+        let dummy = InternalTspResult {
+            path: PathBuffer { nodes: [0; 3 * MAX_ROUTE_ORDERS - 1], len: 0 },
+            total_dist: 0.0,
+            total_empty: 0.0,
+            total_price: 0.0,
+            total_co2: 0.0,
+            total_duration: 0.0,
+            interleavings: 0,
+            penalty: 0.0,
+            waiting: 0.0,
+            return_dist: 0.0,
+        };
+        InternalBestResults { min_dist: dummy, min_empty: dummy, min_price: dummy, min_duration: dummy, valid: false }
+    };
+
+    pub fn for_criterion(&self, criterion: Criterion) -> &InternalTspResult {
+        match criterion {
+            Criterion::Distance => &self.min_dist,
+            Criterion::Price => &self.min_price,
+            Criterion::Empty => &self.min_empty,
+            // A single route's share of the makespan is its length
+            Criterion::Makespan => &self.min_dist,
+            // Any route counts as one vehicle, after that its length decides
+            Criterion::Fleet => &self.min_dist,
+            Criterion::Duration => &self.min_duration,
+        }
+    }
+}
+
+/// Criteria a search keeps incumbents for, one bit per `Criterion`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CriteriaSet(u8);
+
+impl CriteriaSet {
+    pub const ALL: CriteriaSet = CriteriaSet(0b11_1111);
+
+    #[inline(always)]
+    fn bit(criterion: Criterion) -> u8 {
+        match criterion {
+            Criterion::Distance => 1,
+            Criterion::Price => 1 << 1,
+            Criterion::Empty => 1 << 2,
+            Criterion::Makespan => 1 << 3,
+            Criterion::Duration => 1 << 4,
+            Criterion::Fleet => 1 << 5,
+        }
+    }
+
+    pub fn of(criteria: &[Criterion]) -> Self {
+        CriteriaSet(criteria.iter().fold(0, |bits, &criterion| bits | Self::bit(criterion)))
+    }
+
+    #[inline(always)]
+    pub fn contains(self, criterion: Criterion) -> bool {
+        self.0 & Self::bit(criterion) != 0
+    }
+
+    /// The route criteria `InternalBestResults` has to get right for these: makespan and fleet
+    /// are summed from the shortest routes.
+    pub fn route_criteria(self) -> CriteriaSet {
+        let mut bits = self.0 & (Self::bit(Criterion::Price) | Self::bit(Criterion::Empty) | Self::bit(Criterion::Duration));
+        if self.contains(Criterion::Distance) || self.contains(Criterion::Makespan) || self.contains(Criterion::Fleet) {
+            bits |= Self::bit(Criterion::Distance);
+        }
+        CriteriaSet(bits)
+    }
+
+    pub fn with(self, criterion: Criterion) -> CriteriaSet {
+        CriteriaSet(self.0 | Self::bit(criterion))
+    }
+}
+
+// Fleet cost of one used vehicle, far beyond any total distance so the vehicle count decides first
+pub const FLEET_VEHICLE_WEIGHT: f64 = 1e9;
+
+/// Running objective values of a partial assignment, one field per criterion.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PartialCost {
+    pub dist: f64,
+    pub price: f64,
+    pub empty: f64,
+    // Longest single route so far, a lower bound on the final makespan
+    pub makespan: f64,
+    pub duration: f64,
+    // Vehicles used so far times `FLEET_VEHICLE_WEIGHT`, plus their distance
+    pub fleet: f64,
+}
+
+impl PartialCost {
+    pub const UNREACHED: PartialCost = PartialCost {
+        dist: f64::INFINITY,
+        price: f64::INFINITY,
+        empty: f64::INFINITY,
+        makespan: f64::INFINITY,
+        duration: f64::INFINITY,
+        fleet: f64::INFINITY,
+    };
+
+    /// True when `self` is no better than `other` in every criterion.
+    #[inline(always)]
+    pub fn dominated_by(&self, other: &PartialCost) -> bool {
+        self.dist >= other.dist
+            && self.price >= other.price
+            && self.empty >= other.empty
+            && self.makespan >= other.makespan
+            && self.duration >= other.duration
+            && self.fleet >= other.fleet
+    }
+
+    #[inline(always)]
+    pub fn get(&self, criterion: Criterion) -> f64 {
+        match criterion {
+            Criterion::Distance => self.dist,
+            Criterion::Price => self.price,
+            Criterion::Empty => self.empty,
+            Criterion::Makespan => self.makespan,
+            Criterion::Duration => self.duration,
+            Criterion::Fleet => self.fleet,
+        }
+    }
+
+    pub fn set(&mut self, criterion: Criterion, value: f64) {
+        match criterion {
+            Criterion::Distance => self.dist = value,
+            Criterion::Price => self.price = value,
+            Criterion::Empty => self.empty = value,
+            Criterion::Makespan => self.makespan = value,
+            Criterion::Duration => self.duration = value,
+            Criterion::Fleet => self.fleet = value,
+        }
+    }
+
+    #[inline(always)]
+    pub fn from_fn(f: impl Fn(Criterion) -> f64) -> PartialCost {
+        PartialCost {
+            dist: f(Criterion::Distance),
+            price: f(Criterion::Price),
+            empty: f(Criterion::Empty),
+            makespan: f(Criterion::Makespan),
+            duration: f(Criterion::Duration),
+            fleet: f(Criterion::Fleet),
+        }
+    }
+
+    /// Same cost with every criterion outside `criteria` at minus infinity, which never
+    /// decides `dominated_by` either way.
+    #[inline(always)]
+    pub fn only(&self, criteria: CriteriaSet) -> PartialCost {
+        if criteria == CriteriaSet::ALL {
+            return *self;
+        }
+        PartialCost::from_fn(|criterion| if criteria.contains(criterion) { self.get(criterion) } else { f64::NEG_INFINITY })
+    }
+
+    /// Per-criterion minimum of both costs.
+    #[inline(always)]
+    pub fn min(&self, other: &PartialCost) -> PartialCost {
+        PartialCost::from_fn(|criterion| self.get(criterion).min(other.get(criterion)))
+    }
+
+    /// Cost after adding one more vehicle's route. The makespan stays the longest route distance
+    /// when speeds, service times or windows are set, time only counts towards `duration`.
+    #[inline(always)]
+    pub fn with_route(&self, res: &InternalBestResults) -> PartialCost {
+        PartialCost {
+            dist: self.dist + res.min_dist.total_dist + res.min_dist.penalty,
+            price: self.price + res.min_price.total_price + res.min_price.penalty,
+            empty: self.empty + res.min_empty.total_empty + res.min_empty.penalty,
+            makespan: self.makespan.max(res.min_dist.total_dist + res.min_dist.penalty),
+            duration: self.duration + res.min_duration.total_duration + res.min_duration.penalty,
+            fleet: self.fleet + FLEET_VEHICLE_WEIGHT + res.min_dist.total_dist + res.min_dist.penalty,
+        }
+    }
+}
+
+/// Best assignment per criterion among those covering the most orders, reported when no
+/// assignment covers them all.
+#[derive(Clone, Debug)]
+pub struct PartialIncumbents {
+    pub covered: u32,
+    pub cost: PartialCost,
+    pub dist: Vec<OrderMask>,
+    pub price: Vec<OrderMask>,
+    pub empty: Vec<OrderMask>,
+    pub makespan: Vec<OrderMask>,
+    pub duration: Vec<OrderMask>,
+    pub fleet: Vec<OrderMask>,
+}
+
+impl PartialIncumbents {
+    pub fn new(n_vehicles: usize) -> Self {
+        PartialIncumbents {
+            covered: 0,
+            cost: PartialCost::UNREACHED,
+            dist: vec![0; n_vehicles],
+            price: vec![0; n_vehicles],
+            empty: vec![0; n_vehicles],
+            makespan: vec![0; n_vehicles],
+            duration: vec![0; n_vehicles],
+            fleet: vec![0; n_vehicles],
+        }
+    }
+
+    pub fn for_criterion(&self, criterion: Criterion) -> &[OrderMask] {
+        match criterion {
+            Criterion::Distance => &self.dist,
+            Criterion::Price => &self.price,
+            Criterion::Empty => &self.empty,
+            Criterion::Makespan => &self.makespan,
+            Criterion::Duration => &self.duration,
+            Criterion::Fleet => &self.fleet,
+        }
+    }
+
+    /// Takes `assignments` for every criterion it wins by `wins_tie_break`, more orders covered beats any cost.
+    pub fn offer(&mut self, covered: u32, cost: &PartialCost, assignments: &[OrderMask]) {
+        if covered < self.covered {
+            return;
+        }
+        if covered > self.covered {
+            self.covered = covered;
+            self.cost = PartialCost::UNREACHED;
+        }
+        if wins_tie_break(cost.dist, assignments, self.cost.dist, &self.dist) {
+            self.cost.dist = cost.dist;
+            self.dist.copy_from_slice(assignments);
+        }
+        if wins_tie_break(cost.price, assignments, self.cost.price, &self.price) {
+            self.cost.price = cost.price;
+            self.price.copy_from_slice(assignments);
+        }
+        if wins_tie_break(cost.empty, assignments, self.cost.empty, &self.empty) {
+            self.cost.empty = cost.empty;
+            self.empty.copy_from_slice(assignments);
+        }
+        if wins_tie_break(cost.makespan, assignments, self.cost.makespan, &self.makespan) {
+            self.cost.makespan = cost.makespan;
+            self.makespan.copy_from_slice(assignments);
+        }
+        if wins_tie_break(cost.duration, assignments, self.cost.duration, &self.duration) {
+            self.cost.duration = cost.duration;
+            self.duration.copy_from_slice(assignments);
+        }
+        if wins_tie_break(cost.fleet, assignments, self.cost.fleet, &self.fleet) {
+            self.cost.fleet = cost.fleet;
+            self.fleet.copy_from_slice(assignments);
+        }
+    }
+
+    /// Folds in another worker's partials, ties going by `wins_tie_break`.
+    pub fn merge(&mut self, other: &PartialIncumbents) {
+        if other.covered < self.covered {
+            return;
+        }
+        if other.covered > self.covered {
+            self.covered = other.covered;
+            self.cost = PartialCost::UNREACHED;
+        }
+        for criterion in [
+            Criterion::Distance,
+            Criterion::Price,
+            Criterion::Empty,
+            Criterion::Makespan,
+            Criterion::Duration,
+            Criterion::Fleet,
+        ] {
+            let value = other.cost.get(criterion);
+            let only = PartialCost::from_fn(|c| if c == criterion { value } else { f64::INFINITY });
+            self.offer(other.covered, &only, other.for_criterion(criterion));
+        }
+    }
+}
+
+/// Search counters, plain increments so they stay cheap in the hot loops.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolverStats {
+    pub assignment_nodes: u64,
+    pub assignment_revisits_pruned: u64,
+    // Partial assignments cut by the incumbent check on entry
+    pub assignment_bound_prunes: u64,
+    // Order sets offered to a vehicle, whether or not they could be routed
+    pub submasks: u64,
+    pub complete_assignments: u64,
+    pub route_nodes: u64,
+    pub route_bound_prunes: u64,
+    pub memo_hits: u64,
+    pub memo_misses: u64,
+    // Routing results stored in this context's memo. Merging keeps the largest, not the sum
+    pub memo_entries: u64,
+}
+
+// PathBuffer holds two nodes per order
+pub const MAX_ROUTE_ORDERS: usize = 8;
+
+/// One bit per order of the problem, by index.
+pub type OrderMask = u64;
+
+// Orders a mask can tell apart, the limit for anything indexing orders by bit
+pub const MAX_MASK_ORDERS: usize = OrderMask::BITS as usize;
+
+/// Mask with the first `n_orders` bits set.
+#[inline(always)]
+pub fn full_mask(n_orders: usize) -> OrderMask {
+    OrderMask::MAX.checked_shr((MAX_MASK_ORDERS - n_orders) as u32).unwrap_or(0)
+}
+
+// Path node for a return to the vehicle's start. Nodes index orders of the whole problem, so they
+// are always below 2 * MAX_MASK_ORDERS = 128, clear of it
+pub const RELOAD_NODE: u8 = u8::MAX;
+const _: () = assert!(2 * MAX_MASK_ORDERS <= RELOAD_NODE as usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makespan_stays_the_longest_distance_under_the_time_model() {
+        let mut route = InternalBestResults::INVALID;
+        // The shortest route takes longer than the quickest one
+        route.min_dist.total_dist = 10.0;
+        route.min_dist.total_duration = 90.0;
+        route.min_duration.total_dist = 14.0;
+        route.min_duration.total_duration = 60.0;
+        let cost = PartialCost::from_fn(|_| 0.0).with_route(&route).with_route(&route);
+        assert_eq!(cost.makespan, 10.0);
+        assert_eq!(cost.duration, 120.0);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::models::{
+    AlgorithmSolution, AssignmentSuggestion, Criterion, DistanceMode, DistanceOptions, DistanceUnit, InsertionCost, InsertionEvaluation, LnsResult,
+    LocalSearchResult, ObjectiveWeights, Problem, ProblemSolution, RemovalEvaluation, RemovalSaving, SolutionReport, SolveOptions, Vehicle, VehicleRoute,
+    WeightedSolution,
+};
+use crate::utils::EARTH_RADIUS_KM;
+
+const KM_PER_MILE: f64 = 1.609344;
+
+/// Caller distance units per km as the solver measures it, on a sphere of `EARTH_RADIUS_KM`.
+///
+/// Every distance is the same angle times the radius, so a different radius or unit only scales
+/// them. Inputs are converted to solver km before the search and results back afterwards.
+/// Euclidean distances are in coordinate units whatever the radius and unit say.
+pub fn distance_scale(distance: Option<&DistanceOptions>) -> f64 {
+    let Some(distance) = distance else {
+        return 1.0;
+    };
+    if distance.mode == Some(DistanceMode::Euclidean) {
+        return 1.0;
+    }
+    let radius = distance.radius_km.unwrap_or(EARTH_RADIUS_KM) / EARTH_RADIUS_KM;
+    match distance.unit.unwrap_or(DistanceUnit::Km) {
+        DistanceUnit::Km => radius,
+        DistanceUnit::Miles => radius / KM_PER_MILE,
+    }
+}
+
+/// The `distance_scale` of `options.distance`.
+pub fn unit_scale(options: &SolveOptions) -> f64 {
+    distance_scale(options.distance.as_ref())
+}
+
+pub fn vehicle_to_base(vehicle: &mut Vehicle, scale: f64) {
+    vehicle.price_km *= scale;
+    vehicle.price_km_empty = vehicle.price_km_empty.map(|price| price * scale);
+    vehicle.co2_per_km = vehicle.co2_per_km.map(|co2| co2 * scale);
+    vehicle.co2_per_km_empty = vehicle.co2_per_km_empty.map(|co2| co2 * scale);
+    vehicle.speed_kmh = vehicle.speed_kmh.map(|speed| speed / scale);
+    vehicle.max_distance_km = vehicle.max_distance_km.map(|limit| limit / scale);
+}
+
+/// Weights per caller distance unit as weights per solver km, so weighted costs stay the same.
+pub fn weights_to_base(weights: ObjectiveWeights, scale: f64) -> ObjectiveWeights {
+    ObjectiveWeights {
+        distance: weights.distance.map(|weight| weight * scale),
+        empty: weights.empty.map(|weight| weight * scale),
+        ..weights
+    }
+}
+
+/// `options` with its distance caps and weights in solver km. The distance options themselves are
+/// kept, so results can be converted back.
+pub fn options_to_base(mut options: SolveOptions) -> SolveOptions {
+    let scale = unit_scale(&options);
+    options.max_route_spread_km = options.max_route_spread_km.map(|spread| spread / scale);
+    options.max_candidate_radius_km = options.max_candidate_radius_km.map(|radius| radius / scale);
+    options.weights = options.weights.map(|weights| weights_to_base(weights, scale));
+    options
+}
+
+/// `problem` with the distance-like inputs of its vehicles in solver km.
+pub fn problem_to_base(mut problem: Problem, scale: f64) -> Problem {
+    if scale != 1.0 {
+        for vehicle in &mut problem.vehicles {
+            vehicle_to_base(vehicle, scale);
+        }
+    }
+    problem
+}
+
+/// `problem` and `options` with every distance-like input in solver km.
+pub fn to_base(problem: Problem, options: SolveOptions) -> (Problem, SolveOptions) {
+    let scale = unit_scale(&options);
+    (problem_to_base(problem, scale), options_to_base(options))
+}
+
+pub fn route_from_base(route: &mut VehicleRoute, scale: f64) {
+    route.total_distance *= scale;
+    route.empty_distance *= scale;
+    route.return_distance *= scale;
+    for leg in &mut route.legs {
+        leg.distance *= scale;
+    }
+}
+
+pub fn solution_from_base(solution: &mut ProblemSolution, scale: f64) {
+    for route in solution.routes.values_mut().chain(solution.route_list.iter_mut().map(|entry| &mut entry.route)) {
+        route_from_base(route, scale);
+    }
+    solution.total_distance *= scale;
+    solution.empty_distance *= scale;
+    solution.route_spread *= scale;
+    solution.max_route_distance *= scale;
+}
+
+/// Converts every plan of a solve made on `to_base` inputs back to the units of `options`.
+pub fn algorithm_from_base(solution: AlgorithmSolution, options: &SolveOptions) -> AlgorithmSolution {
+    solution.rescaled(unit_scale(options))
+}
+
+/// A value of `criterion` measured in solver km, in caller units.
+pub fn criterion_from_base(value: f64, criterion: Criterion, scale: f64) -> f64 {
+    match criterion {
+        Criterion::Distance | Criterion::Empty | Criterion::Makespan => value * scale,
+        Criterion::Price | Criterion::Duration | Criterion::Fleet => value,
+    }
+}
+
+/// Results of a search in solver km, converted to caller units by multiplying every distance by
+/// `scale`. Prices, durations and emissions are the same in any unit. Plans passed in by the caller
+/// go the other way with `1.0 / scale`.
+pub trait Rescale {
+    fn rescaled(self, scale: f64) -> Self;
+}
+
+impl Rescale for ProblemSolution {
+    fn rescaled(mut self, scale: f64) -> Self {
+        solution_from_base(&mut self, scale);
+        self
+    }
+}
+
+impl Rescale for VehicleRoute {
+    fn rescaled(mut self, scale: f64) -> Self {
+        route_from_base(&mut self, scale);
+        self
+    }
+}
+
+impl Rescale for AlgorithmSolution {
+    fn rescaled(mut self, scale: f64) -> Self {
+        if scale != 1.0 {
+            for plan in [
+                &mut self.best_distance_solution,
+                &mut self.best_price_solution,
+                &mut self.best_empty_solution,
+                &mut self.best_makespan_solution,
+                &mut self.best_duration_solution,
+                &mut self.best_fleet_solution,
+            ]
+            .into_iter()
+            .chain(&mut self.alternatives)
+            {
+                solution_from_base(plan, scale);
+            }
+        }
+        self
+    }
+}
+
+impl Rescale for LnsResult {
+    fn rescaled(self, scale: f64) -> Self {
+        LnsResult { solution: self.solution.rescaled(scale), ..self }
+    }
+}
+
+impl Rescale for LocalSearchResult {
+    fn rescaled(mut self, scale: f64) -> Self {
+        for applied in &mut self.moves {
+            applied.saved_distance *= scale;
+        }
+        LocalSearchResult { solution: self.solution.rescaled(scale), saved_distance: self.saved_distance * scale, ..self }
+    }
+}
+
+impl Rescale for WeightedSolution {
+    fn rescaled(self, scale: f64) -> Self {
+        WeightedSolution { solution: self.solution.rescaled(scale), ..self }
+    }
+}
+
+impl Rescale for AssignmentSuggestion {
+    fn rescaled(self, scale: f64) -> Self {
+        AssignmentSuggestion {
+            estimated_distance: self.estimated_distance * scale,
+            estimated_empty_distance: self.estimated_empty_distance * scale,
+            ..self
+        }
+    }
+}
+
+impl Rescale for InsertionCost {
+    fn rescaled(mut self, scale: f64) -> Self {
+        for option in &mut self.options {
+            option.delta_distance *= scale;
+            option.delta_empty_distance *= scale;
+            option.route = option.route.take().map(|route| route.rescaled(scale));
+        }
+        self
+    }
+}
+
+impl Rescale for InsertionEvaluation {
+    fn rescaled(self, scale: f64) -> Self {
+        InsertionEvaluation {
+            delta_distance: self.delta_distance * scale,
+            delta_empty_distance: self.delta_empty_distance * scale,
+            solution: self.solution.rescaled(scale),
+            ..self
+        }
+    }
+}
+
+impl Rescale for RemovalEvaluation {
+    fn rescaled(self, scale: f64) -> Self {
+        RemovalEvaluation {
+            saved_distance: self.saved_distance * scale,
+            saved_empty_distance: self.saved_empty_distance * scale,
+            solution: self.solution.rescaled(scale),
+            ..self
+        }
+    }
+}
+
+impl Rescale for RemovalSaving {
+    fn rescaled(self, scale: f64) -> Self {
+        RemovalSaving {
+            saved_distance: self.saved_distance * scale,
+            saved_empty_distance: self.saved_empty_distance * scale,
+            route: self.route.rescaled(scale),
+            ..self
+        }
+    }
+}
+
+impl Rescale for SolutionReport {
+    fn rescaled(self, scale: f64) -> Self {
+        SolutionReport { total_distance: self.total_distance * scale, empty_distance: self.empty_distance * scale, ..self }
+    }
+}
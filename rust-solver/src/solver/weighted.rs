@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use crate::error::{Result, SolverError};
+use crate::models::{Criterion, DistanceOptions, ObjectiveWeights, Problem, WeightedSolution};
+use crate::utils::DistanceModel;
+use super::context::{SharedContext, SolverContext};
+use super::objective::{self, Objective};
+use super::units::{self, Rescale};
+use super::{reconstruct_solution, validate_distance_options, validate_problem};
+
+pub(super) fn validate_weights(weights: &ObjectiveWeights) -> Result<()> {
+    let fields = [("distance", weights.distance), ("price", weights.price), ("empty", weights.empty)];
+    let bad: Vec<String> = fields
+        .iter()
+        .filter_map(|&(name, w)| w.filter(|w| !w.is_finite() || *w < 0.0).map(|w| format!("{} = {}", name, w)))
+        .collect();
+    if bad.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::InvalidArg(format!("Weights must be finite and non-negative: {}", bad.join(", "))))
+    }
+}
+
+/// Exact search for the assignment and routes minimizing
+/// `distance * km + price * price + empty * empty km`, keeping a single incumbent at both levels.
+/// Weights of `(1, 0, 0)` give the same plan as `best_distance_solution` of `solve`.
+pub fn solve_weighted(problem: Problem, weights: ObjectiveWeights, distance: Option<&DistanceOptions>) -> Result<WeightedSolution> {
+    validate_distance_options(distance)?;
+    let model = DistanceModel::from_options(distance);
+    let scale = units::distance_scale(distance);
+    let problem = units::problem_to_base(problem, scale);
+    validate_problem(&problem, &model)?;
+    validate_weights(&weights)?;
+    let weights = units::weights_to_base(weights, scale);
+
+    let (w_dist, w_price, w_empty) = (
+        weights.distance.unwrap_or(0.0),
+        weights.price.unwrap_or(0.0),
+        weights.empty.unwrap_or(0.0),
+    );
+    let shared = SharedContext {
+        objective: Some(Objective::weighted(w_dist, w_price, w_empty)),
+        ..SharedContext::from_problem(problem, None, model)
+    };
+    let mut ctx = SolverContext::from_shared(Arc::new(shared));
+    let (assignments, cost) = objective::search(&mut ctx);
+    let solution = reconstruct_solution(&mut ctx, &assignments, Criterion::Distance);
+
+    Ok(WeightedSolution {
+        distance_cost: w_dist * solution.total_distance,
+        price_cost: w_price * solution.total_price,
+        empty_cost: w_empty * solution.empty_distance,
+        cost: w_dist * solution.total_distance + w_price * solution.total_price + w_empty * solution.empty_distance,
+        feasible: cost.is_some(),
+        solution,
+    }
+    .rescaled(scale))
+}
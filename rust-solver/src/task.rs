@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{Env, Error, Result, Status, Task};
+use napi_derive::napi;
+
+use crate::error::catch_panic;
+use crate::models::{AlgorithmSolution, Problem, SolveOptions};
+use crate::solver;
+use crate::solver::context::ImprovementFn;
+
+/// A solve run on the libuv thread pool, resolving the promise returned by `solveBruteForceAsync`.
+pub struct SolveTask {
+    // Owned copies, converted from JS before the task is queued
+    pub problem: Option<Problem>,
+    pub options: SolveOptions,
+    pub cancel: Option<Arc<AtomicBool>>,
+    pub on_improvement: Option<ImprovementFn>,
+}
+
+#[napi]
+impl Task for SolveTask {
+    type Output = AlgorithmSolution;
+    type JsValue = AlgorithmSolution;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let mut problem = self
+            .problem
+            .take()
+            .ok_or_else(|| Error::new(Status::GenericFailure, "Solve task was already run".to_string()))?;
+        let options = std::mem::take(&mut self.options);
+        let cancel = self.cancel.clone();
+        let on_improvement = self.on_improvement.take();
+
+        // A panic would otherwise unwind into the thread pool and abort the process
+        Ok(catch_panic(|| {
+            solver::normalize_locations(problem.locations_mut(), options.distance.as_ref())?;
+            solver::solve_cancellable(problem, options, cancel, on_improvement)
+        })?)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Cancels the async solves it was passed to. A cancelled solve rejects with an error whose
+/// `code` is `"Cancelled"`; cancelling once a solve has finished does nothing.
+#[napi]
+#[derive(Default)]
+pub struct CancellationToken {
+    pub(crate) flag: Arc<AtomicBool>,
+}
+
+#[napi]
+impl CancellationToken {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[napi]
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    #[napi(getter)]
+    pub fn cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
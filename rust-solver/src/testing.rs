@@ -0,0 +1,57 @@
+//! Problem builders for the unit tests.
+
+use crate::generator::generate_problem;
+use crate::models::{GeneratorOptions, Location, Order, Problem, Vehicle};
+
+pub fn place(hash: &str, latitude: f64, longitude: f64) -> Location {
+    Location { hash: hash.to_string(), latitude, longitude }
+}
+
+/// A vehicle at `start` charging 1 per km, without any limits.
+pub fn vehicle(id: u32, start: Location) -> Vehicle {
+    Vehicle {
+        id,
+        start_location: start,
+        price_km: 1.0,
+        price_km_empty: None,
+        stop_fee: None,
+        co2_per_km: None,
+        co2_per_km_empty: None,
+        max_trips: None,
+        allow_multi_trip: None,
+        speed_kmh: None,
+        min_route_price: None,
+        capacity: None,
+        end_location: None,
+        tags: None,
+        max_distance_km: None,
+        max_duration_min: None,
+        max_orders: None,
+        onboard_order_ids: None,
+    }
+}
+
+/// An order filling a whole vehicle, without windows or service times.
+pub fn order(id: u32, pickup: Location, delivery: Location) -> Order {
+    Order {
+        id,
+        pickup_location: pickup,
+        delivery_location: delivery,
+        load_factor: 1.0,
+        demand: None,
+        pickup_window: None,
+        delivery_window: None,
+        pickup_service_min: None,
+        delivery_service_min: None,
+        required_tags: None,
+    }
+}
+
+pub fn problem(vehicles: Vec<Vehicle>, orders: Vec<Order>) -> Problem {
+    Problem { vehicles, orders, forbidden_legs: None, fixed_assignments: None, same_vehicle_groups: None, conflict_pairs: None }
+}
+
+/// The same problem for the same seed, the default `generate_problem` area and ranges.
+pub fn random_problem(seed: u32, n_vehicles: u32, n_orders: u32) -> Problem {
+    generate_problem(GeneratorOptions { n_orders, n_vehicles, seed: Some(seed), ..Default::default() }).unwrap()
+}
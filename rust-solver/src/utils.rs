@@ -1,20 +1,134 @@
-use crate::models::Location;
+use crate::models::{DistanceMode, DistanceOptions, Location};
 use std::f64::consts::PI;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Radius every distance is measured on, `DistanceOptions` rescale results to another one.
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
 
 #[inline(always)]
 fn to_radians(degrees: f64) -> f64 {
     degrees * (PI / 180.0)
 }
 
+/// Location with the trigonometry that only depends on one endpoint done upfront,
+/// so building a distance matrix costs two `sin`, one `sqrt` and one `asin` per cell.
+#[derive(Clone, Copy, Debug)]
+pub struct PreparedLocation {
+    lat: f64,
+    cos_lat: f64,
+    lon: f64,
+    // Digest of `Location::hash`, the hash names the place whatever noise is in its coordinates
+    place: u64,
+    // Plane coordinates in `lat` and `lon`, measured with `hypot`
+    planar: bool,
+    // Road detour factor around this location, a leg takes the mean of its two ends
+    circuity: f64,
+}
+
+impl PreparedLocation {
+    pub fn new(location: &Location) -> Self {
+        Self::in_mode(location, DistanceMode::GreatCircle)
+    }
+
+    /// Prepared for `mode`, both ends of a distance must be prepared for the same one.
+    pub fn in_mode(location: &Location, mode: DistanceMode) -> Self {
+        let mut hasher = DefaultHasher::new();
+        location.hash.hash(&mut hasher);
+        let place = hasher.finish();
+        match mode {
+            DistanceMode::GreatCircle => {
+                let lat = to_radians(location.latitude);
+                PreparedLocation {
+                    lat,
+                    cos_lat: lat.cos(),
+                    lon: to_radians(location.longitude),
+                    place,
+                    planar: false,
+                    circuity: 1.0,
+                }
+            }
+            DistanceMode::Euclidean => PreparedLocation {
+                lat: location.latitude,
+                cos_lat: 1.0,
+                lon: location.longitude,
+                place,
+                planar: true,
+                circuity: 1.0,
+            },
+        }
+    }
+}
+
+/// How a search measures distances: the formula, then the factor roads add on top of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceModel {
+    pub mode: DistanceMode,
+    pub circuity: f64,
+    // Circuity overrides by location hash prefix, longest prefix first
+    pub regions: Vec<(String, f64)>,
+}
+
+impl Default for DistanceModel {
+    fn default() -> Self {
+        DistanceModel {
+            mode: DistanceMode::GreatCircle,
+            circuity: 1.0,
+            regions: Vec::new(),
+        }
+    }
+}
+
+impl DistanceModel {
+    pub fn from_options(options: Option<&DistanceOptions>) -> Self {
+        let Some(options) = options else {
+            return Self::default();
+        };
+        let mut regions: Vec<(String, f64)> =
+            options.circuity_by_prefix.iter().flatten().map(|(prefix, &factor)| (prefix.clone(), factor)).collect();
+        // Two distinct prefixes of one length never both match, so only the length matters
+        regions.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        DistanceModel {
+            mode: options.mode.unwrap_or(DistanceMode::GreatCircle),
+            circuity: options.circuity.unwrap_or(1.0),
+            regions,
+        }
+    }
+
+    /// Whether distances are plain great-circle km, the only ones the distance cache holds.
+    pub fn is_great_circle(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn prepare(&self, location: &Location) -> PreparedLocation {
+        PreparedLocation {
+            circuity: self.circuity_at(&location.hash),
+            ..PreparedLocation::in_mode(location, self.mode)
+        }
+    }
+
+    fn circuity_at(&self, hash: &str) -> f64 {
+        self.regions
+            .iter()
+            .find(|(prefix, _)| hash.starts_with(prefix.as_str()))
+            .map_or(self.circuity, |&(_, factor)| factor)
+    }
+}
+
+/// Haversine or Euclidean distance in km by the locations' mode, times their mean circuity, 0.0 on a shared hash.
 #[inline(always)]
-pub fn calculate_distance(from: &Location, to: &Location) -> f64 {
-    let lat1 = to_radians(from.latitude);
-    let lon1 = to_radians(from.longitude);
-    let lat2 = to_radians(to.latitude);
-    let lon2 = to_radians(to.longitude);
-
-    let val = (lat1.sin() * lat2.sin()) + (lat1.cos() * lat2.cos() * (lon1 - lon2).cos());
-    let clamped = if val > 1.0 { 1.0 } else if val < -1.0 { -1.0 } else { val };
-    
-    clamped.acos() * 6371.0
+pub fn prepared_distance(from: &PreparedLocation, to: &PreparedLocation) -> f64 {
+    if from.place == to.place {
+        return 0.0;
+    }
+    let distance = if from.planar {
+        (to.lat - from.lat).hypot(to.lon - from.lon)
+    } else {
+        let half_lat = ((to.lat - from.lat) / 2.0).sin();
+        let half_lon = ((to.lon - from.lon) / 2.0).sin();
+        let h = half_lat * half_lat + from.cos_lat * to.cos_lat * half_lon * half_lon;
+        2.0 * h.clamp(0.0, 1.0).sqrt().asin() * EARTH_RADIUS_KM
+    };
+
+    distance * ((from.circuity + to.circuity) * 0.5)
 }
+
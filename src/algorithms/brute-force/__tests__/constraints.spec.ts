@@ -0,0 +1,633 @@
+/**
+ * @description
+ * Rules limiting which vehicle may serve which orders and how routes may run.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    Criterion,
+    routeAssignment,
+    solveBruteForce,
+    solveBruteForceWithOptions,
+} from 'rust-solver';
+
+import { AlgorithmSolution, Order, Problem, ProblemSolution } from '../../../types';
+import { criteria, describeAssignment, randomProblem, TOLERANCE } from '../testProblems';
+
+describe('unassigned orders', () => {
+    test('are all reported without vehicles', () => {
+        const problem = randomProblem(0, 0, 3);
+        const solution = solveBruteForce(problem);
+        expect(solution.feasible).toBe(false);
+        expect(solution.bestDistanceSolution.unassignedOrderIds.sort()).toEqual([1, 2, 3]);
+        expect(solution.bestDistanceSolution.routes).toEqual({});
+    });
+
+    test('reject an order too large for any vehicle', () => {
+        const problem = randomProblem(0, 2, 2);
+        problem.orders[1].loadFactor = 0.5;
+        expect(() => solveBruteForce(problem)).toThrow(/load_factor below 1/);
+    });
+
+    test('leave out only what cannot be served', () => {
+        const problem = randomProblem(1, 2, 4);
+        const target = problem.orders[0].pickupLocation.hash;
+        const hashes = [
+            ...problem.vehicles.map(v => v.startLocation.hash),
+            ...problem.orders.flatMap(o => [o.pickupLocation.hash, o.deliveryLocation.hash]),
+        ];
+        const unreachable = {
+            ...problem,
+            forbiddenLegs: hashes.filter(hash => hash !== target).map(fromHash => ({ fromHash, toHash: target })),
+        };
+        const solution = solveBruteForce(unreachable);
+        const rest = solveBruteForce({ ...problem, orders: problem.orders.slice(1) });
+        expect(solution.feasible).toBe(false);
+        for (const { solution: key, metric } of criteria) {
+            expect(solution[key].unassignedOrderIds).toEqual([problem.orders[0].id]);
+            expect(solution[key][metric]).toBeCloseTo(rest[key][metric], 9);
+        }
+    });
+});
+
+describe('time windows', () => {
+    test('a tight pickup window moves its order to the front', () => {
+        const problem = randomProblem(3, 1, 3);
+        const route = (orders: Order[]) => solveBruteForce({ ...problem, orders }).bestDistanceSolution.routes['1'];
+        const free = route(problem.orders);
+        const lastPickup = free.stops.filter(stop => stop.type === 'pickup').pop()!.orderId;
+        const order = problem.orders.find(o => o.id === lastPickup)!;
+        // Served straight from the start is the earliest that pickup can ever be reached
+        const earliestArrival = route([order]).stops[0].arrivalTime;
+
+        const tight = route(
+            problem.orders.map(o => (o === order ? { ...o, pickupWindow: { latest: earliestArrival + 1e-6 } } : o)),
+        );
+        expect(tight.stops[0]).toMatchObject({ orderId: lastPickup, type: 'pickup' });
+        expect(tight.totalDistance).toBeGreaterThanOrEqual(free.totalDistance - TOLERANCE);
+    });
+
+    test('early arrivals wait for the window to open', () => {
+        const problem = randomProblem(4, 1, 1);
+        const free = solveBruteForce(problem).bestDistanceSolution.routes['1'];
+        const opensAt = free.stops[0].arrivalTime + 30;
+        const order = { ...problem.orders[0], pickupWindow: { earliest: opensAt } };
+        const route = solveBruteForce({ ...problem, orders: [order] }).bestDistanceSolution.routes['1'];
+        expect(route.stops[0].arrivalTime).toBeCloseTo(opensAt, 9);
+        expect(route.waitingTime).toBeCloseTo(30, 9);
+        expect(route.totalDurationMin).toBeCloseTo(free.totalDurationMin + 30, 9);
+    });
+
+    test('leave an order unassigned when no vehicle can make its window', () => {
+        const problem = randomProblem(5, 2, 3);
+        problem.orders[0].deliveryWindow = { latest: 0 };
+        const solution = solveBruteForce(problem);
+        expect(solution.feasible).toBe(false);
+        expect(solution.bestDistanceSolution.unassignedOrderIds).toEqual([problem.orders[0].id]);
+    });
+});
+
+describe('service times', () => {
+    test('add to the duration and push later stops back', () => {
+        const problem = randomProblem(6, 1, 3);
+        const plain = solveBruteForce(problem).bestDistanceSolution.routes['1'];
+        const orders = problem.orders.map(o => ({ ...o, pickupServiceMin: 10, deliveryServiceMin: 5 }));
+        const route = solveBruteForce({ ...problem, orders }).bestDistanceSolution.routes['1'];
+        expect(route.totalDistance).toBeCloseTo(plain.totalDistance, 9);
+        expect(route.totalDurationMin).toBeCloseTo(plain.totalDurationMin + orders.length * 15, 9);
+        route.stops.forEach((stop, i) => {
+            expect(stop.departureTime - stop.arrivalTime).toBeCloseTo(stop.type === 'pickup' ? 10 : 5, 9);
+            if (i > 0) expect(stop.arrivalTime).toBeGreaterThan(route.stops[i - 1].departureTime);
+        });
+    });
+});
+
+describe('vehicle capacity', () => {
+    const mixedFleet = (seed: number) => {
+        const problem = randomProblem(seed, 2, 5);
+        problem.vehicles[0].capacity = 10;
+        problem.vehicles[1].capacity = 2;
+        problem.orders.forEach((order, i) => (order.demand = [1, 3, 2, 4, 1][i]));
+        return problem;
+    };
+    const peakLoad = (problem: Problem, route: ProblemSolution['routes'][string]) => {
+        let load = 0;
+        let peak = 0;
+        for (const stop of route.stops) {
+            const demand = problem.orders.find(o => o.id === stop.orderId)!.demand!;
+            load += stop.type === 'pickup' ? demand : -demand;
+            peak = Math.max(peak, load);
+        }
+        return peak;
+    };
+
+    test('a 10-pallet truck and a 2-pallet van each stay within their own capacity', () => {
+        const problem = mixedFleet(7);
+        const solution = solveBruteForce(problem);
+        expect(solution.feasible).toBe(true);
+        for (const { solution: key } of criteria) {
+            const { routes } = solution[key];
+            if (routes['1']) expect(peakLoad(problem, routes['1'])).toBeLessThanOrEqual(10);
+            if (routes['2']) {
+                expect(peakLoad(problem, routes['2'])).toBeLessThanOrEqual(2);
+                // Orders of 3 and 4 pallets can only ever ride the truck
+                expect(routes['2'].stops.some(stop => stop.orderId === 2 || stop.orderId === 4)).toBe(false);
+            }
+        }
+    });
+
+    test('an order larger than every vehicle is left unassigned', () => {
+        const problem = mixedFleet(8);
+        problem.orders[0].demand = 12;
+        const solution = solveBruteForce(problem);
+        expect(solution.feasible).toBe(false);
+        expect(solution.bestDistanceSolution.unassignedOrderIds).toEqual([1]);
+    });
+
+    test('falls back to load factors without demands', () => {
+        const problem = randomProblem(9, 2, 4);
+        const withCapacity = { ...problem, vehicles: problem.vehicles.map(v => ({ ...v, capacity: 10 })) };
+        expect(solveBruteForce(withCapacity).bestDistanceSolution.totalDistance).toBeCloseTo(
+            solveBruteForce(problem).bestDistanceSolution.totalDistance,
+            9,
+        );
+    });
+
+    test('rejects a non-positive capacity', () => {
+        const problem = mixedFleet(7);
+        problem.vehicles[1].capacity = 0;
+        expect(() => solveBruteForce(problem)).toThrow(/capacity on vehicles 2/);
+    });
+});
+
+describe('end location', () => {
+    const at = (hash: string, longitude: number) => ({ hash, latitude: 54, longitude });
+    // Order 1 heads back west past the start, order 2 is a short hop east
+    const problem: Problem = {
+        vehicles: [{ id: 1, startLocation: at('start', 23), priceKm: 1 }],
+        orders: [
+            { id: 1, pickupLocation: at('p1', 23.1), deliveryLocation: at('d1', 22.5), loadFactor: 2 },
+            { id: 2, pickupLocation: at('p2', 23.2), deliveryLocation: at('d2', 23.3), loadFactor: 2 },
+        ],
+    };
+    const stopOrder = (solution: AlgorithmSolution) =>
+        solution.bestDistanceSolution.routes['1'].stops.map(stop => `${stop.type[0]}${stop.orderId}`);
+
+    test('the return leg changes the optimal stop order', () => {
+        expect(stopOrder(solveBruteForce(problem)).at(-1)).toBe('d1');
+
+        const homeward = {
+            ...problem,
+            vehicles: problem.vehicles.map(v => ({ ...v, endLocation: at('depot', 23.3) })),
+        };
+        const solution = solveBruteForce(homeward);
+        expect(stopOrder(solution)).toEqual(['p1', 'd1', 'p2', 'd2']);
+
+        const route = solution.bestDistanceSolution.routes['1'];
+        expect(route.returnDistance).toBeCloseTo(0, 9);
+    });
+
+    test('counts the return leg as priced empty distance', () => {
+        const homeward = {
+            ...problem,
+            vehicles: problem.vehicles.map(v => ({ ...v, endLocation: at('depot', 22.4), priceKm: 2 })),
+        };
+        const plain = solveBruteForce({ ...homeward, vehicles: [{ ...homeward.vehicles[0], endLocation: undefined }] });
+        const route = solveBruteForce(homeward).bestDistanceSolution.routes['1'];
+        const before = plain.bestDistanceSolution.routes['1'];
+        expect(route.returnDistance).toBeGreaterThan(0);
+        expect(route.totalDistance).toBeCloseTo(before.totalDistance + route.returnDistance, 9);
+        expect(route.emptyDistance).toBeCloseTo(before.emptyDistance + route.returnDistance, 9);
+        expect(route.totalPrice).toBeCloseTo(route.totalDistance * 2, 9);
+    });
+});
+
+describe('fixed assignments', () => {
+    test('a pinned order rides on its vehicle in every solution', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = { ...randomProblem(seed, 3, 4), fixedAssignments: { '1': 2 } };
+            const solution = solveBruteForce(problem);
+            for (const plan of [
+                solution.bestDistanceSolution,
+                solution.bestPriceSolution,
+                solution.bestEmptySolution,
+            ]) {
+                expect(plan.routes['2'].stops.some(stop => stop.orderId === 1)).toBe(true);
+            }
+        }
+    });
+
+    test('reports a pin the vehicle cannot carry', () => {
+        const problem = randomProblem(0, 2, 2);
+        problem.vehicles[1].capacity = 1;
+        problem.orders[0].demand = 2;
+        expect(() => solveBruteForce({ ...problem, fixedAssignments: { '1': 2 } })).toThrow(
+            /order 1 is fixed to vehicle 2, which exceeds its capacity/,
+        );
+    });
+});
+
+describe('vehicle tags', () => {
+    test('orders needing a tail-lift only ride on vehicles that have one', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 3, 4);
+            problem.vehicles[1].tags = ['tail-lift'];
+            problem.orders[0].requiredTags = ['tail-lift'];
+            const solution = solveBruteForce(problem);
+            for (const plan of [solution.bestDistanceSolution, solution.bestPriceSolution, solution.bestEmptySolution]) {
+                expect(plan.routes['2'].stops.some(stop => stop.orderId === 1)).toBe(true);
+            }
+        }
+    });
+
+    test('removing the only matching tag makes the problem infeasible', () => {
+        const problem = randomProblem(0, 2, 3);
+        problem.orders[2].requiredTags = ['refrigerated'];
+        expect(() => solveBruteForce(problem)).toThrow(/No vehicle has every tag required by orders 3/);
+    });
+});
+
+describe('order pairing', () => {
+    const sharesVehicle = (plan: ProblemSolution, a: number, b: number) =>
+        Object.values(plan.routes).some(
+            route => route.stops.some(stop => stop.orderId === a) && route.stops.some(stop => stop.orderId === b),
+        );
+
+    test('grouped orders share a vehicle and conflicting orders never do', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 3, 4);
+            const grouped = solveBruteForce({ ...problem, sameVehicleGroups: [[1, 2]] });
+            const split = solveBruteForce({ ...problem, conflictPairs: [{ firstOrderId: 1, secondOrderId: 2 }] });
+            for (const plan of [grouped.bestDistanceSolution, grouped.bestPriceSolution, grouped.bestEmptySolution]) {
+                expect(sharesVehicle(plan, 1, 2)).toBe(true);
+            }
+            for (const plan of [split.bestDistanceSolution, split.bestPriceSolution, split.bestEmptySolution]) {
+                expect(sharesVehicle(plan, 1, 2)).toBe(false);
+            }
+        }
+    });
+
+    test('rejects a conflict inside a group', () => {
+        const problem = {
+            ...randomProblem(0, 2, 3),
+            sameVehicleGroups: [[1, 2], [2, 3]],
+            conflictPairs: [{ firstOrderId: 1, secondOrderId: 3 }],
+        };
+        expect(() => solveBruteForce(problem)).toThrow(/orders 1 and 3 must share a vehicle and may not/);
+    });
+});
+
+describe('route limits', () => {
+    test('a distance limit below the longest route caps every route', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            const limit = solveBruteForce(problem).bestDistanceSolution.maxRouteDistance * 0.8;
+            problem.vehicles.forEach(vehicle => (vehicle.maxDistanceKm = limit));
+            const solution = solveBruteForce(problem);
+            for (const plan of [solution.bestDistanceSolution, solution.bestPriceSolution, solution.bestEmptySolution]) {
+                for (const route of Object.values(plan.routes)) {
+                    expect(route.totalDistance).toBeLessThanOrEqual(limit + 1e-9);
+                }
+            }
+        }
+    });
+
+    test('reports orders no vehicle can serve within its limit', () => {
+        const problem = randomProblem(0, 2, 2);
+        problem.vehicles.forEach(vehicle => (vehicle.maxDistanceKm = 0.001));
+        const solution = solveBruteForce(problem);
+        expect(solution.feasible).toBe(false);
+        expect(solution.unassignableOrders.map(order => order.orderId)).toEqual([1, 2]);
+        expect(solution.unassignableOrders[0].reason).toMatch(/max_distance_km/);
+    });
+});
+
+describe('route spread cap', () => {
+    const at = (hash: string, longitude: number) => ({ hash, latitude: 54, longitude });
+    const order = (id: number, from: number, to: number): Order => ({
+        id,
+        pickupLocation: at(`p${id}`, from),
+        deliveryLocation: at(`d${id}`, to),
+        loadFactor: 2,
+    });
+
+    test('a cap below the optimal spread hands both orders to one vehicle', () => {
+        // Each vehicle serving the order at its start drives 6.5 and 32.7 km
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('a', 23), priceKm: 1 },
+                { id: 2, startLocation: at('b', 24), priceKm: 1 },
+            ],
+            orders: [order(1, 23, 23.1), order(2, 24, 24.5)],
+        };
+        const free = solveBruteForce(problem).bestDistanceSolution;
+        expect(free.routeSpread).toBeCloseTo(26.14, 2);
+
+        const capped = solveBruteForceWithOptions(problem, { maxRouteSpreadKm: 10 });
+        expect(capped.feasible).toBe(true);
+        for (const plan of [capped.bestDistanceSolution, capped.bestPriceSolution]) {
+            expect(Object.keys(plan.routes)).toEqual(['1']);
+            expect(plan.routeSpread).toBe(0);
+        }
+        expect(capped.bestDistanceSolution.totalDistance).toBeGreaterThan(free.totalDistance);
+    });
+
+    test('pruning partial assignments keeps the shortest plan within the cap', () => {
+        let checked = 0;
+        for (let seed = 0; seed < 10; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            const free = solveBruteForce(problem).bestDistanceSolution;
+            if (free.routeSpread === 0) continue;
+            const cap = free.routeSpread / 2;
+
+            // Every split of the orders between the two vehicles
+            let shortest = Infinity;
+            for (let split = 0; split < 1 << problem.orders.length; split++) {
+                const assignments: Record<string, number[]> = {};
+                problem.orders.forEach((o, i) => (assignments[(split >> i) & 1 ? '2' : '1'] ??= []).push(o.id));
+                const plan = routeAssignment(problem, assignments, Criterion.Distance);
+                if (plan.routeSpread <= cap + TOLERANCE) shortest = Math.min(shortest, plan.totalDistance);
+            }
+
+            const capped = solveBruteForceWithOptions(problem, { maxRouteSpreadKm: cap }).bestDistanceSolution;
+            expect(capped.routeSpread).toBeLessThanOrEqual(cap + TOLERANCE);
+            expect(capped.totalDistance).toBeCloseTo(shortest, 6);
+            checked++;
+        }
+        expect(checked).toBeGreaterThan(0);
+    });
+
+    test('reports the plan with the smallest spread when none meets the cap', () => {
+        // One order per vehicle, so both always drive and neither split gets within 10 km
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('a', 23), priceKm: 1, maxOrders: 1 },
+                { id: 2, startLocation: at('b', 25), priceKm: 1, maxOrders: 1 },
+            ],
+            orders: [order(1, 23, 23.1), order(2, 24, 24.3)],
+        };
+        const free = solveBruteForce(problem).bestDistanceSolution;
+        expect(free.routes['1'].stops[0].orderId).toBe(1);
+
+        const capped = solveBruteForceWithOptions(problem, { maxRouteSpreadKm: 10 });
+        expect(capped.feasible).toBe(false);
+        const plan = capped.bestDistanceSolution;
+        expect(plan.unassignedOrderIds).toEqual([]);
+        // Swapping the orders drives further, but narrows the spread from 78.4 to 52.3 km
+        expect(plan.routes['1'].stops[0].orderId).toBe(2);
+        expect(plan.routeSpread).toBeCloseTo(52.28, 2);
+        expect(plan.totalDistance).toBeGreaterThan(free.totalDistance);
+    });
+});
+
+describe('required vehicles', () => {
+    const at = (hash: string, longitude: number) => ({ hash, latitude: 54, longitude });
+    // One vehicle serving both orders back to back is shortest, 13.1 km against 16.3 km for two
+    const problem: Problem = {
+        vehicles: [
+            { id: 1, startLocation: at('a', 23), priceKm: 1 },
+            { id: 2, startLocation: at('b', 23.05), priceKm: 1 },
+        ],
+        orders: [
+            { id: 1, pickupLocation: at('p1', 23), deliveryLocation: at('d1', 23.1), loadFactor: 2 },
+            { id: 2, pickupLocation: at('p2', 23.1), deliveryLocation: at('d2', 23.2), loadFactor: 2 },
+        ],
+    };
+
+    test('put the parked vehicle to work', () => {
+        const free = solveBruteForce(problem).bestDistanceSolution;
+        expect(Object.keys(free.routes)).toEqual(['1']);
+
+        const required = solveBruteForceWithOptions(problem, { requireAllVehicles: true });
+        expect(required.feasible).toBe(true);
+        for (const plan of [required.bestDistanceSolution, required.bestPriceSolution, required.bestEmptySolution]) {
+            expect(Object.keys(plan.routes).sort()).toEqual(['1', '2']);
+        }
+        expect(required.bestDistanceSolution.totalDistance).toBeGreaterThan(free.totalDistance);
+    });
+
+    test('reject fewer orders than vehicles', () => {
+        expect(() =>
+            solveBruteForceWithOptions({ ...problem, orders: problem.orders.slice(1) }, { requireAllVehicles: true }),
+        ).toThrow(/Every vehicle must be used, but there are 1 orders for 2 vehicles/);
+    });
+
+    test('leave the plan infeasible when a vehicle may serve none of the orders', () => {
+        const tagged: Problem = {
+            vehicles: [{ ...problem.vehicles[0], tags: ['tail-lift'] }, problem.vehicles[1]],
+            orders: problem.orders.map(order => ({ ...order, requiredTags: ['tail-lift'] })),
+        };
+        expect(solveBruteForce(tagged).feasible).toBe(true);
+        expect(solveBruteForceWithOptions(tagged, { requireAllVehicles: true }).feasible).toBe(false);
+    });
+});
+
+describe('minimum route price', () => {
+    const at = (hash: string, longitude: number) => ({ hash, latitude: 54, longitude });
+    // Each vehicle has a 3.3 km order at its start
+    const problem: Problem = {
+        vehicles: [
+            { id: 1, startLocation: at('a', 23), priceKm: 1 },
+            { id: 2, startLocation: at('b', 23.3), priceKm: 1 },
+        ],
+        orders: [
+            { id: 1, pickupLocation: at('p1', 23), deliveryLocation: at('d1', 23.05), loadFactor: 2 },
+            { id: 2, pickupLocation: at('p2', 23.3), deliveryLocation: at('d2', 23.35), loadFactor: 2 },
+        ],
+    };
+
+    test('tops up a route below the minimum with more orders', () => {
+        const free = solveBruteForce(problem);
+        expect(free.bestPriceSolution.routes['1'].totalPrice).toBeLessThan(5);
+
+        const minimum = solveBruteForce({ ...problem, vehicles: [{ ...problem.vehicles[0], minRoutePrice: 5 }, problem.vehicles[1]] });
+        expect(minimum.feasible).toBe(true);
+        for (const plan of [minimum.bestDistanceSolution, minimum.bestPriceSolution]) {
+            expect(Object.keys(plan.routes)).toEqual(['1']);
+            expect(plan.routes['1'].totalPrice).toBeGreaterThanOrEqual(5);
+        }
+        expect(minimum.bestPriceSolution.totalPrice).toBeGreaterThan(free.bestPriceSolution.totalPrice);
+    });
+
+    test('leaves orders unassigned when no route reaches the minimum', () => {
+        const solution = solveBruteForce({ ...problem, vehicles: [{ ...problem.vehicles[0], minRoutePrice: 1000 }] });
+        expect(solution.feasible).toBe(false);
+        expect(solution.bestPriceSolution.routes).toEqual({});
+        expect(solution.bestPriceSolution.unassignedOrderIds.sort()).toEqual([1, 2]);
+    });
+});
+
+describe('forbidden legs', () => {
+    const at = (hash: string, longitude: number) => ({ hash, latitude: 54, longitude });
+    const problem: Problem = {
+        vehicles: [{ id: 1, startLocation: at('start', 23), priceKm: 1 }],
+        orders: [
+            { id: 1, pickupLocation: at('p1', 23.1), deliveryLocation: at('d1', 23.2), loadFactor: 2 },
+            { id: 2, pickupLocation: at('p2', 23.3), deliveryLocation: at('d2', 23.4), loadFactor: 2 },
+        ],
+    };
+    const stopOrder = (plan: ProblemSolution) => plan.routes['1'].stops.map(stop => `${stop.type[0]}${stop.orderId}`);
+
+    test('route around a forbidden leg', () => {
+        expect(stopOrder(solveBruteForce(problem).bestDistanceSolution)).toEqual(['p1', 'd1', 'p2', 'd2']);
+
+        const detour = solveBruteForce({ ...problem, forbiddenLegs: [{ fromHash: 'd1', toHash: 'p2' }] });
+        expect(detour.feasible).toBe(true);
+        expect(stopOrder(detour.bestDistanceSolution)).toEqual(['p1', 'p2', 'd1', 'd2']);
+        expect(detour.bestDistanceSolution.totalDistance).toBeCloseTo(39.215, 3);
+    });
+
+    test('report orders that cannot share a vehicle across them', () => {
+        const [first, second] = [['p1', 'd1'], ['p2', 'd2']];
+        const forbiddenLegs = first.flatMap(a => second.flatMap(b => [{ fromHash: a, toHash: b }, { fromHash: b, toHash: a }]));
+        const solution = solveBruteForce({ ...problem, forbiddenLegs });
+        expect(solution.feasible).toBe(false);
+        expect(stopOrder(solution.bestDistanceSolution)).toEqual(['p1', 'd1']);
+        expect(solution.unassignableOrders).toEqual([
+            { orderId: 2, reason: 'No vehicle can serve the order together with the other orders' },
+        ]);
+    });
+});
+
+describe('candidate radius', () => {
+    const at = (hash: string, longitude: number) => ({ hash, latitude: 54, longitude });
+    // The cheap vehicle starts 26 km from the pickup, the dear one 6.5 km
+    const vehicles = [
+        { id: 1, startLocation: at('cheap', 23), priceKm: 0.5 },
+        { id: 2, startLocation: at('dear', 23.5), priceKm: 2 },
+    ];
+    const far = { id: 1, pickupLocation: at('p1', 23.4), deliveryLocation: at('d1', 23.6), loadFactor: 1 };
+
+    test('keeps a far vehicle off the order even when it is cheaper', () => {
+        const problem: Problem = { vehicles, orders: [far] };
+        const free = solveBruteForce(problem);
+        expect(Object.keys(free.bestPriceSolution.routes)).toEqual(['1']);
+        expect(free.stats.filteredCandidatePairs).toBe(0);
+
+        const filtered = solveBruteForceWithOptions(problem, { maxCandidateRadiusKm: 10 });
+        expect(Object.keys(filtered.bestPriceSolution.routes)).toEqual(['2']);
+        expect(filtered.bestPriceSolution.totalPrice).toBeGreaterThan(free.bestPriceSolution.totalPrice);
+        expect(filtered.stats.filteredCandidatePairs).toBe(1);
+    });
+
+    test('grows a radius that would leave an order without vehicles', () => {
+        // 1 km reaches neither pickup, so it grows to 6.5 km, just enough for each order's nearest vehicle
+        const near = { id: 2, pickupLocation: at('p2', 23.1), deliveryLocation: at('d2', 23.2), loadFactor: 1 };
+        const problem: Problem = { vehicles, orders: [far, near] };
+        const solution = solveBruteForceWithOptions(problem, { maxCandidateRadiusKm: 1 });
+        expect(solution.feasible).toBe(true);
+        expect(solution.stats.filteredCandidatePairs).toBe(2);
+        expect(describeAssignment(solution.bestPriceSolution)).toBe('1:2 2:1');
+    });
+});
+
+describe('order cap', () => {
+    test('max_orders of 1 gives every order its own vehicle', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 4, 3);
+            problem.vehicles.forEach(vehicle => (vehicle.maxOrders = 1));
+            const solution = solveBruteForce(problem);
+            for (const plan of [solution.bestDistanceSolution, solution.bestPriceSolution, solution.bestEmptySolution]) {
+                expect(Object.keys(plan.routes)).toHaveLength(3);
+                for (const route of Object.values(plan.routes)) {
+                    expect(route.stops).toHaveLength(2);
+                }
+            }
+        }
+    });
+});
+
+describe('multi-trip', () => {
+    test('allowMultiTrip matches an unbounded maxTrips', () => {
+        for (let seed = 0; seed < 3; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            const multiTrip = solveBruteForce({
+                ...problem,
+                vehicles: problem.vehicles.map(vehicle => ({ ...vehicle, allowMultiTrip: true })),
+            });
+            const maxTrips = solveBruteForce({
+                ...problem,
+                vehicles: problem.vehicles.map(vehicle => ({ ...vehicle, maxTrips: 8 })),
+            });
+            expect(multiTrip.bestDistanceSolution.totalDistance).toBeCloseTo(
+                maxTrips.bestDistanceSolution.totalDistance,
+                9,
+            );
+            expect(multiTrip.bestEmptySolution.emptyDistance).toBeCloseTo(maxTrips.bestEmptySolution.emptyDistance, 9);
+        }
+    });
+
+    test('a second run of the van is cheaper than dispatching a far vehicle', () => {
+        const at = (hash: string, latitude: number, longitude: number) => ({ hash, latitude, longitude });
+        const van = { id: 1, startLocation: at('depot', 54, 23), priceKm: 1 };
+        const problem: Problem = {
+            vehicles: [van, { id: 2, startLocation: at('far', 55, 25), priceKm: 1 }],
+            orders: [
+                { id: 1, pickupLocation: at('p1', 54.01, 23), deliveryLocation: at('d1', 54.02, 23), loadFactor: 1 },
+                { id: 2, pickupLocation: at('p2', 54, 23.01), deliveryLocation: at('d2', 54, 23.02), loadFactor: 1 },
+            ],
+            // No road from either drop-off on to the other order's pickup, only back through the depot
+            forbiddenLegs: [
+                { fromHash: 'd1', toHash: 'p2' },
+                { fromHash: 'd2', toHash: 'p1' },
+            ],
+        };
+        const oneRun = solveBruteForce(problem).bestPriceSolution;
+        expect(Object.keys(oneRun.routes).sort()).toEqual(['1', '2']);
+
+        const twoRuns = solveBruteForce({ ...problem, vehicles: [{ ...van, maxTrips: 2 }, problem.vehicles[1]] }).bestPriceSolution;
+        expect(Object.keys(twoRuns.routes)).toEqual(['1']);
+        expect(twoRuns.routes['1'].stops.map(stop => stop.type)).toEqual(['pickup', 'delivery', 'reload', 'pickup', 'delivery']);
+        expect(twoRuns.totalPrice).toBeLessThan(oneRun.totalPrice);
+    });
+});
+
+describe('orders on board', () => {
+    test('only the delivery of an order on board is routed', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            problem.orders.forEach(order => (order.loadFactor = 2));
+            problem.vehicles[0].onboardOrderIds = [1, 2];
+            const solution = solveBruteForce(problem);
+            for (const plan of [solution.bestDistanceSolution, solution.bestPriceSolution, solution.bestEmptySolution]) {
+                const stops = plan.routes['1'].stops;
+                for (const orderId of [1, 2]) {
+                    expect(stops.filter(stop => stop.orderId === orderId).map(stop => stop.type)).toEqual(['delivery']);
+                }
+                // Full at the start, so nothing is picked up before a delivery
+                expect(stops[0].type).toBe('delivery');
+            }
+        }
+    });
+
+    test('rejects orders on board beyond the capacity', () => {
+        const problem = randomProblem(0, 2, 3);
+        problem.orders.forEach(order => (order.loadFactor = 1));
+        problem.vehicles[0].onboardOrderIds = [1, 2];
+        expect(() => solveBruteForce(problem)).toThrow(/orders on board vehicle 1 exceed its capacity/);
+    });
+});
+
+describe('order limit', () => {
+    test('solves 8 orders', () => {
+        expect(() => solveBruteForce(randomProblem(0, 1, 8))).not.toThrow();
+    });
+
+    test('rejects 9 orders', () => {
+        expect(() => solveBruteForce(randomProblem(0, 1, 9))).toThrow(/max 8 orders/);
+    });
+
+    test('routes a fixed assignment of 40 orders', () => {
+        const problem = randomProblem(0, 5, 40);
+        const assignments = Object.fromEntries(
+            problem.vehicles.map((v, i) => [String(v.id), problem.orders.slice(8 * i, 8 * i + 8).map(o => o.id)]),
+        );
+        const solution = routeAssignment(problem, assignments, Criterion.Distance);
+        expect(Object.values(solution.routes).flatMap(route => route.stops)).toHaveLength(80);
+    });
+
+    test('rejects fixed assignments beyond 64 orders', () => {
+        expect(() => routeAssignment(randomProblem(0, 1, 65), {}, Criterion.Distance)).toThrow(/max 64 orders/);
+    });
+});
@@ -0,0 +1,268 @@
+/**
+ * @description
+ * Distance models, units and coordinate handling.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    Criterion,
+    DistanceMode,
+    DistanceUnit,
+    evaluateSolution,
+    lowerBound,
+    routeAssignment,
+    solveBruteForce,
+    solveBruteForceWithOptions,
+    solveGreedy,
+    solveRegretInsertion,
+    solveSweep,
+    solveWeighted,
+    validateSolution,
+} from 'rust-solver';
+
+import { Order, Problem } from '../../../types';
+import { criteria, randomProblem } from '../testProblems';
+
+describe('shared locations', () => {
+    test('10 orders between 3 warehouses drive no distance between stops at one hash', () => {
+        // Coordinates drift by up to a meter between orders, the hash still names one place
+        const warehouse = (i: number, noise: number) => ({ hash: `wh-${i}`, latitude: 54 + i / 10 + noise, longitude: 23 + i / 15 });
+        const problem = randomProblem(0, 2, 10);
+        problem.orders.forEach((order, i) => {
+            order.pickupLocation = warehouse(i % 3, 1e-6 * i);
+            order.deliveryLocation = warehouse((i + 1) % 3, -1e-6 * i);
+        });
+        const hashOf = (stop: { orderId: number; type: string }) => {
+            const order = problem.orders.find(o => o.id === stop.orderId)!;
+            return (stop.type === 'pickup' ? order.pickupLocation : order.deliveryLocation).hash;
+        };
+
+        const plan = routeAssignment(problem, { '1': [1, 2, 3, 4, 5], '2': [6, 7, 8, 9, 10] }, Criterion.Distance);
+        let sameHashLegs = 0;
+        for (const route of Object.values(plan.routes)) {
+            route.stops.slice(1).forEach((stop, i) => {
+                if (hashOf(stop) === hashOf(route.stops[i])) {
+                    sameHashLegs++;
+                    expect(stop.arrivalTime).toBe(route.stops[i].departureTime);
+                }
+            });
+        }
+        expect(sameHashLegs).toBeGreaterThan(0);
+    });
+});
+
+describe('distance formula', () => {
+    // A route from `from` picking up there and delivering at `to` drives exactly one leg
+    const legLength = (from: { latitude: number; longitude: number }, to: { latitude: number; longitude: number }) => {
+        const problem: Problem = {
+            vehicles: [{ id: 1, startLocation: { hash: 'a', ...from }, priceKm: 1 }],
+            orders: [{ id: 1, pickupLocation: { hash: 'a', ...from }, deliveryLocation: { hash: 'b', ...to }, loadFactor: 1 }],
+        };
+        const stop = { arrivalTime: 0, departureTime: 0, locationHash: '', latitude: 0, longitude: 0, sequence: 0, loadAfter: 0 };
+        return evaluateSolution(problem, {
+            '1': [
+                { ...stop, orderId: 1, type: 'pickup' },
+                { ...stop, orderId: 1, type: 'delivery' },
+            ],
+        }).totalDistance;
+    };
+
+    test('matches known city distances', () => {
+        expect(legLength({ latitude: 51.5074, longitude: -0.1278 }, { latitude: 48.8566, longitude: 2.3522 })).toBeCloseTo(343.556, 3);
+        expect(legLength({ latitude: 54.6872, longitude: 25.2797 }, { latitude: 54.8985, longitude: 23.9036 })).toBeCloseTo(91.292, 3);
+    });
+
+    test('keeps a 50 m leg accurate where the law of cosines drifts', () => {
+        const degreesPerKm = 360 / (2 * Math.PI * 6371);
+        const depot = { latitude: 54.6872, longitude: 25.2797 };
+        expect(legLength(depot, { ...depot, latitude: depot.latitude + 0.05 * degreesPerKm })).toBeCloseTo(0.05, 11);
+        expect(legLength(depot, { ...depot, latitude: depot.latitude + 0.001 * degreesPerKm })).toBeCloseTo(0.001, 11);
+    });
+});
+
+describe('distance units', () => {
+    test('miles with prices per mile give the same plans as km', () => {
+        const kmPerMile = 1.609344;
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const inMiles = { ...problem, vehicles: problem.vehicles.map(v => ({ ...v, priceKm: v.priceKm * kmPerMile })) };
+            const km = solveBruteForceWithOptions(problem, {});
+            const miles = solveBruteForceWithOptions(inMiles, { distance: { unit: DistanceUnit.Miles } });
+            for (const key of ['bestDistanceSolution', 'bestPriceSolution', 'bestEmptySolution'] as const) {
+                expect(miles[key].totalDistance).toBeCloseTo(km[key].totalDistance / kmPerMile, 9);
+                expect(miles[key].emptyDistance).toBeCloseTo(km[key].emptyDistance / kmPerMile, 9);
+                expect(miles[key].totalPrice).toBeCloseTo(km[key].totalPrice, 9);
+            }
+        }
+    });
+
+    test('heuristics and plan checks read and report miles too', () => {
+        const kmPerMile = 1.609344;
+        const miles = { unit: DistanceUnit.Miles };
+        const problem = randomProblem(1, 2, 4);
+        const inMiles = { ...problem, vehicles: problem.vehicles.map(v => ({ ...v, priceKm: v.priceKm * kmPerMile })) };
+
+        const swept = solveSweep(problem);
+        const sweptMiles = solveSweep(inMiles, miles);
+        expect(sweptMiles.totalDistance).toBeCloseTo(swept.totalDistance / kmPerMile, 9);
+        expect(sweptMiles.totalPrice).toBeCloseTo(swept.totalPrice, 9);
+        const regret = solveRegretInsertion(problem).totalDistance;
+        expect(solveRegretInsertion(inMiles, { distance: miles }).totalDistance).toBeCloseTo(regret / kmPerMile, 9);
+        const weighted = solveWeighted(problem, { distance: 1 }).cost;
+        expect(solveWeighted(inMiles, { distance: 1 }, miles).cost).toBeCloseTo(weighted / kmPerMile, 9);
+        expect(lowerBound(inMiles, Criterion.Distance, miles)).toBeCloseTo(lowerBound(problem, Criterion.Distance) / kmPerMile, 9);
+        expect(lowerBound(inMiles, Criterion.Price, miles)).toBeCloseTo(lowerBound(problem, Criterion.Price), 9);
+
+        const report = validateSolution(inMiles, sweptMiles, miles);
+        expect(report.violations).toHaveLength(0);
+        expect(report.totalDistance).toBeCloseTo(sweptMiles.totalDistance, 9);
+    });
+
+    test('a larger radius scales every distance', () => {
+        const problem = randomProblem(0, 2, 4);
+        const base = solveBruteForceWithOptions(problem, {}).bestDistanceSolution;
+        const mean = solveBruteForceWithOptions(problem, { distance: { radiusKm: 6371.0088 } }).bestDistanceSolution;
+        expect(mean.totalDistance).toBeCloseTo((base.totalDistance * 6371.0088) / 6371, 9);
+    });
+});
+
+describe('euclidean distances', () => {
+    // Grid points, x as longitude and y as latitude
+    const at = (hash: string, x: number, y: number) => ({ hash, latitude: y, longitude: x });
+    const order = (id: number, pickup: Order['pickupLocation'], delivery: Order['deliveryLocation']): Order => ({
+        id,
+        pickupLocation: pickup,
+        deliveryLocation: delivery,
+        loadFactor: 1,
+    });
+    const euclidean = { distance: { mode: DistanceMode.Euclidean } };
+
+    test('finds the hand-checked tour on a 4 x 3 rectangle', () => {
+        // Around the rectangle is 3 + 4 + 3 + 4, every interleaving or the reverse order is longer
+        const problem: Problem = {
+            vehicles: [{ id: 1, startLocation: at('s', 0, 0), priceKm: 2 }],
+            orders: [order(1, at('a', 0, 3), at('b', 4, 3)), order(2, at('c', 4, 0), at('d', 0, 0))],
+        };
+        const solution = solveBruteForceWithOptions(problem, euclidean).bestDistanceSolution;
+        expect(solution.totalDistance).toBeCloseTo(14, 9);
+        expect(solution.emptyDistance).toBeCloseTo(6, 9);
+        expect(solution.totalPrice).toBeCloseTo(28, 9);
+        expect(solution.routes['1'].stops.map(stop => stop.orderId)).toEqual([1, 1, 2, 2]);
+    });
+
+    test('gives each order to the vehicle in its column', () => {
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('s1', 0, 0), priceKm: 1 },
+                { id: 2, startLocation: at('s2', 10, 0), priceKm: 1 },
+            ],
+            orders: [order(1, at('a', 0, 1), at('b', 0, 2)), order(2, at('c', 10, 1), at('d', 10, 3))],
+        };
+        const solution = solveBruteForceWithOptions(problem, euclidean).bestDistanceSolution;
+        expect(solution.totalDistance).toBeCloseTo(5, 9);
+        expect(solution.emptyDistance).toBeCloseTo(2, 9);
+        expect(solution.routes['1'].stops.map(stop => stop.orderId)).toEqual([1, 1]);
+        expect(solution.routes['2'].stops.map(stop => stop.orderId)).toEqual([2, 2]);
+    });
+
+    test('heuristics and plan checks measure on the plane too', () => {
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('s1', 0, 0), priceKm: 1 },
+                { id: 2, startLocation: at('s2', 10, 0), priceKm: 1 },
+            ],
+            orders: [order(1, at('a', 0, 1), at('b', 0, 2)), order(2, at('c', 10, 1), at('d', 10, 3))],
+        };
+        const swept = solveSweep(problem, euclidean.distance);
+        expect(swept.totalDistance).toBeCloseTo(5, 9);
+        const report = validateSolution(problem, swept, euclidean.distance);
+        expect(report.violations).toHaveLength(0);
+        expect(report.totalDistance).toBeCloseTo(5, 9);
+        expect(solveGreedy(problem, euclidean.distance).bestDistanceSolution.totalDistance).toBeCloseTo(5, 9);
+        expect(solveRegretInsertion(problem, euclidean).totalDistance).toBeCloseTo(5, 9);
+        expect(lowerBound(problem, Criterion.Distance, euclidean.distance)).toBeLessThanOrEqual(5 + 1e-9);
+    });
+
+    test('ignores the radius and unit', () => {
+        const problem: Problem = {
+            vehicles: [{ id: 1, startLocation: at('s', 0, 0), priceKm: 1 }],
+            orders: [order(1, at('a', 3, 4), at('b', 3, 0))],
+        };
+        const solution = solveBruteForceWithOptions(problem, {
+            distance: { mode: DistanceMode.Euclidean, radiusKm: 1, unit: DistanceUnit.Miles },
+        }).bestDistanceSolution;
+        expect(solution.totalDistance).toBeCloseTo(9, 9);
+    });
+});
+
+describe('circuity', () => {
+    test('a factor of 1.0 changes nothing', () => {
+        const problem = randomProblem(2, 2, 5);
+        const plain = solveBruteForceWithOptions(problem, {});
+        const unit = solveBruteForceWithOptions(problem, { distance: { circuity: 1 } });
+        for (const { solution, metric } of criteria) {
+            expect(unit[solution][metric]).toBe(plain[solution][metric]);
+        }
+    });
+
+    test('scales every distance by the factor', () => {
+        const problem = randomProblem(2, 2, 5);
+        const plain = solveBruteForceWithOptions(problem, {});
+        const roads = solveBruteForceWithOptions(problem, { distance: { circuity: 1.3 } });
+        for (const { solution, metric } of criteria) {
+            expect(roads[solution][metric]).toBeCloseTo(plain[solution][metric] * 1.3, 9);
+        }
+    });
+
+    test('averages the factors of both ends of a leg', () => {
+        // Start, pickup and delivery hashes start with 'v-', 'p-' and 'd-'
+        const problem = randomProblem(2, 1, 1);
+        const plain = solveBruteForceWithOptions(problem, {}).bestDistanceSolution;
+        const roads = solveBruteForceWithOptions(problem, {
+            distance: { circuity: 1.2, circuityByPrefix: { 'd-': 2, 'p-': 1.6 } },
+        }).bestDistanceSolution;
+        const [approach, haul] = [plain.emptyDistance, plain.totalDistance - plain.emptyDistance];
+        expect(roads.totalDistance).toBeCloseTo(approach * 1.4 + haul * 1.8, 9);
+    });
+
+    test('rejects a factor that is not positive', () => {
+        expect(() => solveBruteForceWithOptions(randomProblem(0, 1, 1), { distance: { circuity: 0 } })).toThrow(
+            /circuity must be positive/,
+        );
+    });
+});
+
+describe('coordinates', () => {
+    const at = (hash: string, latitude: number, longitude: number) => ({ hash, latitude, longitude });
+    const oneLeg = (from: ReturnType<typeof at>, to: ReturnType<typeof at>): Problem => ({
+        vehicles: [{ id: 1, startLocation: from, priceKm: 1 }],
+        orders: [{ id: 1, pickupLocation: from, deliveryLocation: to, loadFactor: 1 }],
+    });
+
+    test('measures across the antimeridian the short way', () => {
+        const solution = solveBruteForce(oneLeg(at('east', 0, 179.9), at('west', 0, -179.9))).bestDistanceSolution;
+        expect(solution.totalDistance).toBeCloseTo(22.239, 3);
+    });
+
+    test('wraps longitudes outside (-180, 180]', () => {
+        const solution = solveBruteForce(oneLeg(at('east', 0, 179.9), at('wrapped', 0, 540))).bestDistanceSolution;
+        expect(solution.totalDistance).toBeCloseTo(11.119, 3);
+        expect(solution.routes['1'].stops[1].longitude).toBe(180);
+    });
+
+    test.each([
+        ['latitude beyond the pole', at('north', 91, 0)],
+        ['NaN', at('north', NaN, 0)],
+        ['Infinity', at('north', 0, Infinity)],
+    ])('rejects %s naming the location', (_, location) => {
+        const problem = oneLeg(at('south', 0, 0), location);
+        expect(() => solveBruteForce(problem)).toThrow(/^InvalidCoordinate: Invalid coordinates at locations north$/);
+    });
+
+    test('leaves plane coordinates beyond the globe alone', () => {
+        const problem = oneLeg(at('a', 200, 0), at('b', 200, 300));
+        const solution = solveBruteForceWithOptions(problem, { distance: { mode: DistanceMode.Euclidean } });
+        expect(solution.bestDistanceSolution.totalDistance).toBeCloseTo(300, 9);
+    });
+});
@@ -0,0 +1,127 @@
+/**
+ * @description
+ * Pricing changes to existing plans, checking hand-edited plans and the optimality gap.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    compareSolutions,
+    Criterion,
+    evaluateInsertion,
+    evaluateRemoval,
+    evaluateSolution,
+    lowerBound,
+    solveBruteForce,
+    validateSolution,
+} from 'rust-solver';
+
+import { criteria, randomProblem } from '../testProblems';
+
+describe('insertion evaluation', () => {
+    test('adds one pickup and delivery to the cheapest route', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const [newOrder] = problem.orders.splice(4, 1);
+            const plan = solveBruteForce(problem).bestPriceSolution;
+            const result = evaluateInsertion(problem, plan, newOrder);
+            expect(result.feasible).toBe(true);
+            expect(result.solution.totalPrice).toBeCloseTo(plan.totalPrice + result.deltaPrice, 9);
+            // Re-solving from scratch can only do as well or better than a single insertion
+            expect(solveBruteForce({ ...problem, orders: [...problem.orders, newOrder] }).bestPriceSolution.totalPrice).toBeLessThanOrEqual(
+                result.solution.totalPrice + 1e-9,
+            );
+            const stops = result.solution.routes[String(result.vehicleId)].stops;
+            expect(stops[result.pickupPosition!]).toMatchObject({ orderId: newOrder.id, type: 'pickup' });
+            expect(stops[result.deliveryPosition!]).toMatchObject({ orderId: newOrder.id, type: 'delivery' });
+        }
+    });
+
+    test('names the binding constraint when no position fits', () => {
+        const problem = randomProblem(0, 2, 3);
+        const [newOrder] = problem.orders.splice(2, 1);
+        const plan = solveBruteForce(problem).bestPriceSolution;
+        const result = evaluateInsertion(problem, plan, { ...newOrder, loadFactor: 0.5 });
+        expect(result.feasible).toBe(false);
+        expect(result.bindingConstraint).toMatch(/capacity/);
+    });
+});
+
+describe('removal evaluation', () => {
+    test('undoes an insertion exactly', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const [newOrder] = problem.orders.splice(4, 1);
+            const plan = solveBruteForce(problem).bestPriceSolution;
+            const inserted = evaluateInsertion(problem, plan, newOrder);
+            const removed = evaluateRemoval({ ...problem, orders: [...problem.orders, newOrder] }, inserted.solution, newOrder.id);
+            expect(removed.savedPrice).toBeCloseTo(inserted.deltaPrice, 9);
+            expect(removed.solution.totalDistance).toBeCloseTo(plan.totalDistance, 9);
+            expect(removed.solution.emptyDistance).toBeCloseTo(plan.emptyDistance, 9);
+            expect(Object.keys(removed.solution.routes).sort()).toEqual(Object.keys(plan.routes).sort());
+        }
+    });
+});
+
+describe('solution validation', () => {
+    test('accepts solver plans', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const plan = solveBruteForce(problem).bestPriceSolution;
+            const report = validateSolution(problem, plan);
+            expect(report.violations).toEqual([]);
+            expect(report.totalPrice).toBeCloseTo(plan.totalPrice, 9);
+        }
+    });
+});
+
+describe('solution evaluation', () => {
+    test('measures solver routes like the solver', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const plan = solveBruteForce(problem).bestEmptySolution;
+            const routes = Object.fromEntries(Object.entries(plan.routes).map(([key, route]) => [key, route.stops]));
+            const measured = evaluateSolution(problem, routes);
+            expect(measured.totalDistance).toBeCloseTo(plan.totalDistance, 9);
+            expect(measured.emptyDistance).toBeCloseTo(plan.emptyDistance, 9);
+            expect(measured.totalPrice).toBeCloseTo(plan.totalPrice, 9);
+        }
+    });
+
+    test('rejects unknown vehicles', () => {
+        const problem = randomProblem(0, 1, 1);
+        const stops = solveBruteForce(problem).bestDistanceSolution.routes['1'].stops;
+        expect(() => evaluateSolution(problem, { '99': stops })).toThrow(/Route 99: no vehicle has this id/);
+    });
+});
+
+describe('optimality gap', () => {
+    test('no plan beats the lower bound', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const exact = solveBruteForce(problem);
+            for (const { criterion, solution, metric } of criteria) {
+                expect(lowerBound(problem, criterion as Criterion)).toBeLessThanOrEqual(exact[solution][metric] + 1e-6);
+            }
+        }
+    });
+
+    test('compares two plans per criterion', () => {
+        const problem = randomProblem(59, 2, 5);
+        const a = solveBruteForce(problem).bestDistanceSolution;
+        const b = { ...a, totalDistance: a.totalDistance * 1.25, totalPrice: a.totalPrice - 10 };
+        const deltas = Object.fromEntries(compareSolutions(problem, a, b).deltas.map(delta => [delta.criterion, delta]));
+        expect(Object.keys(deltas)).toHaveLength(6);
+        expect(deltas[Criterion.Distance].delta).toBeCloseTo(a.totalDistance * 0.25, 9);
+        expect(deltas[Criterion.Distance].percent).toBeCloseTo(25, 9);
+        expect(deltas[Criterion.Price].delta).toBeCloseTo(-10, 9);
+        expect(deltas[Criterion.Price].percent).toBeCloseTo((-10 / a.totalPrice) * 100, 9);
+        expect(deltas[Criterion.Empty].delta).toBe(0);
+        expect(deltas[Criterion.Fleet].a).toBe(Object.values(a.routes).filter(route => route.stops.length > 0).length);
+
+        const idle = { ...a, totalDistance: 0 };
+        expect(compareSolutions(problem, idle, a).deltas[0].percent).toBeUndefined();
+        const stray = { ...a, routes: { '99': Object.values(a.routes)[0] } };
+        expect(() => compareSolutions(problem, a, stray)).toThrow(/Solution b routes unknown vehicle id 99/);
+    });
+});
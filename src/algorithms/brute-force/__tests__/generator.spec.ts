@@ -0,0 +1,85 @@
+/**
+ * @description
+ * Seeded benchmark problem generator.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    generateProblem,
+    solveBruteForce,
+    solveGreedy,
+    validateSolution,
+} from 'rust-solver';
+
+import { greatCircleDistanceCalculator } from '../../../utils/greatCircleDistanceCalculator';
+
+describe('problem generator', () => {
+    const bbox = { minLatitude: 40, maxLatitude: 41, minLongitude: -4, maxLongitude: -3 };
+
+    test('same options, same problem', () => {
+        const options = { nOrders: 12, nVehicles: 3, seed: 7, clustering: 2 };
+        expect(generateProblem(options)).toEqual(generateProblem(options));
+        expect(generateProblem({ ...options, seed: 8 })).not.toEqual(generateProblem(options));
+    });
+
+    test('generated problems always validate', () => {
+        for (let seed = 0; seed < 50; seed++) {
+            const nOrders = seed % 13;
+            const nVehicles = 1 + (seed % 4);
+            const problem = generateProblem({
+                nOrders,
+                nVehicles,
+                seed,
+                bbox,
+                loadFactorRange: { min: 1, max: 3 },
+                priceRange: { min: 0.8, max: 1.2 },
+                clustering: seed % 2 === 0 ? 1 + (seed % 3) : undefined,
+            });
+            expect(problem.orders).toHaveLength(nOrders);
+            expect(problem.vehicles).toHaveLength(nVehicles);
+
+            const locations = [
+                ...problem.vehicles.map(vehicle => vehicle.startLocation),
+                ...problem.orders.flatMap(order => [order.pickupLocation, order.deliveryLocation]),
+            ];
+            expect(new Set(locations.map(location => `${location.latitude},${location.longitude}`)).size).toBe(locations.length);
+            expect(new Set(locations.map(location => location.hash)).size).toBe(locations.length);
+            for (const location of locations) {
+                expect(location.latitude).toBeGreaterThanOrEqual(bbox.minLatitude);
+                expect(location.latitude).toBeLessThanOrEqual(bbox.maxLatitude);
+                expect(location.longitude).toBeGreaterThanOrEqual(bbox.minLongitude);
+                expect(location.longitude).toBeLessThanOrEqual(bbox.maxLongitude);
+            }
+            for (const order of problem.orders) {
+                expect([1, 2, 3]).toContain(order.loadFactor);
+            }
+            for (const vehicle of problem.vehicles) {
+                expect(vehicle.priceKm).toBeGreaterThanOrEqual(0.8);
+                expect(vehicle.priceKm).toBeLessThanOrEqual(1.2);
+            }
+
+            const plan = nOrders <= 6 ? solveBruteForce(problem).bestDistanceSolution : solveGreedy(problem);
+            expect(validateSolution(problem, plan).violations).toHaveLength(0);
+        }
+    });
+
+    test('clustered pickups gather around their centres', () => {
+        const spread = (problem: ReturnType<typeof generateProblem>) => {
+            const pickups = problem.orders.map(order => order.pickupLocation);
+            const distances = pickups.flatMap((a, i) => pickups.slice(i + 1).map(b => greatCircleDistanceCalculator(a, b)));
+            return distances.sort((a, b) => a - b)[Math.floor(distances.length / 2)];
+        };
+        const options = { nOrders: 40, nVehicles: 2, seed: 3 };
+        expect(spread(generateProblem({ ...options, clustering: 1 }))).toBeLessThan(spread(generateProblem(options)) / 4);
+    });
+
+    test('rejects options no problem can satisfy', () => {
+        expect(() => generateProblem({ nOrders: 3, nVehicles: 0 })).toThrow(/n_vehicles must be at least 1/);
+        expect(() => generateProblem({ nOrders: 3, nVehicles: 1, bbox: { ...bbox, maxLatitude: 39 } })).toThrow(/bbox/);
+        expect(() => generateProblem({ nOrders: 3, nVehicles: 1, loadFactorRange: { min: 0.5, max: 2 } })).toThrow(/load_factor_range/);
+        expect(() => generateProblem({ nOrders: 3, nVehicles: 1, loadFactorRange: { min: 1.2, max: 1.8 } })).toThrow(/no whole number/);
+        expect(() => generateProblem({ nOrders: 3, nVehicles: 1, priceRange: { min: 2, max: 1 } })).toThrow(/price_range/);
+        expect(() => generateProblem({ nOrders: 3, nVehicles: 1, clustering: 0 })).toThrow(/clustering must be at least 1/);
+    });
+});
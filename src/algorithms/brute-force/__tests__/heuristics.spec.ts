@@ -0,0 +1,278 @@
+/**
+ * @description
+ * Heuristic planners and the automatic choice between them and the exact search.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    AutoAlgorithm,
+    Criterion,
+    improveSolution,
+    localSearch,
+    LocalSearchMove,
+    solveAuto,
+    solveBeam,
+    solveBruteForce,
+    solveBruteForceWarm,
+    solveClustered,
+    solveGreedy,
+    solveRegretInsertion,
+    solveSimulatedAnnealing,
+    solveSweep,
+    validateSolution,
+} from 'rust-solver';
+
+import { Problem } from '../../../types';
+import { criteria, randomProblem, TOLERANCE } from '../testProblems';
+
+describe('greedy', () => {
+    test('serves every order of a large problem', () => {
+        const solution = solveGreedy(randomProblem(0, 4, 30));
+        const stops = Object.values(solution.bestDistanceSolution.routes).flatMap(route => route.stops);
+        expect(solution.feasible).toBe(true);
+        expect(solution.optimal).toBe(false);
+        expect(stops).toHaveLength(60);
+    });
+
+    test('is never shorter than brute force', () => {
+        const problem = randomProblem(1, 2, 5);
+        expect(solveGreedy(problem).bestDistanceSolution.totalDistance).toBeGreaterThanOrEqual(
+            solveBruteForce(problem).bestDistanceSolution.totalDistance - TOLERANCE,
+        );
+    });
+});
+
+describe('simulated annealing', () => {
+    test('is deterministic for a seed and never worse than its start', () => {
+        const problem = randomProblem(2, 3, 20);
+        const first = solveSimulatedAnnealing(problem, { seed: 42, iterations: 5000 });
+        const second = solveSimulatedAnnealing(problem, { seed: 42, iterations: 5000 });
+        expect(first.solution.totalDistance).toBe(second.solution.totalDistance);
+        expect(first.stats.bestCost).toBeLessThanOrEqual(first.stats.initialCost);
+        expect(first.solution.totalDistance).toBeLessThanOrEqual(
+            solveGreedy(problem).bestDistanceSolution.totalDistance + TOLERANCE,
+        );
+    });
+});
+
+describe('beam search', () => {
+    test('an unbounded beam matches the exact search', () => {
+        for (let seed = 0; seed < 5; ++seed) {
+            const problem = randomProblem(seed, 3, 6);
+            const exact = solveBruteForce(problem);
+            for (const { criterion, solution, metric } of criteria) {
+                const beam = solveBeam(problem, { beamWidth: Infinity, criterion: criterion as Criterion });
+                expect(beam[metric]).toBeCloseTo(exact[solution][metric], 9);
+            }
+        }
+    });
+
+    test('a narrow beam plans every order of larger problems', () => {
+        const problem = randomProblem(7, 4, 16);
+        const solution = solveBeam(problem, { beamWidth: 50 });
+        const served = new Set(Object.values(solution.routes).flatMap(route => route.stops.map(stop => stop.orderId)));
+        expect(served.size).toBe(problem.orders.length);
+        expect(validateSolution(problem, solution).violations).toHaveLength(0);
+    });
+
+    test('rejects a beam narrower than one', () => {
+        expect(() => solveBeam(randomProblem(0, 1, 2), { beamWidth: 0 })).toThrow(/beam_width must be at least 1/);
+    });
+});
+
+describe('large neighbourhood search', () => {
+    test('improves the greedy plan without breaking it, the same way for the same seed', () => {
+        const problem = randomProblem(11, 3, 14);
+        const greedy = solveGreedy(problem).bestDistanceSolution;
+        const improved = improveSolution(problem, greedy, { iterations: 300, seed: 7 });
+        expect(improved.solution.totalDistance).toBeLessThanOrEqual(greedy.totalDistance + 1e-9);
+        expect(improved.solution.unassignedOrderIds.length).toBeLessThanOrEqual(greedy.unassignedOrderIds.length);
+        expect(validateSolution(problem, improved.solution).violations).toHaveLength(0);
+        expect(improveSolution(problem, greedy, { iterations: 300, seed: 7 })).toEqual(improved);
+    });
+
+    test('never beats the exact optimum and keeps the plan without accepted moves', () => {
+        const problem = randomProblem(2, 2, 6);
+        const exact = solveBruteForce(problem).bestDistanceSolution;
+        const improved = improveSolution(problem, solveGreedy(problem).bestDistanceSolution, { iterations: 200 });
+        expect(improved.solution.totalDistance).toBeGreaterThanOrEqual(exact.totalDistance - 1e-9);
+        const untouched = improveSolution(problem, exact, { iterations: 0 });
+        expect(untouched.acceptedMoves).toBe(0);
+        expect(untouched.solution.totalDistance).toBeCloseTo(exact.totalDistance, 9);
+    });
+});
+
+describe('local search', () => {
+    test('logs every move it applies and stops at a local optimum', () => {
+        const problem = randomProblem(13, 3, 12);
+        const greedy = solveGreedy(problem).bestDistanceSolution;
+        const polished = localSearch(problem, greedy);
+        expect(validateSolution(problem, polished.solution).violations).toHaveLength(0);
+        expect(polished.solution.unassignedOrderIds).toEqual(greedy.unassignedOrderIds);
+        expect(polished.solution.totalDistance).toBeCloseTo(greedy.totalDistance - polished.savedDistance, 6);
+        const logged = polished.moves.reduce((sum, move) => sum + move.savedDistance, 0);
+        expect(logged).toBeCloseTo(polished.savedDistance, 9);
+        for (const move of polished.moves) {
+            expect(move.savedDistance).toBeGreaterThan(0);
+            expect(move.fromVehicleId).not.toBe(move.toVehicleId);
+            expect(move.orderIds.length).toBeGreaterThan(0);
+        }
+        expect(localSearch(problem, polished.solution).moves).toHaveLength(0);
+    });
+
+    test('only tries the moves asked for', () => {
+        const problem = randomProblem(17, 3, 10);
+        const greedy = solveGreedy(problem).bestDistanceSolution;
+        const relocated = localSearch(problem, greedy, { moves: [LocalSearchMove.Relocate] });
+        for (const move of relocated.moves) {
+            expect(move.kind).toBe(LocalSearchMove.Relocate);
+            expect(move.orderIds).toHaveLength(1);
+        }
+        expect(localSearch(problem, greedy, { moves: [] }).moves).toHaveLength(0);
+        const exact = solveBruteForce(problem).bestDistanceSolution;
+        expect(localSearch(problem, greedy).solution.totalDistance).toBeGreaterThanOrEqual(exact.totalDistance - 1e-9);
+    });
+});
+
+describe('clustered', () => {
+    test('serves every order of a large problem once, one cluster per vehicle', () => {
+        const problem = randomProblem(19, 5, 120);
+        const clustered = solveClustered(problem);
+        expect(clustered.clusters).toHaveLength(5);
+        expect(new Set(clustered.clusters.map(cluster => cluster.vehicleId)).size).toBe(5);
+        const members = clustered.clusters.flatMap(cluster => cluster.orderIds).sort((a, b) => a - b);
+        expect(members).toEqual(problem.orders.map(order => order.id));
+        expect(clustered.solution.unassignedOrderIds).toHaveLength(0);
+        expect(validateSolution(problem, clustered.solution).violations).toHaveLength(0);
+        expect(clustered.clusters.every(cluster => !cluster.exact)).toBe(true);
+    });
+
+    test('routes small clusters exactly', () => {
+        const problem = randomProblem(23, 3, 12);
+        const clustered = solveClustered(problem, { clusters: 3 });
+        expect(clustered.clusters.every(cluster => cluster.exact && cluster.orderIds.length === 4)).toBe(true);
+        expect(clustered.solution.totalDistance).toBeGreaterThanOrEqual(solveBruteForce(problem).bestDistanceSolution.totalDistance - 1e-9);
+    });
+
+    test('spills orders their cluster cannot take to another route', () => {
+        const problem = randomProblem(3, 2, 8);
+        problem.vehicles[0].tags = ['cold'];
+        problem.orders.forEach(order => (order.requiredTags = ['cold']));
+        const clustered = solveClustered(problem);
+        expect(clustered.spilledOrderIds).toHaveLength(4);
+        expect(clustered.solution.unassignedOrderIds).toHaveLength(0);
+        expect(Object.values(clustered.solution.routes).filter(route => route.stops.length > 0)).toHaveLength(1);
+        expect(validateSolution(problem, clustered.solution).violations).toHaveLength(0);
+    });
+
+    test('rejects more clusters than vehicles', () => {
+        expect(() => solveClustered(randomProblem(0, 2, 4), { clusters: 3 })).toThrow(/clusters must be between 1/);
+        expect(() => solveClustered(randomProblem(0, 2, 4), { clusters: 0 })).toThrow(/clusters must be between 1/);
+    });
+});
+
+describe('sweep', () => {
+    test('serves every order and seeds the exact search', () => {
+        const problem = randomProblem(29, 3, 12);
+        const swept = solveSweep(problem);
+        expect(swept.unassignedOrderIds).toHaveLength(0);
+        expect(validateSolution(problem, swept).violations).toHaveLength(0);
+        const exact = solveBruteForce(problem);
+        expect(swept.totalDistance).toBeGreaterThanOrEqual(exact.bestDistanceSolution.totalDistance - 1e-9);
+        const warm = solveBruteForceWarm(problem, swept);
+        expect(warm.bestDistanceSolution.totalDistance).toBeCloseTo(exact.bestDistanceSolution.totalDistance, 6);
+    });
+
+    test('orders by delivery distance when every pickup shares one location', () => {
+        const problem = randomProblem(31, 2, 8);
+        problem.orders.forEach(order => (order.pickupLocation = { ...problem.orders[0].pickupLocation }));
+        const swept = solveSweep(problem);
+        expect(swept.unassignedOrderIds).toHaveLength(0);
+        expect(validateSolution(problem, swept).violations).toHaveLength(0);
+        expect(solveSweep(problem)).toEqual(swept);
+    });
+
+    test('rejects more orders than fit a mask', () => {
+        expect(() => solveSweep(randomProblem(0, 2, 65))).toThrow(/exceeds max 64 orders/);
+    });
+});
+
+describe('regret insertion', () => {
+    // Along the equator, one order per vehicle: the nearer order is cheapest for vehicle 1,
+    // but the other one loses far more by going to vehicle 2
+    const place = (hash: string, longitude: number) => ({ hash, latitude: 0, longitude });
+    const crafted: Problem = {
+        vehicles: [
+            { id: 1, startLocation: place('v1', 0), priceKm: 1, maxOrders: 1 },
+            { id: 2, startLocation: place('v2', 10), priceKm: 1, maxOrders: 1 },
+        ],
+        orders: [
+            { id: 1, pickupLocation: place('p1', 0.9), deliveryLocation: place('d1', 0.9), loadFactor: 1 },
+            { id: 2, pickupLocation: place('p2', -1), deliveryLocation: place('d2', -1), loadFactor: 1 },
+        ],
+    };
+
+    test('beats cheapest insertion where the cheapest order is not the urgent one', () => {
+        const greedy = solveGreedy(crafted).bestDistanceSolution;
+        const regret = solveRegretInsertion(crafted);
+        expect(greedy.unassignedOrderIds).toHaveLength(0);
+        expect(regret.unassignedOrderIds).toHaveLength(0);
+        expect(regret.totalDistance).toBeLessThan(greedy.totalDistance - 100);
+        expect(regret.totalDistance).toBeCloseTo(solveBruteForce(crafted).bestDistanceSolution.totalDistance, 6);
+        expect(solveRegretInsertion(crafted, { regretK: 1 }).totalDistance).toBeCloseTo(greedy.totalDistance, 6);
+    });
+
+    test('builds a valid plan for any regret depth', () => {
+        const problem = randomProblem(37, 4, 20);
+        for (const regretK of [1, 2, 3, 4]) {
+            const plan = solveRegretInsertion(problem, { regretK });
+            expect(plan.unassignedOrderIds).toHaveLength(0);
+            expect(validateSolution(problem, plan).violations).toHaveLength(0);
+        }
+        expect(() => solveRegretInsertion(problem, { regretK: 0 })).toThrow(/regret_k must be at least 1/);
+    });
+});
+
+describe('automatic algorithm selection', () => {
+    test.each([
+        [3, 5, AutoAlgorithm.Exact],
+        [3, 6, AutoAlgorithm.Exact],
+        [3, 7, AutoAlgorithm.SeededExact],
+        [3, 8, AutoAlgorithm.SeededExact],
+        [3, 9, AutoAlgorithm.Lns],
+        [4, 40, AutoAlgorithm.Lns],
+        [9, 4, AutoAlgorithm.Lns],
+    ])('%i vehicles and %i orders run %s', (vehicleCount, orderCount, algorithm) => {
+        const auto = solveAuto(randomProblem(41, vehicleCount, orderCount), { timeLimitMs: 2000 });
+        expect(auto.algorithm).toBe(algorithm);
+        expect(auto.optimal).toBe(auto.solution.optimal);
+        if (algorithm === AutoAlgorithm.Lns) {
+            expect(auto.optimal).toBe(false);
+        }
+    });
+
+    test('exact results match the brute force and are proven optimal', () => {
+        const problem = randomProblem(43, 3, 6);
+        const auto = solveAuto(problem);
+        expect(auto.optimal).toBe(true);
+        expect(auto.solution.bestDistanceSolution.totalDistance).toBeCloseTo(solveBruteForce(problem).bestDistanceSolution.totalDistance, 6);
+    });
+
+    test('thresholds can be overridden', () => {
+        const problem = randomProblem(47, 2, 6);
+        expect(solveAuto(problem, { exactMaxOrders: 4 }).algorithm).toBe(AutoAlgorithm.SeededExact);
+        expect(solveAuto(problem, { exactMaxOrders: 2, seededMaxOrders: 4 }).algorithm).toBe(AutoAlgorithm.Lns);
+        expect(solveAuto(problem, { exactMaxVehicles: 1 }).algorithm).toBe(AutoAlgorithm.Lns);
+        expect(() => solveAuto(problem, { exactMaxOrders: 6, seededMaxOrders: 4 })).toThrow(/seeded_max_orders \(4\) must be at least exact_max_orders \(6\)/);
+        expect(() => solveAuto(problem, { seededMaxOrders: 9 })).toThrow(/seeded_max_orders must be at most 8, got 9/);
+    });
+
+    test('the heuristic band still serves every order within the time limit', () => {
+        const problem = randomProblem(53, 4, 60);
+        const auto = solveAuto(problem, { timeLimitMs: 300 });
+        expect(auto.algorithm).toBe(AutoAlgorithm.Lns);
+        expect(auto.solution.bestDistanceSolution.unassignedOrderIds).toHaveLength(0);
+        expect(validateSolution(problem, auto.solution.bestDistanceSolution).violations).toHaveLength(0);
+    });
+});
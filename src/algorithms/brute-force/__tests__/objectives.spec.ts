@@ -0,0 +1,242 @@
+/**
+ * @description
+ * Objectives and route pricing beyond total distance.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    DistanceMode,
+    solutionDominates,
+    solveBruteForce,
+    solveBruteForceWithOptions,
+    solveParetoFront,
+    solveWeighted,
+} from 'rust-solver';
+
+import { Problem, ProblemSolution } from '../../../types';
+import { criteria, randomProblem, TOLERANCE } from '../testProblems';
+
+describe('fleet objective', () => {
+    const usedVehicles = (solution: ProblemSolution) => Object.keys(solution.routes).length;
+
+    test('never uses more vehicles than the distance-optimal plan', () => {
+        let fewer = 0;
+        for (let seed = 0; seed < 10; seed++) {
+            const solution = solveBruteForce(randomProblem(seed, 3, 4));
+            const fleet = solution.bestFleetSolution;
+            const distance = solution.bestDistanceSolution;
+            expect(usedVehicles(fleet)).toBeLessThanOrEqual(usedVehicles(distance));
+            if (usedVehicles(fleet) < usedVehicles(distance)) {
+                fewer++;
+                expect(fleet.totalDistance).toBeGreaterThan(distance.totalDistance);
+            } else {
+                expect(fleet.totalDistance).toBeCloseTo(distance.totalDistance, 9);
+            }
+        }
+        expect(fewer).toBeGreaterThan(0);
+    });
+
+    test('agrees with the partition DP', () => {
+        const problem = randomProblem(11, 3, 5);
+        const dp = solveBruteForceWithOptions(problem, { assignmentDp: true }).bestFleetSolution;
+        const search = solveBruteForce(problem).bestFleetSolution;
+        expect(usedVehicles(dp)).toBe(usedVehicles(search));
+        expect(dp.totalDistance).toBeCloseTo(search.totalDistance, 9);
+    });
+});
+
+describe('workload balance', () => {
+    test('the makespan plan has the shortest longest route', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const solution = solveBruteForce(randomProblem(seed, 3, 5));
+            const balanced = solution.bestMakespanSolution;
+            const longest = Math.max(...Object.values(balanced.routes).map(route => route.totalDistance));
+            expect(balanced.maxRouteDistance).toBeCloseTo(longest, 9);
+            expect(solution.bestDistanceSolution.maxRouteDistance).toBeGreaterThanOrEqual(
+                balanced.maxRouteDistance - TOLERANCE,
+            );
+        }
+    });
+});
+
+describe('weighted objective', () => {
+    test('distance-only weights reproduce the distance plan', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            const weighted = solveWeighted(problem, { distance: 1, price: 0, empty: 0 });
+            expect(weighted.solution.totalDistance).toBe(solveBruteForce(problem).bestDistanceSolution.totalDistance);
+            expect(weighted.cost).toBeCloseTo(weighted.distanceCost, 9);
+        }
+    });
+
+    test('splits the weighted cost per objective', () => {
+        const weighted = solveWeighted(randomProblem(1, 2, 4), { distance: 0.5, price: 1, empty: 2 });
+        const { solution } = weighted;
+        expect(weighted.distanceCost).toBeCloseTo(0.5 * solution.totalDistance, 9);
+        expect(weighted.priceCost).toBeCloseTo(solution.totalPrice, 9);
+        expect(weighted.emptyCost).toBeCloseTo(2 * solution.emptyDistance, 9);
+        expect(weighted.cost).toBeCloseTo(weighted.distanceCost + weighted.priceCost + weighted.emptyCost, 9);
+    });
+
+    test('rejects negative weights', () => {
+        expect(() => solveWeighted(randomProblem(0, 1, 2), { distance: -1 })).toThrow(/non-negative/);
+    });
+});
+
+describe('lexicographic criteria', () => {
+    test('the first criterion matches its standalone optimum', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            const standalone = solveBruteForce(problem);
+            const ordered = solveBruteForceWithOptions(problem, { criteria: ['empty', 'price', 'distance'] });
+            expect(ordered.bestDistanceSolution.emptyDistance).toBeCloseTo(
+                standalone.bestEmptySolution.emptyDistance,
+                9,
+            );
+            expect(ordered.bestPriceSolution).toEqual(ordered.bestDistanceSolution);
+        }
+    });
+
+    test('rejects unknown criteria', () => {
+        expect(() => solveBruteForceWithOptions(randomProblem(0, 1, 2), { criteria: ['speed'] })).toThrow(
+            /unknown criterion/,
+        );
+    });
+});
+
+describe('pareto front', () => {
+    test('no plan on the front dominates another', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const front = solveParetoFront(randomProblem(seed, 2, 4));
+            expect(front.length).toBeGreaterThan(0);
+            for (const a of front) {
+                for (const b of front) {
+                    expect(solutionDominates(a, b)).toBe(false);
+                }
+            }
+            const distances = front.map(solution => solution.totalDistance);
+            expect(distances).toEqual([...distances].sort((a, b) => a - b));
+        }
+    });
+
+    test('includes the distance optimum and respects the cap', () => {
+        const problem = randomProblem(2, 3, 5);
+        const front = solveParetoFront(problem, 3);
+        expect(front.length).toBeLessThanOrEqual(3);
+        expect(front[0].totalDistance).toBeCloseTo(solveBruteForce(problem).bestDistanceSolution.totalDistance, 9);
+    });
+});
+
+describe('travel time', () => {
+    test('a faster vehicle wins on duration while losing on distance', () => {
+        // On a plane: the slow vehicle drives 3 units at 10 per hour, the fast one 5 at 60
+        const at = (hash: string, y: number) => ({ hash, latitude: y, longitude: 0 });
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('slow', 0), priceKm: 1, speedKmh: 10 },
+                { id: 2, startLocation: at('fast', -2), priceKm: 1, speedKmh: 60 },
+            ],
+            orders: [{ id: 1, pickupLocation: at('a', 1), deliveryLocation: at('b', 3), loadFactor: 1 }],
+        };
+        const solution = solveBruteForceWithOptions(problem, { distance: { mode: DistanceMode.Euclidean } });
+
+        expect(Object.keys(solution.bestDistanceSolution.routes)).toEqual(['1']);
+        expect(solution.bestDistanceSolution.totalDistance).toBeCloseTo(3, 9);
+        expect(solution.bestDistanceSolution.totalDuration).toBeCloseTo(18, 9);
+
+        expect(Object.keys(solution.bestDurationSolution.routes)).toEqual(['2']);
+        expect(solution.bestDurationSolution.totalDistance).toBeCloseTo(5, 9);
+        expect(solution.bestDurationSolution.totalDuration).toBeCloseTo(5, 9);
+    });
+});
+
+describe('empty km price', () => {
+    test('an unset price matches pricing empty km at price_km', () => {
+        const problem = randomProblem(4, 2, 5);
+        const same = { ...problem, vehicles: problem.vehicles.map(v => ({ ...v, priceKmEmpty: v.priceKm })) };
+        expect(solveBruteForce(same).bestPriceSolution.totalPrice).toBe(solveBruteForce(problem).bestPriceSolution.totalPrice);
+    });
+
+    test('cheap repositioning makes the price-optimal route differ from the shortest one', () => {
+        // On a plane: carrying A while fetching B is shortest, but drives 16.3 units loaded
+        // against 11 when each order goes straight to its delivery
+        const at = (hash: string, x: number, y: number) => ({ hash, latitude: y, longitude: x });
+        const problem: Problem = {
+            vehicles: [{ id: 1, startLocation: at('s', 0, 0), priceKm: 1, priceKmEmpty: 0.2 }],
+            orders: [
+                { id: 1, pickupLocation: at('a', 1, 0), deliveryLocation: at('b', 10, 0), loadFactor: 1 },
+                { id: 2, pickupLocation: at('c', 0, 3), deliveryLocation: at('d', 0, 5), loadFactor: 1 },
+            ],
+        };
+        const solution = solveBruteForceWithOptions(problem, { distance: { mode: DistanceMode.Euclidean } });
+        const order = (plan: ProblemSolution) => plan.routes['1'].stops.map(stop => `${stop.type}${stop.orderId}`);
+
+        const shortest = solution.bestDistanceSolution;
+        expect(order(shortest)).toEqual(['pickup1', 'pickup2', 'delivery2', 'delivery1']);
+        expect(shortest.totalDistance).toBeCloseTo(1 + Math.hypot(1, 3) + 2 + Math.hypot(10, 5), 9);
+
+        const cheapest = solution.bestPriceSolution;
+        const empty = 3 + Math.hypot(1, 5);
+        expect(order(cheapest)).toEqual(['pickup2', 'delivery2', 'pickup1', 'delivery1']);
+        expect(cheapest.routes['1'].costBreakdown.loadedCost).toBeCloseTo(11, 9);
+        expect(cheapest.routes['1'].costBreakdown.emptyCost).toBeCloseTo(empty * 0.2, 9);
+        expect(cheapest.totalPrice).toBeCloseTo(11 + empty * 0.2, 9);
+    });
+});
+
+describe('stop fees', () => {
+    test('a cheaper per-km vehicle with a high fee loses the price solution', () => {
+        // On a plane: 5.5 units at 1 plus two fees of 20, against 6 units at 2
+        const at = (hash: string, y: number) => ({ hash, latitude: y, longitude: 0 });
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('near', 0.5), priceKm: 1, stopFee: 20 },
+                { id: 2, startLocation: at('far', 0), priceKm: 2 },
+            ],
+            orders: [{ id: 1, pickupLocation: at('a', 1), deliveryLocation: at('b', 6), loadFactor: 1 }],
+        };
+        const solution = solveBruteForceWithOptions(problem, { distance: { mode: DistanceMode.Euclidean } });
+
+        expect(Object.keys(solution.bestDistanceSolution.routes)).toEqual(['1']);
+        expect(solution.bestDistanceSolution.totalPrice).toBeCloseTo(45.5, 9);
+        expect(solution.bestDistanceSolution.routes['1'].costBreakdown.stopFees).toBeCloseTo(40, 9);
+
+        expect(Object.keys(solution.bestPriceSolution.routes)).toEqual(['2']);
+        expect(solution.bestPriceSolution.totalPrice).toBeCloseTo(12, 9);
+        expect(solution.bestPriceSolution.routes['2'].costBreakdown.stopFees).toBe(0);
+    });
+});
+
+describe('co2', () => {
+    const at = (hash: string, y: number) => ({ hash, latitude: y, longitude: 0 });
+    const problem: Problem = {
+        vehicles: [
+            { id: 1, startLocation: at('near', 0.5), priceKm: 1, co2PerKm: 1000 },
+            { id: 2, startLocation: at('far', 0), priceKm: 1, co2PerKm: 100, co2PerKmEmpty: 50 },
+        ],
+        orders: [{ id: 1, pickupLocation: at('a', 1), deliveryLocation: at('b', 6), loadFactor: 1 }],
+    };
+    const distance = { mode: DistanceMode.Euclidean };
+
+    test('the co2 criterion picks the cleaner vehicle over the closer one', () => {
+        const standalone = solveBruteForceWithOptions(problem, { distance });
+        expect(Object.keys(standalone.bestDistanceSolution.routes)).toEqual(['1']);
+        expect(standalone.bestDistanceSolution.totalCo2).toBeCloseTo(5500, 9);
+
+        // 1 empty unit at 50 plus 5 loaded units at 100
+        const cleanest = solveBruteForceWithOptions(problem, { distance, criteria: ['co2', 'distance'] });
+        expect(Object.keys(cleanest.bestDistanceSolution.routes)).toEqual(['2']);
+        expect(cleanest.bestDistanceSolution.totalCo2).toBeCloseTo(550, 9);
+        expect(cleanest.bestDistanceSolution.routes['2'].totalCo2).toBeCloseTo(550, 9);
+    });
+
+    test('reports 0 and rejects the criterion without emission rates', () => {
+        const bare: Problem = {
+            ...problem,
+            vehicles: problem.vehicles.map(({ co2PerKm: _, co2PerKmEmpty: __, ...vehicle }) => vehicle),
+        };
+        expect(solveBruteForceWithOptions(bare, { distance }).bestDistanceSolution.totalCo2).toBe(0);
+        expect(() => solveBruteForceWithOptions(bare, { distance, criteria: ['co2'] })).toThrow(/co2_per_km/);
+    });
+});
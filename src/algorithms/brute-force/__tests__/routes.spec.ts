@@ -0,0 +1,86 @@
+/**
+ * @description
+ * Route details reported alongside each plan.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    solveBruteForce,
+} from 'rust-solver';
+
+import { criteria, randomProblem } from '../testProblems';
+
+describe('route legs', () => {
+    test('legs chain from the vehicle start and add up to the route distance exactly', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            const solution = solveBruteForce(problem);
+            for (const { solution: key } of criteria) {
+                for (const [vehicleId, route] of Object.entries(solution[key].routes)) {
+                    const vehicle = problem.vehicles.find(v => String(v.id) === vehicleId)!;
+                    expect(route.legs[0].from.hash).toBe(vehicle.startLocation.hash);
+                    route.legs.slice(1).forEach((leg, i) => expect(leg.from.hash).toBe(route.legs[i].to.hash));
+                    expect(route.legs.reduce((sum, leg) => sum + leg.distance, 0)).toBe(route.totalDistance);
+                    expect(route.legs.filter(leg => leg.empty).reduce((sum, leg) => sum + leg.distance, 0)).toBeCloseTo(
+                        route.emptyDistance,
+                        9,
+                    );
+                    expect(route.legs.reduce((sum, leg) => sum + leg.price, 0)).toBeCloseTo(route.totalPrice, 9);
+                }
+            }
+        }
+    });
+});
+
+describe('route stops', () => {
+    test('stops carry their location and position, routes their vehicle id', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 4);
+            for (const [vehicleId, route] of Object.entries(solveBruteForce(problem).bestDistanceSolution.routes)) {
+                expect(String(route.vehicleId)).toBe(vehicleId);
+                route.stops.forEach((stop, sequence) => {
+                    const order = problem.orders.find(o => o.id === stop.orderId)!;
+                    const location = stop.type === 'pickup' ? order.pickupLocation : order.deliveryLocation;
+                    const { hash: locationHash, latitude, longitude } = location;
+                    expect(stop).toMatchObject({ sequence, locationHash, latitude, longitude });
+                    expect(stop.locationHash).toBe(route.legs[sequence].to.hash);
+                });
+            }
+        }
+    });
+});
+
+describe('route list', () => {
+    test('lists the routes of the map in vehicle id order', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const solution = solveBruteForce(randomProblem(seed, 3, 4));
+            for (const { solution: key } of criteria) {
+                const plan = solution[key];
+                const ids = plan.routeList.map(entry => entry.vehicleId);
+                expect(ids).toEqual([...ids].sort((a, b) => a - b));
+                expect(ids.map(String).sort()).toEqual(Object.keys(plan.routes).sort());
+                for (const entry of plan.routeList) {
+                    expect(entry.route).toEqual(plan.routes[String(entry.vehicleId)]);
+                }
+            }
+        }
+    });
+});
+
+describe('load profile', () => {
+    test('solver routes never load past capacity and average below their peak', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const solution = solveBruteForce(randomProblem(seed, 2, 5));
+            for (const { solution: key } of criteria) {
+                for (const route of Object.values(solution[key].routes)) {
+                    expect(route.maxLoad).toBeLessThanOrEqual(1 + 1e-6);
+                    expect(route.maxLoad).toBe(Math.max(...route.stops.map(stop => stop.loadAfter)));
+                    expect(route.avgUtilization).toBeGreaterThan(0);
+                    expect(route.avgUtilization).toBeLessThanOrEqual(route.maxLoad);
+                    expect(route.stops.at(-1)!.loadAfter).toBe(0);
+                }
+            }
+        }
+    });
+});
@@ -0,0 +1,494 @@
+/**
+ * @description
+ * Exact search: parallel workers, pruning, seeding, budgets and solve options.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    CancellationToken,
+    Criterion,
+    Improvement,
+    solve,
+    solveBruteForce,
+    solveBruteForceAsync,
+    solveBruteForceWarm,
+    solveBruteForceWithMemo,
+    solveBruteForceWithOptions,
+    solveGreedy,
+    SolveHandle,
+    TspMemo,
+} from 'rust-solver';
+
+import { Problem, ProblemSolution } from '../../../types';
+import { criteria, describeAssignment, randomProblem } from '../testProblems';
+
+describe('parallel search', () => {
+    // The memo solve runs single-threaded, so it doubles as the sequential reference
+    test('matches the sequential search on 7 orders, 3 vehicles', () => {
+        for (let seed = 0; seed < 5; ++seed) {
+            const problem = randomProblem(seed, 3, 7);
+            const parallel = solveBruteForce(problem);
+            const sequential = solveBruteForceWithMemo(problem, new TspMemo());
+            for (const { solution, metric } of criteria) {
+                expect(parallel[solution][metric]).toBe(sequential[solution][metric]);
+            }
+        }
+    });
+});
+
+describe('heuristic seed', () => {
+    test('leaves the optimal values unchanged', () => {
+        for (let seed = 0; seed < 5; ++seed) {
+            const problem = randomProblem(seed, 3, 6);
+            const seeded = solveBruteForceWithOptions(problem, {});
+            const unseeded = solveBruteForceWithOptions(problem, { heuristicSeed: false });
+            for (const { solution, metric } of criteria) {
+                expect(seeded[solution][metric]).toBe(unseeded[solution][metric]);
+            }
+        }
+    });
+});
+
+describe('nearest-first branching', () => {
+    test('matches index-order branching with fewer route nodes', () => {
+        let nearestNodes = 0;
+        let indexNodes = 0;
+        for (let seed = 0; seed < 5; ++seed) {
+            const problem = randomProblem(seed, 2, 6);
+            const nearest = solveBruteForceWithOptions(problem, {});
+            const indexOrder = solveBruteForceWithOptions(problem, { nearestFirst: false });
+            for (const { solution, metric } of criteria) {
+                expect(nearest[solution][metric]).toBeCloseTo(indexOrder[solution][metric], 9);
+            }
+            nearestNodes += nearest.stats.routeNodes;
+            indexNodes += indexOrder.stats.routeNodes;
+        }
+        expect(nearestNodes).toBeLessThanOrEqual(indexNodes);
+    });
+});
+
+describe('route lower bound', () => {
+    test('keeps optimal values and expands fewer route nodes', () => {
+        for (let seed = 0; seed < 5; ++seed) {
+            const problem = randomProblem(seed, 2, 6);
+            const bounded = solveBruteForceWithOptions(problem, {});
+            const plain = solveBruteForceWithOptions(problem, { routeLowerBound: false });
+            for (const { solution, metric } of criteria) {
+                expect(bounded[solution][metric]).toBeCloseTo(plain[solution][metric], 9);
+            }
+            expect(bounded.stats.routeNodes).toBeLessThanOrEqual(plain.stats.routeNodes);
+            expect(plain.stats.routeBoundPrunes).toBe(0);
+        }
+    });
+});
+
+describe('route DP', () => {
+    test('matches the DFS on every order subset of a 6-order problem', () => {
+        const problem = randomProblem(4, 1, 6);
+        for (let mask = 1; mask < 1 << 6; ++mask) {
+            const subset = { ...problem, orders: problem.orders.filter((_, i) => mask & (1 << i)) };
+            const dp = solveBruteForceWithOptions(subset, { routeDp: true });
+            const dfs = solveBruteForceWithOptions(subset, { routeDp: false });
+            for (const { solution, metric } of criteria) {
+                expect(dp[solution][metric]).toBeCloseTo(dfs[solution][metric], 9);
+            }
+        }
+    });
+});
+
+describe('assignment DP', () => {
+    test('matches the recursive search on large fleets', () => {
+        for (let seed = 0; seed < 3; ++seed) {
+            const problem = randomProblem(seed, 6, 6);
+            const dp = solveBruteForceWithOptions(problem, { assignmentDp: true });
+            const recursive = solveBruteForceWithOptions(problem, { assignmentDp: false });
+            for (const { solution, metric } of criteria) {
+                expect(dp[solution][metric]).toBeCloseTo(recursive[solution][metric], 9);
+            }
+            expect(dp.stats.assignmentNodes).toBeLessThan(recursive.stats.assignmentNodes);
+        }
+    });
+});
+
+describe('identical vehicles', () => {
+    test('share routing results and still serve every order', () => {
+        const base = randomProblem(5, 4, 6);
+        const [first] = base.vehicles;
+        const problem = {
+            ...base,
+            vehicles: base.vehicles.map(v => ({ ...v, startLocation: first.startLocation, priceKm: first.priceKm })),
+        };
+        const memo = new TspMemo();
+        const shared = solveBruteForceWithMemo(problem, memo);
+        expect(memo.size).toBeLessThanOrEqual(2 ** 6 - 1);
+
+        // A negligible price difference keeps every vehicle on its own memo row
+        const distinct = solveBruteForce({
+            ...problem,
+            vehicles: problem.vehicles.map((v, i) => ({ ...v, priceKm: v.priceKm + i * 1e-12 })),
+        });
+        for (const { solution, metric } of criteria) {
+            expect(shared[solution][metric]).toBeCloseTo(distinct[solution][metric], 6);
+            const pickups = Object.values(shared[solution].routes)
+                .flatMap(route => route.stops)
+                .filter(stop => stop.type === 'pickup')
+                .map(stop => stop.orderId)
+                .sort((a, b) => a - b);
+            expect(pickups).toEqual(problem.orders.map(order => order.id));
+        }
+    });
+});
+
+describe('warm start', () => {
+    test('matches a cold solve after orders change', () => {
+        for (let seed = 0; seed < 5; seed++) {
+            const problem = randomProblem(seed, 2, 5);
+            const previous = solveBruteForce(problem).bestDistanceSolution;
+            const next = {
+                ...problem,
+                orders: [...problem.orders.slice(1), { ...randomProblem(seed + 50, 1, 1).orders[0], id: 99 }],
+            };
+            const cold = solveBruteForce(next);
+            const warm = solveBruteForceWarm(next, previous);
+            expect(warm.bestDistanceSolution.totalDistance).toBeCloseTo(cold.bestDistanceSolution.totalDistance, 9);
+            expect(warm.bestPriceSolution.totalPrice).toBeCloseTo(cold.bestPriceSolution.totalPrice, 9);
+            expect(warm.bestEmptySolution.emptyDistance).toBeCloseTo(cold.bestEmptySolution.emptyDistance, 9);
+        }
+    });
+});
+
+describe('tie-breaking', () => {
+    test('a symmetric instance gives byte-identical output every run', () => {
+        // Two identical vehicles and orders mirrored around their depot, every plan has a twin
+        const at = (hash: string, latitude: number, longitude: number) => ({ hash, latitude, longitude });
+        const mirrored = (id: number, offset: number) => ({
+            id,
+            pickupLocation: at(`p${id}`, 54.0, 25.0 + offset),
+            deliveryLocation: at(`d${id}`, 54.1, 25.0 + 2 * offset),
+            loadFactor: 1,
+        });
+        const problem: Problem = {
+            vehicles: [1, 2].map(id => ({ id, startLocation: at('depot', 54.0, 25.0), priceKm: 1 })),
+            orders: [mirrored(1, 0.1), mirrored(2, -0.1), mirrored(3, 0.2), mirrored(4, -0.2)],
+        };
+        // Search counters depend on how the workers were scheduled, everything else may not
+        const plans = () => JSON.stringify({ ...solveBruteForce(problem), stats: undefined });
+        const first = plans();
+        for (let run = 1; run < 100; run++) {
+            expect(plans()).toBe(first);
+        }
+    });
+});
+
+describe('time limit', () => {
+    test('returns the best solutions found so far', () => {
+        const solution = solveBruteForceWithOptions(randomProblem(3, 4, 8), { timeLimitMs: 0 });
+        expect(solution.optimal).toBe(false);
+        expect(solution.feasible).toBe(true);
+        expect(Object.keys(solution.bestDistanceSolution.routes).length).toBeGreaterThan(0);
+    });
+
+    test('is optimal without a limit', () => {
+        const solution = solveBruteForceWithOptions(randomProblem(3, 2, 3), {});
+        expect(solution.optimal).toBe(true);
+    });
+});
+
+describe('node budget', () => {
+    const nodes = (solution: ReturnType<typeof solve>) => solution.stats.assignmentNodes + solution.stats.routeNodes;
+
+    test('a tiny budget stops early with the best solutions found so far', () => {
+        const problem = randomProblem(3, 4, 8);
+        const exact = solve(problem, { threads: 1 });
+        const cut = solve(problem, { maxNodes: 100 });
+        expect(cut.optimal).toBe(false);
+        expect(cut.feasible).toBe(true);
+        expect(nodes(cut)).toBeLessThan(nodes(exact) / 10);
+        expect(cut.bestDistanceSolution.totalDistance).toBeGreaterThanOrEqual(exact.bestDistanceSolution.totalDistance - 1e-9);
+        // One worker, so the same budget ends in the same place
+        expect(solve(problem, { maxNodes: 100 }).stats).toMatchObject({ assignmentNodes: cut.stats.assignmentNodes, routeNodes: cut.stats.routeNodes });
+    });
+
+    test('a large budget reproduces the exact optimum', () => {
+        const problem = randomProblem(3, 3, 6);
+        const exact = solveBruteForce(problem);
+        const solution = solve(problem, { maxNodes: 1e12 });
+        expect(solution.optimal).toBe(true);
+        for (const { solution: key, metric } of criteria) {
+            expect(solution[key][metric]).toBeCloseTo(exact[key][metric], 9);
+        }
+        expect(() => solve(problem, { maxNodes: 0 })).toThrow(/max_nodes must be at least 1/);
+    });
+});
+
+describe('async solve', () => {
+    test('matches the synchronous solve', async () => {
+        const problem = randomProblem(1, 2, 4);
+        const solution = await solveBruteForceAsync(problem);
+        expect(solution.bestPriceSolution.totalPrice).toBeCloseTo(solveBruteForce(problem).bestPriceSolution.totalPrice);
+    });
+
+    test('rejects invalid problems', async () => {
+        await expect(solveBruteForceAsync(randomProblem(0, 1, 9))).rejects.toThrow(/max 8 orders/);
+    });
+
+    test('rejects with Cancelled once the token is cancelled', async () => {
+        const token = new CancellationToken();
+        const pending = solveBruteForceAsync(randomProblem(2, 4, 8), {}, token);
+        token.cancel();
+        await expect(pending).rejects.toMatchObject({ code: 'Cancelled' });
+    });
+
+    test('reports improving incumbents before the result', async () => {
+        const problem = randomProblem(2, 4, 8);
+        const improvements: Improvement[] = [];
+        const solution = await solveBruteForceAsync(problem, { improvementSolutions: true }, null, improvement => {
+            improvements.push(improvement);
+        });
+        // Let callbacks queued just before the result run
+        await new Promise(resolve => setImmediate(resolve));
+
+        const distances = improvements.filter(improvement => improvement.criterion === Criterion.Distance);
+        expect(distances.length).toBeGreaterThan(0);
+        expect(distances[0].elapsedMs).toBeLessThanOrEqual(solution.stats.elapsedMs);
+        for (let i = 1; i < distances.length; ++i) {
+            expect(distances[i].value).toBeLessThan(distances[i - 1].value);
+        }
+        const last = distances.at(-1)!;
+        expect(last.value).toBeCloseTo(solution.bestDistanceSolution.totalDistance, 9);
+        expect(last.solution!.totalDistance).toBeCloseTo(last.value, 9);
+    });
+
+    test('ignores cancellation after completion', async () => {
+        const token = new CancellationToken();
+        const solution = await solveBruteForceAsync(randomProblem(2, 2, 3), {}, token);
+        token.cancel();
+        expect(solution.optimal).toBe(true);
+    });
+});
+
+describe('solve handle', () => {
+    // Price callbacks run on this thread, so poll without blocking it
+    const finish = async (handle: SolveHandle) => {
+        while (!handle.current().finished) {
+            await new Promise(resolve => setTimeout(resolve, 5));
+        }
+        return handle.current();
+    };
+
+    test('prices routes with the callback', async () => {
+        const priced: number[][] = [];
+        const handle = SolveHandle.start(randomProblem(1, 2, 4), {}, route => {
+            priced.push(route.orderIds);
+            return route.price;
+        });
+        const progress = await finish(handle);
+        expect(progress.error).toBeUndefined();
+        expect(progress.solution).toBeDefined();
+        expect(priced.length).toBeGreaterThan(0);
+    });
+
+    test('a callback surcharging one vehicle moves the price optimum to the other', async () => {
+        const at = (hash: string, latitude: number) => ({ hash, latitude, longitude: 25 });
+        const problem: Problem = {
+            vehicles: [
+                { id: 1, startLocation: at('near', 54.0), priceKm: 1 },
+                { id: 2, startLocation: at('far', 55.0), priceKm: 1 },
+            ],
+            orders: [
+                { id: 1, pickupLocation: at('a', 54.01), deliveryLocation: at('b', 54.02), loadFactor: 1 },
+                { id: 2, pickupLocation: at('c', 54.03), deliveryLocation: at('d', 54.04), loadFactor: 1 },
+            ],
+        };
+        const builtIn = solveBruteForce(problem).bestPriceSolution;
+        expect(Object.keys(builtIn.routes)).toEqual(['1']);
+
+        const progress = await finish(SolveHandle.start(problem, {}, route => route.price + (route.vehicleId === 1 ? 1000 : 0)));
+        expect(progress.error).toBeUndefined();
+        const cheapest = progress.solution!.bestPriceSolution;
+        expect(Object.keys(cheapest.routes)).toEqual(['2']);
+        // The callback added nothing to vehicle 2, so its legs still add up to the price
+        const route = cheapest.routes['2'];
+        const { loadedCost, emptyCost, stopFees, customAdjustment } = route.costBreakdown;
+        expect(customAdjustment).toBe(0);
+        expect(loadedCost + emptyCost + stopFees).toBeCloseTo(route.totalPrice, 9);
+        expect(route.legs.reduce((sum, leg) => sum + leg.price, 0)).toBeCloseTo(route.totalPrice, 9);
+    });
+
+    test('reports the surcharge of a callback price apart from the leg prices', async () => {
+        const problem = randomProblem(1, 2, 4);
+        const progress = await finish(SolveHandle.start(problem, {}, route => route.price + 50));
+        expect(progress.error).toBeUndefined();
+        for (const route of Object.values(progress.solution!.bestPriceSolution.routes)) {
+            const { loadedCost, emptyCost, stopFees, customAdjustment } = route.costBreakdown;
+            expect(customAdjustment).toBeCloseTo(50, 9);
+            expect(loadedCost + emptyCost + stopFees + customAdjustment).toBeCloseTo(route.totalPrice, 9);
+            expect(route.legs.reduce((sum, leg) => sum + leg.price, 0) + customAdjustment).toBeCloseTo(route.totalPrice, 9);
+        }
+    });
+
+    test('finishes with an error when the callback throws', async () => {
+        const handle = SolveHandle.start(randomProblem(1, 2, 4), {}, () => {
+            throw new Error('no tariff');
+        });
+        const progress = await finish(handle);
+        expect(progress.cancelled).toBe(false);
+        expect(progress.error).toMatch(/routeCostFn failed for vehicle \d+ with orders/);
+    });
+
+    test('finishes with an error when the callback returns no number', async () => {
+        const progress = await finish(SolveHandle.start(randomProblem(1, 2, 4), {}, () => NaN));
+        expect(progress.error).toMatch(/routeCostFn returned NaN/);
+    });
+});
+
+describe('solver statistics', () => {
+    test('count the search on both the sync and async solve', async () => {
+        const problem = randomProblem(2, 3, 7);
+        for (const { stats } of [solve(problem, { threads: 1 }), await solveBruteForceAsync(problem, { threads: 1 })]) {
+            expect(stats.assignmentNodes).toBeGreaterThan(0);
+            expect(stats.submasks).toBeGreaterThan(0);
+            expect(stats.memoMisses).toBeGreaterThan(0);
+            expect(stats.memoHits).toBeGreaterThan(0);
+            // A single worker stores every set it routes
+            expect(stats.memoEntries).toBe(stats.memoMisses);
+            expect(stats.elapsedMs).toBeGreaterThanOrEqual(0);
+        }
+    });
+
+    test('the assignment DP offers every set once per reachable state', () => {
+        const { stats } = solve(randomProblem(0, 1, 4), { assignmentDp: true });
+        // One vehicle, only the empty state is reachable and it offers all 15 non-empty sets
+        expect(stats.submasks).toBe(15);
+    });
+});
+
+describe('solve options', () => {
+    const distances = (solution: ReturnType<typeof solve>) => [
+        solution.bestDistanceSolution.totalDistance,
+        solution.bestPriceSolution.totalPrice,
+        solution.bestEmptySolution.emptyDistance,
+    ];
+
+    test('without options matches solveBruteForce', () => {
+        const problem = randomProblem(3, 2, 5);
+        expect(distances(solve(problem))).toEqual(distances(solveBruteForce(problem)));
+    });
+
+    test('weights report the weighted plan under every criterion', () => {
+        const problem = randomProblem(3, 2, 5);
+        const solution = solve(problem, { weights: { price: 1 } });
+        expect(solution.bestDistanceSolution.totalPrice).toBeCloseTo(solveBruteForce(problem).bestPriceSolution.totalPrice, 9);
+        expect(() => solve(problem, { weights: { price: 1 }, criteria: ['distance'] })).toThrow(/criteria and weights cannot be combined/);
+    });
+
+    test('maxOrdersExact plans larger problems greedily', () => {
+        const problem = randomProblem(0, 3, 12);
+        expect(() => solve(problem)).toThrow(/max 8 orders/);
+        const solution = solve(problem, { maxOrdersExact: 8 });
+        expect(solution.optimal).toBe(false);
+        expect(solution.bestDistanceSolution.totalDistance).toBeCloseTo(solveGreedy(problem).bestDistanceSolution.totalDistance, 9);
+        expect(solve(randomProblem(0, 2, 4), { maxOrdersExact: 8 }).optimal).toBe(true);
+        expect(() => solve(problem, { maxOrdersExact: 9 })).toThrow(/max_orders_exact must be at most 8/);
+    });
+
+    test('threads runs the same search on fewer workers', () => {
+        const problem = randomProblem(4, 3, 6);
+        expect(distances(solve(problem, { threads: 1 }))).toEqual(distances(solve(problem)));
+        expect(() => solve(problem, { threads: 0 })).toThrow(/threads must be at least 1/);
+    });
+
+    test('epsilon trades optimality for fewer nodes', () => {
+        const problem = randomProblem(5, 3, 7);
+        const exact = solve(problem);
+        const approx = solve(problem, { epsilon: 0.5 });
+        expect(approx.optimal).toBe(false);
+        expect(approx.stats.assignmentNodes).toBeLessThan(exact.stats.assignmentNodes);
+        expect(approx.bestDistanceSolution.totalDistance).toBeLessThanOrEqual(exact.bestDistanceSolution.totalDistance * 1.5 + 1e-9);
+        expect(() => solve(problem, { epsilon: -1 })).toThrow(/epsilon must be non-negative/);
+        expect(() => solve(problem, { epsilon: 0.1, criteria: ['price'] })).toThrow(/epsilon only applies/);
+    });
+
+    test('gapTolerance keeps every plan within the gap and reports the gap achieved', () => {
+        for (let seed = 0; seed < 3; ++seed) {
+            const problem = randomProblem(seed, 3, 7);
+            // One worker, so node counts do not depend on scheduling
+            const exact = solve(problem, { threads: 1 });
+            expect(exact.stats.achievedGap).toBe(0);
+
+            const zero = solve(problem, { gapTolerance: 0 });
+            expect(zero.optimal).toBe(true);
+            expect(distances(zero)).toEqual(distances(exact));
+            expect(zero.stats.achievedGap).toBe(0);
+
+            for (const gap of [0.05, 0.5]) {
+                const approx = solve(problem, { threads: 1, gapTolerance: gap });
+                expect(approx.optimal).toBe(false);
+                expect(approx.stats.assignmentNodes).toBeLessThanOrEqual(exact.stats.assignmentNodes);
+                expect(approx.stats.achievedGap).toBeLessThanOrEqual(gap + 1e-9);
+                for (const { solution, metric } of criteria) {
+                    expect(approx[solution][metric]).toBeLessThanOrEqual(exact[solution][metric] * (1 + gap) + 1e-9);
+                    expect(approx[solution][metric]).toBeLessThanOrEqual(exact[solution][metric] * (1 + approx.stats.achievedGap!) + 1e-9);
+                }
+            }
+        }
+        const problem = randomProblem(0, 2, 4);
+        expect(solve(problem, { maxNodes: 1, gapTolerance: 0.05 }).stats.achievedGap).toBeUndefined();
+        expect(() => solve(problem, { gapTolerance: 0.1, epsilon: 0.1 })).toThrow(/give only one of them/);
+        expect(() => solve(problem, { gapTolerance: -0.1 })).toThrow(/gap_tolerance must be non-negative/);
+    });
+
+    test('compute searches only the requested criteria and matches the full search on them', () => {
+        for (let seed = 0; seed < 5; ++seed) {
+            const problem = randomProblem(seed, 3, 7);
+            // One worker, so node counts do not depend on scheduling
+            const full = solve(problem, { threads: 1 });
+            for (const { criterion, solution, metric } of criteria) {
+                const single = solve(problem, { threads: 1, compute: [criterion as Criterion] });
+                expect(single[solution][metric]).toBeCloseTo(full[solution][metric], 9);
+                expect(single.stats.routeNodes).toBeLessThanOrEqual(full.stats.routeNodes);
+                for (const other of criteria.filter(c => c.criterion !== criterion)) {
+                    expect(single[other.solution].routeList).toHaveLength(0);
+                }
+            }
+        }
+        expect(() => solve(randomProblem(0, 1, 2), { compute: [] })).toThrow(/compute needs at least one criterion/);
+    });
+
+    test('topK lists the best distinct distance plans', () => {
+        const problem = randomProblem(2, 3, 6);
+        const single = solve(problem, { topK: 1 });
+        expect(single.alternatives).toHaveLength(0);
+        expect(distances(single)).toEqual(distances(solve(problem)));
+
+        const listed = solve(problem, { topK: 3 });
+        expect(listed.alternatives.length).toBeGreaterThan(1);
+        expect(listed.alternatives.length).toBeLessThanOrEqual(3);
+        expect(listed.alternatives[0].totalDistance).toBeCloseTo(listed.bestDistanceSolution.totalDistance, 9);
+        for (let i = 1; i < listed.alternatives.length; ++i) {
+            expect(listed.alternatives[i].totalDistance).toBeGreaterThanOrEqual(listed.alternatives[i - 1].totalDistance - 1e-9);
+        }
+        expect(new Set(listed.alternatives.map(describeAssignment)).size).toBe(listed.alternatives.length);
+
+        // Twin vehicles swapping their orders is the same plan
+        const twins = { ...problem, vehicles: [problem.vehicles[0], { ...problem.vehicles[0], id: problem.vehicles[1].id }] };
+        const orderSets = (solution: ProblemSolution) =>
+            Object.values(solution.routes)
+                .map(route => [...new Set(route.stops.map(stop => stop.orderId))].sort((a, b) => a - b).join(','))
+                .sort()
+                .join(' ');
+        const twinPlans = solve(twins, { topK: 5 }).alternatives;
+        expect(new Set(twinPlans.map(orderSets)).size).toBe(twinPlans.length);
+
+        expect(() => solve(problem, { topK: 0 })).toThrow(/top_k must be at least 1/);
+        expect(() => solve(problem, { topK: 2, weights: { price: 1 } })).toThrow(/top_k only applies/);
+    });
+
+    test('returnLegs false leaves the legs out', () => {
+        const problem = randomProblem(1, 2, 4);
+        const routes = (solution: ReturnType<typeof solve>) => Object.values(solution.bestDistanceSolution.routes);
+        expect(routes(solve(problem)).some(route => route.legs.length > 0)).toBe(true);
+        expect(routes(solve(problem, { returnLegs: false })).every(route => route.legs.length === 0)).toBe(true);
+    });
+});
@@ -0,0 +1,113 @@
+/**
+ * @description
+ * Input validation and error codes.
+ */
+
+import { describe, expect, test } from 'vitest';
+
+import {
+    Criterion,
+    routeAssignment,
+    solveBruteForce,
+    solveBruteForceWithOptions,
+    solveGreedy,
+} from 'rust-solver';
+
+import { randomProblem } from '../testProblems';
+
+describe('load factor validation', () => {
+    test.each([
+        [0, /zero load_factor on orders 1/],
+        [-2, /negative load_factor on orders 1/],
+        [NaN, /non-finite load_factor on orders 1/],
+        [0.5, /load_factor below 1/],
+    ])('rejects %s', (loadFactor, message) => {
+        const problem = randomProblem(0, 1, 2);
+        problem.orders[0].loadFactor = loadFactor;
+        expect(() => solveBruteForce(problem)).toThrow(message);
+    });
+});
+
+describe('error codes', () => {
+    const thrown = (run: () => unknown) => {
+        try {
+            run();
+        } catch (err) {
+            return err as Error & { code: string };
+        }
+        throw new Error('expected a throw');
+    };
+
+    test.each([
+        ['TooManyOrders', () => solveBruteForce(randomProblem(0, 1, 9))],
+        ['TooManyOrders', () => routeAssignment(randomProblem(0, 1, 65), {}, Criterion.Distance)],
+        [
+            'InvalidLoadFactor',
+            () => {
+                const problem = randomProblem(0, 1, 2);
+                problem.orders[0].loadFactor = 0;
+                return solveBruteForce(problem);
+            },
+        ],
+        [
+            'DuplicateOrderId',
+            () => {
+                const problem = randomProblem(0, 1, 3);
+                problem.orders[2].id = problem.orders[0].id;
+                return solveBruteForce(problem);
+            },
+        ],
+        [
+            'DuplicateVehicleId',
+            () => {
+                const problem = randomProblem(0, 2, 3);
+                problem.vehicles[1].id = problem.vehicles[0].id;
+                return solveGreedy(problem);
+            },
+        ],
+        [
+            'Infeasible',
+            () => {
+                const problem = randomProblem(0, 1, 2);
+                problem.vehicles[0].maxDistanceKm = 0.001;
+                return routeAssignment(problem, { '1': [1, 2] }, Criterion.Distance);
+            },
+        ],
+        ['Infeasible', () => solveBruteForceWithOptions(randomProblem(0, 3, 2), { requireAllVehicles: true })],
+        [
+            'InvalidCoordinate',
+            () => {
+                const problem = randomProblem(0, 1, 2);
+                problem.orders[0].pickupLocation.latitude = NaN;
+                return solveBruteForce(problem);
+            },
+        ],
+        ['InvalidArg', () => routeAssignment(randomProblem(0, 1, 2), { '99': [1] }, Criterion.Distance)],
+    ])('%s', (code, run) => {
+        const err = thrown(run);
+        expect(err.code).toBe(code);
+        expect(err.message.startsWith(`${code}: `)).toBe(true);
+    });
+
+    test('names every duplicated id', () => {
+        const problem = randomProblem(0, 1, 6);
+        problem.orders[5].id = 2;
+        problem.orders[4].id = 2;
+        problem.orders[3].id = 1;
+        expect(() => solveBruteForce(problem)).toThrow('Order ids used by more than one order: 1, 2');
+
+        const fleet = randomProblem(0, 3, 2);
+        fleet.vehicles[2].id = 1;
+        expect(() => solveBruteForce(fleet)).toThrow('Vehicle ids used by more than one vehicle: 1');
+    });
+
+    test('rejects orders without any location hash', () => {
+        const problem = randomProblem(0, 1, 3);
+        problem.orders[1].pickupLocation.hash = '';
+        problem.orders[1].deliveryLocation.hash = '';
+        expect(() => solveBruteForce(problem)).toThrow('Empty pickup and delivery location hashes on orders 2');
+
+        problem.orders[1].deliveryLocation.hash = 'd2';
+        expect(() => solveBruteForce(problem)).not.toThrow();
+    });
+});
@@ -15,7 +15,7 @@
  * Where V = Number of Vehicles, N = Number of Orders.
  */
 
-import { solveBruteForce } from 'rust-solver';
+import { solveBruteForceAsync } from 'rust-solver';
 import {
     AlgorithmConfig,
     Order,
@@ -50,7 +50,8 @@ export class BruteForceAlgorithmRust implements MultiTargetAlgorithm {
             throw new Error(`Problem too large for ${this.name} implementation.`);
         }
 
-        return new Promise(res => res({ solution: solveBruteForce(problem), history: [] }));
+        // Runs off the event loop, the search can take seconds for larger problems
+        return solveBruteForceAsync(problem).then(solution => ({ solution, history: [] }));
     }
 }
 
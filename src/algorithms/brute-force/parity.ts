@@ -0,0 +1,88 @@
+/**
+ * @module brute-force-parity
+ * @description
+ * Cross-checks the Rust brute force solver against the original JavaScript implementation.
+ *
+ * Both solvers are exact, so for the same problem they must agree on every objective value
+ * (within floating point tolerance) and, barring ties, on which orders each vehicle serves.
+ * Any divergent instance is written out as a JSON fixture so it can be replayed in isolation.
+ */
+
+import fs from 'fs';
+import path from 'path';
+
+import { solveBruteForce } from 'rust-solver';
+import { AlgorithmSolution, OptimizationTarget, Problem } from '../../types';
+import { greatCircleDistanceCalculator } from '../../utils/greatCircleDistanceCalculator';
+import { BruteForceAlgorithmJS } from './index';
+import { criteria, describeAssignment, randomProblem, TOLERANCE } from './testProblems';
+
+export interface ParityDivergence {
+    criterion: 'distance' | 'price' | 'empty';
+    reason: 'objective' | 'assignment';
+    js: number | string;
+    rust: number | string;
+}
+
+export const compareSolutions = (js: AlgorithmSolution, rust: AlgorithmSolution): ParityDivergence[] => {
+    const divergences: ParityDivergence[] = [];
+
+    for (const { criterion, solution, metric } of criteria) {
+        const jsValue = js[solution][metric];
+        const rustValue = rust[solution][metric];
+
+        if (Math.abs(jsValue - rustValue) > TOLERANCE * Math.max(1, Math.abs(jsValue))) {
+            divergences.push({ criterion, reason: 'objective', js: jsValue, rust: rustValue });
+            continue;
+        }
+
+        const jsAssignment = describeAssignment(js[solution]);
+        const rustAssignment = describeAssignment(rust[solution]);
+        if (jsAssignment !== rustAssignment) {
+            divergences.push({ criterion, reason: 'assignment', js: jsAssignment, rust: rustAssignment });
+        }
+    }
+
+    return divergences;
+};
+
+export const checkParity = (problem: Problem): ParityDivergence[] => {
+    const js = new BruteForceAlgorithmJS().solveSync(problem, {
+        distanceCalc: greatCircleDistanceCalculator,
+        target: OptimizationTarget.DISTANCE,
+    }).solution;
+
+    return compareSolutions(js, solveBruteForce(problem));
+};
+
+export const writeParityFixture = (dir: string, name: string, problem: Problem, divergences: ParityDivergence[]) => {
+    fs.mkdirSync(dir, { recursive: true });
+    const filePath = path.resolve(dir, `${name}.json`);
+    fs.writeFileSync(filePath, JSON.stringify({ problem, divergences }, null, 2));
+    return filePath;
+};
+
+if (import.meta.vitest) {
+    const { describe, test, expect } = import.meta.vitest;
+
+    const fixturesDir = path.resolve(__dirname, '__parity_failures__');
+
+    describe('brute force parity (JS vs Rust)', () => {
+        for (let vehicleCount = 1; vehicleCount <= 3; ++vehicleCount) {
+            for (let orderCount = 1; orderCount <= 5; ++orderCount) {
+                test(`${vehicleCount} vehicles, ${orderCount} orders`, () => {
+                    for (let seed = 0; seed < 5; ++seed) {
+                        const problem = randomProblem(seed, vehicleCount, orderCount);
+                        const divergences = checkParity(problem);
+
+                        if (divergences.length > 0) {
+                            const name = `${vehicleCount}_${orderCount}_${seed}`;
+                            const fixture = writeParityFixture(fixturesDir, name, problem, divergences);
+                            expect.fail(`Divergence on ${name}, reproducible instance written to ${fixture}`);
+                        }
+                    }
+                });
+            }
+        }
+    });
+}
@@ -0,0 +1,56 @@
+/**
+ * @description
+ * Reproducible problems and helpers shared by the parity harness and the Rust solver specs.
+ */
+
+import { Order, Problem, ProblemSolution, Vehicle } from '../../types';
+
+export const TOLERANCE = 1e-6;
+
+export const criteria = [
+    { criterion: 'distance', solution: 'bestDistanceSolution', metric: 'totalDistance' },
+    { criterion: 'price', solution: 'bestPriceSolution', metric: 'totalPrice' },
+    { criterion: 'empty', solution: 'bestEmptySolution', metric: 'emptyDistance' },
+] as const;
+
+// Mulberry32, good enough for reproducible instance generation
+const seededRandom = (seed: number) => () => {
+    seed = (seed + 0x6d2b79f5) | 0;
+    let t = Math.imul(seed ^ (seed >>> 15), 1 | seed);
+    t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+};
+
+export const randomProblem = (seed: number, vehicleCount: number, orderCount: number): Problem => {
+    const random = seededRandom(seed);
+    const location = (prefix: string, i: number) => ({
+        hash: `${prefix}-${seed}-${i}`,
+        latitude: 54 + random() * 2,
+        longitude: 23 + random() * 3,
+    });
+
+    const vehicles: Vehicle[] = Array.from({ length: vehicleCount }, (_, i) => ({
+        id: i + 1,
+        startLocation: location('v', i),
+        priceKm: Math.round((0.5 + random()) * 100) / 100,
+    }));
+    const orders: Order[] = Array.from({ length: orderCount }, (_, i) => ({
+        id: i + 1,
+        pickupLocation: location('p', i),
+        deliveryLocation: location('d', i),
+        loadFactor: [1, 2, 3, 4][Math.floor(random() * 4)],
+    }));
+
+    return { vehicles, orders };
+};
+
+// Canonical "vehicle:order,order" description of which vehicle serves which orders
+export const describeAssignment = (solution: ProblemSolution) =>
+    Object.entries(solution.routes)
+        .filter(([, route]) => route.stops.length > 0)
+        .map(([vehicleId, route]) => {
+            const orderIds = [...new Set(route.stops.map(stop => stop.orderId))].sort((a, b) => a - b);
+            return `${vehicleId}:${orderIds.join(',')}`;
+        })
+        .sort()
+        .join(' ');